@@ -1,13 +1,19 @@
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
 use rustyline::DefaultEditor;
 use rustyline::error::ReadlineError;
 use std::io::Write;
+use storage::catalog::CATALOG_FILE_ID;
+use storage::database::Database;
+use storage::disk::buffer_pool::BufferPool;
 use storage::disk::disk_manager::DiskManager;
 use storage::disk::file_system::FsDiskManager;
 use storage::heap::heap_page::HeapPage;
-use storage::query::executor::{QueryExecutor, QueryResult};
-use storage::query::parser::parse_sql;
-use storage::query::planner::QueryPlanner;
+use storage::heap::toast::TOAST_FILE_ID;
+use storage::query::executor::QueryResult;
+use storage::query::parser::{is_complete_script, split_statements};
+use storage::query::session::SessionConfig;
+use storage::query::types::{Column, DataType, Schema, Value};
+use storage::wal::{WAL_FILE_NAME, WalDiskManager};
 
 #[derive(Parser)]
 #[command(name = "boxsqld")]
@@ -18,6 +24,56 @@ struct Cli {
 
     #[arg(short, long, default_value = "./data")]
     data_dir: String,
+
+    #[arg(long, value_enum, default_value_t = OutputFormat::Table)]
+    format: OutputFormat,
+
+    #[arg(long, value_enum, default_value_t = BoolFormat::TrueFalse)]
+    bool_format: BoolFormat,
+
+    #[arg(long, help = "Stop returning rows after this many")]
+    max_rows: Option<u32>,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum OutputFormat {
+    #[value(help = "Aligned columns")]
+    Table,
+    #[value(help = "One JSON object per row")]
+    Jsonl,
+}
+
+#[derive(Clone, Copy)]
+struct OutputOptions {
+    format: OutputFormat,
+    bool_format: BoolFormat,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum BoolFormat {
+    #[value(help = "true / false")]
+    TrueFalse,
+    #[value(help = "TRUE / FALSE")]
+    Upper,
+    #[value(help = "t / f")]
+    Tf,
+    #[value(help = "1 / 0")]
+    OneZero,
+}
+
+impl BoolFormat {
+    fn format(self, b: bool) -> &'static str {
+        match (self, b) {
+            (BoolFormat::TrueFalse, true) => "true",
+            (BoolFormat::TrueFalse, false) => "false",
+            (BoolFormat::Upper, true) => "TRUE",
+            (BoolFormat::Upper, false) => "FALSE",
+            (BoolFormat::Tf, true) => "t",
+            (BoolFormat::Tf, false) => "f",
+            (BoolFormat::OneZero, true) => "1",
+            (BoolFormat::OneZero, false) => "0",
+        }
+    }
 }
 
 #[derive(Subcommand)]
@@ -31,78 +87,162 @@ fn main() -> anyhow::Result<()> {
     let cli = Cli::parse();
 
     let data_dir = std::env::var("BOXSQLD_DATA").unwrap_or(cli.data_dir);
+    let output = OutputOptions {
+        format: cli.format,
+        bool_format: cli.bool_format,
+    };
+    let session = SessionConfig {
+        max_rows: cli.max_rows,
+        ..SessionConfig::default()
+    };
 
     match cli.command {
         Some(Commands::Exec { sql }) => {
-            execute_sql(&sql, &data_dir)?;
+            execute_sql(&sql, &data_dir, &session, output)?;
         }
         Some(Commands::Shell) => {
-            start_interactive_shell(&data_dir)?;
+            start_interactive_shell(&data_dir, &session, output)?;
         }
         Some(Commands::InitData) => {
             initialize_sample_data(&data_dir)?;
         }
         None => {
-            start_interactive_shell(&data_dir)?;
+            start_interactive_shell(&data_dir, &session, output)?;
         }
     }
 
     Ok(())
 }
 
-fn execute_sql(sql: &str, data_dir: &str) -> anyhow::Result<()> {
-    let mut dm = FsDiskManager::new(data_dir)?;
-
-    let stmt = parse_sql(sql)?;
-    let planner = QueryPlanner::new();
-    let plan = planner.plan(&stmt)?;
-    let executor = QueryExecutor::new();
-    let result = executor.execute(plan, &mut dm)?;
+fn execute_sql(
+    sql: &str,
+    data_dir: &str,
+    session: &SessionConfig,
+    output: OutputOptions,
+) -> anyhow::Result<()> {
+    let mut db = Database::open(open_data_dir(data_dir)?, session.clone())?;
+    let plan = db.plan(sql)?;
+
+    let result = match output.format {
+        OutputFormat::Table => db
+            .execute_plan(plan)
+            .map(|result| print!("{}", render_result(&result, output.bool_format))),
+        // Rows are written as the executor produces them
+        OutputFormat::Jsonl => db.describe(&plan).and_then(|schema| {
+            let mut stdout = std::io::stdout().lock();
+            db.execute_each(plan, |row| write_jsonl_row(&schema, &row, &mut stdout))
+        }),
+    };
+    // Whatever the statement wrote, even if it failed part way, leaves the
+    // pool before it is dropped
+    db.disk_manager().sync()?;
+    result
+}
 
-    display_result(&result);
+// Pages cached by each statement's buffer pool
+const BUFFER_POOL_PAGES: usize = 1024;
 
-    Ok(())
+// Changes are logged before they reach the data files, and any the last run
+// logged but did not write are replayed on open. Reads and writes go through
+// a buffer pool, which must be synced before it is dropped.
+fn open_data_dir(data_dir: &str) -> anyhow::Result<BufferPool<WalDiskManager<FsDiskManager>>> {
+    let disk = WalDiskManager::open(data_dir, FsDiskManager::new(data_dir)?)?;
+    Ok(BufferPool::new(disk, BUFFER_POOL_PAGES))
 }
 
-fn start_interactive_shell(data_dir: &str) -> anyhow::Result<()> {
+fn start_interactive_shell(
+    data_dir: &str,
+    session: &SessionConfig,
+    output: OutputOptions,
+) -> anyhow::Result<()> {
     println!("BoxSQL Interactive Shell");
     println!("Type 'help' for help, 'exit' or 'quit' to quit");
     println!("Data directory: {}\n", data_dir);
 
     let mut rl = DefaultEditor::new()?;
+    run_shell(&mut rl, data_dir, session, output)
+}
+
+// Where the shell gets its input; the readline editor outside of tests.
+trait LineReader {
+    fn read_line(&mut self, prompt: &str) -> Result<String, ReadlineError>;
+    fn add_history(&mut self, entry: &str) -> Result<(), ReadlineError>;
+}
+
+impl LineReader for DefaultEditor {
+    fn read_line(&mut self, prompt: &str) -> Result<String, ReadlineError> {
+        self.readline(prompt)
+    }
+
+    fn add_history(&mut self, entry: &str) -> Result<(), ReadlineError> {
+        self.add_history_entry(entry).map(|_| ())
+    }
+}
+
+// Lines are collected until they end in a semicolon outside of quotes, so a
+// statement can span several lines. Shell commands are only recognised at
+// the start of a statement.
+fn run_shell(
+    reader: &mut impl LineReader,
+    data_dir: &str,
+    session: &SessionConfig,
+    mut output: OutputOptions,
+) -> anyhow::Result<()> {
+    // `.set` changes these for the rest of the shell session only
+    let mut session = session.clone();
+    let mut buffer = String::new();
 
     loop {
-        let readline = rl.readline("boxsql> ");
-        match readline {
+        let prompt = if buffer.is_empty() {
+            "boxsql> "
+        } else {
+            "     -> "
+        };
+        match reader.read_line(prompt) {
             Ok(line) => {
-                let line = line.trim();
-
-                if line.is_empty() {
+                if buffer.is_empty() && line.trim_start().starts_with('.') {
+                    match run_dot_command(line.trim(), &mut session, &mut output) {
+                        Ok(text) => print!("{}", text),
+                        Err(e) => println!("Error: {}", e),
+                    }
                     continue;
                 }
+                if buffer.is_empty() {
+                    match line.trim().to_lowercase().as_str() {
+                        "" => continue,
+                        "exit" | "quit" => {
+                            println!("Goodbye!");
+                            break;
+                        }
+                        "help" => {
+                            print_help();
+                            continue;
+                        }
+                        "clear" | "cls" => {
+                            clear_terminal();
+                            continue;
+                        }
+                        _ => {}
+                    }
+                }
 
-                rl.add_history_entry(line)?;
+                buffer.push_str(&line);
+                buffer.push('\n');
+                if !is_complete_script(&buffer) {
+                    continue;
+                }
 
-                match line.to_lowercase().as_str() {
-                    "exit" | "quit" => {
-                        println!("Goodbye!");
-                        break;
-                    }
-                    "help" => {
-                        print_help();
-                    }
-                    "clear" | "cls" => {
-                        clear_terminal();
-                    }
-                    _ => {
-                        if let Err(e) = execute_sql(line, data_dir) {
-                            println!("Error: {}", e);
-                        }
+                let script = std::mem::take(&mut buffer);
+                reader.add_history(script.trim())?;
+                for (_, statement) in split_statements(&script) {
+                    if let Err(e) = execute_sql(statement, data_dir, &session, output) {
+                        println!("Error: {}", e);
                     }
                 }
             }
             Err(ReadlineError::Interrupted) => {
                 println!("^C");
+                buffer.clear();
                 continue;
             }
             Err(ReadlineError::Eof) => {
@@ -119,19 +259,109 @@ fn start_interactive_shell(data_dir: &str) -> anyhow::Result<()> {
     Ok(())
 }
 
+// Runs a `.set key value` or `.show` command and returns what to print.
+fn run_dot_command(
+    line: &str,
+    session: &mut SessionConfig,
+    output: &mut OutputOptions,
+) -> anyhow::Result<String> {
+    let mut words = line.split_whitespace();
+    match words.next().unwrap_or_default() {
+        ".show" => Ok(show_settings(session, output)),
+        ".set" => {
+            let (Some(key), Some(value), None) = (words.next(), words.next(), words.next()) else {
+                anyhow::bail!("usage: .set <key> <value>");
+            };
+            apply_setting(key, value, session, output)?;
+            Ok(String::new())
+        }
+        command => anyhow::bail!("unknown command {}", command),
+    }
+}
+
+fn apply_setting(
+    key: &str,
+    value: &str,
+    session: &mut SessionConfig,
+    output: &mut OutputOptions,
+) -> anyhow::Result<()> {
+    let invalid = || anyhow::anyhow!("invalid value '{}' for {}", value, key);
+    match key.to_lowercase().as_str() {
+        "format" => output.format = OutputFormat::from_str(value, true).map_err(|_| invalid())?,
+        "bool_format" => {
+            output.bool_format = BoolFormat::from_str(value, true).map_err(|_| invalid())?
+        }
+        "max_rows" => {
+            session.max_rows = match value.to_lowercase().as_str() {
+                "off" | "none" => None,
+                _ => Some(value.parse().map_err(|_| invalid())?),
+            }
+        }
+        "case_insensitive_identifiers" => {
+            session.case_insensitive_identifiers = match value.to_lowercase().as_str() {
+                "on" | "true" => true,
+                "off" | "false" => false,
+                _ => return Err(invalid()),
+            }
+        }
+        _ => anyhow::bail!("unknown setting {}", key),
+    }
+    Ok(())
+}
+
+fn show_settings(session: &SessionConfig, output: &OutputOptions) -> String {
+    let name = |value: Option<clap::builder::PossibleValue>| {
+        value.map(|v| v.get_name().to_string()).unwrap_or_default()
+    };
+    let on_off = |b: bool| if b { "on" } else { "off" };
+    format!(
+        "format = {}\nbool_format = {}\nmax_rows = {}\ncase_insensitive_identifiers = {}\n",
+        name(output.format.to_possible_value()),
+        name(output.bool_format.to_possible_value()),
+        session
+            .max_rows
+            .map_or("off".to_string(), |n| n.to_string()),
+        on_off(session.case_insensitive_identifiers),
+    )
+}
+
 fn initialize_sample_data(data_dir: &str) -> anyhow::Result<()> {
     println!("Initializing sample data in {}...", data_dir);
 
-    let db_file_path = std::path::Path::new(data_dir).join("base_1.db");
-    if db_file_path.exists() {
-        std::fs::remove_file(&db_file_path)?;
-        println!("Removed existing database file");
+    for file_id in [CATALOG_FILE_ID, 1, TOAST_FILE_ID] {
+        let file = format!("base_{}.db", file_id);
+        let db_file_path = std::path::Path::new(data_dir).join(&file);
+        if db_file_path.exists() {
+            std::fs::remove_file(&db_file_path)?;
+            println!("Removed existing database file {}", file);
+        }
+    }
+    // The old log describes the removed files
+    let wal_path = std::path::Path::new(data_dir).join(WAL_FILE_NAME);
+    if wal_path.exists() {
+        std::fs::remove_file(&wal_path)?;
     }
 
-    let mut dm = FsDiskManager::new(data_dir)?;
-
-    let pid = dm.allocate_page(1)?;
-    let mut hp = HeapPage::new_empty(pid);
+    let mut db = Database::open(open_data_dir(data_dir)?, SessionConfig::default())?;
+    let file_id = db.create_table(
+        "users",
+        Schema::new(vec![
+            Column {
+                name: "id".to_string(),
+                data_type: DataType::Integer,
+                nullable: false,
+            },
+            Column {
+                name: "name".to_string(),
+                data_type: DataType::Varchar(255),
+                nullable: true,
+            },
+        ]),
+    )?;
+
+    let dm = db.disk_manager();
+    let mut hp = HeapPage::allocate(dm, file_id)?;
+    let pid = hp.page.page_id();
 
     let sample_users = [
         (1i32, "Alice"),
@@ -159,6 +389,7 @@ fn initialize_sample_data(data_dir: &str) -> anyhow::Result<()> {
     }
 
     dm.write_page(&hp.page)?;
+    dm.sync()?;
     println!(
         "✓ Created {} user records in page {:?}",
         sample_users.len(),
@@ -174,11 +405,13 @@ fn initialize_sample_data(data_dir: &str) -> anyhow::Result<()> {
     Ok(())
 }
 
-fn display_result(result: &QueryResult) -> () {
+fn render_result(result: &QueryResult, bool_format: BoolFormat) -> String {
+    use std::fmt::Write;
+
     if result.rows.is_empty() {
-        println!("(no rows)");
-        return;
+        return "(no rows)\n".to_string();
     }
+    let mut out = String::new();
 
     let headers: Vec<String> = result
         .schema
@@ -193,10 +426,10 @@ fn display_result(result: &QueryResult) -> () {
         .map(|row| {
             row.iter()
                 .map(|value| match value {
-                    storage::query::types::Value::Integer(i) => i.to_string(),
-                    storage::query::types::Value::Varchar(s) => s.clone(),
-                    storage::query::types::Value::Boolean(b) => b.to_string(),
-                    storage::query::types::Value::Null => "NULL".to_string(),
+                    Value::Integer(i) => i.to_string(),
+                    Value::Varchar(s) => s.clone(),
+                    Value::Boolean(b) => bool_format.format(*b).to_string(),
+                    Value::Null => "NULL".to_string(),
                 })
                 .collect()
         })
@@ -215,13 +448,13 @@ fn display_result(result: &QueryResult) -> () {
         .zip(&column_widths)
         .map(|(header, &width)| format!("{:<width$}", header, width = width))
         .collect();
-    println!("{}", padded_headers.join(" | "));
+    writeln!(out, "{}", padded_headers.join(" | ")).unwrap();
 
     let separator: Vec<String> = column_widths
         .iter()
         .map(|&width| "-".repeat(width))
         .collect();
-    println!("{}", separator.join("-|-"));
+    writeln!(out, "{}", separator.join("-|-")).unwrap();
 
     for row in &data_rows {
         let padded_row: Vec<String> = row
@@ -229,10 +462,50 @@ fn display_result(result: &QueryResult) -> () {
             .zip(&column_widths)
             .map(|(cell, &width)| format!("{:<width$}", cell, width = width))
             .collect();
-        println!("{}", padded_row.join(" | "));
+        writeln!(out, "{}", padded_row.join(" | ")).unwrap();
     }
 
-    println!("\n({} rows)", result.rows.len());
+    writeln!(out, "\n({} rows)", result.rows.len()).unwrap();
+    out
+}
+
+// Writes one row as a JSON object on its own line. Booleans stay JSON
+// booleans.
+fn write_jsonl_row(schema: &Schema, row: &[Value], out: &mut impl Write) -> anyhow::Result<()> {
+    let fields: Vec<String> = schema
+        .columns
+        .iter()
+        .zip(row)
+        .map(|(col, value)| {
+            let value = match value {
+                Value::Integer(i) => i.to_string(),
+                Value::Varchar(s) => json_string(s),
+                Value::Boolean(b) => b.to_string(),
+                Value::Null => "null".to_string(),
+            };
+            format!("{}:{}", json_string(&col.name), value)
+        })
+        .collect();
+    writeln!(out, "{{{}}}", fields.join(","))?;
+    Ok(())
+}
+
+fn json_string(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len() + 2);
+    escaped.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
 }
 
 fn clear_terminal() {
@@ -244,10 +517,192 @@ fn print_help() {
     println!("BoxSQL Help:");
     println!("-----------");
     println!();
+    println!("  End each SQL statement with a semicolon; it may span several lines.");
+    println!();
     println!("  Shell Commands:");
     println!("    help    - Show this help");
     println!("    clear   - Clear the terminal screen");
     println!("    cls     - Clear the terminal screen");
     println!("    exit    - Exit the shell");
     println!("    quit    - Exit the shell");
+    println!("    .show   - Show the current settings");
+    println!("    .set <key> <value>");
+    println!("            - Change a setting for this session: format, bool_format,");
+    println!("              max_rows (a number or off), case_insensitive_identifiers (on/off)");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+
+    struct ScriptedLines {
+        lines: VecDeque<&'static str>,
+        history: Vec<String>,
+    }
+
+    impl LineReader for ScriptedLines {
+        fn read_line(&mut self, _prompt: &str) -> Result<String, ReadlineError> {
+            self.lines
+                .pop_front()
+                .map(str::to_string)
+                .ok_or(ReadlineError::Eof)
+        }
+
+        fn add_history(&mut self, entry: &str) -> Result<(), ReadlineError> {
+            self.history.push(entry.to_string());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn multi_line_insert_runs_once_terminated() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let data_dir = dir.path().to_str().unwrap();
+        initialize_sample_data(data_dir)?;
+
+        let mut reader = ScriptedLines {
+            lines: VecDeque::from([
+                "INSERT INTO users (id, name)",
+                "  VALUES (11, 'Kim;'),",
+                "",
+                "         (12, 'Lee');",
+                "exit",
+            ]),
+            history: Vec::new(),
+        };
+        let output = OutputOptions {
+            format: OutputFormat::Table,
+            bool_format: BoolFormat::TrueFalse,
+        };
+        run_shell(&mut reader, data_dir, &SessionConfig::default(), output)?;
+        assert_eq!(
+            reader.history,
+            vec!["INSERT INTO users (id, name)\n  VALUES (11, 'Kim;'),\n\n         (12, 'Lee');"]
+        );
+        assert!(reader.lines.is_empty());
+
+        let mut db = Database::open(FsDiskManager::new(data_dir)?, SessionConfig::default())?;
+        let result = db.execute("SELECT name FROM users WHERE id > 10")?;
+        assert_eq!(
+            result.rows,
+            vec![
+                vec![Value::Varchar("Kim;".to_string())],
+                vec![Value::Varchar("Lee".to_string())],
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn set_changes_settings_for_the_session() -> anyhow::Result<()> {
+        let mut session = SessionConfig::default();
+        let mut output = OutputOptions {
+            format: OutputFormat::Table,
+            bool_format: BoolFormat::TrueFalse,
+        };
+        let mut run = |line: &str| run_dot_command(line, &mut session, &mut output);
+
+        run(".set format JSONL")?;
+        run(".set bool_format one-zero")?;
+        run(".set max_rows 5")?;
+        run(".set case_insensitive_identifiers off")?;
+        assert_eq!(
+            run(".show")?,
+            "format = jsonl\nbool_format = one-zero\nmax_rows = 5\n\
+             case_insensitive_identifiers = off\n"
+        );
+        run(".set max_rows off")?;
+        assert!(run(".show")?.contains("max_rows = off"));
+
+        assert!(run(".set max_rows many").is_err());
+        assert!(run(".set colour blue").is_err());
+        assert!(run(".set format").is_err());
+        assert!(run(".frobnicate").is_err());
+        assert_eq!(session.max_rows, None);
+        assert!(!session.case_insensitive_identifiers);
+        assert!(matches!(output.format, OutputFormat::Jsonl));
+        Ok(())
+    }
+
+    #[test]
+    fn boolean_format_applies_to_output() {
+        let result = QueryResult {
+            rows: vec![
+                vec![Value::Integer(1), Value::Boolean(true)],
+                vec![Value::Integer(2), Value::Boolean(false)],
+            ],
+            schema: Schema::new(vec![
+                Column {
+                    name: "id".to_string(),
+                    data_type: DataType::Integer,
+                    nullable: false,
+                },
+                Column {
+                    name: "active".to_string(),
+                    data_type: DataType::Boolean,
+                    nullable: false,
+                },
+            ]),
+        };
+
+        let output = render_result(&result, BoolFormat::Tf);
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines[2], "1  | t     ");
+        assert_eq!(lines[3], "2  | f     ");
+
+        let output = render_result(&result, BoolFormat::Upper);
+        assert!(output.contains("TRUE"));
+        assert!(output.contains("FALSE"));
+    }
+
+    #[test]
+    fn jsonl_emits_one_object_per_row() {
+        let result = QueryResult {
+            rows: vec![
+                vec![
+                    Value::Integer(1),
+                    Value::Varchar("Al \"Bo\"".to_string()),
+                    Value::Boolean(true),
+                ],
+                vec![Value::Integer(2), Value::Null, Value::Boolean(false)],
+            ],
+            schema: Schema::new(vec![
+                Column {
+                    name: "id".to_string(),
+                    data_type: DataType::Integer,
+                    nullable: false,
+                },
+                Column {
+                    name: "name".to_string(),
+                    data_type: DataType::Varchar(255),
+                    nullable: true,
+                },
+                Column {
+                    name: "active".to_string(),
+                    data_type: DataType::Boolean,
+                    nullable: false,
+                },
+            ]),
+        };
+
+        let mut out = Vec::new();
+        for row in &result.rows {
+            write_jsonl_row(&result.schema, row, &mut out).unwrap();
+        }
+        let output = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(
+            lines,
+            vec![
+                r#"{"id":1,"name":"Al \"Bo\"","active":true}"#,
+                r#"{"id":2,"name":null,"active":false}"#,
+            ]
+        );
+    }
+
+    #[test]
+    fn json_strings_are_escaped() {
+        assert_eq!(json_string("a\\b\nc\u{1}"), r#""a\\b\nc\u0001""#);
+    }
 }
@@ -0,0 +1,266 @@
+use crate::disk::disk_manager::DiskManager;
+use crate::page::{
+    constants::PAGE_DATA_END,
+    page_file::Page,
+    page_header::PageHeader,
+    page_id::{PageFlags, PageId},
+};
+use crate::query::types::{Column, DataType, Schema};
+use std::collections::BTreeMap;
+
+// The catalog lives in page 0 of its own file. Table data files are
+// numbered from 1.
+pub const CATALOG_FILE_ID: u32 = 0;
+
+#[derive(Debug, thiserror::Error)]
+pub enum CatalogError {
+    #[error("table '{0}' not found")]
+    TableNotFound(String),
+    #[error("table '{0}' already exists")]
+    TableExists(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableInfo {
+    pub file_id: u32,
+    pub schema: Schema,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Catalog {
+    tables: BTreeMap<String, TableInfo>,
+}
+
+impl Catalog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Reads the catalog page, or returns an empty catalog if the database
+    // has none yet.
+    pub fn load<D: DiskManager>(disk_manager: &D) -> anyhow::Result<Self> {
+        if disk_manager.page_count(CATALOG_FILE_ID)? == 0 {
+            return Ok(Self::new());
+        }
+        let page = disk_manager.read_page(PageId::new(CATALOG_FILE_ID, 0))?;
+        if page.header().page_flags != PageFlags::Meta as u16 {
+            anyhow::bail!("catalog page is not a meta page");
+        }
+        decode(&page)
+    }
+
+    pub fn save<D: DiskManager>(&self, disk_manager: &mut D) -> anyhow::Result<()> {
+        let pid = PageId::new(CATALOG_FILE_ID, 0);
+        if disk_manager.page_count(CATALOG_FILE_ID)? == 0 {
+            disk_manager.allocate_page(CATALOG_FILE_ID)?;
+        }
+        let mut page = Page::new(pid, PageFlags::Meta);
+        let bytes = self.encode();
+        if PageHeader::LEN + 4 + bytes.len() > PAGE_DATA_END {
+            anyhow::bail!("catalog of {} bytes does not fit in one page", bytes.len());
+        }
+        page.write_bytes(PageHeader::LEN, &(bytes.len() as u32).to_le_bytes())?;
+        page.write_bytes(PageHeader::LEN + 4, &bytes)?;
+        page.recompute_checksum();
+        disk_manager.write_page(&page)
+    }
+
+    // Registers a table in memory only; see `create_table`.
+    pub fn add_table(&mut self, name: &str, file_id: u32, schema: Schema) -> anyhow::Result<()> {
+        if self.tables.contains_key(name) {
+            return Err(CatalogError::TableExists(name.to_string()).into());
+        }
+        self.tables
+            .insert(name.to_string(), TableInfo { file_id, schema });
+        Ok(())
+    }
+
+    // Adds a table with the next free file id and persists the catalog.
+    pub fn create_table<D: DiskManager>(
+        &mut self,
+        disk_manager: &mut D,
+        name: &str,
+        schema: Schema,
+    ) -> anyhow::Result<u32> {
+        let file_id = self
+            .tables
+            .values()
+            .map(|table| table.file_id + 1)
+            .max()
+            .unwrap_or(CATALOG_FILE_ID + 1);
+        self.add_table(name, file_id, schema)?;
+        self.save(disk_manager)?;
+        Ok(file_id)
+    }
+
+    pub fn table(&self, name: &str) -> Result<&TableInfo, CatalogError> {
+        self.tables
+            .get(name)
+            .ok_or_else(|| CatalogError::TableNotFound(name.to_string()))
+    }
+
+    pub fn tables(&self) -> impl Iterator<Item = (&str, &TableInfo)> {
+        self.tables
+            .iter()
+            .map(|(name, table)| (name.as_str(), table))
+    }
+
+    // Layout: table count, then per table its name, file id and columns;
+    // each column is a name, a type tag, a varchar width and a nullable
+    // flag. Counts and lengths are u16 and ids and widths u32, little-endian.
+    fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        let put_str = |out: &mut Vec<u8>, s: &str| {
+            out.extend_from_slice(&(s.len() as u16).to_le_bytes());
+            out.extend_from_slice(s.as_bytes());
+        };
+
+        out.extend_from_slice(&(self.tables.len() as u16).to_le_bytes());
+        for (name, table) in &self.tables {
+            put_str(&mut out, name);
+            out.extend_from_slice(&table.file_id.to_le_bytes());
+            out.extend_from_slice(&(table.schema.columns.len() as u16).to_le_bytes());
+            for column in &table.schema.columns {
+                put_str(&mut out, &column.name);
+                let (tag, width) = match column.data_type {
+                    DataType::Integer => (0u8, 0),
+                    DataType::Varchar(width) => (1, width as u32),
+                    DataType::Boolean => (2, 0),
+                    DataType::Null => (3, 0),
+                };
+                out.push(tag);
+                out.extend_from_slice(&width.to_le_bytes());
+                out.push(column.nullable as u8);
+            }
+        }
+        out
+    }
+}
+
+fn decode(page: &Page) -> anyhow::Result<Catalog> {
+    let truncated = || anyhow::anyhow!("catalog page is truncated");
+    let len = page.read_bytes(PageHeader::LEN, 4).ok_or_else(truncated)?;
+    let len = u32::from_le_bytes(len.try_into()?) as usize;
+    let mut bytes = page
+        .read_bytes(PageHeader::LEN + 4, len)
+        .ok_or_else(truncated)?;
+
+    let mut take = |n: usize| -> anyhow::Result<&[u8]> {
+        if bytes.len() < n {
+            return Err(truncated());
+        }
+        let (head, rest) = bytes.split_at(n);
+        bytes = rest;
+        Ok(head)
+    };
+    fn u16_at(bytes: &[u8]) -> usize {
+        u16::from_le_bytes([bytes[0], bytes[1]]) as usize
+    }
+    fn u32_at(bytes: &[u8]) -> u32 {
+        u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+    }
+
+    let mut catalog = Catalog::new();
+    for _ in 0..u16_at(take(2)?) {
+        let name_len = u16_at(take(2)?);
+        let name = String::from_utf8(take(name_len)?.to_vec())?;
+        let file_id = u32_at(take(4)?);
+        let mut columns = Vec::new();
+        for _ in 0..u16_at(take(2)?) {
+            let name_len = u16_at(take(2)?);
+            let column_name = String::from_utf8(take(name_len)?.to_vec())?;
+            let tag = take(1)?[0];
+            let width = u32_at(take(4)?);
+            let data_type = match tag {
+                0 => DataType::Integer,
+                1 => DataType::Varchar(width as usize),
+                2 => DataType::Boolean,
+                3 => DataType::Null,
+                other => anyhow::bail!("unknown column type tag {} in catalog", other),
+            };
+            columns.push(Column {
+                name: column_name,
+                data_type,
+                nullable: take(1)?[0] != 0,
+            });
+        }
+        catalog.add_table(&name, file_id, Schema::new(columns))?;
+    }
+    Ok(catalog)
+}
+
+// The users(id, name) table in file 1 that most query tests run against.
+#[cfg(test)]
+pub(crate) fn users_catalog() -> Catalog {
+    let mut catalog = Catalog::new();
+    catalog
+        .add_table(
+            "users",
+            1,
+            Schema::new(vec![
+                Column {
+                    name: "id".to_string(),
+                    data_type: DataType::Integer,
+                    nullable: false,
+                },
+                Column {
+                    name: "name".to_string(),
+                    data_type: DataType::Varchar(255),
+                    nullable: true,
+                },
+            ]),
+        )
+        .unwrap();
+    catalog
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::disk::file_system::FsDiskManager;
+
+    #[test]
+    fn catalog_persists_across_managers() -> anyhow::Result<()> {
+        let td = tempfile::tempdir()?;
+        let mut dm = FsDiskManager::new(td.path())?;
+        assert_eq!(Catalog::load(&dm)?.tables().count(), 0);
+
+        let mut catalog = Catalog::new();
+        let users = users_catalog().table("users")?.schema.clone();
+        let flags = Schema::new(vec![Column {
+            name: "on".to_string(),
+            data_type: DataType::Boolean,
+            nullable: true,
+        }]);
+        assert_eq!(catalog.create_table(&mut dm, "users", users.clone())?, 1);
+        assert_eq!(catalog.create_table(&mut dm, "flags", flags.clone())?, 2);
+        assert!(
+            catalog
+                .create_table(&mut dm, "users", flags.clone())
+                .is_err()
+        );
+        drop(dm);
+
+        let dm = FsDiskManager::new(td.path())?;
+        let loaded = Catalog::load(&dm)?;
+        assert_eq!(
+            loaded.table("users")?,
+            &TableInfo {
+                file_id: 1,
+                schema: users
+            }
+        );
+        assert_eq!(
+            loaded.table("flags")?,
+            &TableInfo {
+                file_id: 2,
+                schema: flags
+            }
+        );
+        assert!(matches!(
+            loaded.table("missing"),
+            Err(CatalogError::TableNotFound(name)) if name == "missing"
+        ));
+        Ok(())
+    }
+}
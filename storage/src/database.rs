@@ -0,0 +1,156 @@
+use crate::catalog::Catalog;
+use crate::disk::disk_manager::DiskManager;
+use crate::query::cursor::Cursor;
+use crate::query::executor::{QueryExecutor, QueryResult};
+use crate::query::parser::parse_sql;
+use crate::query::planner::{PhysicalPlan, QueryPlanner};
+use crate::query::session::SessionConfig;
+use crate::query::types::{Row, Schema, Value};
+
+// An open database: the disk manager, the catalog of the tables on it and
+// the executor that runs statements against them. Statements that change
+// the catalog, such as SELECT INTO, update this copy along with the one on
+// disk, so callers never keep a catalog of their own.
+pub struct Database<D: DiskManager> {
+    catalog: Catalog,
+    disk_manager: D,
+    executor: QueryExecutor,
+}
+
+impl<D: DiskManager> Database<D> {
+    // Reads the catalog from `disk_manager`; a new database starts empty.
+    pub fn open(disk_manager: D, config: SessionConfig) -> anyhow::Result<Self> {
+        Ok(Self {
+            catalog: Catalog::load(&disk_manager)?,
+            disk_manager,
+            executor: QueryExecutor::with_config(config),
+        })
+    }
+
+    pub fn catalog(&self) -> &Catalog {
+        &self.catalog
+    }
+
+    pub fn config(&self) -> &SessionConfig {
+        self.executor.config()
+    }
+
+    pub fn disk_manager(&mut self) -> &mut D {
+        &mut self.disk_manager
+    }
+
+    pub fn into_disk_manager(self) -> D {
+        self.disk_manager
+    }
+
+    // See `QueryExecutor::register_function`.
+    pub fn register_function<F>(&mut self, name: &str, arity: usize, func: F)
+    where
+        F: Fn(&[Value]) -> anyhow::Result<Value> + Send + Sync + 'static,
+    {
+        self.executor.register_function(name, arity, func);
+    }
+
+    // Adds an empty table and saves the catalog; returns its file id.
+    pub fn create_table(&mut self, name: &str, schema: Schema) -> anyhow::Result<u32> {
+        self.catalog
+            .create_table(&mut self.disk_manager, name, schema)
+    }
+
+    pub fn plan(&self, sql: &str) -> anyhow::Result<PhysicalPlan> {
+        QueryPlanner::with_config(&self.catalog, self.config().clone()).plan(&parse_sql(sql)?)
+    }
+
+    // The schema `execute_plan` would return for `plan`.
+    pub fn describe(&self, plan: &PhysicalPlan) -> anyhow::Result<Schema> {
+        self.executor.describe(plan)
+    }
+
+    pub fn execute(&mut self, sql: &str) -> anyhow::Result<QueryResult> {
+        let plan = self.plan(sql)?;
+        self.execute_plan(plan)
+    }
+
+    pub fn execute_plan(&mut self, plan: PhysicalPlan) -> anyhow::Result<QueryResult> {
+        self.executor
+            .execute_with_catalog(plan, &mut self.catalog, &mut self.disk_manager)
+    }
+
+    // Hands each result row of `plan` to `on_row`; see
+    // `QueryExecutor::execute_each`. SELECT INTO, which only returns a row
+    // count, runs to completion first.
+    pub fn execute_each(
+        &mut self,
+        plan: PhysicalPlan,
+        mut on_row: impl FnMut(Row) -> anyhow::Result<()>,
+    ) -> anyhow::Result<()> {
+        if let PhysicalPlan::SelectInto { .. } = plan {
+            return self
+                .execute_plan(plan)?
+                .rows
+                .into_iter()
+                .try_for_each(on_row);
+        }
+        self.executor
+            .execute_each(plan, &mut self.disk_manager, &mut on_row)
+    }
+
+    pub fn open_cursor(&mut self, sql: &str) -> anyhow::Result<Cursor> {
+        Ok(Cursor::new(self.execute(sql)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::catalog::users_catalog;
+    use crate::disk::memory::MemDiskManager;
+
+    fn users_database() -> anyhow::Result<Database<MemDiskManager>> {
+        let mut db = Database::open(MemDiskManager::new(), SessionConfig::default())?;
+        let users = users_catalog().table("users")?.schema.clone();
+        db.create_table("users", users)?;
+        db.execute("INSERT INTO users VALUES (1, 'ann'), (2, 'bob'), (3, 'cy')")?;
+        Ok(db)
+    }
+
+    #[test]
+    fn select_into_is_visible_to_the_next_statement() -> anyhow::Result<()> {
+        let mut db = users_database()?;
+        db.execute("SELECT id, name INTO later FROM users WHERE id > 1")?;
+        assert_eq!(
+            db.execute("SELECT name FROM later")?.rows,
+            vec![
+                vec![Value::Varchar("bob".to_string())],
+                vec![Value::Varchar("cy".to_string())],
+            ]
+        );
+
+        // The table was saved as well
+        let reopened = Database::open(db.into_disk_manager(), SessionConfig::default())?;
+        assert_eq!(reopened.catalog().table("later")?.file_id, 2);
+        Ok(())
+    }
+
+    #[test]
+    fn execute_each_runs_every_kind_of_plan() -> anyhow::Result<()> {
+        let mut db = users_database()?;
+        let mut rows = Vec::new();
+        let plan = db.plan("SELECT id INTO ids FROM users")?;
+        db.execute_each(plan, |row| {
+            rows.push(row);
+            Ok(())
+        })?;
+        assert_eq!(rows, vec![vec![Value::Integer(3)]]);
+
+        rows = Vec::new();
+        let plan = db.plan("SELECT id FROM ids WHERE id <> 2")?;
+        assert_eq!(db.describe(&plan)?.columns[0].name, "id");
+        db.execute_each(plan, |row| {
+            rows.push(row);
+            Ok(())
+        })?;
+        assert_eq!(rows, vec![vec![Value::Integer(1)], vec![Value::Integer(3)]]);
+        Ok(())
+    }
+}
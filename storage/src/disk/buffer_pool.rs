@@ -0,0 +1,274 @@
+use crate::disk::disk_manager::DiskManager;
+use crate::page::{page_file::Page, page_id::PageId};
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+struct Frame {
+    page: Page,
+    dirty: bool,
+    last_used: u64,
+}
+
+#[derive(Default)]
+struct Frames {
+    frames: HashMap<PageId, Frame>,
+    // Incremented on every access; a frame's `last_used` orders it for LRU
+    clock: u64,
+}
+
+impl Frames {
+    fn touch(&mut self, pid: PageId) -> Option<&Frame> {
+        self.clock += 1;
+        let clock = self.clock;
+        let frame = self.frames.get_mut(&pid)?;
+        frame.last_used = clock;
+        Some(frame)
+    }
+
+    fn insert(&mut self, page: Page, dirty: bool) {
+        self.clock += 1;
+        let frame = Frame {
+            page,
+            dirty,
+            last_used: self.clock,
+        };
+        self.frames.insert(frame.page.page_id(), frame);
+    }
+
+    fn least_recently_used(&self, clean_only: bool) -> Option<PageId> {
+        self.frames
+            .iter()
+            .filter(|(_, frame)| !(clean_only && frame.dirty))
+            .min_by_key(|(_, frame)| frame.last_used)
+            .map(|(pid, _)| *pid)
+    }
+}
+
+// An LRU cache of pages in front of another disk manager. Reads are served
+// from the cache when they can be, and writes stay in the cache as dirty
+// pages until they are evicted or the pool is flushed.
+//
+// The pool never holds more than `capacity` pages. Reads only have `&self`
+// and so can only evict clean pages; if every cached page is dirty, a page
+// read on a miss is returned without being cached.
+//
+// Dirty pages are not written back when the pool is dropped. Call `flush`,
+// `sync` or `into_inner` first, or the writes still in the pool are lost.
+pub struct BufferPool<D> {
+    disk: D,
+    capacity: usize,
+    frames: RefCell<Frames>,
+}
+
+impl<D: DiskManager> BufferPool<D> {
+    // A capacity of 0 is treated as 1
+    pub fn new(disk: D, capacity: usize) -> Self {
+        Self {
+            disk,
+            capacity: capacity.max(1),
+            frames: RefCell::new(Frames::default()),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    // Number of pages currently cached
+    pub fn len(&self) -> usize {
+        self.frames.borrow().frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn disk(&self) -> &D {
+        &self.disk
+    }
+
+    // Flushes the dirty pages and returns the wrapped disk manager.
+    pub fn into_inner(mut self) -> anyhow::Result<D> {
+        self.flush()?;
+        Ok(self.disk)
+    }
+
+    // Returns a copy of the page, reading it from disk on a miss.
+    pub fn fetch_page(&self, pid: PageId) -> anyhow::Result<Page> {
+        let mut frames = self.frames.borrow_mut();
+        if let Some(frame) = frames.touch(pid) {
+            return Ok(frame.page.clone());
+        }
+        let page = self.disk.read_page(pid)?;
+        if frames.frames.len() >= self.capacity {
+            let Some(victim) = frames.least_recently_used(true) else {
+                return Ok(page);
+            };
+            frames.frames.remove(&victim);
+        }
+        frames.insert(page.clone(), false);
+        Ok(page)
+    }
+
+    // Makes `page` the cached copy of its page id. It is written to disk
+    // when evicted or flushed.
+    pub fn mark_dirty(&mut self, page: &Page) -> anyhow::Result<()> {
+        self.frames.get_mut().insert(page.clone(), true);
+        self.evict_to_capacity()
+    }
+
+    // Writes every dirty page back to disk. The pages stay cached.
+    pub fn flush(&mut self) -> anyhow::Result<()> {
+        let frames = self.frames.get_mut();
+        let mut dirty: Vec<&mut Frame> = frames
+            .frames
+            .values_mut()
+            .filter(|frame| frame.dirty)
+            .collect();
+        // Page order keeps the writes sequential within each file
+        dirty.sort_by_key(|frame| frame.page.page_id());
+        for frame in dirty {
+            self.disk.write_page(&frame.page)?;
+            frame.dirty = false;
+        }
+        self.evict_to_capacity()
+    }
+
+    fn evict_to_capacity(&mut self) -> anyhow::Result<()> {
+        let frames = self.frames.get_mut();
+        while frames.frames.len() > self.capacity {
+            let Some(victim) = frames.least_recently_used(false) else {
+                break;
+            };
+            let frame = frames.frames.remove(&victim).unwrap();
+            if frame.dirty {
+                self.disk.write_page(&frame.page)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<D: DiskManager> DiskManager for BufferPool<D> {
+    fn allocate_page(&mut self, file_id: u32) -> anyhow::Result<PageId> {
+        self.disk.allocate_page(file_id)
+    }
+
+    fn read_page(&self, pid: PageId) -> anyhow::Result<Page> {
+        self.fetch_page(pid)
+    }
+
+    fn write_page(&mut self, page: &Page) -> anyhow::Result<()> {
+        self.mark_dirty(page)
+    }
+
+    fn sync(&mut self) -> anyhow::Result<()> {
+        self.flush()?;
+        self.disk.sync()
+    }
+
+    fn page_count(&self, file_id: u32) -> anyhow::Result<u32> {
+        self.disk.page_count(file_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::disk::generic::GenericDiskManager;
+    use crate::heap::heap_page::HeapPage;
+    use std::io::Cursor;
+
+    type MemDisk = GenericDiskManager<Cursor<Vec<u8>>>;
+
+    fn disk_with_pages(count: usize) -> anyhow::Result<(MemDisk, Vec<PageId>)> {
+        let mut dm = GenericDiskManager::new(|_| Ok(Cursor::new(Vec::new())));
+        let mut pids = Vec::new();
+        for i in 0..count {
+            let mut hp = HeapPage::allocate(&mut dm, 1)?;
+            hp.insert_tuple(format!("page {}", i).as_bytes())?;
+            dm.write_page(&hp.page)?;
+            pids.push(hp.page.page_id());
+        }
+        dm.reset_stats();
+        Ok((dm, pids))
+    }
+
+    fn first_tuple(page: Page) -> Vec<u8> {
+        HeapPage { page }.read_tuple(0).unwrap().to_vec()
+    }
+
+    #[test]
+    fn cache_hits_do_not_read_from_disk() -> anyhow::Result<()> {
+        let (dm, pids) = disk_with_pages(3)?;
+        let pool = BufferPool::new(dm, 2);
+
+        pool.fetch_page(pids[0])?;
+        pool.fetch_page(pids[1])?;
+        pool.fetch_page(pids[0])?;
+        pool.fetch_page(pids[1])?;
+        assert_eq!(pool.disk().stats().page_reads, 2);
+
+        // pids[0] is the least recently used, so it makes room for pids[2]
+        pool.fetch_page(pids[1])?;
+        pool.fetch_page(pids[2])?;
+        assert_eq!(pool.len(), 2);
+        pool.fetch_page(pids[1])?;
+        assert_eq!(pool.disk().stats().page_reads, 3);
+        assert_eq!(first_tuple(pool.fetch_page(pids[0])?), b"page 0");
+        assert_eq!(pool.disk().stats().page_reads, 4);
+        Ok(())
+    }
+
+    #[test]
+    fn dirty_pages_are_written_back_on_eviction() -> anyhow::Result<()> {
+        let (dm, pids) = disk_with_pages(3)?;
+        let mut pool = BufferPool::new(dm, 2);
+
+        let mut hp = HeapPage {
+            page: pool.fetch_page(pids[0])?,
+        };
+        hp.insert_tuple(b"more")?;
+        pool.mark_dirty(&hp.page)?;
+        assert_eq!(pool.disk().stats().page_writes, 0);
+        assert_eq!(pool.fetch_page(pids[0])?.page_id(), pids[0]);
+
+        // A read cannot write back, so it leaves the dirty page cached
+        pool.fetch_page(pids[1])?;
+        pool.fetch_page(pids[2])?;
+        assert_eq!(pool.disk().stats().page_writes, 0);
+        assert_eq!(pool.len(), 2);
+
+        // Writing a third page evicts the dirty one, which goes to disk
+        let page = pool.fetch_page(pids[2])?;
+        pool.fetch_page(pids[1])?;
+        pool.write_page(&page)?;
+        assert_eq!(pool.disk().stats().page_writes, 1);
+        let on_disk = HeapPage {
+            page: pool.disk().read_page(pids[0])?,
+        };
+        assert_eq!(on_disk.read_tuple(1).unwrap(), b"more");
+
+        let dm = pool.into_inner()?;
+        assert_eq!(dm.stats().page_writes, 2);
+        Ok(())
+    }
+
+    #[test]
+    fn reads_never_grow_a_pool_of_dirty_pages() -> anyhow::Result<()> {
+        let (dm, pids) = disk_with_pages(3)?;
+        let mut pool = BufferPool::new(dm, 2);
+        for pid in &pids[..2] {
+            let page = pool.fetch_page(*pid)?;
+            pool.write_page(&page)?;
+        }
+
+        // Nothing clean can make room, so pids[2] is read but not cached
+        assert_eq!(first_tuple(pool.fetch_page(pids[2])?), b"page 2");
+        assert_eq!(pool.len(), 2);
+        pool.fetch_page(pids[2])?;
+        assert_eq!(pool.disk().stats().page_reads, 4);
+        assert_eq!(pool.disk().stats().page_writes, 0);
+        Ok(())
+    }
+}
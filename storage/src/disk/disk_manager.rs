@@ -1,9 +1,27 @@
 use crate::page::page_file::Page;
-use crate::page::page_id::PageId;
+use crate::page::page_id::{PageFlags, PageId};
 
 pub trait DiskManager {
     fn allocate_page(&mut self, file_id: u32) -> anyhow::Result<PageId>;
+
+    // Allocates a page that already carries a valid header and checksum.
+    fn allocate_initialized_page(
+        &mut self,
+        file_id: u32,
+        flags: PageFlags,
+    ) -> anyhow::Result<Page> {
+        let pid = self.allocate_page(file_id)?;
+        let page = Page::new(pid, flags);
+        self.write_page(&page)?;
+        Ok(page)
+    }
     fn read_page(&self, pid: PageId) -> anyhow::Result<Page>;
     fn write_page(&mut self, page: &Page) -> anyhow::Result<()>;
     fn sync(&mut self) -> anyhow::Result<()>;
+    fn page_count(&self, file_id: u32) -> anyhow::Result<u32>;
+
+    fn page_ids(&self, file_id: u32) -> anyhow::Result<impl Iterator<Item = PageId>> {
+        let count = self.page_count(file_id)?;
+        Ok((0..count).map(move |page_no| PageId::new(file_id, page_no)))
+    }
 }
@@ -1,6 +1,13 @@
 use crate::disk::disk_manager::DiskManager;
-use crate::page::{constants::PAGE_SIZE, page_file::Page, page_id::PageId};
+use crate::disk::stats::{IoCounters, IoStats};
+use crate::page::{
+    constants::{FORMAT_VERSION, PAGE_SIZE},
+    page_file::Page,
+    page_header::PageHeader,
+    page_id::{PageFlags, PageId},
+};
 use anyhow::Context;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::fs;
 use std::fs::{File, OpenOptions};
 use std::io::{Read, Seek, SeekFrom, Write};
@@ -10,85 +17,423 @@ use std::path::{Path, PathBuf};
 pub enum DiskError {
     #[error("checksum mismatch for {0:?}")]
     Checksum(PageId),
+    #[error("torn write detected for {0:?}")]
+    TornPage(PageId),
+    #[error("{pid:?} has format version {version}, expected {FORMAT_VERSION}")]
+    UnsupportedFormat { pid: PageId, version: u16 },
+    #[error("database is open read-only")]
+    ReadOnly,
+    #[error("storage limit exceeded: file {file_id} is limited to {max_pages} pages")]
+    StorageLimit { file_id: u32, max_pages: u32 },
+}
+
+// Gives `page` the generation after `stored`, the one of the copy already in
+// storage, or after its own if nothing is stored yet. Callers keep the page
+// they passed to `write_page`, so its own generation may be stale.
+pub(crate) fn next_generation(stored: Option<u32>, page: &mut Page) {
+    page.set_generation(stored.unwrap_or(page.generation()).wrapping_add(1));
+}
+
+// The generation in the header of the page stored at `offset` of `stream`, or
+// None if the stream ends first. The page is not validated: a torn page has
+// to be overwritable.
+pub(crate) fn stored_generation<T: Read + Seek>(
+    stream: &mut T,
+    offset: u64,
+) -> anyhow::Result<Option<u32>> {
+    let mut buf = [0u8; 4];
+    stream.seek(SeekFrom::Start(
+        offset + PageHeader::GENERATION_OFFSET as u64,
+    ))?;
+    match stream.read_exact(&mut buf) {
+        Ok(()) => Ok(Some(u32::from_le_bytes(buf))),
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+// Rejects a page just read from storage if it was torn or is corrupt, or was
+// written in another format. The version is only trusted on an intact page.
+pub(crate) fn validate_page(pid: PageId, page: Page) -> anyhow::Result<Page> {
+    let version = page.format_version();
+    if version != FORMAT_VERSION && page.verify_checksum() {
+        return Err(DiskError::UnsupportedFormat { pid, version })
+            .with_context(|| format!("while reading {:?}", pid));
+    }
+    if page.is_torn() {
+        return Err(DiskError::TornPage(pid)).with_context(|| format!("while reading {:?}", pid));
+    }
+    if !page.verify_checksum() {
+        return Err(DiskError::Checksum(pid)).with_context(|| format!("while reading {:?}", pid));
+    }
+    Ok(page)
 }
 
 pub struct FsDiskManager {
     base: PathBuf,
+    // Pages written since the last sync when write buffering is enabled
+    write_buffer: Option<BTreeMap<PageId, Page>>,
+    // Contents of each file when opened from compressed archives; writes are
+    // refused in this mode
+    read_only_files: Option<HashMap<u32, Vec<u8>>>,
+    // Allocation fails once a file holds this many pages
+    max_pages_per_file: Option<u32>,
+    // Files written without the write buffer since the last sync
+    unsynced_files: BTreeSet<u32>,
+    counters: IoCounters,
 }
 
 impl FsDiskManager {
     pub fn new<P: AsRef<Path>>(base: P) -> anyhow::Result<Self> {
         let base = base.as_ref();
         fs::create_dir_all(base).with_context(|| format!("creating data dir {:?}", base))?;
+        let dm = Self {
+            base: base.to_path_buf(),
+            write_buffer: None,
+            read_only_files: None,
+            max_pages_per_file: None,
+            unsynced_files: BTreeSet::new(),
+            counters: IoCounters::default(),
+        };
+        dm.truncate_partial_pages()?;
+        Ok(dm)
+    }
+
+    // Pages are only ever appended whole, so a partial page at the end of a
+    // data file is left by a crash while extending it and was never handed
+    // out. Cutting it off keeps the pages allocated after it aligned. This is
+    // the only place a data file shrinks; `MmapDiskManager` never maps a
+    // partial page, so the cut cannot pull pages out from under a mapping.
+    fn truncate_partial_pages(&self) -> anyhow::Result<()> {
+        let entries =
+            fs::read_dir(&self.base).with_context(|| format!("reading {:?}", self.base))?;
+        for entry in entries {
+            let path = entry?.path();
+            let is_data_file = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| name.strip_prefix("base_")?.strip_suffix(".db"))
+                .is_some_and(|id| id.parse::<u32>().is_ok());
+            if !is_data_file {
+                continue;
+            }
+            let len = fs::metadata(&path)?.len();
+            let partial = len % PAGE_SIZE as u64;
+            if partial != 0 {
+                let file = OpenOptions::new().write(true).open(&path)?;
+                file.set_len(len - partial)
+                    .and_then(|_| file.sync_all())
+                    .with_context(|| format!("truncating partial page of {:?}", path))?;
+            }
+        }
+        Ok(())
+    }
+
+    // Opens a directory of gzip-compressed `base_<file_id>.db.gz` files
+    // read-only. Every file is decompressed into memory up front.
+    pub fn open_gzip<P: AsRef<Path>>(base: P) -> anyhow::Result<Self> {
+        let base = base.as_ref();
+        let mut files = HashMap::new();
+        for entry in fs::read_dir(base).with_context(|| format!("reading {:?}", base))? {
+            let path = entry?.path();
+            let Some(file_id) = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| name.strip_prefix("base_")?.strip_suffix(".db.gz"))
+                .and_then(|id| id.parse::<u32>().ok())
+            else {
+                continue;
+            };
+
+            let mut contents = Vec::new();
+            flate2::read::GzDecoder::new(File::open(&path)?)
+                .read_to_end(&mut contents)
+                .with_context(|| format!("decompressing {:?}", path))?;
+            if contents.len() % PAGE_SIZE != 0 {
+                anyhow::bail!(
+                    "{:?} is not a whole number of pages ({} bytes)",
+                    path,
+                    contents.len()
+                );
+            }
+            files.insert(file_id, contents);
+        }
+
         Ok(Self {
             base: base.to_path_buf(),
+            write_buffer: None,
+            read_only_files: Some(files),
+            max_pages_per_file: None,
+            unsynced_files: BTreeSet::new(),
+            counters: IoCounters::default(),
         })
     }
 
-    fn file_path(&self, file_id: u32) -> PathBuf {
+    pub fn stats(&self) -> IoStats {
+        self.counters.snapshot()
+    }
+
+    pub fn reset_stats(&self) {
+        self.counters.reset();
+    }
+
+    fn check_writable(&self) -> anyhow::Result<()> {
+        if self.read_only_files.is_some() {
+            return Err(DiskError::ReadOnly.into());
+        }
+        Ok(())
+    }
+
+    // Like `new`, but `write_page` only buffers pages in memory. Buffered
+    // pages are visible to `read_page` immediately and are written out,
+    // coalesced into contiguous runs, on the next `sync`. Pages that were
+    // never synced are lost when the manager is dropped.
+    pub fn new_buffered<P: AsRef<Path>>(base: P) -> anyhow::Result<Self> {
+        let mut dm = Self::new(base)?;
+        dm.write_buffer = Some(BTreeMap::new());
+        Ok(dm)
+    }
+
+    // Caps every file at `max_pages` pages; allocating past the cap fails
+    // with `DiskError::StorageLimit` and leaves the file unchanged.
+    pub fn with_max_pages_per_file(mut self, max_pages: u32) -> Self {
+        self.max_pages_per_file = Some(max_pages);
+        self
+    }
+
+    // Gives every page of `file_id` whose checksum does not match its
+    // contents a freshly computed one, and returns how many pages were
+    // rewritten. This is an escape hatch for recovering files that were
+    // edited on purpose or written without checksums: it accepts whatever
+    // bytes are on disk, so it also hides real corruption. Torn pages are
+    // left as they are.
+    pub fn recompute_checksums(&mut self, file_id: u32) -> anyhow::Result<u32> {
+        self.check_writable()?;
+        let page_count = self.page_count(file_id)?;
+        if page_count == 0 {
+            return Ok(0);
+        }
+        let path = self.file_path(file_id);
+        let mut file = self.open_rw(&path)?;
+
+        let mut fixed = 0;
+        for page_no in 0..page_count {
+            let off = page_no as u64 * PAGE_SIZE as u64;
+            let mut page = Page {
+                buf: [0u8; PAGE_SIZE],
+            };
+            file.seek(SeekFrom::Start(off))?;
+            file.read_exact(&mut page.buf)?;
+            self.counters.record_read(PAGE_SIZE);
+            if page.is_torn() || page.verify_checksum() {
+                continue;
+            }
+
+            page.recompute_checksum();
+            file.seek(SeekFrom::Start(off))?;
+            file.write_all(&page.buf)?;
+            self.counters.record_write(1, PAGE_SIZE);
+            fixed += 1;
+        }
+        file.sync_data()
+            .with_context(|| format!("syncing {:?}", path))?;
+        Ok(fixed)
+    }
+
+    // Page number the next allocation in `file` would get.
+    fn next_page_no(&self, file_id: u32, file: &File) -> anyhow::Result<u32> {
+        let page_no = (file.metadata()?.len() / PAGE_SIZE as u64) as u32;
+        if let Some(max_pages) = self.max_pages_per_file
+            && page_no >= max_pages
+        {
+            return Err(DiskError::StorageLimit { file_id, max_pages }.into());
+        }
+        Ok(page_no)
+    }
+
+    pub(crate) fn file_path(&self, file_id: u32) -> PathBuf {
         self.base.join(format!("base_{}.db", file_id))
     }
 
     fn open_rw(&self, path: &Path) -> anyhow::Result<File> {
         Ok(OpenOptions::new()
             .create(true)
+            .truncate(false)
             .read(true)
             .write(true)
             .open(path)?)
     }
+
+    fn write_at(&self, pid: PageId, bytes: &[u8]) -> anyhow::Result<File> {
+        let path = self.file_path(pid.file_id());
+        let mut file = self.open_rw(&path)?;
+
+        let off = (pid.page_no() as u64) * (PAGE_SIZE as u64);
+        file.seek(SeekFrom::Start(off))?;
+        file.write_all(bytes)?;
+        Ok(file)
+    }
+
+    fn flush_write_buffer(&mut self) -> anyhow::Result<()> {
+        let Some(mut buffer) = self.write_buffer.take() else {
+            return Ok(());
+        };
+        let result = self.write_runs(&mut buffer);
+        self.write_buffer = Some(buffer);
+        result
+    }
+
+    // Writes out the buffered pages. A run of pages leaves the buffer only
+    // once it has been written and synced, so after an error the pages not
+    // yet on disk are still buffered and the next sync retries them.
+    fn write_runs(&self, buffer: &mut BTreeMap<PageId, Page>) -> anyhow::Result<()> {
+        while let Some(&start) = buffer.keys().next() {
+            // PageIds order by file and then page number, so runs of adjacent
+            // pages can be written with a single call.
+            let mut run = Vec::new();
+            let mut count = 0;
+            for (pid, page) in buffer.range(start..) {
+                if pid.file_id() != start.file_id()
+                    || start.page_no().checked_add(count) != Some(pid.page_no())
+                {
+                    break;
+                }
+                run.extend_from_slice(&page.buf);
+                count += 1;
+            }
+            let last = PageId::new(start.file_id(), start.page_no() + count - 1);
+
+            let file = self
+                .write_at(start, &run)
+                .with_context(|| format!("flushing pages {:?}..={:?}", start, last))?;
+            file.sync_data()?;
+            self.counters.record_write(run.len() / PAGE_SIZE, run.len());
+            for page_no in start.page_no()..=last.page_no() {
+                buffer.remove(&PageId::new(start.file_id(), page_no));
+            }
+        }
+        Ok(())
+    }
 }
 
 impl DiskManager for FsDiskManager {
     fn allocate_page(&mut self, file_id: u32) -> anyhow::Result<PageId> {
+        self.check_writable()?;
         let path = self.file_path(file_id);
         let mut file = self.open_rw(&path)?;
-        let len = file.metadata()?.len() as usize;
-        let page_no = (len / PAGE_SIZE) as u32;
+        let page_no = self.next_page_no(file_id, &file)?;
 
         file.seek(SeekFrom::End(0))?;
         file.write_all(&vec![0u8; PAGE_SIZE])?;
+        self.unsynced_files.insert(file_id);
+        self.counters.record_write(1, PAGE_SIZE);
         Ok(PageId::new(file_id, page_no))
     }
 
-    fn read_page(&self, pid: PageId) -> anyhow::Result<Page> {
-        let path = self.file_path(pid.file_id());
+    // Appends the initialized page directly instead of extending the file
+    // with zeroes and then overwriting it.
+    fn allocate_initialized_page(
+        &mut self,
+        file_id: u32,
+        flags: PageFlags,
+    ) -> anyhow::Result<Page> {
+        self.check_writable()?;
+        let path = self.file_path(file_id);
         let mut file = self.open_rw(&path)?;
-        let mut buf = [0u8; PAGE_SIZE];
+        let page_no = self.next_page_no(file_id, &file)?;
+        let page = Page::new(PageId::new(file_id, page_no), flags);
 
-        let off = (pid.page_no() as u64) * (PAGE_SIZE as u64);
-        file.seek(SeekFrom::Start(off))?;
-        file.read_exact(&mut buf)?;
+        file.seek(SeekFrom::End(0))?;
+        file.write_all(&page.buf)?;
+        self.unsynced_files.insert(file_id);
+        self.counters.record_write(1, PAGE_SIZE);
+        Ok(page)
+    }
+
+    fn read_page(&self, pid: PageId) -> anyhow::Result<Page> {
+        if let Some(page) = self.write_buffer.as_ref().and_then(|b| b.get(&pid)) {
+            self.counters.record_buffer_hit();
+            return Ok(page.clone());
+        }
 
-        let p = Page { buf };
-        if !p.verify_checksum() {
-            return Err(DiskError::Checksum(pid))
-                .with_context(|| format!("while reading {:?}", pid));
+        let mut buf = [0u8; PAGE_SIZE];
+        let off = (pid.page_no() as u64) * (PAGE_SIZE as u64);
+        if let Some(files) = self.read_only_files.as_ref() {
+            let page = files
+                .get(&pid.file_id())
+                .and_then(|contents| contents.get(off as usize..off as usize + PAGE_SIZE))
+                .ok_or_else(|| anyhow::anyhow!("{:?} does not exist", pid))?;
+            buf.copy_from_slice(page);
+        } else {
+            let path = self.file_path(pid.file_id());
+            let mut file = self.open_rw(&path)?;
+            file.seek(SeekFrom::Start(off))?;
+            file.read_exact(&mut buf)?;
         }
-        Ok(p)
+
+        self.counters.record_read(PAGE_SIZE);
+        validate_page(pid, Page { buf })
     }
 
     fn write_page(&mut self, page: &Page) -> anyhow::Result<()> {
+        self.check_writable()?;
+        let mut page = page.clone();
         let pid = page.page_id();
-        let path = self.file_path(pid.file_id());
-        let mut file = self.open_rw(&path)?;
+        let stored = match self.write_buffer.as_ref().and_then(|b| b.get(&pid)) {
+            Some(buffered) => Some(buffered.generation()),
+            None => match File::open(self.file_path(pid.file_id())) {
+                Ok(mut file) => {
+                    stored_generation(&mut file, pid.page_no() as u64 * PAGE_SIZE as u64)?
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+                Err(e) => return Err(e.into()),
+            },
+        };
+        next_generation(stored, &mut page);
+
+        if let Some(buffer) = self.write_buffer.as_mut() {
+            buffer.insert(page.page_id(), page);
+            return Ok(());
+        }
 
-        let off = (pid.page_no() as u64) * (PAGE_SIZE as u64);
-        file.seek(SeekFrom::Start(off))?;
-        file.write_all(&page.clone().buf)?;
+        self.write_at(page.page_id(), &page.buf)?;
+        self.unsynced_files.insert(page.page_id().file_id());
+        self.counters.record_write(1, PAGE_SIZE);
         Ok(())
     }
 
     fn sync(&mut self) -> anyhow::Result<()> {
+        if self.read_only_files.is_some() {
+            return Ok(());
+        }
+        self.flush_write_buffer()?;
+        while let Some(&file_id) = self.unsynced_files.first() {
+            self.open_rw(&self.file_path(file_id))?.sync_data()?;
+            self.unsynced_files.remove(&file_id);
+        }
         let directory = File::open(&self.base)?;
         directory.sync_all()?;
         Ok(())
     }
+
+    fn page_count(&self, file_id: u32) -> anyhow::Result<u32> {
+        if let Some(files) = self.read_only_files.as_ref() {
+            return Ok(files
+                .get(&file_id)
+                .map_or(0, |c| (c.len() / PAGE_SIZE) as u32));
+        }
+        let path = self.file_path(file_id);
+        match fs::metadata(&path) {
+            Ok(meta) => Ok((meta.len() / PAGE_SIZE as u64) as u32),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(0),
+            Err(e) => Err(e).with_context(|| format!("reading metadata of {:?}", path)),
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::page::page_id::PageFlags;
     use tempfile::TempDir;
 
     #[test]
@@ -128,6 +473,34 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn allocation_stops_at_page_limit() -> anyhow::Result<()> {
+        let temp_directory = TempDir::new()?;
+        let mut dm = FsDiskManager::new(temp_directory.path())?.with_max_pages_per_file(2);
+
+        dm.allocate_page(1)?;
+        dm.allocate_initialized_page(1, PageFlags::Heap)?;
+        for err in [
+            dm.allocate_page(1).unwrap_err(),
+            dm.allocate_initialized_page(1, PageFlags::Heap)
+                .unwrap_err(),
+        ] {
+            assert!(matches!(
+                err.downcast_ref::<DiskError>(),
+                Some(DiskError::StorageLimit {
+                    file_id: 1,
+                    max_pages: 2
+                })
+            ));
+        }
+        assert_eq!(dm.page_count(1)?, 2);
+
+        // The limit is per file
+        assert_eq!(dm.allocate_page(2)?.page_no(), 0);
+
+        Ok(())
+    }
+
     #[test]
     fn page_write_read_round_trip() -> anyhow::Result<()> {
         let temp_directory = TempDir::new()?;
@@ -180,6 +553,161 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn recompute_checksums_repairs_edited_pages() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let mut dm = FsDiskManager::new(temp_dir.path())?;
+        let edited = dm.allocate_initialized_page(5, PageFlags::Heap)?.page_id();
+        let intact = dm.allocate_initialized_page(5, PageFlags::Heap)?.page_id();
+
+        let mut file = OpenOptions::new()
+            .write(true)
+            .open(temp_dir.path().join("base_5.db"))?;
+        file.seek(SeekFrom::Start(100))?;
+        file.write_all(&[0xFF])?;
+        drop(file);
+        assert!(dm.read_page(edited).is_err());
+
+        assert_eq!(dm.recompute_checksums(5)?, 1);
+        let page = dm.read_page(edited)?;
+        assert_eq!(page.buf[100], 0xFF);
+        assert!(dm.read_page(intact).is_ok());
+
+        assert_eq!(dm.recompute_checksums(5)?, 0);
+        assert_eq!(dm.recompute_checksums(6)?, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn partial_trailing_page_is_truncated_on_open() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let mut dm = FsDiskManager::new(temp_dir.path())?;
+        let pid = dm.allocate_initialized_page(1, PageFlags::Heap)?.page_id();
+        drop(dm);
+
+        // A crash while appending the second page leaves part of it behind
+        let path = temp_dir.path().join("base_1.db");
+        let mut file = OpenOptions::new().append(true).open(&path)?;
+        file.write_all(&[0xAB; 100])?;
+        drop(file);
+
+        let mut dm = FsDiskManager::new(temp_dir.path())?;
+        assert_eq!(fs::metadata(&path)?.len(), PAGE_SIZE as u64);
+        assert_eq!(dm.page_count(1)?, 1);
+        assert!(dm.read_page(pid)?.verify_checksum());
+
+        let next = dm.allocate_initialized_page(1, PageFlags::Heap)?.page_id();
+        assert_eq!(next.page_no(), 1);
+        assert!(dm.read_page(next)?.verify_checksum());
+        Ok(())
+    }
+
+    #[test]
+    fn rewriting_the_same_page_advances_the_generation() -> anyhow::Result<()> {
+        for buffered in [false, true] {
+            let temp_dir = TempDir::new()?;
+            let mut dm = if buffered {
+                FsDiskManager::new_buffered(temp_dir.path())?
+            } else {
+                FsDiskManager::new(temp_dir.path())?
+            };
+
+            // The caller's copy keeps generation 0 across both writes
+            let page = dm.allocate_initialized_page(2, PageFlags::Heap)?;
+            dm.write_page(&page)?;
+            assert_eq!(dm.read_page(page.page_id())?.generation(), 1);
+            dm.write_page(&page)?;
+            dm.sync()?;
+            assert_eq!(page.generation(), 0);
+            assert_eq!(dm.read_page(page.page_id())?.generation(), 2);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn generation_carries_on_after_reopening() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let mut dm = FsDiskManager::new(temp_dir.path())?;
+        let page = dm.allocate_initialized_page(2, PageFlags::Heap)?;
+        dm.write_page(&page)?;
+        dm.write_page(&page)?;
+        drop(dm);
+
+        let mut dm = FsDiskManager::new(temp_dir.path())?;
+        dm.write_page(&page)?;
+        assert_eq!(dm.read_page(page.page_id())?.generation(), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn pages_of_another_format_are_rejected() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let mut dm = FsDiskManager::new(temp_dir.path())?;
+        let pid = dm.allocate_page(6)?;
+        let mut pg = Page::new(pid, PageFlags::Heap);
+        let mut hdr = pg.header();
+        hdr.format_version = 0;
+        pg.write_header(&hdr);
+        pg.recompute_checksum();
+        dm.write_at(pid, &pg.buf)?;
+
+        let error = dm.read_page(pid).unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<DiskError>(),
+            Some(DiskError::UnsupportedFormat { pid: p, version: 0 }) if *p == pid
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn torn_write_detected() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let mut dm = FsDiskManager::new(temp_dir.path())?;
+
+        let pid = dm.allocate_page(6)?;
+        dm.write_page(&Page::new(pid, PageFlags::Heap))?;
+        let old = dm.read_page(pid)?;
+        assert_eq!(old.generation(), 1);
+
+        let mut pg = old.clone();
+        pg.buf[5000] = 0xAA;
+        pg.recompute_checksum();
+        dm.write_page(&pg)?;
+        let new = dm.read_page(pid)?;
+        assert_eq!(new.generation(), 2);
+
+        // Simulate a crash that persisted only the first half of the new write
+        let mut torn = new.buf;
+        torn[PAGE_SIZE / 2..].copy_from_slice(&old.buf[PAGE_SIZE / 2..]);
+        dm.write_at(pid, &torn)?;
+
+        let error = dm.read_page(pid).unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<DiskError>(),
+            Some(DiskError::TornPage(p)) if *p == pid
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn initialized_page_allocation() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let mut dm = FsDiskManager::new(temp_dir.path())?;
+
+        let first = dm.allocate_page(6)?;
+        let page = dm.allocate_initialized_page(6, PageFlags::Meta)?;
+        assert_eq!(page.page_id(), PageId::new(6, first.page_no() + 1));
+        assert_eq!(dm.page_count(6)?, 2);
+
+        let read = dm.read_page(page.page_id())?;
+        assert!(read.verify_checksum());
+        assert_eq!(read.buf, page.buf);
+        assert_eq!(read.header().page_flags, PageFlags::Meta as u16);
+
+        Ok(())
+    }
+
     #[test]
     fn multiple_pages_same_file() -> anyhow::Result<()> {
         let temp_dir = TempDir::new()?;
@@ -236,6 +764,107 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn buffered_writes_visible_before_sync_and_durable_after() -> anyhow::Result<()> {
+        let temp_directory = TempDir::new()?;
+        let mut dm = FsDiskManager::new_buffered(temp_directory.path())?;
+
+        let mut pids = Vec::new();
+        for i in 0..4u8 {
+            let pid = dm.allocate_page(8)?;
+            let mut pg = Page::new(pid, PageFlags::Heap);
+            pg.buf[100] = i;
+            pg.recompute_checksum();
+            dm.write_page(&pg)?;
+            pids.push(pid);
+        }
+
+        let mut rewritten = Page::new(pids[1], PageFlags::Heap);
+        rewritten.buf[100] = 0xEE;
+        rewritten.recompute_checksum();
+        dm.write_page(&rewritten)?;
+
+        assert_eq!(dm.read_page(pids[0])?.buf[100], 0);
+        assert_eq!(dm.read_page(pids[1])?.buf[100], 0xEE);
+
+        let unbuffered = FsDiskManager::new(temp_directory.path())?;
+        assert!(unbuffered.read_page(pids[0]).is_err());
+
+        dm.sync()?;
+        drop(dm);
+
+        let reopened = FsDiskManager::new(temp_directory.path())?;
+        assert_eq!(reopened.read_page(pids[0])?.buf[100], 0);
+        assert_eq!(reopened.read_page(pids[1])?.buf[100], 0xEE);
+        assert_eq!(reopened.read_page(pids[2])?.buf[100], 2);
+        assert_eq!(reopened.read_page(pids[3])?.buf[100], 3);
+
+        Ok(())
+    }
+
+    #[test]
+    fn failed_flush_keeps_unwritten_pages_buffered() -> anyhow::Result<()> {
+        let temp_directory = TempDir::new()?;
+        let mut dm = FsDiskManager::new_buffered(temp_directory.path())?;
+        let pids = [PageId::new(1, 0), PageId::new(1, 1), PageId::new(2, 0)];
+        for (i, pid) in pids.iter().enumerate() {
+            let mut pg = Page::new(*pid, PageFlags::Heap);
+            pg.buf[100] = i as u8;
+            pg.recompute_checksum();
+            dm.write_page(&pg)?;
+        }
+
+        // A directory where file 2 should be makes its write fail
+        let blocker = dm.file_path(2);
+        fs::create_dir(&blocker)?;
+        assert!(dm.sync().is_err());
+
+        // File 1 was flushed before the failure; file 2 is still buffered
+        assert_eq!(fs::metadata(dm.file_path(1))?.len(), 2 * PAGE_SIZE as u64);
+        assert_eq!(dm.stats().page_writes, 2);
+        assert_eq!(dm.read_page(pids[2])?.buf[100], 2);
+
+        fs::remove_dir(&blocker)?;
+        dm.sync()?;
+        drop(dm);
+        let reopened = FsDiskManager::new(temp_directory.path())?;
+        for (i, pid) in pids.iter().enumerate() {
+            assert_eq!(reopened.read_page(*pid)?.buf[100], i as u8);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn io_stats_track_page_traffic() -> anyhow::Result<()> {
+        let temp_directory = TempDir::new()?;
+        let mut dm = FsDiskManager::new_buffered(temp_directory.path())?;
+
+        let pid = dm.allocate_page(1)?;
+        dm.write_page(&Page::new(pid, PageFlags::Heap))?;
+        dm.read_page(pid)?;
+        assert_eq!(
+            dm.stats(),
+            IoStats {
+                page_writes: 1,
+                buffer_hits: 1,
+                bytes_written: PAGE_SIZE as u64,
+                ..IoStats::default()
+            }
+        );
+
+        dm.sync()?;
+        dm.read_page(pid)?;
+        dm.read_page(pid)?;
+        let stats = dm.stats();
+        assert_eq!(stats.page_writes, 2);
+        assert_eq!(stats.page_reads, 2);
+        assert_eq!(stats.bytes_read, 2 * PAGE_SIZE as u64);
+
+        dm.reset_stats();
+        assert_eq!(dm.stats(), IoStats::default());
+        Ok(())
+    }
+
     #[test]
     fn file_path_generation() -> anyhow::Result<()> {
         let temp_directory = TempDir::new()?;
@@ -251,6 +880,32 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn page_count_matches_allocations() -> anyhow::Result<()> {
+        let temp_directory = TempDir::new()?;
+        let mut dm = FsDiskManager::new(temp_directory.path())?;
+
+        assert_eq!(dm.page_count(3)?, 0);
+        assert!(!temp_directory.path().join("base_3.db").exists());
+
+        for _ in 0..5 {
+            dm.allocate_page(3)?;
+        }
+        dm.allocate_page(4)?;
+
+        assert_eq!(dm.page_count(3)?, 5);
+        assert_eq!(dm.page_count(4)?, 1);
+
+        let pids: Vec<PageId> = dm.page_ids(3)?.collect();
+        assert_eq!(pids.len(), 5);
+        for (i, pid) in pids.iter().enumerate() {
+            assert_eq!(pid.file_id(), 3);
+            assert_eq!(pid.page_no(), i as u32);
+        }
+
+        Ok(())
+    }
+
     #[test]
     fn error_handling() -> anyhow::Result<()> {
         let temp_directory = TempDir::new()?;
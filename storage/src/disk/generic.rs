@@ -0,0 +1,178 @@
+use crate::disk::disk_manager::DiskManager;
+use crate::disk::file_system::{next_generation, stored_generation, validate_page};
+use crate::disk::stats::{IoCounters, IoStats};
+use crate::page::{constants::PAGE_SIZE, page_file::Page, page_id::PageId};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::{Read, Seek, SeekFrom, Write};
+
+type OpenFn<T> = Box<dyn FnMut(u32) -> anyhow::Result<T>>;
+
+// A disk manager over arbitrary `Read + Write + Seek` streams, one per
+// file id, laid out exactly like the files `FsDiskManager` writes. Streams
+// for new file ids come from the `open` callback given to `new`.
+pub struct GenericDiskManager<T> {
+    // Reads need to seek, so the streams sit behind RefCells
+    files: HashMap<u32, RefCell<T>>,
+    open: OpenFn<T>,
+    counters: IoCounters,
+}
+
+impl<T: Read + Write + Seek> GenericDiskManager<T> {
+    pub fn new(open: impl FnMut(u32) -> anyhow::Result<T> + 'static) -> Self {
+        Self {
+            files: HashMap::new(),
+            open: Box::new(open),
+            counters: IoCounters::default(),
+        }
+    }
+
+    pub fn stats(&self) -> IoStats {
+        self.counters.snapshot()
+    }
+
+    pub fn reset_stats(&self) {
+        self.counters.reset();
+    }
+
+    // Uses `stream` as the existing contents of `file_id`.
+    pub fn with_file(mut self, file_id: u32, stream: T) -> Self {
+        self.files.insert(file_id, RefCell::new(stream));
+        self
+    }
+
+    pub fn into_files(self) -> HashMap<u32, T> {
+        self.files
+            .into_iter()
+            .map(|(file_id, stream)| (file_id, stream.into_inner()))
+            .collect()
+    }
+
+    fn file(&mut self, file_id: u32) -> anyhow::Result<&mut T> {
+        if !self.files.contains_key(&file_id) {
+            let stream = (self.open)(file_id)?;
+            self.files.insert(file_id, RefCell::new(stream));
+        }
+        Ok(self.files.get_mut(&file_id).unwrap().get_mut())
+    }
+}
+
+impl<T: Read + Write + Seek> DiskManager for GenericDiskManager<T> {
+    fn allocate_page(&mut self, file_id: u32) -> anyhow::Result<PageId> {
+        let stream = self.file(file_id)?;
+        let len = stream.seek(SeekFrom::End(0))?;
+        stream.write_all(&[0u8; PAGE_SIZE])?;
+        self.counters.record_write(1, PAGE_SIZE);
+        Ok(PageId::new(file_id, (len / PAGE_SIZE as u64) as u32))
+    }
+
+    fn read_page(&self, pid: PageId) -> anyhow::Result<Page> {
+        let mut stream = self
+            .files
+            .get(&pid.file_id())
+            .ok_or_else(|| anyhow::anyhow!("{:?} does not exist", pid))?
+            .borrow_mut();
+        let mut buf = [0u8; PAGE_SIZE];
+        stream.seek(SeekFrom::Start(pid.page_no() as u64 * PAGE_SIZE as u64))?;
+        stream.read_exact(&mut buf)?;
+        self.counters.record_read(PAGE_SIZE);
+        validate_page(pid, Page { buf })
+    }
+
+    fn write_page(&mut self, page: &Page) -> anyhow::Result<()> {
+        let mut page = page.clone();
+        let pid = page.page_id();
+        let offset = pid.page_no() as u64 * PAGE_SIZE as u64;
+        let stream = self.file(pid.file_id())?;
+        next_generation(stored_generation(stream, offset)?, &mut page);
+        stream.seek(SeekFrom::Start(offset))?;
+        stream.write_all(&page.buf)?;
+        self.counters.record_write(1, PAGE_SIZE);
+        Ok(())
+    }
+
+    fn sync(&mut self) -> anyhow::Result<()> {
+        for stream in self.files.values_mut() {
+            stream.get_mut().flush()?;
+        }
+        Ok(())
+    }
+
+    fn page_count(&self, file_id: u32) -> anyhow::Result<u32> {
+        let Some(stream) = self.files.get(&file_id) else {
+            return Ok(0);
+        };
+        let len = stream.borrow_mut().seek(SeekFrom::End(0))?;
+        Ok((len / PAGE_SIZE as u64) as u32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::heap::heap_page::HeapPage;
+    use crate::page::page_id::PageFlags;
+    use std::io::Cursor;
+
+    #[test]
+    fn heap_pages_round_trip_in_memory() -> anyhow::Result<()> {
+        let mut dm = GenericDiskManager::new(|_| Ok(Cursor::new(Vec::new())));
+
+        let mut hp = HeapPage::allocate(&mut dm, 1)?;
+        hp.insert_tuple(b"alpha")?;
+        dm.write_page(&hp.page)?;
+        let pid = dm.allocate_page(1)?;
+        let mut second = HeapPage::new_empty(pid);
+        second.insert_tuple(b"beta")?;
+        dm.write_page(&second.page)?;
+        dm.sync()?;
+
+        assert_eq!(dm.page_count(1)?, 2);
+        assert_eq!(dm.page_count(2)?, 0);
+        let read = HeapPage {
+            page: dm.read_page(pid)?,
+        };
+        assert_eq!(read.read_tuple(0).unwrap(), b"beta");
+        assert_eq!(dm.stats().page_reads, 1);
+        assert_eq!(dm.stats().page_writes, 5);
+
+        // The bytes are the same as an on-disk file and can be reopened
+        let bytes = dm.into_files().remove(&1).unwrap().into_inner();
+        assert_eq!(bytes.len(), 2 * PAGE_SIZE);
+        let mut reopened = GenericDiskManager::new(|_| Ok(Cursor::new(Vec::new())))
+            .with_file(1, Cursor::new(bytes));
+        let first = HeapPage {
+            page: reopened.read_page(PageId::new(1, 0))?,
+        };
+        assert_eq!(first.read_tuple(0).unwrap(), b"alpha");
+        let page = reopened.allocate_initialized_page(1, PageFlags::Heap)?;
+        assert_eq!(page.page_id(), PageId::new(1, 2));
+
+        Ok(())
+    }
+
+    #[test]
+    fn rewriting_the_same_page_advances_the_generation() -> anyhow::Result<()> {
+        let mut dm = GenericDiskManager::new(|_| Ok(Cursor::new(Vec::new())));
+        let hp = HeapPage::allocate(&mut dm, 1)?;
+        let pid = hp.page.page_id();
+        dm.write_page(&hp.page)?;
+        assert_eq!(dm.read_page(pid)?.generation(), 2);
+        dm.write_page(&hp.page)?;
+        assert_eq!(dm.read_page(pid)?.generation(), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn corrupt_page_is_rejected() -> anyhow::Result<()> {
+        let mut dm = GenericDiskManager::new(|_| Ok(Cursor::new(Vec::new())));
+        let hp = HeapPage::allocate(&mut dm, 1)?;
+        let mut bytes = dm.into_files().remove(&1).unwrap().into_inner();
+        bytes[100] ^= 0xFF;
+
+        let dm = GenericDiskManager::new(|_| Ok(Cursor::new(Vec::new())))
+            .with_file(1, Cursor::new(bytes));
+        assert!(dm.read_page(hp.page.page_id()).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,144 @@
+use crate::disk::disk_manager::DiskManager;
+use crate::disk::file_system::{next_generation, validate_page};
+use crate::disk::stats::{IoCounters, IoStats};
+use crate::page::{constants::PAGE_SIZE, page_file::Page, page_id::PageId};
+use std::collections::HashMap;
+
+// A disk manager that keeps every file in memory, page by page, with the
+// same allocate, read and write behaviour as `FsDiskManager`: written pages
+// get a new generation and reads reject torn or corrupt pages. Nothing
+// outlives the manager.
+#[derive(Default)]
+pub struct MemDiskManager {
+    files: HashMap<u32, Vec<[u8; PAGE_SIZE]>>,
+    counters: IoCounters,
+}
+
+impl MemDiskManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn stats(&self) -> IoStats {
+        self.counters.snapshot()
+    }
+
+    pub fn reset_stats(&self) {
+        self.counters.reset();
+    }
+
+    // The stored bytes of a page, for tests that need to damage it.
+    pub fn page_bytes_mut(&mut self, pid: PageId) -> Option<&mut [u8; PAGE_SIZE]> {
+        self.files
+            .get_mut(&pid.file_id())?
+            .get_mut(pid.page_no() as usize)
+    }
+}
+
+impl DiskManager for MemDiskManager {
+    fn allocate_page(&mut self, file_id: u32) -> anyhow::Result<PageId> {
+        let pages = self.files.entry(file_id).or_default();
+        pages.push([0u8; PAGE_SIZE]);
+        self.counters.record_write(1, PAGE_SIZE);
+        Ok(PageId::new(file_id, pages.len() as u32 - 1))
+    }
+
+    fn read_page(&self, pid: PageId) -> anyhow::Result<Page> {
+        let buf = self
+            .files
+            .get(&pid.file_id())
+            .and_then(|pages| pages.get(pid.page_no() as usize))
+            .ok_or_else(|| anyhow::anyhow!("{:?} does not exist", pid))?;
+        self.counters.record_read(PAGE_SIZE);
+        validate_page(pid, Page { buf: *buf })
+    }
+
+    fn write_page(&mut self, page: &Page) -> anyhow::Result<()> {
+        let mut page = page.clone();
+        let pid = page.page_id();
+        let pages = self.files.entry(pid.file_id()).or_default();
+        let index = pid.page_no() as usize;
+        let stored = pages.get(index).map(|buf| Page { buf: *buf }.generation());
+        next_generation(stored, &mut page);
+        // Writing past the end extends the file, as a file write would
+        if index >= pages.len() {
+            pages.resize(index + 1, [0u8; PAGE_SIZE]);
+        }
+        pages[index] = page.buf;
+        self.counters.record_write(1, PAGE_SIZE);
+        Ok(())
+    }
+
+    fn sync(&mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    fn page_count(&self, file_id: u32) -> anyhow::Result<u32> {
+        Ok(self
+            .files
+            .get(&file_id)
+            .map_or(0, |pages| pages.len() as u32))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::disk::file_system::DiskError;
+    use crate::page::page_id::PageFlags;
+
+    #[test]
+    fn page_write_read_round_trip() -> anyhow::Result<()> {
+        let mut dm = MemDiskManager::new();
+
+        let pid = dm.allocate_page(10)?;
+        let mut pg = Page::new(pid, PageFlags::Heap);
+        pg.set_lsn(12345);
+        pg.buf[100] = 0xAB;
+        pg.buf[200] = 0xCD;
+        pg.recompute_checksum();
+
+        dm.write_page(&pg)?;
+
+        let read_pg = dm.read_page(pid)?;
+        assert_eq!(read_pg.page_id(), pid);
+        assert_eq!(read_pg.header().page_lsn, 12345);
+        assert_eq!(read_pg.buf[100], 0xAB);
+        assert_eq!(read_pg.buf[200], 0xCD);
+        assert!(read_pg.verify_checksum());
+
+        assert_eq!(dm.page_count(10)?, 1);
+        assert_eq!(dm.page_count(11)?, 0);
+        assert!(dm.read_page(PageId::new(10, 1)).is_err());
+        assert_eq!(dm.stats().page_reads, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn rewriting_the_same_page_advances_the_generation() -> anyhow::Result<()> {
+        let mut dm = MemDiskManager::new();
+        let page = dm.allocate_initialized_page(3, PageFlags::Heap)?;
+        let pid = page.page_id();
+        dm.write_page(&page)?;
+        assert_eq!(dm.read_page(pid)?.generation(), 2);
+        dm.write_page(&page)?;
+        assert_eq!(dm.read_page(pid)?.generation(), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn corrupted_page_fails_checksum() -> anyhow::Result<()> {
+        let mut dm = MemDiskManager::new();
+        let page = dm.allocate_initialized_page(5, PageFlags::Index)?;
+        let pid = page.page_id();
+        assert!(dm.read_page(pid)?.verify_checksum());
+
+        dm.page_bytes_mut(pid).unwrap()[100] ^= 0xFF;
+        let error = dm.read_page(pid).unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<DiskError>(),
+            Some(DiskError::Checksum(p)) if *p == pid
+        ));
+        Ok(())
+    }
+}
@@ -0,0 +1,271 @@
+use crate::disk::disk_manager::DiskManager;
+use crate::disk::file_system::{FsDiskManager, validate_page};
+use crate::disk::stats::{IoCounters, IoStats};
+use crate::page::{
+    constants::PAGE_SIZE,
+    page_file::Page,
+    page_id::{PageFlags, PageId},
+};
+use anyhow::Context;
+use std::collections::HashMap;
+use std::fs::File;
+use std::os::fd::AsRawFd;
+use std::path::Path;
+use std::sync::RwLock;
+
+// A read-only shared mapping of a whole data file.
+struct Mapping {
+    ptr: *mut libc::c_void,
+    len: usize,
+}
+
+// The mapping is plain memory that is only ever read through `bytes`
+unsafe impl Send for Mapping {}
+unsafe impl Sync for Mapping {}
+
+impl Mapping {
+    fn new(file: &File, len: usize) -> anyhow::Result<Self> {
+        // SAFETY: maps `len` bytes of an open file read-only; the result is
+        // checked before use and unmapped on drop.
+        let ptr = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                len,
+                libc::PROT_READ,
+                libc::MAP_SHARED,
+                file.as_raw_fd(),
+                0,
+            )
+        };
+        if ptr == libc::MAP_FAILED {
+            return Err(std::io::Error::last_os_error().into());
+        }
+        Ok(Self { ptr, len })
+    }
+
+    fn bytes(&self) -> &[u8] {
+        // SAFETY: `ptr` points to `len` readable bytes for as long as self
+        // lives, provided the file is never shrunk below `len` while it is
+        // mapped. Mappings only cover whole pages, and the only shrinking of
+        // a data file is `FsDiskManager::new` cutting off a partial trailing
+        // page, so the mapped pages stay backed.
+        unsafe { std::slice::from_raw_parts(self.ptr as *const u8, self.len) }
+    }
+}
+
+impl Drop for Mapping {
+    fn drop(&mut self) {
+        // SAFETY: `ptr` and `len` came from a successful mmap
+        unsafe {
+            libc::munmap(self.ptr, self.len);
+        }
+    }
+}
+
+// A disk manager that serves `read_page` from memory-mapped data files
+// instead of a read syscall per page. Files are mapped on first read and
+// remapped when a read goes past the end of the current mapping. Writes go
+// through an `FsDiskManager` on the same directory; the mappings are
+// shared, so they see those writes straight away.
+pub struct MmapDiskManager {
+    files: FsDiskManager,
+    maps: RwLock<HashMap<u32, Mapping>>,
+    reads: IoCounters,
+}
+
+impl MmapDiskManager {
+    pub fn new<P: AsRef<Path>>(base: P) -> anyhow::Result<Self> {
+        Ok(Self {
+            files: FsDiskManager::new(base)?,
+            maps: RwLock::new(HashMap::new()),
+            reads: IoCounters::default(),
+        })
+    }
+
+    // Page reads come from the mappings; everything else from the writer.
+    pub fn stats(&self) -> IoStats {
+        let reads = self.reads.snapshot();
+        IoStats {
+            page_reads: reads.page_reads,
+            bytes_read: reads.bytes_read,
+            ..self.files.stats()
+        }
+    }
+
+    pub fn reset_stats(&self) {
+        self.files.reset_stats();
+        self.reads.reset();
+    }
+
+    // Maps the whole pages of `file_id` as it is now, replacing any older
+    // mapping. A partial page at the end is left out; it may be truncated
+    // while mapped.
+    fn remap(&self, file_id: u32) -> anyhow::Result<()> {
+        let path = self.files.file_path(file_id);
+        let file = match File::open(&path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e).with_context(|| format!("opening {:?}", path)),
+        };
+        let len = file.metadata()?.len() as usize;
+        let len = len - len % PAGE_SIZE;
+        let mut maps = self
+            .maps
+            .write()
+            .map_err(|_| anyhow::anyhow!("mapping lock poisoned"))?;
+        if len == 0 {
+            maps.remove(&file_id);
+        } else {
+            let mapping =
+                Mapping::new(&file, len).with_context(|| format!("mapping {:?}", path))?;
+            maps.insert(file_id, mapping);
+        }
+        Ok(())
+    }
+
+    // Copies the page out of the current mapping, if it covers the page.
+    fn copy_page(&self, pid: PageId) -> anyhow::Result<Option<[u8; PAGE_SIZE]>> {
+        let off = pid.page_no() as usize * PAGE_SIZE;
+        let maps = self
+            .maps
+            .read()
+            .map_err(|_| anyhow::anyhow!("mapping lock poisoned"))?;
+        Ok(maps
+            .get(&pid.file_id())
+            .and_then(|mapping| mapping.bytes().get(off..off + PAGE_SIZE))
+            .map(|bytes| bytes.try_into().unwrap()))
+    }
+}
+
+impl DiskManager for MmapDiskManager {
+    fn allocate_page(&mut self, file_id: u32) -> anyhow::Result<PageId> {
+        self.files.allocate_page(file_id)
+    }
+
+    fn allocate_initialized_page(
+        &mut self,
+        file_id: u32,
+        flags: PageFlags,
+    ) -> anyhow::Result<Page> {
+        self.files.allocate_initialized_page(file_id, flags)
+    }
+
+    fn read_page(&self, pid: PageId) -> anyhow::Result<Page> {
+        let buf = match self.copy_page(pid)? {
+            Some(buf) => buf,
+            None => {
+                self.remap(pid.file_id())?;
+                self.copy_page(pid)?
+                    .ok_or_else(|| anyhow::anyhow!("{:?} does not exist", pid))?
+            }
+        };
+        self.reads.record_read(PAGE_SIZE);
+        validate_page(pid, Page { buf })
+    }
+
+    fn write_page(&mut self, page: &Page) -> anyhow::Result<()> {
+        self.files.write_page(page)
+    }
+
+    fn sync(&mut self) -> anyhow::Result<()> {
+        self.files.sync()
+    }
+
+    fn page_count(&self, file_id: u32) -> anyhow::Result<u32> {
+        self.files.page_count(file_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::disk::file_system::DiskError;
+    use crate::heap::heap_page::HeapPage;
+    use std::fs::OpenOptions;
+    use std::io::{Seek, SeekFrom, Write};
+    use tempfile::TempDir;
+
+    #[test]
+    fn mapped_reads_match_file_reads() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let mut mm = MmapDiskManager::new(temp_dir.path())?;
+        let fs = FsDiskManager::new(temp_dir.path())?;
+
+        let mut pids = Vec::new();
+        for i in 0..3 {
+            let mut hp = HeapPage::allocate(&mut mm, 1)?;
+            hp.insert_tuple(format!("tuple {}", i).as_bytes())?;
+            mm.write_page(&hp.page)?;
+            pids.push(hp.page.page_id());
+        }
+        for &pid in &pids {
+            assert_eq!(mm.read_page(pid)?.buf, fs.read_page(pid)?.buf);
+        }
+
+        // Growing the file and rewriting mapped pages are both picked up
+        let mut hp = HeapPage::allocate(&mut mm, 1)?;
+        hp.insert_tuple(b"late")?;
+        mm.write_page(&hp.page)?;
+        let mut first = HeapPage {
+            page: mm.read_page(pids[0])?,
+        };
+        first.insert_tuple(b"more")?;
+        mm.write_page(&first.page)?;
+        pids.push(hp.page.page_id());
+        for &pid in &pids {
+            assert_eq!(mm.read_page(pid)?.buf, fs.read_page(pid)?.buf);
+        }
+        assert_eq!(mm.stats().page_reads, 8);
+
+        assert!(mm.read_page(PageId::new(1, 4)).is_err());
+        assert!(mm.read_page(PageId::new(2, 0)).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn truncating_a_partial_page_leaves_mappings_valid() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let mm = {
+            let mut mm = MmapDiskManager::new(temp_dir.path())?;
+            mm.allocate_initialized_page(3, PageFlags::Heap)?;
+            mm
+        };
+        // A crash while extending the file left half a page behind
+        let path = temp_dir.path().join("base_3.db");
+        OpenOptions::new()
+            .append(true)
+            .open(&path)?
+            .write_all(&[0u8; PAGE_SIZE / 2])?;
+        let pid = PageId::new(3, 0);
+        assert!(mm.read_page(pid)?.verify_checksum());
+
+        // Opening the directory again cuts it off under the live mapping
+        FsDiskManager::new(temp_dir.path())?;
+        assert_eq!(std::fs::metadata(&path)?.len(), PAGE_SIZE as u64);
+        assert!(mm.read_page(pid)?.verify_checksum());
+        assert!(mm.read_page(PageId::new(3, 1)).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn mapped_reads_verify_checksums() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let mut mm = MmapDiskManager::new(temp_dir.path())?;
+        let pid = mm.allocate_initialized_page(5, PageFlags::Heap)?.page_id();
+        assert!(mm.read_page(pid)?.verify_checksum());
+
+        let mut file = OpenOptions::new()
+            .write(true)
+            .open(temp_dir.path().join("base_5.db"))?;
+        file.seek(SeekFrom::Start(100))?;
+        file.write_all(&[0xFF])?;
+        drop(file);
+
+        let err = mm.read_page(pid).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<DiskError>(),
+            Some(DiskError::Checksum(p)) if *p == pid
+        ));
+        Ok(())
+    }
+}
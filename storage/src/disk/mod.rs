@@ -1,2 +1,9 @@
+pub mod buffer_pool;
 pub mod disk_manager;
 pub mod file_system;
+pub mod generic;
+pub mod memory;
+#[cfg(unix)]
+pub mod mmap;
+pub mod read_handle;
+pub mod stats;
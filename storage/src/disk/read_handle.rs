@@ -0,0 +1,54 @@
+use crate::disk::disk_manager::DiskManager;
+use crate::disk::file_system::DiskError;
+use crate::page::{page_file::Page, page_id::PageId};
+use std::sync::{Arc, RwLock};
+
+// A cheaply clonable, read-only view of a shared disk manager. Clones can
+// be handed to concurrent readers; the single writer keeps the `Arc` and
+// takes the write lock, so readers never see a half-written page.
+pub struct ReadHandle<D> {
+    dm: Arc<RwLock<D>>,
+}
+
+impl<D> ReadHandle<D> {
+    pub fn new(dm: Arc<RwLock<D>>) -> Self {
+        Self { dm }
+    }
+}
+
+// Derived Clone would require D: Clone
+impl<D> Clone for ReadHandle<D> {
+    fn clone(&self) -> Self {
+        Self {
+            dm: Arc::clone(&self.dm),
+        }
+    }
+}
+
+impl<D: DiskManager> DiskManager for ReadHandle<D> {
+    fn allocate_page(&mut self, _file_id: u32) -> anyhow::Result<PageId> {
+        Err(DiskError::ReadOnly.into())
+    }
+
+    fn read_page(&self, pid: PageId) -> anyhow::Result<Page> {
+        self.dm
+            .read()
+            .map_err(|_| anyhow::anyhow!("disk manager lock poisoned"))?
+            .read_page(pid)
+    }
+
+    fn write_page(&mut self, _page: &Page) -> anyhow::Result<()> {
+        Err(DiskError::ReadOnly.into())
+    }
+
+    fn sync(&mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    fn page_count(&self, file_id: u32) -> anyhow::Result<u32> {
+        self.dm
+            .read()
+            .map_err(|_| anyhow::anyhow!("disk manager lock poisoned"))?
+            .page_count(file_id)
+    }
+}
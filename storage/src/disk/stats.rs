@@ -0,0 +1,63 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+// Page I/O performed by a disk manager since it was created or its stats
+// were last reset. Reads served from an in-memory write buffer count as
+// buffer hits, not page reads.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct IoStats {
+    pub page_reads: u64,
+    pub page_writes: u64,
+    pub buffer_hits: u64,
+    pub bytes_read: u64,
+    pub bytes_written: u64,
+}
+
+// Atomic so that `read_page`, which only has `&self`, can count and shared
+// managers stay Sync.
+#[derive(Debug, Default)]
+pub(crate) struct IoCounters {
+    page_reads: AtomicU64,
+    page_writes: AtomicU64,
+    buffer_hits: AtomicU64,
+    bytes_read: AtomicU64,
+    bytes_written: AtomicU64,
+}
+
+impl IoCounters {
+    pub(crate) fn record_read(&self, bytes: usize) {
+        self.page_reads.fetch_add(1, Ordering::Relaxed);
+        self.bytes_read.fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_write(&self, pages: usize, bytes: usize) {
+        self.page_writes.fetch_add(pages as u64, Ordering::Relaxed);
+        self.bytes_written
+            .fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_buffer_hit(&self) {
+        self.buffer_hits.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn snapshot(&self) -> IoStats {
+        IoStats {
+            page_reads: self.page_reads.load(Ordering::Relaxed),
+            page_writes: self.page_writes.load(Ordering::Relaxed),
+            buffer_hits: self.buffer_hits.load(Ordering::Relaxed),
+            bytes_read: self.bytes_read.load(Ordering::Relaxed),
+            bytes_written: self.bytes_written.load(Ordering::Relaxed),
+        }
+    }
+
+    pub(crate) fn reset(&self) {
+        for counter in [
+            &self.page_reads,
+            &self.page_writes,
+            &self.buffer_hits,
+            &self.bytes_read,
+            &self.bytes_written,
+        ] {
+            counter.store(0, Ordering::Relaxed);
+        }
+    }
+}
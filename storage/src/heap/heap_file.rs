@@ -0,0 +1,195 @@
+use crate::disk::disk_manager::DiskManager;
+use crate::heap::heap_page::HeapPage;
+use crate::heap::slot::Slot;
+use crate::page::{constants::PAGE_DATA_END, page_header::PageHeader, page_id::PageId};
+
+// Largest tuple that fits on an empty heap page
+pub const MAX_TUPLE_LEN: usize = PAGE_DATA_END - PageHeader::LEN - Slot::SIZE;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TupleSizeWarning {
+    pub tuple_len: usize,
+    pub threshold: usize,
+}
+
+pub type TupleSizeHook = Box<dyn Fn(TupleSizeWarning) + Send + Sync>;
+
+pub struct HeapFile {
+    file_id: u32,
+    // Called for inserted tuples longer than the threshold
+    size_warning: Option<(usize, TupleSizeHook)>,
+}
+
+impl HeapFile {
+    pub fn new(file_id: u32) -> Self {
+        Self {
+            file_id,
+            size_warning: None,
+        }
+    }
+
+    pub fn file_id(&self) -> u32 {
+        self.file_id
+    }
+
+    // Calls `hook` whenever `insert` stores a tuple longer than `fraction`
+    // of `MAX_TUPLE_LEN`. Such tuples leave little room for anything else
+    // on their page and usually point at a schema that needs rethinking.
+    pub fn with_tuple_size_warning(
+        mut self,
+        fraction: f64,
+        hook: impl Fn(TupleSizeWarning) + Send + Sync + 'static,
+    ) -> Self {
+        let threshold = (MAX_TUPLE_LEN as f64 * fraction.clamp(0.0, 1.0)) as usize;
+        self.size_warning = Some((threshold, Box::new(hook)));
+        self
+    }
+
+    // Appends `tuple` to the last page of the file, or to a new page when it
+    // does not fit, and returns where it was stored.
+    pub fn insert<D: DiskManager>(
+        &self,
+        disk_manager: &mut D,
+        tuple: &[u8],
+    ) -> anyhow::Result<(PageId, usize)> {
+        if tuple.len() > MAX_TUPLE_LEN {
+            anyhow::bail!(
+                "tuple of {} bytes exceeds the {} byte page limit",
+                tuple.len(),
+                MAX_TUPLE_LEN
+            );
+        }
+        if let Some((threshold, hook)) = &self.size_warning
+            && tuple.len() > *threshold
+        {
+            hook(TupleSizeWarning {
+                tuple_len: tuple.len(),
+                threshold: *threshold,
+            });
+        }
+
+        let last = disk_manager.page_ids(self.file_id)?.last();
+        let mut heap_page = match last {
+            Some(pid) => HeapPage {
+                page: disk_manager.read_page(pid)?,
+            },
+            None => HeapPage::allocate(disk_manager, self.file_id)?,
+        };
+        if heap_page.page.free_space() < tuple.len() + Slot::SIZE {
+            heap_page = HeapPage::allocate(disk_manager, self.file_id)?;
+        }
+        let slot_no = heap_page.insert_tuple(tuple)?;
+        disk_manager.write_page(&heap_page.page)?;
+        Ok((heap_page.page.page_id(), slot_no))
+    }
+
+    // Calls `visit` with the location and bytes of every live tuple, without
+    // deserializing. The bytes borrow the page buffer, which is only held for
+    // the duration of the call.
+    pub fn scan_raw<D, F>(&self, disk_manager: &D, mut visit: F) -> anyhow::Result<()>
+    where
+        D: DiskManager,
+        F: FnMut(PageId, usize, &[u8]) -> anyhow::Result<()>,
+    {
+        for pid in disk_manager.page_ids(self.file_id)? {
+            let heap_page = HeapPage {
+                page: disk_manager.read_page(pid)?,
+            };
+            for (slot_no, tuple) in heap_page.tuples() {
+                visit(pid, slot_no, tuple)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::disk::file_system::FsDiskManager;
+    use crate::query::tuple::serialize_row;
+    use crate::query::types::{Column, DataType, Schema, Value};
+
+    #[test]
+    fn scan_raw_yields_serialized_rows() -> anyhow::Result<()> {
+        let td = tempfile::tempdir()?;
+        let mut dm = FsDiskManager::new(td.path())?;
+        let schema = Schema::new(vec![
+            Column {
+                name: "id".to_string(),
+                data_type: DataType::Integer,
+                nullable: false,
+            },
+            Column {
+                name: "name".to_string(),
+                data_type: DataType::Varchar(255),
+                nullable: true,
+            },
+        ]);
+
+        let mut expected = Vec::new();
+        for page in 0..2 {
+            let mut hp = HeapPage::allocate(&mut dm, 1)?;
+            for i in 0..3 {
+                let id = page * 3 + i;
+                let row = vec![Value::Integer(id), Value::Varchar(format!("user_{}", id))];
+                let bytes = serialize_row(&row, &schema)?;
+                hp.insert_tuple(&bytes)?;
+                expected.push(bytes);
+            }
+            if page == 1 {
+                hp.delete_tuple(1)?;
+                expected.remove(4);
+            }
+            dm.write_page(&hp.page)?;
+        }
+
+        let mut scanned = Vec::new();
+        HeapFile::new(1).scan_raw(&dm, |pid, slot_no, tuple| {
+            assert_eq!(pid.file_id(), 1);
+            assert!(slot_no < 3);
+            scanned.push(tuple.to_vec());
+            Ok(())
+        })?;
+
+        assert_eq!(scanned, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn large_tuple_triggers_size_warning() -> anyhow::Result<()> {
+        use std::sync::{Arc, Mutex};
+
+        let td = tempfile::tempdir()?;
+        let mut dm = FsDiskManager::new(td.path())?;
+        let warnings = Arc::new(Mutex::new(Vec::new()));
+        let seen = Arc::clone(&warnings);
+        let heap = HeapFile::new(1)
+            .with_tuple_size_warning(0.5, move |warning| seen.lock().unwrap().push(warning));
+
+        heap.insert(&mut dm, &[1u8; 100])?;
+        assert!(warnings.lock().unwrap().is_empty());
+
+        let big = vec![2u8; MAX_TUPLE_LEN * 3 / 4];
+        let (pid, slot_no) = heap.insert(&mut dm, &big)?;
+        assert_eq!(
+            *warnings.lock().unwrap(),
+            vec![TupleSizeWarning {
+                tuple_len: big.len(),
+                threshold: MAX_TUPLE_LEN / 2,
+            }]
+        );
+
+        // The tuple is still stored; a second one spills onto a new page
+        let page = HeapPage {
+            page: dm.read_page(pid)?,
+        };
+        assert_eq!(page.read_tuple(slot_no).unwrap(), &big[..]);
+        let (second, _) = heap.insert(&mut dm, &big)?;
+        assert_eq!(second.page_no(), pid.page_no() + 1);
+        assert_eq!(warnings.lock().unwrap().len(), 2);
+
+        assert!(heap.insert(&mut dm, &vec![0u8; MAX_TUPLE_LEN + 1]).is_err());
+        Ok(())
+    }
+}
@@ -1,11 +1,20 @@
+use crate::disk::disk_manager::DiskManager;
 use crate::heap::slot::Slot;
 use crate::page::{
-    constants::PAGE_SIZE,
+    constants::{PAGE_DATA_END, PAGE_SIZE},
     page_file::Page,
     page_header::PageHeader,
     page_id::{PageFlags, PageId},
 };
 
+// Result of a best-effort scan: every tuple whose slot points inside the
+// page's tuple area, plus the slots that did not.
+#[derive(Debug, Default)]
+pub struct SalvageReport<'a> {
+    pub tuples: Vec<(usize, &'a [u8])>,
+    pub skipped: Vec<usize>,
+}
+
 #[derive(Clone)]
 pub struct HeapPage {
     pub page: Page,
@@ -18,91 +27,202 @@ impl HeapPage {
         Self { page: pg }
     }
 
-    pub fn slot_count(&self) -> usize {
-        let hdr = self.page.header();
-        ((PAGE_SIZE as u16 - hdr.upper) as usize) / Slot::SIZE
+    pub fn allocate<D: DiskManager>(dm: &mut D, file_id: u32) -> anyhow::Result<Self> {
+        let page = dm.allocate_initialized_page(file_id, PageFlags::Heap)?;
+        Ok(Self { page })
     }
 
-    fn read_slot(&self, slot_no: usize) -> Slot {
+    // The header's `lower` and `upper`, checked to lie in order between the
+    // header and the end of the page with a whole number of slots after
+    // `upper`.
+    fn bounds(&self) -> anyhow::Result<(usize, usize)> {
         let hdr = self.page.header();
-        let base = (PAGE_SIZE as u16 - ((slot_no as u16 + 1) * Slot::SIZE as u16)) as usize;
-        let base = base.max(hdr.upper as usize);
-        let off = self.page.read_u16(base);
-        let len = self.page.read_u16(base + 2);
-        Slot { off, len }
+        let (lower, upper) = (hdr.lower as usize, hdr.upper as usize);
+        if PageHeader::LEN <= lower
+            && lower <= upper
+            && upper <= PAGE_DATA_END
+            && (PAGE_DATA_END - upper).is_multiple_of(Slot::SIZE)
+        {
+            Ok((lower, upper))
+        } else {
+            anyhow::bail!(
+                "corrupt header on {:?}: lower {} upper {}",
+                self.page.page_id(),
+                lower,
+                upper
+            )
+        }
     }
 
-    fn write_slot(&mut self, slot_no: usize, slot: Slot) {
-        let base = PAGE_SIZE - ((slot_no + 1) * Slot::SIZE);
-        self.page.write_u16(base, slot.off);
-        self.page.write_u16(base + 2, slot.len);
+    // A page with a corrupt header has no slots
+    pub fn slot_count(&self) -> usize {
+        self.bounds()
+            .map_or(0, |(_, upper)| (PAGE_DATA_END - upper) / Slot::SIZE)
+    }
+
+    // Offset of a slot's entry in the directory, which grows down from the
+    // end of the page
+    fn slot_offset(slot_no: usize) -> anyhow::Result<usize> {
+        slot_no
+            .checked_add(1)
+            .and_then(|n| n.checked_mul(Slot::SIZE))
+            .and_then(|n| PAGE_DATA_END.checked_sub(n))
+            .filter(|base| *base >= PageHeader::LEN)
+            .ok_or_else(|| anyhow::anyhow!("slot {} lies outside the page", slot_no))
+    }
+
+    // None for a slot past the end of the directory
+    fn read_slot(&self, slot_no: usize) -> Option<Slot> {
+        if slot_no >= self.slot_count() {
+            return None;
+        }
+        let base = Self::slot_offset(slot_no).ok()?;
+        let off = self.page.read_u16(base)?;
+        let len = self.page.read_u16(base + 2)?;
+        Some(Slot { off, len })
+    }
+
+    fn slot_bytes(slot: Slot) -> [u8; Slot::SIZE] {
+        let mut bytes = [0u8; Slot::SIZE];
+        bytes[0..2].copy_from_slice(&slot.off.to_le_bytes());
+        bytes[2..4].copy_from_slice(&slot.len.to_le_bytes());
+        bytes
+    }
+
+    fn write_slot(&mut self, slot_no: usize, slot: Slot) -> anyhow::Result<()> {
+        let base = Self::slot_offset(slot_no)?;
+        self.page.write_bytes(base, &Self::slot_bytes(slot))
+    }
+
+    fn patch_slot(&mut self, slot_no: usize, slot: Slot) -> anyhow::Result<()> {
+        let base = Self::slot_offset(slot_no)?;
+        self.page.patch(base, &Self::slot_bytes(slot));
+        Ok(())
     }
 
     pub fn insert_tuple(&mut self, tuple: &[u8]) -> anyhow::Result<usize> {
+        let (off, upper) = self.bounds()?;
         let need = tuple.len() + Slot::SIZE;
-        if need > self.page.free_space() {
+        if need > upper - off {
             anyhow::bail!("not enough free space")
         }
 
-        let mut hdr = self.page.header();
         let slot_no = self.slot_count();
 
-        let off = hdr.lower as usize;
-        let len = tuple.len() as u16;
-        self.page.buf[off..off + tuple.len()].copy_from_slice(tuple);
-        hdr.lower = (off + tuple.len()) as u16;
-
-        hdr.upper = hdr.upper - Slot::SIZE as u16;
-        self.page.write_header(&hdr);
-        self.write_slot(
+        // Small edits, so the checksum is patched rather than recomputed
+        let lower = (off + tuple.len()) as u16;
+        let upper = (upper - Slot::SIZE) as u16;
+        self.page.patch(off, tuple);
+        self.page.patch_lower_upper(lower, upper);
+        self.patch_slot(
             slot_no,
             Slot {
                 off: off as u16,
-                len,
+                len: tuple.len() as u16,
             },
-        );
-        self.page.recompute_checksum();
+        )?;
         Ok(slot_no)
     }
 
     pub fn read_tuple(&self, slot_no: usize) -> Option<&[u8]> {
-        if slot_no >= self.slot_count() {
-            return None;
-        }
-        let slot = self.read_slot(slot_no);
+        let slot = self.read_slot(slot_no)?;
         if slot.len == 0 {
             return None;
         }
-        let off = slot.off as usize;
-        let len = slot.len as usize;
-        Some(&self.page.buf[off..off + len])
+        self.page.read_bytes(slot.off as usize, slot.len as usize)
     }
 
-    pub fn delete_tuple(&mut self, slot_no: usize) -> anyhow::Result<()> {
-        if slot_no >= self.slot_count() {
-            anyhow::bail!("slot out of range")
+    // Live tuples in slot order, borrowed from the page buffer.
+    pub fn tuples(&self) -> impl Iterator<Item = (usize, &[u8])> {
+        (0..self.slot_count()).filter_map(|slot_no| Some((slot_no, self.read_tuple(slot_no)?)))
+    }
+
+    // Salvage path for recovery tooling. Never panics on a bad slot: slots
+    // whose extent falls outside the tuple area are skipped and reported.
+    pub fn salvage_tuples(&self) -> SalvageReport<'_> {
+        let hdr = self.page.header();
+        let upper = (hdr.upper as usize).clamp(PageHeader::LEN, PAGE_DATA_END);
+        let data_end = (hdr.lower as usize).clamp(PageHeader::LEN, upper);
+        let slots = (PAGE_DATA_END - upper) / Slot::SIZE;
+
+        let mut report = SalvageReport::default();
+        for slot_no in 0..slots {
+            let base = PAGE_DATA_END - (slot_no + 1) * Slot::SIZE;
+            let (Some(off), Some(len)) = (self.page.read_u16(base), self.page.read_u16(base + 2))
+            else {
+                report.skipped.push(slot_no);
+                continue;
+            };
+            let (off, len) = (off as usize, len as usize);
+            if len == 0 {
+                continue;
+            }
+            match self.page.read_bytes(off, len) {
+                Some(tuple) if off >= PageHeader::LEN && off + len <= data_end => {
+                    report.tuples.push((slot_no, tuple));
+                }
+                _ => report.skipped.push(slot_no),
+            }
         }
-        let slot = self.read_slot(slot_no);
+        report
+    }
+
+    pub fn delete_tuple(&mut self, slot_no: usize) -> anyhow::Result<()> {
+        let slot = self
+            .read_slot(slot_no)
+            .ok_or_else(|| anyhow::anyhow!("slot out of range"))?;
         if slot.len == 0 {
             return Ok(());
         }
-        self.write_slot(
+        self.patch_slot(
             slot_no,
             Slot {
                 off: slot.off,
                 len: 0,
             },
-        );
-        self.page.recompute_checksum();
+        )
+    }
+
+    // Overwrites a live tuple with one of the same length. Tuples cannot grow
+    // or shrink in place.
+    pub fn replace_tuple(&mut self, slot_no: usize, tuple: &[u8]) -> anyhow::Result<()> {
+        let slot = self
+            .read_slot(slot_no)
+            .ok_or_else(|| anyhow::anyhow!("slot out of range"))?;
+        if slot.len == 0 {
+            anyhow::bail!("slot {} holds no tuple", slot_no)
+        }
+        if slot.len as usize != tuple.len() {
+            anyhow::bail!(
+                "replacement is {} bytes but the tuple is {}",
+                tuple.len(),
+                slot.len
+            )
+        }
+        self.page.patch(slot.off as usize, tuple);
         Ok(())
     }
 
-    pub fn compact(&mut self) {
+    // Copies a live tuple into `dest` and tombstones it here, returning its
+    // slot in `dest`. Neither page changes if `dest` is out of space.
+    pub fn move_tuple_to(&mut self, slot_no: usize, dest: &mut HeapPage) -> anyhow::Result<usize> {
+        let tuple = self
+            .read_tuple(slot_no)
+            .ok_or_else(|| anyhow::anyhow!("no live tuple in slot {}", slot_no))?;
+        let dest_slot = dest.insert_tuple(tuple)?;
+        self.delete_tuple(slot_no)?;
+        Ok(dest_slot)
+    }
+
+    // Slots that point outside the page are corrupt and are tombstoned
+    // instead of copied. A page with a corrupt header is left alone.
+    pub fn compact(&mut self) -> anyhow::Result<()> {
+        self.bounds()?;
         let mut hdr = self.page.header();
         let slots = self.slot_count();
 
         let mut live: Vec<(usize, Slot)> = (0..slots)
-            .map(|i| (i, self.read_slot(i)))
+            .filter_map(|i| Some((i, self.read_slot(i)?)))
             .filter(|(_, s)| s.len != 0)
             .collect();
 
@@ -110,27 +230,70 @@ impl HeapPage {
 
         let mut scratch = vec![0u8; PAGE_SIZE - PageHeader::LEN];
         for (i, s) in live.iter_mut() {
-            let data = &self.page.buf[s.off as usize..(s.off + s.len) as usize];
-            let off = lower as usize;
-            scratch[off - PageHeader::LEN..off - PageHeader::LEN + data.len()]
-                .copy_from_slice(data);
-            s.off = lower;
-            lower += s.len;
-            self.write_slot(*i, *s);
+            let off = lower as usize - PageHeader::LEN;
+            match self
+                .page
+                .read_bytes(s.off as usize, s.len as usize)
+                .filter(|data| off + data.len() <= scratch.len())
+            {
+                Some(data) => {
+                    scratch[off..off + data.len()].copy_from_slice(data);
+                    s.off = lower;
+                    lower += s.len;
+                }
+                None => s.len = 0,
+            }
+            self.write_slot(*i, *s)?;
         }
 
-        let tgt = PageHeader::LEN..(lower as usize);
-        self.page.buf[tgt.clone()].copy_from_slice(&scratch[0..tgt.len()]);
+        self.page.write_bytes(
+            PageHeader::LEN,
+            &scratch[..lower as usize - PageHeader::LEN],
+        )?;
         hdr.lower = lower;
         self.page.write_header(&hdr);
         self.page.recompute_checksum();
+        Ok(())
+    }
+
+    // Like `compact`, but also drops tombstoned slots from the directory and
+    // renumbers the live ones densely in their original order. Returns the
+    // new slot of each old slot (None for tombstones) so that anything
+    // holding slot numbers can be updated.
+    pub fn compact_dense(&mut self) -> anyhow::Result<Vec<Option<usize>>> {
+        self.compact()?;
+        let slots = self.slot_count();
+
+        let mut mapping = Vec::with_capacity(slots);
+        let mut live = Vec::new();
+        for slot_no in 0..slots {
+            match self.read_slot(slot_no) {
+                Some(slot) if slot.len != 0 => {
+                    mapping.push(Some(live.len()));
+                    live.push(slot);
+                }
+                _ => mapping.push(None),
+            }
+        }
+
+        for (slot_no, slot) in live.iter().enumerate() {
+            self.write_slot(slot_no, *slot)?;
+        }
+        let mut hdr = self.page.header();
+        hdr.upper = (PAGE_DATA_END - live.len() * Slot::SIZE) as u16;
+        self.page.write_header(&hdr);
+        // Clear the directory entries that are no longer in use
+        let freed = PAGE_DATA_END - slots * Slot::SIZE;
+        self.page
+            .write_bytes(freed, &vec![0; hdr.upper as usize - freed])?;
+        self.page.recompute_checksum();
+        Ok(mapping)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::disk::disk_manager::DiskManager;
     use crate::disk::file_system::FsDiskManager;
     use crate::page::page_id::PageId;
 
@@ -153,7 +316,7 @@ mod tests {
         hp.delete_tuple(sb).unwrap();
         assert!(hp.read_tuple(sb).is_none());
 
-        hp.compact();
+        hp.compact().unwrap();
         assert_eq!(hp.read_tuple(sa).unwrap(), a);
         assert_eq!(hp.read_tuple(sc).unwrap(), c);
     }
@@ -194,6 +357,24 @@ mod tests {
         assert_eq!(t0[0..2], 0u16.to_le_bytes());
     }
 
+    #[test]
+    fn allocated_heap_page_is_empty_and_valid() {
+        let td = tempfile::tempdir().unwrap();
+        let mut dm = FsDiskManager::new(td.path()).unwrap();
+
+        let hp = HeapPage::allocate(&mut dm, 3).unwrap();
+        assert_eq!(hp.page.page_id(), PageId::new(3, 0));
+        assert_eq!(hp.slot_count(), 0);
+
+        let read = HeapPage {
+            page: dm.read_page(hp.page.page_id()).unwrap(),
+        };
+        assert!(read.page.verify_checksum());
+        assert_eq!(read.page.header().page_flags, PageFlags::Heap as u16);
+        assert_eq!(read.slot_count(), 0);
+        assert_eq!(read.page.free_space(), PAGE_DATA_END - PageHeader::LEN);
+    }
+
     #[test]
     fn checksum_catches_corruption() {
         use std::fs::OpenOptions;
@@ -251,7 +432,7 @@ mod tests {
 
         let pre_compact_free = hp.page.free_space();
 
-        hp.compact();
+        hp.compact().unwrap();
         let post_compact_free = hp.page.free_space();
 
         assert!(post_compact_free > pre_compact_free);
@@ -263,6 +444,157 @@ mod tests {
         assert!(hp.read_tuple(slots[3]).is_none());
     }
 
+    #[test]
+    fn dense_compaction_shrinks_slot_directory() {
+        let mut hp = HeapPage::new_empty(PageId::new(1, 8));
+        for i in 0..6u8 {
+            hp.insert_tuple(&[i; 10]).unwrap();
+        }
+        for slot_no in [0, 2, 3] {
+            hp.delete_tuple(slot_no).unwrap();
+        }
+        let free_before = hp.page.free_space();
+
+        let mapping = hp.compact_dense().unwrap();
+        assert_eq!(mapping, vec![None, Some(0), None, None, Some(1), Some(2)]);
+        assert_eq!(hp.slot_count(), 3);
+        assert_eq!(hp.page.free_space(), free_before + 3 * 10 + 3 * Slot::SIZE);
+        for (old, new) in mapping.iter().enumerate() {
+            if let Some(new) = new {
+                assert_eq!(hp.read_tuple(*new).unwrap(), &[old as u8; 10]);
+            }
+        }
+        assert!(hp.page.verify_checksum());
+
+        // New tuples take the next dense slot
+        assert_eq!(hp.insert_tuple(b"next").unwrap(), 3);
+    }
+
+    #[test]
+    fn move_tuple_between_pages() {
+        let mut src = HeapPage::new_empty(PageId::new(1, 0));
+        let mut dest = HeapPage::new_empty(PageId::new(1, 1));
+
+        let a = src.insert_tuple(b"alpha").unwrap();
+        let b = src.insert_tuple(b"beta").unwrap();
+        dest.insert_tuple(b"existing").unwrap();
+
+        let moved = src.move_tuple_to(a, &mut dest).unwrap();
+        assert_eq!(moved, 1);
+        assert_eq!(dest.read_tuple(moved).unwrap(), b"alpha");
+        assert_eq!(dest.read_tuple(0).unwrap(), b"existing");
+        assert!(src.read_tuple(a).is_none());
+        assert_eq!(src.read_tuple(b).unwrap(), b"beta");
+        assert!(src.page.verify_checksum());
+        assert!(dest.page.verify_checksum());
+
+        assert!(src.move_tuple_to(a, &mut dest).is_err());
+        assert!(src.move_tuple_to(9, &mut dest).is_err());
+    }
+
+    #[test]
+    fn replace_tuple_in_place() {
+        let mut hp = HeapPage::new_empty(PageId::new(1, 0));
+        let a = hp.insert_tuple(b"alpha").unwrap();
+        let b = hp.insert_tuple(b"beta").unwrap();
+
+        hp.replace_tuple(a, b"omega").unwrap();
+        assert_eq!(hp.read_tuple(a).unwrap(), b"omega");
+        assert_eq!(hp.read_tuple(b).unwrap(), b"beta");
+        assert!(hp.page.verify_checksum());
+
+        assert!(hp.replace_tuple(a, b"longer").is_err());
+        hp.delete_tuple(b).unwrap();
+        assert!(hp.replace_tuple(b, b"gone").is_err());
+        assert!(hp.replace_tuple(9, b"x").is_err());
+    }
+
+    #[test]
+    fn move_tuple_fails_cleanly_when_destination_full() {
+        let mut src = HeapPage::new_empty(PageId::new(1, 0));
+        let mut dest = HeapPage::new_empty(PageId::new(1, 1));
+
+        let slot = src.insert_tuple(&[7u8; 100]).unwrap();
+        let filler = dest.page.free_space() - Slot::SIZE - 50;
+        dest.insert_tuple(&vec![1u8; filler]).unwrap();
+        let src_before = src.page.buf;
+        let dest_before = dest.page.buf;
+
+        assert!(src.move_tuple_to(slot, &mut dest).is_err());
+        assert_eq!(src.page.buf, src_before);
+        assert_eq!(dest.page.buf, dest_before);
+        assert_eq!(src.read_tuple(slot).unwrap(), &[7u8; 100]);
+    }
+
+    #[test]
+    fn salvage_skips_corrupt_slot() {
+        let mut hp = HeapPage::new_empty(PageId::new(1, 7));
+        hp.insert_tuple(b"alpha").unwrap();
+        let bad = hp.insert_tuple(b"beta").unwrap();
+        hp.insert_tuple(b"gamma").unwrap();
+
+        // Point the middle slot past the end of the page
+        hp.write_slot(
+            bad,
+            Slot {
+                off: 8100,
+                len: 500,
+            },
+        )
+        .unwrap();
+
+        let report = hp.salvage_tuples();
+        assert_eq!(report.skipped, vec![bad]);
+        assert_eq!(report.tuples, vec![(0, &b"alpha"[..]), (2, &b"gamma"[..])]);
+    }
+
+    #[test]
+    fn corrupt_slot_reads_as_missing() {
+        let mut hp = HeapPage::new_empty(PageId::new(1, 9));
+        hp.insert_tuple(b"alpha").unwrap();
+        let bad = hp.insert_tuple(b"beta").unwrap();
+        hp.write_slot(
+            bad,
+            Slot {
+                off: 8190,
+                len: 100,
+            },
+        )
+        .unwrap();
+
+        assert!(hp.read_tuple(bad).is_none());
+        assert_eq!(hp.tuples().count(), 1);
+
+        hp.compact().unwrap();
+        assert_eq!(hp.read_tuple(0).unwrap(), b"alpha");
+        assert!(hp.read_tuple(bad).is_none());
+    }
+
+    #[test]
+    fn corrupt_header_is_an_error_not_a_panic() {
+        // lower past upper, upper past the end of the page, lower inside the
+        // header, and an upper that splits a slot
+        for (lower, upper) in [(5000, 4000), (32, 9000), (2, 8188), (32, 8186)] {
+            let mut hp = HeapPage::new_empty(PageId::new(1, 10));
+            hp.insert_tuple(b"alpha").unwrap();
+            hp.page.set_lower(lower);
+            hp.page.set_upper(upper);
+            hp.page.recompute_checksum();
+            let before = hp.page.buf;
+
+            assert_eq!(hp.slot_count(), 0);
+            assert!(hp.read_tuple(0).is_none());
+            assert_eq!(hp.tuples().count(), 0);
+            assert!(hp.insert_tuple(b"beta").is_err());
+            assert!(hp.delete_tuple(0).is_err());
+            assert!(hp.replace_tuple(0, b"omega").is_err());
+            assert!(hp.compact().is_err());
+            assert!(hp.compact_dense().is_err());
+            assert_eq!(hp.page.buf, before);
+            hp.salvage_tuples();
+        }
+    }
+
     #[test]
     fn heap_page_near_full() {
         let pid = PageId::new(1, 4);
@@ -319,7 +651,7 @@ mod tests {
         }
 
         for (i, &slot_no) in slots.iter().enumerate() {
-            assert_eq!(slot_no as usize, i);
+            assert_eq!(slot_no, i);
         }
 
         for (i, &slot_no) in slots.iter().enumerate() {
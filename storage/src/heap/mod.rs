@@ -1,2 +1,4 @@
+pub mod heap_file;
 pub mod heap_page;
 pub mod slot;
+pub mod toast;
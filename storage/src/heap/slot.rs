@@ -68,6 +68,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::clone_on_copy)]
     fn slot_equality_and_copy() {
         let slot1 = Slot::new(100, 50);
         let slot2 = Slot::new(100, 50);
@@ -0,0 +1,215 @@
+use crate::disk::disk_manager::DiskManager;
+use crate::page::{
+    constants::PAGE_DATA_END,
+    page_file::Page,
+    page_header::PageHeader,
+    page_id::{PageFlags, PageId},
+};
+
+// Varchars longer than this many bytes are stored out of line
+pub const TOAST_THRESHOLD: usize = 2048;
+
+// All tables share one large-object file, kept clear of the ids the catalog
+// hands out.
+pub const TOAST_FILE_ID: u32 = u32::MAX;
+
+const CHUNK_LEN: usize = PAGE_DATA_END - PageHeader::LEN;
+
+// Where an out-of-line value lives: `len` bytes spread over consecutive
+// overflow pages starting at `page_no`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ToastPointer {
+    pub page_no: u32,
+    pub len: u32,
+}
+
+pub struct ToastFile {
+    file_id: u32,
+}
+
+impl ToastFile {
+    pub fn new(file_id: u32) -> Self {
+        Self { file_id }
+    }
+
+    pub fn file_id(&self) -> u32 {
+        self.file_id
+    }
+
+    // Writes `value` to the first run of freed pages long enough to hold it,
+    // or else to newly allocated pages at the end of the file. Pages are only
+    // ever appended, so new ones come out consecutive.
+    pub fn store<D: DiskManager>(
+        &self,
+        disk_manager: &mut D,
+        value: &[u8],
+    ) -> anyhow::Result<ToastPointer> {
+        let pages = value.len().div_ceil(CHUNK_LEN) as u32;
+        if let Some(first) = self.find_free_run(disk_manager, pages)? {
+            for (i, chunk) in value.chunks(CHUNK_LEN).enumerate() {
+                let mut page = Page::new(
+                    PageId::new(self.file_id, first + i as u32),
+                    PageFlags::Overflow,
+                );
+                page.write_bytes(PageHeader::LEN, chunk)?;
+                page.recompute_checksum();
+                disk_manager.write_page(&page)?;
+            }
+            return Ok(ToastPointer {
+                page_no: first,
+                len: value.len() as u32,
+            });
+        }
+
+        let mut first = None;
+        for (i, chunk) in value.chunks(CHUNK_LEN).enumerate() {
+            let mut page =
+                disk_manager.allocate_initialized_page(self.file_id, PageFlags::Overflow)?;
+            let page_no = page.page_id().page_no();
+            match first {
+                None => first = Some(page_no),
+                Some(first) if page_no != first + i as u32 => {
+                    anyhow::bail!("overflow pages for one value are not consecutive")
+                }
+                Some(_) => {}
+            }
+            page.write_bytes(PageHeader::LEN, chunk)?;
+            page.recompute_checksum();
+            disk_manager.write_page(&page)?;
+        }
+        Ok(ToastPointer {
+            page_no: first.unwrap_or(0),
+            len: value.len() as u32,
+        })
+    }
+
+    // Marks the pages of a value free for `store` to reuse. The tuple that
+    // pointed to them must already be gone from its page on disk, so that a
+    // crash in between can leak the pages but never leave a tuple pointing
+    // at reused ones.
+    pub fn free<D: DiskManager>(
+        &self,
+        disk_manager: &mut D,
+        pointer: ToastPointer,
+    ) -> anyhow::Result<()> {
+        let pages = (pointer.len as usize).div_ceil(CHUNK_LEN) as u32;
+        for page_no in pointer.page_no..pointer.page_no + pages {
+            let pid = PageId::new(self.file_id, page_no);
+            if disk_manager.read_page(pid)?.header().page_flags != PageFlags::Overflow as u16 {
+                anyhow::bail!(
+                    "page {} of file {} is not an overflow page in use",
+                    page_no,
+                    self.file_id
+                );
+            }
+            disk_manager.write_page(&Page::new(pid, PageFlags::FreeOverflow))?;
+        }
+        Ok(())
+    }
+
+    // The first page of the earliest run of `pages` consecutive free pages.
+    // Finding it reads every page header in the file.
+    fn find_free_run<D: DiskManager>(
+        &self,
+        disk_manager: &D,
+        pages: u32,
+    ) -> anyhow::Result<Option<u32>> {
+        if pages == 0 {
+            return Ok(None);
+        }
+        let mut run = 0;
+        for page_no in 0..disk_manager.page_count(self.file_id)? {
+            let page = disk_manager.read_page(PageId::new(self.file_id, page_no))?;
+            if page.header().page_flags == PageFlags::FreeOverflow as u16 {
+                run += 1;
+                if run == pages {
+                    return Ok(Some(page_no + 1 - pages));
+                }
+            } else {
+                run = 0;
+            }
+        }
+        Ok(None)
+    }
+
+    pub fn fetch<D: DiskManager>(
+        &self,
+        disk_manager: &D,
+        pointer: ToastPointer,
+    ) -> anyhow::Result<Vec<u8>> {
+        let len = pointer.len as usize;
+        let mut value = Vec::with_capacity(len);
+        let mut page_no = pointer.page_no;
+        while value.len() < len {
+            let page = disk_manager.read_page(PageId::new(self.file_id, page_no))?;
+            if page.header().page_flags != PageFlags::Overflow as u16 {
+                anyhow::bail!(
+                    "page {} of file {} is not an overflow page",
+                    page_no,
+                    self.file_id
+                );
+            }
+            let take = (len - value.len()).min(CHUNK_LEN);
+            let chunk = page
+                .read_bytes(PageHeader::LEN, take)
+                .ok_or_else(|| anyhow::anyhow!("overflow page {} is truncated", page_no))?;
+            value.extend_from_slice(chunk);
+            page_no += 1;
+        }
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::disk::file_system::FsDiskManager;
+
+    #[test]
+    fn store_and_fetch_spanning_pages() -> anyhow::Result<()> {
+        let td = tempfile::tempdir()?;
+        let mut dm = FsDiskManager::new(td.path())?;
+        let toast = ToastFile::new(3);
+
+        let small = b"abc".to_vec();
+        let large: Vec<u8> = (0..CHUNK_LEN * 2 + 100).map(|i| (i % 251) as u8).collect();
+        let small_ptr = toast.store(&mut dm, &small)?;
+        let large_ptr = toast.store(&mut dm, &large)?;
+
+        assert_eq!(small_ptr, ToastPointer { page_no: 0, len: 3 });
+        assert_eq!(large_ptr.page_no, 1);
+        assert_eq!(dm.page_count(3)?, 4);
+        assert_eq!(toast.fetch(&dm, small_ptr)?, small);
+        assert_eq!(toast.fetch(&dm, large_ptr)?, large);
+        Ok(())
+    }
+
+    #[test]
+    fn freed_pages_are_reused() -> anyhow::Result<()> {
+        let td = tempfile::tempdir()?;
+        let mut dm = FsDiskManager::new(td.path())?;
+        let toast = ToastFile::new(3);
+
+        let two_pages = vec![1u8; CHUNK_LEN + 1];
+        let first = toast.store(&mut dm, &two_pages)?;
+        let second = toast.store(&mut dm, b"keep")?;
+        toast.free(&mut dm, first)?;
+        assert!(toast.fetch(&dm, first).is_err());
+        assert!(toast.free(&mut dm, first).is_err());
+
+        // A value needing more pages than the freed run goes at the end
+        let three_pages = vec![2u8; CHUNK_LEN * 2 + 1];
+        let third = toast.store(&mut dm, &three_pages)?;
+        assert_eq!(third.page_no, 3);
+
+        let reused = toast.store(&mut dm, b"small")?;
+        assert_eq!(reused.page_no, 0);
+        let reused_too = toast.store(&mut dm, b"also small")?;
+        assert_eq!(reused_too.page_no, 1);
+        assert_eq!(dm.page_count(3)?, 6);
+        assert_eq!(toast.fetch(&dm, second)?, b"keep");
+        assert_eq!(toast.fetch(&dm, third)?, three_pages);
+        assert_eq!(toast.fetch(&dm, reused_too)?, b"also small");
+        Ok(())
+    }
+}
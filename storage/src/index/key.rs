@@ -0,0 +1,213 @@
+use crate::query::types::{DataType, Value};
+
+// Every component starts with a tag byte so that NULL sorts after all
+// non-null values, matching ORDER BY's NULLS LAST.
+const TAG_VALUE: u8 = 0x01;
+const TAG_NULL: u8 = 0x02;
+
+// Varchar bytes are terminated by 0x00 0x00, with embedded 0x00 bytes
+// escaped as 0x00 0xFF, so a shorter string sorts before its extensions.
+const VARCHAR_ESCAPE: u8 = 0xFF;
+
+// Encodes a tuple of values into a byte string whose lexicographic order
+// matches the order of the tuples compared component by component.
+// Encoding a prefix of a tuple yields a prefix of the tuple's key, so
+// range scans over leading components work on the encoded keys directly.
+pub fn encode_key(values: &[Value]) -> Vec<u8> {
+    let mut key = Vec::new();
+    for value in values {
+        encode_component(value, &mut key);
+    }
+    key
+}
+
+fn encode_component(value: &Value, key: &mut Vec<u8>) {
+    match value {
+        Value::Null => key.push(TAG_NULL),
+        Value::Integer(i) => {
+            key.push(TAG_VALUE);
+            // Flipping the sign bit makes big-endian two's complement sort
+            // negative numbers first.
+            key.extend_from_slice(&((*i as u32) ^ 0x8000_0000).to_be_bytes());
+        }
+        Value::Boolean(b) => {
+            key.push(TAG_VALUE);
+            key.push(*b as u8);
+        }
+        Value::Varchar(s) => {
+            key.push(TAG_VALUE);
+            for &byte in s.as_bytes() {
+                key.push(byte);
+                if byte == 0 {
+                    key.push(VARCHAR_ESCAPE);
+                }
+            }
+            key.extend_from_slice(&[0, 0]);
+        }
+    }
+}
+
+pub fn decode_key(mut key: &[u8], types: &[DataType]) -> anyhow::Result<Vec<Value>> {
+    let mut values = Vec::with_capacity(types.len());
+    for data_type in types {
+        let (&tag, rest) = key
+            .split_first()
+            .ok_or_else(|| anyhow::anyhow!("Key ended before {:?} component", data_type))?;
+        key = rest;
+        if tag == TAG_NULL {
+            values.push(Value::Null);
+            continue;
+        }
+        if tag != TAG_VALUE {
+            anyhow::bail!("Invalid key component tag: {:#04x}", tag);
+        }
+
+        let value = match data_type {
+            DataType::Integer => {
+                let (bytes, rest) = key
+                    .split_first_chunk::<4>()
+                    .ok_or_else(|| anyhow::anyhow!("Truncated integer key component"))?;
+                key = rest;
+                Value::Integer((u32::from_be_bytes(*bytes) ^ 0x8000_0000) as i32)
+            }
+            DataType::Boolean => {
+                let (&byte, rest) = key
+                    .split_first()
+                    .ok_or_else(|| anyhow::anyhow!("Truncated boolean key component"))?;
+                key = rest;
+                Value::Boolean(byte != 0)
+            }
+            DataType::Varchar(_) => {
+                let mut bytes = Vec::new();
+                loop {
+                    match key {
+                        [0, 0, rest @ ..] => {
+                            key = rest;
+                            break;
+                        }
+                        [0, VARCHAR_ESCAPE, rest @ ..] => {
+                            bytes.push(0);
+                            key = rest;
+                        }
+                        [byte, rest @ ..] if *byte != 0 => {
+                            bytes.push(*byte);
+                            key = rest;
+                        }
+                        _ => anyhow::bail!("Unterminated varchar key component"),
+                    }
+                }
+                Value::Varchar(String::from_utf8(bytes)?)
+            }
+            DataType::Null => anyhow::bail!("Non-null key component for a NULL column"),
+        };
+        values.push(value);
+    }
+
+    if !key.is_empty() {
+        anyhow::bail!("{} trailing bytes after key", key.len());
+    }
+    Ok(values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    fn int(i: i32) -> Value {
+        Value::Integer(i)
+    }
+
+    fn text(s: &str) -> Value {
+        Value::Varchar(s.to_string())
+    }
+
+    #[test]
+    fn single_component_ordering() {
+        let ordered = [
+            vec![int(i32::MIN)],
+            vec![int(-1)],
+            vec![int(0)],
+            vec![int(1)],
+            vec![int(i32::MAX)],
+            vec![Value::Null],
+        ];
+        for pair in ordered.windows(2) {
+            assert!(encode_key(&pair[0]) < encode_key(&pair[1]), "{:?}", pair);
+        }
+
+        let ordered = [
+            text(""),
+            text("\0"),
+            text("a"),
+            text("a\0b"),
+            text("ab"),
+            text("b"),
+        ];
+        for pair in ordered.windows(2) {
+            assert!(encode_key(&[pair[0].clone()]) < encode_key(&[pair[1].clone()]));
+        }
+
+        assert!(encode_key(&[Value::Boolean(false)]) < encode_key(&[Value::Boolean(true)]));
+    }
+
+    #[test]
+    fn composite_keys_sort_lexicographically() {
+        let mut tuples = vec![
+            vec![text("b"), int(1)],
+            vec![text("a"), int(10)],
+            vec![text("ab"), int(-5)],
+            vec![text("a"), int(-3)],
+            vec![text("a"), Value::Null],
+            vec![text("b"), int(0)],
+        ];
+        let mut encoded: Vec<Vec<u8>> = tuples.iter().map(|t| encode_key(t)).collect();
+        encoded.sort();
+
+        tuples.sort_by_key(|t| encode_key(t));
+        assert_eq!(
+            tuples,
+            vec![
+                vec![text("a"), int(-3)],
+                vec![text("a"), int(10)],
+                vec![text("a"), Value::Null],
+                vec![text("ab"), int(-5)],
+                vec![text("b"), int(0)],
+                vec![text("b"), int(1)],
+            ]
+        );
+
+        let types = [DataType::Varchar(10), DataType::Integer];
+        for (key, tuple) in encoded.iter().zip(&tuples) {
+            assert_eq!(&decode_key(key, &types).unwrap(), tuple);
+        }
+    }
+
+    #[test]
+    fn range_scan_over_first_component() {
+        let mut index = BTreeMap::new();
+        for file_id in 0..4 {
+            for page_no in [7, 2, 5] {
+                index.insert(
+                    encode_key(&[int(file_id), int(page_no)]),
+                    (file_id, page_no),
+                );
+            }
+        }
+
+        let start = encode_key(&[int(2)]);
+        let end = encode_key(&[int(3)]);
+        let matches: Vec<_> = index.range(start..end).map(|(_, v)| *v).collect();
+        assert_eq!(matches, vec![(2, 2), (2, 5), (2, 7)]);
+    }
+
+    #[test]
+    fn decode_rejects_malformed_keys() {
+        let types = [DataType::Integer];
+        assert!(decode_key(&[TAG_VALUE, 0, 0], &types).is_err());
+        assert!(decode_key(&[0x07, 0, 0, 0, 0], &types).is_err());
+        assert!(decode_key(&[TAG_NULL, 0], &types).is_err());
+        assert!(decode_key(&[TAG_VALUE, b'a'], &[DataType::Varchar(1)]).is_err());
+        assert_eq!(decode_key(&[TAG_NULL], &types).unwrap(), vec![Value::Null]);
+    }
+}
@@ -1,4 +1,8 @@
+pub mod catalog;
+pub mod database;
 pub mod disk;
 pub mod heap;
+pub mod index;
 pub mod page;
 pub mod query;
+pub mod wal;
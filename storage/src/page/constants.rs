@@ -1 +1,8 @@
 pub const PAGE_SIZE: usize = 8192;
+// The last bytes of every page repeat the header's write generation so a
+// partially persisted (torn) write can be told apart from other corruption.
+pub const PAGE_TRAILER_LEN: usize = 4;
+pub const PAGE_DATA_END: usize = PAGE_SIZE - PAGE_TRAILER_LEN;
+// Stored in every page header. Bumped whenever the page layout changes so
+// pages written in an older layout are rejected rather than misread.
+pub const FORMAT_VERSION: u16 = 1;
@@ -1,5 +1,5 @@
 use crate::page::{
-    constants::PAGE_SIZE,
+    constants::{PAGE_DATA_END, PAGE_SIZE},
     page_header::PageHeader,
     page_id::{PageFlags, PageId},
 };
@@ -22,49 +22,92 @@ impl Page {
     }
 
     pub fn header(&self) -> PageHeader {
-        let mut hdr = PageHeader {
+        PageHeader {
             checksum: u32::from_le_bytes(self.buf[0..4].try_into().unwrap()),
             page_id: u64::from_le_bytes(self.buf[4..12].try_into().unwrap()),
             page_lsn: u64::from_le_bytes(self.buf[12..20].try_into().unwrap()),
             page_flags: u16::from_le_bytes(self.buf[20..22].try_into().unwrap()),
             lower: u16::from_le_bytes(self.buf[22..24].try_into().unwrap()),
             upper: u16::from_le_bytes(self.buf[24..26].try_into().unwrap()),
-            reserved: [0u8; 6],
-        };
-        hdr.reserved.copy_from_slice(&self.buf[26..32]);
-        hdr
+            generation: u32::from_le_bytes(self.buf[26..30].try_into().unwrap()),
+            format_version: self.format_version(),
+        }
     }
     pub fn write_header(&mut self, hdr: &PageHeader) {
         self.buf[0..4].copy_from_slice(&hdr.checksum.to_le_bytes());
         self.buf[4..12].copy_from_slice(&hdr.page_id.to_le_bytes());
-        self.buf[12..20].copy_from_slice(&hdr.page_lsn.to_le_bytes());
+        self.set_lsn(hdr.page_lsn);
         self.buf[20..22].copy_from_slice(&hdr.page_flags.to_le_bytes());
-        self.buf[22..24].copy_from_slice(&hdr.lower.to_le_bytes());
-        self.buf[24..26].copy_from_slice(&hdr.upper.to_le_bytes());
-        self.buf[26..32].copy_from_slice(&hdr.reserved);
+        self.set_lower(hdr.lower);
+        self.set_upper(hdr.upper);
+        self.buf[26..30].copy_from_slice(&hdr.generation.to_le_bytes());
+        self.buf[30..32].copy_from_slice(&hdr.format_version.to_le_bytes());
+    }
+
+    // Sets lower and upper together, keeping the checksum current
+    pub fn patch_lower_upper(&mut self, lower: u16, upper: u16) {
+        self.patch(PageHeader::LOWER_OFFSET, &lower.to_le_bytes());
+        self.patch(PageHeader::UPPER_OFFSET, &upper.to_le_bytes());
+    }
+
+    // Sets page_lsn, keeping the checksum current
+    pub fn patch_lsn(&mut self, lsn: u64) {
+        self.patch(PageHeader::LSN_OFFSET, &lsn.to_le_bytes());
     }
 
     pub fn set_lsn(&mut self, lsn: u64) {
-        self.buf[12..20].copy_from_slice(&lsn.to_le_bytes());
+        let offset = PageHeader::LSN_OFFSET;
+        self.buf[offset..offset + 8].copy_from_slice(&lsn.to_le_bytes());
     }
 
     pub fn set_lower(&mut self, lower: u16) {
-        self.buf[22..24].copy_from_slice(&lower.to_le_bytes());
+        self.write_u16(PageHeader::LOWER_OFFSET, lower);
     }
 
     pub fn set_upper(&mut self, upper: u16) {
-        self.buf[24..26].copy_from_slice(&upper.to_le_bytes());
+        self.write_u16(PageHeader::UPPER_OFFSET, upper);
     }
 
+    // A corrupt header with `lower` past `upper` has no free space
     pub fn free_space(&self) -> usize {
         let hdr = self.header();
-        (hdr.upper - hdr.lower) as usize
+        hdr.upper.saturating_sub(hdr.lower) as usize
     }
 
     pub fn page_id(&self) -> PageId {
         PageId(u64::from_le_bytes(self.buf[4..12].try_into().unwrap()))
     }
 
+    pub fn format_version(&self) -> u16 {
+        u16::from_le_bytes(self.buf[30..32].try_into().unwrap())
+    }
+
+    pub fn generation(&self) -> u32 {
+        let offset = PageHeader::GENERATION_OFFSET;
+        u32::from_le_bytes(self.buf[offset..offset + 4].try_into().unwrap())
+    }
+
+    fn trailer_generation(&self) -> u32 {
+        u32::from_le_bytes(self.buf[PAGE_DATA_END..].try_into().unwrap())
+    }
+
+    // A page whose head and tail generations differ was only partly written.
+    pub fn is_torn(&self) -> bool {
+        self.generation() != self.trailer_generation()
+    }
+
+    // Advances the write generation at both ends of the page. Called for
+    // every page write so a torn write leaves mismatched markers behind.
+    pub fn bump_generation(&mut self) {
+        self.set_generation(self.generation().wrapping_add(1));
+    }
+
+    pub fn set_generation(&mut self, generation: u32) {
+        let generation = generation.to_le_bytes();
+        self.patch(PageHeader::GENERATION_OFFSET, &generation);
+        self.patch(PAGE_DATA_END, &generation);
+    }
+
     pub fn verify_checksum(&self) -> bool {
         let mut hasher = Hasher::new();
         hasher.update(&self.buf[4..]);
@@ -80,8 +123,66 @@ impl Page {
         self.buf[0..4].copy_from_slice(&sum.to_le_bytes());
     }
 
-    pub fn read_u16(&self, offset: usize) -> u16 {
-        u16::from_le_bytes(self.buf[offset..offset + 2].try_into().unwrap())
+    // Overwrites `bytes` at `offset` and folds only the changed range into the
+    // checksum rather than rehashing the page. The checksum must be current
+    // beforehand, or it stays wrong afterwards, so debug builds check it.
+    // Call `recompute_checksum` after unchecked writes before patching.
+    pub fn patch(&mut self, offset: usize, bytes: &[u8]) {
+        assert!(offset >= 4, "cannot patch the checksum field");
+        debug_assert!(
+            self.verify_checksum(),
+            "patching {:?} whose checksum is stale",
+            self.page_id()
+        );
+        let end = offset + bytes.len();
+        let delta: Vec<u8> = self.buf[offset..end]
+            .iter()
+            .zip(bytes)
+            .map(|(old, new)| old ^ new)
+            .collect();
+        self.buf[offset..end].copy_from_slice(bytes);
+
+        // CRC-32 is linear, so for inputs of equal length the checksum changes
+        // by the CRC of the XOR difference taken with a zero register and no
+        // final inversion. Zeros before the changed range leave that CRC at
+        // zero, and the zeros after it are applied as a shift via `combine`.
+        let mut raw = Hasher::new_with_initial(!0);
+        raw.update(&delta);
+        let mut shifted = Hasher::new_with_initial(!raw.finalize());
+        shifted.combine(&Hasher::new_with_initial_len(0, (PAGE_SIZE - end) as u64));
+
+        let sum = u32::from_le_bytes(self.buf[0..4].try_into().unwrap()) ^ shifted.finalize();
+        self.buf[0..4].copy_from_slice(&sum.to_le_bytes());
+    }
+
+    // Checked view of `len` bytes at `offset`; None if any of them lie
+    // outside the page.
+    pub fn read_bytes(&self, offset: usize, len: usize) -> Option<&[u8]> {
+        self.buf.get(offset..offset.checked_add(len)?)
+    }
+
+    // Checked copy of `data` to `offset`. Like `write_u16`, it leaves the
+    // checksum stale.
+    pub fn write_bytes(&mut self, offset: usize, data: &[u8]) -> anyhow::Result<()> {
+        let target = offset
+            .checked_add(data.len())
+            .and_then(|end| self.buf.get_mut(offset..end))
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "write of {} bytes at offset {} is outside the page",
+                    data.len(),
+                    offset
+                )
+            })?;
+        target.copy_from_slice(data);
+        Ok(())
+    }
+
+    // None if the two bytes at `offset` are not both within the page
+    pub fn read_u16(&self, offset: usize) -> Option<u16> {
+        Some(u16::from_le_bytes(
+            self.read_bytes(offset, 2)?.try_into().ok()?,
+        ))
     }
 
     pub fn write_u16(&mut self, offset: usize, value: u16) {
@@ -116,7 +217,8 @@ mod tests {
         hdr.page_lsn = 12345;
         hdr.lower = 64;
         hdr.upper = 8000;
-        hdr.reserved = [1, 2, 3, 4, 5, 6];
+        hdr.generation = 77;
+        hdr.format_version = 513;
 
         pg.write_header(&hdr);
         let read_hdr = pg.header();
@@ -126,7 +228,8 @@ mod tests {
         assert_eq!(read_hdr.page_flags, PageFlags::Index as u16);
         assert_eq!(read_hdr.lower, 64);
         assert_eq!(read_hdr.upper, 8000);
-        assert_eq!(read_hdr.reserved, [1, 2, 3, 4, 5, 6]);
+        assert_eq!(read_hdr.generation, 77);
+        assert_eq!(read_hdr.format_version, 513);
     }
 
     #[test]
@@ -151,8 +254,8 @@ mod tests {
 
         let initial_hdr = pg.header();
         assert_eq!(initial_hdr.lower, 32);
-        assert_eq!(initial_hdr.upper, 8192);
-        assert_eq!(pg.free_space(), 8160);
+        assert_eq!(initial_hdr.upper, 8188);
+        assert_eq!(pg.free_space(), 8156);
 
         pg.set_lower(100);
         pg.set_upper(8000);
@@ -172,8 +275,12 @@ mod tests {
         assert_eq!(pg.free_space(), 0);
 
         pg.set_lower(32);
-        pg.set_upper(8192);
-        assert_eq!(pg.free_space(), 8160);
+        pg.set_upper(8188);
+        assert_eq!(pg.free_space(), 8156);
+
+        pg.set_lower(5000);
+        pg.set_upper(4000);
+        assert_eq!(pg.free_space(), 0);
     }
 
     #[test]
@@ -185,8 +292,10 @@ mod tests {
 
         for (offset, value) in test_cases {
             pg.write_u16(offset, value);
-            assert_eq!(pg.read_u16(offset), value);
+            assert_eq!(pg.read_u16(offset), Some(value));
         }
+        assert_eq!(pg.read_u16(PAGE_SIZE - 1), None);
+        assert_eq!(pg.read_u16(usize::MAX), None);
     }
 
     #[test]
@@ -226,4 +335,71 @@ mod tests {
         assert_eq!(original_pg.buf[200], 0);
         assert_eq!(modified_clone.buf[200], 99);
     }
+
+    #[test]
+    fn patched_checksum_matches_full_recompute() {
+        let pid = PageId::new(4, 40);
+        let mut pg = Page::new(pid, PageFlags::Heap);
+
+        let edits: [(usize, &[u8]); 6] = [
+            (4, &[0xAB]),
+            (22, &64u16.to_le_bytes()),
+            (100, b"hello world"),
+            (101, b"ELLO"),
+            (PAGE_SIZE - 4, &[1, 2, 3, 4]),
+            (32, &[0xFF; 300]),
+        ];
+        for (offset, bytes) in edits {
+            pg.patch(offset, bytes);
+            assert_eq!(&pg.buf[offset..offset + bytes.len()], bytes);
+            assert!(pg.verify_checksum());
+
+            let mut recomputed = pg.clone();
+            recomputed.recompute_checksum();
+            assert_eq!(recomputed.buf[0..4], pg.buf[0..4]);
+        }
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "checksum is stale")]
+    fn patching_a_stale_checksum_panics() {
+        let mut pg = Page::new(PageId::new(4, 41), PageFlags::Heap);
+        pg.write_u16(100, 7);
+        pg.patch(200, b"x");
+    }
+
+    #[test]
+    fn checked_accessors_reject_out_of_range() {
+        let mut pg = Page::new(PageId::new(1, 0), PageFlags::Heap);
+
+        pg.write_bytes(100, b"abc").unwrap();
+        assert_eq!(pg.read_bytes(100, 3), Some(&b"abc"[..]));
+        assert_eq!(pg.read_bytes(PAGE_SIZE - 2, 2).map(<[u8]>::len), Some(2));
+        assert_eq!(pg.read_bytes(PAGE_SIZE, 0), Some(&[][..]));
+
+        assert!(pg.read_bytes(PAGE_SIZE - 2, 3).is_none());
+        assert!(pg.read_bytes(PAGE_SIZE + 1, 0).is_none());
+        assert!(pg.read_bytes(usize::MAX, 2).is_none());
+        let before = pg.buf;
+        assert!(pg.write_bytes(PAGE_SIZE - 1, b"ab").is_err());
+        assert!(pg.write_bytes(usize::MAX, b"a").is_err());
+        assert_eq!(pg.buf, before);
+    }
+
+    #[test]
+    fn generation_markers() {
+        let mut pg = Page::new(PageId::new(1, 3), PageFlags::Heap);
+        assert_eq!(pg.generation(), 0);
+        assert!(!pg.is_torn());
+
+        pg.bump_generation();
+        pg.bump_generation();
+        assert_eq!(pg.generation(), 2);
+        assert!(!pg.is_torn());
+        assert!(pg.verify_checksum());
+
+        pg.buf[PAGE_DATA_END] ^= 0xFF;
+        assert!(pg.is_torn());
+    }
 }
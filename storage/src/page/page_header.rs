@@ -1,5 +1,5 @@
 use crate::page::{
-    constants::PAGE_SIZE,
+    constants::{FORMAT_VERSION, PAGE_DATA_END},
     page_id::{PageFlags, PageId},
 };
 
@@ -11,11 +11,17 @@ pub struct PageHeader {
     pub page_flags: u16,
     pub lower: u16,
     pub upper: u16,
-    pub reserved: [u8; 6],
+    pub generation: u32,
+    pub format_version: u16,
 }
 
 impl PageHeader {
     pub const LEN: usize = 32;
+    // Byte offsets of the fields within the page
+    pub const LSN_OFFSET: usize = 12;
+    pub const LOWER_OFFSET: usize = 22;
+    pub const UPPER_OFFSET: usize = 24;
+    pub const GENERATION_OFFSET: usize = 26;
 
     pub fn new(pid: PageId, flags: PageFlags) -> Self {
         Self {
@@ -24,8 +30,9 @@ impl PageHeader {
             page_lsn: 0,
             page_flags: flags as u16,
             lower: Self::LEN as u16,
-            upper: PAGE_SIZE as u16,
-            reserved: [0u8; 6],
+            upper: PAGE_DATA_END as u16,
+            generation: 0,
+            format_version: FORMAT_VERSION,
         }
     }
 }
@@ -44,8 +51,9 @@ mod tests {
         assert_eq!(hdr.page_lsn, 0);
         assert_eq!(hdr.page_flags, PageFlags::Heap as u16);
         assert_eq!(hdr.lower, PageHeader::LEN as u16);
-        assert_eq!(hdr.upper, PAGE_SIZE as u16);
-        assert_eq!(hdr.reserved, [0u8; 6]);
+        assert_eq!(hdr.upper, PAGE_DATA_END as u16);
+        assert_eq!(hdr.generation, 0);
+        assert_eq!(hdr.format_version, FORMAT_VERSION);
     }
 
     #[test]
@@ -74,14 +82,15 @@ mod tests {
         let hdr = PageHeader::new(pid, PageFlags::Heap);
 
         assert_eq!(hdr.lower, 32);
-        assert_eq!(hdr.upper, 8192);
+        assert_eq!(hdr.upper, 8188);
 
         let free_space = hdr.upper - hdr.lower;
-        assert_eq!(free_space, PAGE_SIZE as u16 - PageHeader::LEN as u16);
-        assert_eq!(free_space, 8160);
+        assert_eq!(free_space, PAGE_DATA_END as u16 - PageHeader::LEN as u16);
+        assert_eq!(free_space, 8156);
     }
 
     #[test]
+    #[allow(clippy::clone_on_copy)]
     fn page_header_clone_copy() {
         let pid = PageId::new(5, 123);
         let original_hdr = PageHeader::new(pid, PageFlags::Index);
@@ -6,6 +6,10 @@ pub enum PageFlags {
     Heap = 1,
     Index = 2,
     Meta = 4,
+    // Holds part of a large value stored out of line
+    Overflow = 8,
+    // An overflow page whose value was deleted, free to be reused
+    FreeOverflow = 16,
 }
 
 #[derive(
@@ -91,9 +95,13 @@ mod tests {
         assert_eq!(PageFlags::Heap as u16, 1);
         assert_eq!(PageFlags::Index as u16, 2);
         assert_eq!(PageFlags::Meta as u16, 4);
+        assert_eq!(PageFlags::Overflow as u16, 8);
+        assert_eq!(PageFlags::FreeOverflow as u16, 16);
 
         assert_eq!((PageFlags::Heap as u16).count_ones(), 1);
         assert_eq!((PageFlags::Index as u16).count_ones(), 1);
         assert_eq!((PageFlags::Meta as u16).count_ones(), 1);
+        assert_eq!((PageFlags::Overflow as u16).count_ones(), 1);
+        assert_eq!((PageFlags::FreeOverflow as u16).count_ones(), 1);
     }
 }
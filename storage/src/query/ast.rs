@@ -1,16 +1,81 @@
 use crate::query::types::Value;
+use std::fmt;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Statement {
     Select(SelectStatement),
+    // VALUES (..), (..): literal rows with no table behind them
+    Values(Vec<Vec<Expression>>),
+    // left INTERSECT/EXCEPT [ALL] right
+    SetOperation {
+        op: SetOperator,
+        all: bool,
+        left: Box<Statement>,
+        right: Box<Statement>,
+    },
+    Explain {
+        analyze: bool,
+        statement: Box<Statement>,
+    },
+    // INSERT INTO table [(columns)] VALUES (..), (..); with no column list
+    // the values are in table order
+    Insert {
+        table: String,
+        columns: Option<Vec<ColumnName>>,
+        rows: Vec<Vec<Expression>>,
+    },
+    // UPDATE table SET column = expr, ... [WHERE predicate]
+    Update {
+        table: String,
+        assignments: Vec<(ColumnName, Expression)>,
+        where_clause: Option<Expression>,
+    },
+    // DELETE FROM table [WHERE predicate]; without a predicate every row goes
+    Delete {
+        table: String,
+        where_clause: Option<Expression>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SetOperator {
+    Intersect,
+    Except,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct SelectStatement {
+    // SELECT DISTINCT drops repeated result rows
+    pub distinct: bool,
     pub select_list: Vec<SelectItem>,
+    // SELECT ... INTO table, which creates the table from the result
+    pub into: Option<String>,
     pub from: Option<String>,
+    // FROM table [AS] alias; the alias replaces the table name as the
+    // qualifier of its columns
+    pub from_alias: Option<String>,
+    // FROM name(args): a set-returning function such as GENERATE_SERIES in
+    // place of a table; always an `Expression::Function`
+    pub table_function: Option<Box<Expression>>,
+    // Tables joined onto `from`, in order
+    pub joins: Vec<Join>,
     pub where_clause: Option<Expression>,
+    pub group_by: Vec<Expression>,
+    // HAVING predicate, applied to the groups
+    pub having: Option<Expression>,
+    // ORDER BY keys, each with true for DESC
+    pub order_by: Vec<(Expression, bool)>,
     pub limit: Option<u32>,
+    // LIMIT n PERCENT
+    pub limit_percent: bool,
+}
+
+// [INNER] JOIN table [[AS] alias] ON condition
+#[derive(Debug, Clone, PartialEq)]
+pub struct Join {
+    pub table: String,
+    pub alias: Option<String>,
+    pub condition: Expression,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -22,19 +87,71 @@ pub enum SelectItem {
     },
 }
 
+// A column named by INSERT or UPDATE, with its quotes removed. A quoted
+// name is matched exactly; an unquoted one may match ignoring case.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnName {
+    pub name: String,
+    pub quoted: bool,
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum Expression {
+    // `quoted` is set for a double-quoted name, which must match a column
+    // exactly, and cleared once the planner has resolved the name
     Column {
         name: String,
+        quoted: bool,
     },
     Literal {
         value: Value,
     },
+    // A `$n` placeholder, numbered from 1, that is replaced by a value
+    // before the plan runs
+    Parameter {
+        index: usize,
+    },
     BinaryOp {
         left: Box<Expression>,
         op: BinaryOperator,
         right: Box<Expression>,
     },
+    Function {
+        name: String,
+        args: Vec<Expression>,
+    },
+    // expr [NOT] IN (list)
+    InList {
+        expr: Box<Expression>,
+        list: Vec<Expression>,
+        negated: bool,
+    },
+    // NOT expr
+    Not(Box<Expression>),
+    // expr [NOT] BETWEEN [SYMMETRIC] low AND high, bounds included. With
+    // SYMMETRIC the bounds may come in either order.
+    Between {
+        expr: Box<Expression>,
+        low: Box<Expression>,
+        high: Box<Expression>,
+        negated: bool,
+        symmetric: bool,
+    },
+    // COUNT(*) when `arg` is None, otherwise func(arg) over the rows of a
+    // group; NULL arguments are skipped
+    Aggregate {
+        func: AggregateFunction,
+        arg: Option<Box<Expression>>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AggregateFunction {
+    Count,
+    Sum,
+    Avg,
+    Min,
+    Max,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -49,26 +166,143 @@ pub enum BinaryOperator {
     Sub,
     Mul,
     Div,
+    Mod,
     And,
     Or,
+    Like,
+    // `IS` and `IS NOT`; the parser only builds these against a NULL literal
+    Is,
+    IsNot,
+}
+
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expression::Column { name, quoted } if *quoted => {
+                write!(f, "\"{}\"", name.replace('"', "\"\""))
+            }
+            Expression::Column { name, .. } => write!(f, "{}", name),
+            Expression::Literal { value } => write!(f, "{}", value),
+            Expression::Parameter { index } => write!(f, "${}", index),
+            Expression::BinaryOp { left, op, right } => write!(f, "({} {} {})", left, op, right),
+            Expression::Function { name, args } => {
+                let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+                write!(f, "{}({})", name, args.join(", "))
+            }
+            Expression::InList {
+                expr,
+                list,
+                negated,
+            } => {
+                let list: Vec<String> = list.iter().map(|item| item.to_string()).collect();
+                let not = if *negated { "NOT " } else { "" };
+                write!(f, "({} {}IN ({}))", expr, not, list.join(", "))
+            }
+            Expression::Not(expr) => write!(f, "(NOT {})", expr),
+            Expression::Between {
+                expr,
+                low,
+                high,
+                negated,
+                symmetric,
+            } => {
+                let not = if *negated { "NOT " } else { "" };
+                let symmetric = if *symmetric { "SYMMETRIC " } else { "" };
+                write!(
+                    f,
+                    "({} {}BETWEEN {}{} AND {})",
+                    expr, not, symmetric, low, high
+                )
+            }
+            Expression::Aggregate { func, arg: None } => write!(f, "{}(*)", func),
+            Expression::Aggregate {
+                func,
+                arg: Some(arg),
+            } => write!(f, "{}({})", func, arg),
+        }
+    }
+}
+
+impl fmt::Display for AggregateFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            AggregateFunction::Count => "COUNT",
+            AggregateFunction::Sum => "SUM",
+            AggregateFunction::Avg => "AVG",
+            AggregateFunction::Min => "MIN",
+            AggregateFunction::Max => "MAX",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl fmt::Display for BinaryOperator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let symbol = match self {
+            BinaryOperator::Eq => "=",
+            BinaryOperator::Ne => "!=",
+            BinaryOperator::Lt => "<",
+            BinaryOperator::Le => "<=",
+            BinaryOperator::Gt => ">",
+            BinaryOperator::Ge => ">=",
+            BinaryOperator::Add => "+",
+            BinaryOperator::Sub => "-",
+            BinaryOperator::Mul => "*",
+            BinaryOperator::Div => "/",
+            BinaryOperator::Mod => "%",
+            BinaryOperator::And => "AND",
+            BinaryOperator::Or => "OR",
+            BinaryOperator::Like => "LIKE",
+            BinaryOperator::Is => "IS",
+            BinaryOperator::IsNot => "IS NOT",
+        };
+        write!(f, "{}", symbol)
+    }
+}
+
+impl ColumnName {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            quoted: false,
+        }
+    }
 }
 
 impl SelectStatement {
     pub fn select_all_from(table: &str) -> Self {
         Self {
+            distinct: false,
             select_list: vec![SelectItem::Wildcard],
+            into: None,
             from: Some(table.to_string()),
+            from_alias: None,
+            table_function: None,
+            joins: Vec::new(),
             where_clause: None,
+            group_by: Vec::new(),
+            having: None,
+            order_by: Vec::new(),
             limit: None,
+            limit_percent: false,
         }
     }
 
     pub fn select_expression(expr: Expression) -> Self {
         Self {
+            distinct: false,
             select_list: vec![SelectItem::Expression { expr, alias: None }],
+            into: None,
             from: None,
+            from_alias: None,
+            table_function: None,
+            joins: Vec::new(),
             where_clause: None,
+            group_by: Vec::new(),
+            having: None,
+            order_by: Vec::new(),
             limit: None,
+            limit_percent: false,
         }
     }
 }
@@ -77,6 +311,14 @@ impl Expression {
     pub fn column(name: &str) -> Self {
         Self::Column {
             name: name.to_string(),
+            quoted: false,
+        }
+    }
+
+    pub fn quoted_column(name: &str) -> Self {
+        Self::Column {
+            name: name.to_string(),
+            quoted: true,
         }
     }
 
@@ -98,6 +340,13 @@ impl Expression {
         }
     }
 
+    pub fn function(name: &str, args: Vec<Expression>) -> Self {
+        Self::Function {
+            name: name.to_string(),
+            args,
+        }
+    }
+
     pub fn eq(left: Expression, right: Expression) -> Self {
         Self::BinaryOp {
             left: Box::new(left),
@@ -106,6 +355,7 @@ impl Expression {
         }
     }
 
+    #[allow(clippy::should_implement_trait)]
     pub fn add(left: Expression, right: Expression) -> Self {
         Self::BinaryOp {
             left: Box::new(left),
@@ -113,6 +363,111 @@ impl Expression {
             right: Box::new(right),
         }
     }
+
+    pub fn children(&self) -> Vec<&Expression> {
+        match self {
+            Expression::Column { .. }
+            | Expression::Literal { .. }
+            | Expression::Parameter { .. } => Vec::new(),
+            Expression::BinaryOp { left, right, .. } => vec![left, right],
+            Expression::Function { args, .. } => args.iter().collect(),
+            Expression::InList { expr, list, .. } => {
+                std::iter::once(expr.as_ref()).chain(list).collect()
+            }
+            Expression::Not(expr) => vec![expr],
+            Expression::Between {
+                expr, low, high, ..
+            } => vec![expr, low, high],
+            Expression::Aggregate { arg, .. } => arg.iter().map(|arg| arg.as_ref()).collect(),
+        }
+    }
+
+    fn children_mut(&mut self) -> Vec<&mut Expression> {
+        match self {
+            Expression::Column { .. }
+            | Expression::Literal { .. }
+            | Expression::Parameter { .. } => Vec::new(),
+            Expression::BinaryOp { left, right, .. } => vec![left, right],
+            Expression::Function { args, .. } => args.iter_mut().collect(),
+            Expression::InList { expr, list, .. } => {
+                std::iter::once(expr.as_mut()).chain(list).collect()
+            }
+            Expression::Not(expr) => vec![expr],
+            Expression::Between {
+                expr, low, high, ..
+            } => vec![expr, low, high],
+            Expression::Aggregate { arg, .. } => arg.iter_mut().map(|arg| arg.as_mut()).collect(),
+        }
+    }
+
+    pub fn contains_aggregate(&self) -> bool {
+        let mut found = false;
+        self.walk(&mut |node| found |= matches!(node, Expression::Aggregate { .. }));
+        found
+    }
+
+    // Visits every node, parents before children.
+    pub fn walk(&self, visit: &mut impl FnMut(&Expression)) {
+        visit(self);
+        for child in self.children() {
+            child.walk(visit);
+        }
+    }
+
+    // Visits every node in place, parents before children. Children are only
+    // visited when `visit` returns true, so a replaced node is not descended into.
+    pub fn walk_mut(&mut self, visit: &mut impl FnMut(&mut Expression) -> bool) {
+        if visit(self) {
+            for child in self.children_mut() {
+                child.walk_mut(visit);
+            }
+        }
+    }
+
+    // Rebuilds the tree bottom-up: each node is passed to `f` after its
+    // children have been mapped.
+    pub fn map(self, f: &mut impl FnMut(Expression) -> Expression) -> Expression {
+        let mapped = match self {
+            Expression::BinaryOp { left, op, right } => Expression::BinaryOp {
+                left: Box::new(left.map(f)),
+                op,
+                right: Box::new(right.map(f)),
+            },
+            Expression::Function { name, args } => Expression::Function {
+                name,
+                args: args.into_iter().map(|arg| arg.map(f)).collect(),
+            },
+            Expression::InList {
+                expr,
+                list,
+                negated,
+            } => Expression::InList {
+                expr: Box::new(expr.map(f)),
+                list: list.into_iter().map(|item| item.map(f)).collect(),
+                negated,
+            },
+            Expression::Not(expr) => Expression::Not(Box::new(expr.map(f))),
+            Expression::Between {
+                expr,
+                low,
+                high,
+                negated,
+                symmetric,
+            } => Expression::Between {
+                expr: Box::new(expr.map(f)),
+                low: Box::new(low.map(f)),
+                high: Box::new(high.map(f)),
+                negated,
+                symmetric,
+            },
+            Expression::Aggregate { func, arg } => Expression::Aggregate {
+                func,
+                arg: arg.map(|arg| Box::new(arg.map(f))),
+            },
+            leaf => leaf,
+        };
+        f(mapped)
+    }
 }
 
 #[cfg(test)]
@@ -139,7 +494,8 @@ mod tests {
         assert_eq!(
             col_expr,
             Expression::Column {
-                name: "name".to_string()
+                name: "name".to_string(),
+                quoted: false,
             }
         );
         assert_eq!(
@@ -181,4 +537,75 @@ mod tests {
             panic!("Expected BinaryOp");
         }
     }
+
+    #[test]
+    fn test_walk_counts_nodes() {
+        let expr = Expression::function(
+            "GREATEST",
+            vec![
+                Expression::add(Expression::column("id"), Expression::integer(1)),
+                Expression::column("id"),
+            ],
+        );
+
+        let mut nodes = 0;
+        let mut columns = 0;
+        expr.walk(&mut |node| {
+            nodes += 1;
+            if matches!(node, Expression::Column { .. }) {
+                columns += 1;
+            }
+        });
+
+        assert_eq!(nodes, 5);
+        assert_eq!(columns, 2);
+    }
+
+    #[test]
+    fn test_map_rewrites_columns() {
+        let expr = Expression::eq(
+            Expression::add(Expression::column("id"), Expression::integer(1)),
+            Expression::function("TRIM", vec![Expression::column("name")]),
+        );
+
+        let mapped = expr.map(&mut |node| match node {
+            Expression::Column { name, .. } => Expression::column(&format!("t.{}", name)),
+            other => other,
+        });
+
+        assert_eq!(
+            mapped,
+            Expression::eq(
+                Expression::add(Expression::column("t.id"), Expression::integer(1)),
+                Expression::function("TRIM", vec![Expression::column("t.name")]),
+            )
+        );
+    }
+
+    #[test]
+    fn test_walk_mut_stops_at_replaced_nodes() {
+        let mut expr = Expression::add(
+            Expression::add(Expression::column("a"), Expression::column("b")),
+            Expression::column("c"),
+        );
+
+        let mut visited = Vec::new();
+        expr.walk_mut(&mut |node| match node {
+            Expression::BinaryOp { right, .. } if **right == Expression::column("b") => {
+                *node = Expression::integer(0);
+                false
+            }
+            Expression::Column { name, .. } => {
+                visited.push(name.clone());
+                true
+            }
+            _ => true,
+        });
+
+        assert_eq!(
+            expr,
+            Expression::add(Expression::integer(0), Expression::column("c"))
+        );
+        assert_eq!(visited, vec!["c".to_string()]);
+    }
 }
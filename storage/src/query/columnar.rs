@@ -0,0 +1,175 @@
+use crate::query::executor::QueryResult;
+use crate::query::types::{DataType, Value};
+
+// Column-at-a-time copy of a QueryResult, laid out the way Arrow and
+// dataframe libraries expect: one typed vector per column plus a validity
+// bitmap (bit i, least significant first, set when row i is not NULL).
+// NULL slots hold the type's default value.
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ColumnValues {
+    Integer(Vec<i32>),
+    Varchar(Vec<String>),
+    Boolean(Vec<bool>),
+    // A column of untyped NULLs only has a length
+    Null(usize),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnVector {
+    pub name: String,
+    pub values: ColumnValues,
+    pub validity: Vec<u8>,
+}
+
+impl ColumnVector {
+    pub fn is_null(&self, row: usize) -> bool {
+        self.validity[row / 8] & (1 << (row % 8)) == 0
+    }
+
+    pub fn null_count(&self) -> usize {
+        let len = match &self.values {
+            ColumnValues::Integer(v) => v.len(),
+            ColumnValues::Varchar(v) => v.len(),
+            ColumnValues::Boolean(v) => v.len(),
+            ColumnValues::Null(len) => *len,
+        };
+        (0..len).filter(|&row| self.is_null(row)).count()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnarBatch {
+    pub num_rows: usize,
+    pub columns: Vec<ColumnVector>,
+}
+
+impl QueryResult {
+    pub fn to_columnar(&self) -> anyhow::Result<ColumnarBatch> {
+        let num_rows = self.rows.len();
+        let mut columns = Vec::with_capacity(self.schema.columns.len());
+
+        for (index, column) in self.schema.columns.iter().enumerate() {
+            let mut validity = vec![0u8; num_rows.div_ceil(8)];
+            let mut values = match column.data_type {
+                DataType::Integer => ColumnValues::Integer(Vec::with_capacity(num_rows)),
+                DataType::Varchar(_) => ColumnValues::Varchar(Vec::with_capacity(num_rows)),
+                DataType::Boolean => ColumnValues::Boolean(Vec::with_capacity(num_rows)),
+                DataType::Null => ColumnValues::Null(num_rows),
+            };
+
+            for (row_no, row) in self.rows.iter().enumerate() {
+                let value = row
+                    .get(index)
+                    .ok_or_else(|| anyhow::anyhow!("Row {} has no column {}", row_no, index))?;
+                if *value != Value::Null {
+                    validity[row_no / 8] |= 1 << (row_no % 8);
+                }
+                match (&mut values, value) {
+                    (ColumnValues::Integer(v), Value::Integer(i)) => v.push(*i),
+                    (ColumnValues::Integer(v), Value::Null) => v.push(0),
+                    (ColumnValues::Varchar(v), Value::Varchar(s)) => v.push(s.clone()),
+                    (ColumnValues::Varchar(v), Value::Null) => v.push(String::new()),
+                    (ColumnValues::Boolean(v), Value::Boolean(b)) => v.push(*b),
+                    (ColumnValues::Boolean(v), Value::Null) => v.push(false),
+                    (ColumnValues::Null(_), Value::Null) => {}
+                    _ => anyhow::bail!(
+                        "Value {:?} does not match column '{}' of type {:?}",
+                        value,
+                        column.name,
+                        column.data_type
+                    ),
+                }
+            }
+
+            columns.push(ColumnVector {
+                name: column.name.clone(),
+                values,
+                validity,
+            });
+        }
+
+        Ok(ColumnarBatch { num_rows, columns })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::query::types::{Column, Schema};
+
+    fn column(name: &str, data_type: DataType) -> Column {
+        Column {
+            name: name.to_string(),
+            data_type,
+            nullable: true,
+        }
+    }
+
+    #[test]
+    fn mixed_result_to_columns() -> anyhow::Result<()> {
+        let result = QueryResult {
+            schema: Schema::new(vec![
+                column("id", DataType::Integer),
+                column("name", DataType::Varchar(255)),
+                column("active", DataType::Boolean),
+                column("nothing", DataType::Null),
+            ]),
+            rows: (0..10)
+                .map(|i| {
+                    vec![
+                        Value::Integer(i),
+                        if i == 3 {
+                            Value::Null
+                        } else {
+                            Value::Varchar(format!("user_{}", i))
+                        },
+                        if i == 9 {
+                            Value::Null
+                        } else {
+                            Value::Boolean(i % 2 == 0)
+                        },
+                        Value::Null,
+                    ]
+                })
+                .collect(),
+        };
+
+        let batch = result.to_columnar()?;
+        assert_eq!(batch.num_rows, 10);
+        let [id, name, active, nothing] = &batch.columns[..] else {
+            panic!("Expected four columns");
+        };
+
+        assert_eq!(id.values, ColumnValues::Integer((0..10).collect()));
+        assert_eq!(id.validity, vec![0xFF, 0x03]);
+        assert_eq!(id.null_count(), 0);
+
+        let ColumnValues::Varchar(names) = &name.values else {
+            panic!("Expected varchar column");
+        };
+        assert_eq!(names[2], "user_2");
+        assert_eq!(names[3], "");
+        assert!(name.is_null(3));
+        assert_eq!(name.null_count(), 1);
+
+        let ColumnValues::Boolean(flags) = &active.values else {
+            panic!("Expected boolean column");
+        };
+        assert_eq!(&flags[..3], &[true, false, true]);
+        assert!(active.is_null(9) && !active.is_null(8));
+
+        assert_eq!(nothing.values, ColumnValues::Null(10));
+        assert_eq!(nothing.null_count(), 10);
+        Ok(())
+    }
+
+    #[test]
+    fn mismatched_value_is_rejected() {
+        let result = QueryResult {
+            schema: Schema::new(vec![column("id", DataType::Integer)]),
+            rows: vec![vec![Value::Boolean(true)]],
+        };
+        assert!(result.to_columnar().is_err());
+    }
+}
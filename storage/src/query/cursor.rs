@@ -0,0 +1,109 @@
+use crate::query::executor::QueryResult;
+use crate::query::types::{Row, Schema};
+
+pub struct Cursor {
+    result: QueryResult,
+    position: usize,
+}
+
+impl Cursor {
+    pub fn new(result: QueryResult) -> Self {
+        Self {
+            result,
+            position: 0,
+        }
+    }
+
+    pub fn schema(&self) -> &Schema {
+        &self.result.schema
+    }
+
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    pub fn len(&self) -> usize {
+        self.result.rows.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.result.rows.is_empty()
+    }
+
+    // Positions the cursor so that the next row returned is row `position`.
+    // Seeking to `len()` leaves the cursor exhausted.
+    pub fn seek(&mut self, position: usize) -> anyhow::Result<()> {
+        if position > self.len() {
+            anyhow::bail!(
+                "Cannot seek to row {} of a {}-row result",
+                position,
+                self.len()
+            );
+        }
+        self.position = position;
+        Ok(())
+    }
+}
+
+impl Iterator for Cursor {
+    type Item = Row;
+
+    fn next(&mut self) -> Option<Row> {
+        let row = self.result.rows.get(self.position)?.clone();
+        self.position += 1;
+        Some(row)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::query::types::{Column, DataType, Value};
+
+    fn numbered_result(count: i32) -> QueryResult {
+        QueryResult {
+            rows: (0..count).map(|i| vec![Value::Integer(i)]).collect(),
+            schema: Schema::new(vec![Column {
+                name: "n".to_string(),
+                data_type: DataType::Integer,
+                nullable: false,
+            }]),
+        }
+    }
+
+    #[test]
+    fn cursor_reads_in_order() {
+        let mut cursor = Cursor::new(numbered_result(3));
+        assert_eq!(cursor.len(), 3);
+        assert_eq!(cursor.schema().columns[0].name, "n");
+
+        assert_eq!(cursor.position(), 0);
+        assert_eq!(cursor.next(), Some(vec![Value::Integer(0)]));
+        assert_eq!(cursor.next(), Some(vec![Value::Integer(1)]));
+        assert_eq!(cursor.position(), 2);
+        assert_eq!(cursor.next(), Some(vec![Value::Integer(2)]));
+        assert_eq!(cursor.next(), None);
+        assert_eq!(cursor.position(), 3);
+    }
+
+    #[test]
+    fn cursor_seek() -> anyhow::Result<()> {
+        let mut cursor = Cursor::new(numbered_result(10));
+
+        cursor.seek(7)?;
+        assert_eq!(cursor.position(), 7);
+        assert_eq!(cursor.next(), Some(vec![Value::Integer(7)]));
+        assert_eq!(cursor.position(), 8);
+
+        cursor.seek(2)?;
+        assert_eq!(cursor.next(), Some(vec![Value::Integer(2)]));
+
+        cursor.seek(10)?;
+        assert_eq!(cursor.next(), None);
+
+        assert!(cursor.seek(11).is_err());
+        assert_eq!(cursor.position(), 10);
+
+        Ok(())
+    }
+}
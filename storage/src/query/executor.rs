@@ -1,33 +1,371 @@
+use crate::catalog::Catalog;
 use crate::disk::disk_manager::DiskManager;
+use crate::heap::heap_file::HeapFile;
 use crate::heap::heap_page::HeapPage;
-use crate::query::ast::Expression;
-use crate::query::planner::PhysicalPlan;
-use crate::query::types::{Row, Schema, Value};
+use crate::heap::toast::{TOAST_FILE_ID, TOAST_THRESHOLD, ToastFile};
+use crate::page::page_id::PageId;
+use crate::query::ast::{AggregateFunction, Expression, SetOperator};
+use crate::query::cursor::Cursor;
+use crate::query::planner::{
+    COMPUTED_COLUMN_PREFIX, PhysicalPlan, ROW_NUMBER_COLUMN, generate_series_schema,
+    group_column_names, projection_column_names,
+};
+use crate::query::session::SessionConfig;
+use crate::query::tuple;
+use crate::query::types::{Column, DataType, Row, Schema, Value};
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
 
 pub struct QueryResult {
     pub rows: Vec<Row>,
     pub schema: Schema,
 }
 
-pub struct QueryExecutor;
+#[derive(Debug, Clone, Copy, Default)]
+struct NodeStats {
+    rows: usize,
+    elapsed: Duration,
+}
+
+// Running state of one aggregate call over one group
+enum Accumulator {
+    Count(i64),
+    Sum(Option<i64>),
+    Avg { sum: i64, count: i64 },
+    Min(Value),
+    Max(Value),
+}
+
+impl Accumulator {
+    fn new(func: AggregateFunction) -> Self {
+        match func {
+            AggregateFunction::Count => Accumulator::Count(0),
+            AggregateFunction::Sum => Accumulator::Sum(None),
+            AggregateFunction::Avg => Accumulator::Avg { sum: 0, count: 0 },
+            AggregateFunction::Min => Accumulator::Min(Value::Null),
+            AggregateFunction::Max => Accumulator::Max(Value::Null),
+        }
+    }
+
+    fn add(&mut self, value: Value) -> anyhow::Result<()> {
+        if value == Value::Null {
+            return Ok(());
+        }
+        match (self, &value) {
+            (Accumulator::Count(count), _) => *count += 1,
+            (Accumulator::Sum(sum), Value::Integer(v)) => {
+                *sum = Some(sum.unwrap_or(0) + i64::from(*v));
+            }
+            (Accumulator::Avg { sum, count }, Value::Integer(v)) => {
+                *sum += i64::from(*v);
+                *count += 1;
+            }
+            (Accumulator::Sum(_) | Accumulator::Avg { .. }, _) => {
+                anyhow::bail!("Cannot sum non-integer value {}", value)
+            }
+            (Accumulator::Min(best), _) => {
+                if *best == Value::Null || value.compare(best)? == Ordering::Less {
+                    *best = value;
+                }
+            }
+            (Accumulator::Max(best), _) => {
+                if *best == Value::Null || value.compare(best)? == Ordering::Greater {
+                    *best = value;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // AVG is truncated towards zero, as there is no fractional type
+    fn finish(self) -> anyhow::Result<Value> {
+        let integer = |n: i64| {
+            i32::try_from(n)
+                .map(Value::Integer)
+                .map_err(|_| anyhow::anyhow!("Aggregate result {} is out of range", n))
+        };
+        match self {
+            Accumulator::Count(count) => integer(count),
+            Accumulator::Sum(None) | Accumulator::Avg { count: 0, .. } => Ok(Value::Null),
+            Accumulator::Sum(Some(sum)) => integer(sum),
+            Accumulator::Avg { sum, count } => integer(sum / count),
+            Accumulator::Min(value) | Accumulator::Max(value) => Ok(value),
+        }
+    }
+}
+
+pub type ScalarFunction = Box<dyn Fn(&[Value]) -> anyhow::Result<Value> + Send + Sync>;
+
+struct RegisteredFunction {
+    arity: usize,
+    func: ScalarFunction,
+}
+
+pub struct QueryExecutor {
+    functions: HashMap<String, RegisteredFunction>,
+    config: SessionConfig,
+}
 
 impl QueryExecutor {
     pub fn new() -> Self {
-        Self
+        Self::with_config(SessionConfig::default())
+    }
+
+    pub fn with_config(config: SessionConfig) -> Self {
+        Self {
+            functions: HashMap::new(),
+            config,
+        }
+    }
+
+    pub fn config(&self) -> &SessionConfig {
+        &self.config
+    }
+
+    // Registers a scalar SQL function taking exactly `arity` arguments.
+    // Names are case-insensitive and built-in functions take precedence.
+    pub fn register_function<F>(&mut self, name: &str, arity: usize, func: F)
+    where
+        F: Fn(&[Value]) -> anyhow::Result<Value> + Send + Sync + 'static,
+    {
+        self.functions.insert(
+            name.to_ascii_uppercase(),
+            RegisteredFunction {
+                arity,
+                func: Box::new(func),
+            },
+        );
+    }
+
+    // Computes the schema `execute` would return for `plan` without reading
+    // any pages.
+    pub fn describe(&self, plan: &PhysicalPlan) -> anyhow::Result<Schema> {
+        match plan {
+            PhysicalPlan::SeqScan { schema, .. } | PhysicalPlan::Values { schema, .. } => {
+                Ok(schema.clone())
+            }
+            PhysicalPlan::GenerateSeries { .. } => Ok(generate_series_schema()),
+            PhysicalPlan::Compute { exprs, input } => {
+                let input_schema = self.describe(input)?;
+                self.create_compute_schema(exprs, input_schema)
+            }
+            PhysicalPlan::Projection {
+                exprs,
+                aliases,
+                input,
+            } => {
+                let input_schema = self.describe(input)?;
+                self.create_projection_schema(exprs, aliases, &input_schema)
+            }
+            PhysicalPlan::Explain { .. } => Ok(Self::explain_schema()),
+            PhysicalPlan::Insert { .. } | PhysicalPlan::SelectInto { .. } => {
+                Ok(Self::insert_schema())
+            }
+            PhysicalPlan::Update { .. } => Ok(Self::update_schema()),
+            PhysicalPlan::Delete { .. } => Ok(Self::delete_schema()),
+            PhysicalPlan::SetOperation { left, right, .. } => {
+                Self::set_operation_schema(self.describe(left)?, &self.describe(right)?)
+            }
+            PhysicalPlan::NestedLoopJoin { left, right, .. } => {
+                let mut schema = self.describe(left)?;
+                schema.columns.extend(self.describe(right)?.columns);
+                Ok(schema)
+            }
+            PhysicalPlan::Aggregate {
+                aggregates,
+                group_by,
+                input,
+            } => self.aggregate_schema(aggregates, group_by, &self.describe(input)?),
+            PhysicalPlan::RowNumber { input } => {
+                let mut schema = self.describe(input)?;
+                schema.columns.push(Column {
+                    name: ROW_NUMBER_COLUMN.to_string(),
+                    data_type: DataType::Integer,
+                    nullable: false,
+                });
+                Ok(schema)
+            }
+            PhysicalPlan::Filter { input, .. }
+            | PhysicalPlan::Sort { input, .. }
+            | PhysicalPlan::Distinct { input }
+            | PhysicalPlan::Limit { input, .. }
+            | PhysicalPlan::LimitPercent { input, .. } => self.describe(input),
+        }
+    }
+
+    pub fn open_cursor<D: DiskManager>(
+        &self,
+        plan: PhysicalPlan,
+        disk_manager: &mut D,
+    ) -> anyhow::Result<Cursor> {
+        Ok(Cursor::new(self.execute(plan, disk_manager)?))
     }
 
     pub fn execute<D: DiskManager>(
         &self,
         plan: PhysicalPlan,
         disk_manager: &mut D,
+    ) -> anyhow::Result<QueryResult> {
+        let plan = match self.config.max_rows {
+            Some(max_rows) => PhysicalPlan::Limit {
+                limit: max_rows,
+                input: Box::new(plan),
+            },
+            None => plan,
+        };
+        self.execute_plan(plan, disk_manager, None)
+    }
+
+    // Like `execute`, but a SELECT INTO adds its table to `catalog`, which
+    // should be the catalog the plan was made from. The caller's copy then
+    // knows the new table, and its file id cannot collide with a table the
+    // caller has added but not saved. `Database` runs every plan this way.
+    pub(crate) fn execute_with_catalog<D: DiskManager>(
+        &self,
+        plan: PhysicalPlan,
+        catalog: &mut Catalog,
+        disk_manager: &mut D,
+    ) -> anyhow::Result<QueryResult> {
+        let PhysicalPlan::SelectInto { table_name, input } = plan else {
+            return self.execute(plan, disk_manager);
+        };
+        let input_result = self.execute_plan(*input, disk_manager, None)?;
+        let inserted =
+            self.execute_select_into(&table_name, input_result, catalog, disk_manager)?;
+        Ok(QueryResult {
+            rows: vec![vec![Value::Integer(inserted as i32)]],
+            schema: Self::insert_schema(),
+        })
+    }
+
+    // Like `execute`, but hands each result row to `on_row` instead of
+    // collecting them; the schema comes from `describe`. Plans made of
+    // row-at-a-time operators over a scan deliver each row as soon as it is
+    // read, without holding the result in memory. Other plans run to
+    // completion first. An error from `on_row` stops the query.
+    pub fn execute_each<D: DiskManager>(
+        &self,
+        plan: PhysicalPlan,
+        disk_manager: &mut D,
+        mut on_row: impl FnMut(Row) -> anyhow::Result<()>,
+    ) -> anyhow::Result<()> {
+        let plan = match self.config.max_rows {
+            Some(max_rows) => PhysicalPlan::Limit {
+                limit: max_rows,
+                input: Box::new(plan),
+            },
+            None => plan,
+        };
+        if Self::is_streamable(&plan) {
+            return self.stream_rows(&plan, disk_manager, &mut on_row);
+        }
+        for row in self.execute_plan(plan, disk_manager, None)?.rows {
+            on_row(row)?;
+        }
+        Ok(())
+    }
+
+    // Runs `plan`, recording the rows produced by and time spent in each node
+    // (in pre-order) when `stats` is given.
+    fn execute_plan<D: DiskManager>(
+        &self,
+        plan: PhysicalPlan,
+        disk_manager: &mut D,
+        stats: Option<&mut Vec<NodeStats>>,
+    ) -> anyhow::Result<QueryResult> {
+        let Some(stats) = stats else {
+            return self.execute_node(plan, disk_manager, None);
+        };
+        let index = stats.len();
+        stats.push(NodeStats::default());
+        let start = Instant::now();
+        let result = self.execute_node(plan, disk_manager, Some(&mut *stats))?;
+        stats[index] = NodeStats {
+            rows: result.rows.len(),
+            elapsed: start.elapsed(),
+        };
+        Ok(result)
+    }
+
+    fn execute_node<D: DiskManager>(
+        &self,
+        plan: PhysicalPlan,
+        disk_manager: &mut D,
+        mut stats: Option<&mut Vec<NodeStats>>,
     ) -> anyhow::Result<QueryResult> {
         match plan {
-            PhysicalPlan::SeqScan { table_name, schema } => {
-                let rows = self.execute_seq_scan(&table_name, &schema, disk_manager)?;
+            PhysicalPlan::SeqScan {
+                file_id, schema, ..
+            } => {
+                let rows = self.execute_seq_scan(file_id, &schema, disk_manager)?;
+                Ok(QueryResult { rows, schema })
+            }
+            PhysicalPlan::Values { schema, rows } => Ok(QueryResult { rows, schema }),
+            PhysicalPlan::GenerateSeries { start, stop, step } => {
+                let rows = self.execute_generate_series(&start, &stop, &step)?;
+                Ok(QueryResult {
+                    rows,
+                    schema: generate_series_schema(),
+                })
+            }
+            PhysicalPlan::Insert {
+                file_id,
+                schema,
+                rows,
+                ..
+            } => {
+                let inserted = self.execute_insert(file_id, &schema, &rows, disk_manager)?;
+                Ok(QueryResult {
+                    rows: vec![vec![Value::Integer(inserted as i32)]],
+                    schema: Self::insert_schema(),
+                })
+            }
+            // Creating the table needs the caller's catalog
+            PhysicalPlan::SelectInto { table_name, .. } => anyhow::bail!(
+                "SELECT INTO {} must be run with execute_with_catalog",
+                table_name
+            ),
+            PhysicalPlan::Update {
+                file_id,
+                schema,
+                assignments,
+                predicate,
+                ..
+            } => {
+                let updated = self.execute_update(
+                    file_id,
+                    &schema,
+                    &assignments,
+                    predicate.as_ref(),
+                    disk_manager,
+                )?;
+                Ok(QueryResult {
+                    rows: vec![vec![Value::Integer(updated as i32)]],
+                    schema: Self::update_schema(),
+                })
+            }
+            PhysicalPlan::Delete {
+                file_id,
+                schema,
+                predicate,
+                ..
+            } => {
+                let deleted =
+                    self.execute_delete(file_id, &schema, predicate.as_ref(), disk_manager)?;
+                Ok(QueryResult {
+                    rows: vec![vec![Value::Integer(deleted as i32)]],
+                    schema: Self::delete_schema(),
+                })
+            }
+            PhysicalPlan::Compute { exprs, input } => {
+                let input_result = self.execute_plan(*input, disk_manager, stats)?;
+                let (rows, schema) =
+                    self.execute_compute(&exprs, input_result.rows, input_result.schema)?;
                 Ok(QueryResult { rows, schema })
             }
             PhysicalPlan::Filter { predicate, input } => {
-                let input_result = self.execute(*input, disk_manager)?;
+                let input_result = self.execute_plan(*input, disk_manager, stats)?;
                 let rows = self.execute_filter_with_schema(
                     &predicate,
                     input_result.rows,
@@ -38,65 +376,941 @@ impl QueryExecutor {
                     schema: input_result.schema,
                 })
             }
-            PhysicalPlan::Projection { exprs, input } => {
-                let input_result = self.execute(*input, disk_manager)?;
+            PhysicalPlan::Projection {
+                exprs,
+                aliases,
+                input,
+            } => {
+                let input_result = self.execute_plan(*input, disk_manager, stats)?;
                 let (rows, schema) = self.execute_projection_with_schema(
                     &exprs,
+                    &aliases,
+                    input_result.rows,
+                    &input_result.schema,
+                )?;
+                Ok(QueryResult { rows, schema })
+            }
+            PhysicalPlan::Sort { keys, input } => {
+                let input_result = self.execute_plan(*input, disk_manager, stats)?;
+                let rows = self.execute_sort(&keys, input_result.rows, &input_result.schema)?;
+                Ok(QueryResult {
+                    rows,
+                    schema: input_result.schema,
+                })
+            }
+            PhysicalPlan::Aggregate {
+                aggregates,
+                group_by,
+                input,
+            } => {
+                let input_result = self.execute_plan(*input, disk_manager, stats)?;
+                let schema = self.aggregate_schema(&aggregates, &group_by, &input_result.schema)?;
+                let rows = self.execute_aggregate(
+                    &aggregates,
+                    &group_by,
                     input_result.rows,
                     &input_result.schema,
                 )?;
                 Ok(QueryResult { rows, schema })
             }
+            PhysicalPlan::RowNumber { input } => {
+                let schema = self.describe(&PhysicalPlan::RowNumber {
+                    input: input.clone(),
+                })?;
+                let input_result = self.execute_plan(*input, disk_manager, stats)?;
+                let rows = input_result
+                    .rows
+                    .into_iter()
+                    .zip(1..)
+                    .map(|(mut row, n)| {
+                        row.push(Value::Integer(n));
+                        row
+                    })
+                    .collect();
+                Ok(QueryResult { rows, schema })
+            }
+            PhysicalPlan::Distinct { input } => {
+                let input_result = self.execute_plan(*input, disk_manager, stats)?;
+                let mut seen = HashSet::new();
+                let rows = input_result
+                    .rows
+                    .into_iter()
+                    .filter(|row| seen.insert(row.clone()))
+                    .collect();
+                Ok(QueryResult {
+                    rows,
+                    schema: input_result.schema,
+                })
+            }
+            // Streaming would hide the row counts of the nodes below
+            PhysicalPlan::Limit { limit, input }
+                if stats.is_none() && Self::is_streamable(&input) =>
+            {
+                self.execute_streaming(PhysicalPlan::Limit { limit, input }, disk_manager)
+            }
             PhysicalPlan::Limit { limit, input } => {
-                let input_result = self.execute(*input, disk_manager)?;
+                let input_result = self.execute_plan(*input, disk_manager, stats)?;
                 let rows = self.execute_limit(limit, input_result.rows);
                 Ok(QueryResult {
                     rows,
                     schema: input_result.schema,
                 })
             }
+            PhysicalPlan::Explain { analyze, input } => {
+                let mut node_stats = Vec::new();
+                if analyze {
+                    self.execute_plan((*input).clone(), disk_manager, Some(&mut node_stats))?;
+                }
+                let mut lines = Vec::new();
+                Self::explain_lines(&input, 0, &mut node_stats.into_iter(), &mut lines);
+                Ok(QueryResult {
+                    rows: lines
+                        .into_iter()
+                        .map(|line| vec![Value::Varchar(line)])
+                        .collect(),
+                    schema: Self::explain_schema(),
+                })
+            }
+            PhysicalPlan::SetOperation {
+                op,
+                all,
+                left,
+                right,
+            } => {
+                let left = self.execute_plan(*left, disk_manager, stats.as_deref_mut())?;
+                let right = self.execute_plan(*right, disk_manager, stats)?;
+                let schema = Self::set_operation_schema(left.schema, &right.schema)?;
+                let rows = Self::execute_set_operation(op, all, left.rows, right.rows);
+                Ok(QueryResult { rows, schema })
+            }
+            PhysicalPlan::NestedLoopJoin {
+                left,
+                right,
+                condition,
+            } => {
+                let left = self.execute_plan(*left, disk_manager, stats.as_deref_mut())?;
+                let right = self.execute_plan(*right, disk_manager, stats)?;
+                let mut schema = left.schema;
+                schema.columns.extend(right.schema.columns);
+                let rows =
+                    self.execute_nested_loop_join(&condition, &left.rows, &right.rows, &schema)?;
+                Ok(QueryResult { rows, schema })
+            }
+            PhysicalPlan::LimitPercent { percent, input } => {
+                // The row count depends on the size of the whole input
+                let input_result = self.execute_plan(*input, disk_manager, stats)?;
+                let total = input_result.rows.len() as u64;
+                let limit = (total * u64::from(percent)).div_ceil(100).min(total);
+                let rows = self.execute_limit(limit as u32, input_result.rows);
+                Ok(QueryResult {
+                    rows,
+                    schema: input_result.schema,
+                })
+            }
+        }
+    }
+
+    // Both sides must have the same number of columns with matching types;
+    // the result takes the left side's names.
+    fn set_operation_schema(mut left: Schema, right: &Schema) -> anyhow::Result<Schema> {
+        if left.columns.len() != right.columns.len() {
+            anyhow::bail!(
+                "Set operation inputs have {} and {} columns",
+                left.columns.len(),
+                right.columns.len()
+            );
+        }
+        for (l, r) in left.columns.iter_mut().zip(&right.columns) {
+            l.data_type = match (&l.data_type, &r.data_type) {
+                (DataType::Varchar(a), DataType::Varchar(b)) => DataType::Varchar(*a.max(b)),
+                (DataType::Null, other) | (other, DataType::Null) => other.clone(),
+                (a, b) if a == b => a.clone(),
+                (a, b) => anyhow::bail!(
+                    "Set operation column '{}' has mismatched types {:?} and {:?}",
+                    l.name,
+                    a,
+                    b
+                ),
+            };
+            l.nullable |= r.nullable;
+        }
+        Ok(left)
+    }
+
+    // Keeps left rows in their original order. NULLs compare equal here, as
+    // SQL set operations treat them as not distinct.
+    fn execute_set_operation(
+        op: SetOperator,
+        all: bool,
+        left: Vec<Row>,
+        right: Vec<Row>,
+    ) -> Vec<Row> {
+        let mut counts: HashMap<Row, usize> = HashMap::new();
+        for row in right {
+            *counts.entry(row).or_insert(0) += 1;
+        }
+
+        let mut emitted = HashSet::new();
+        left.into_iter()
+            .filter(|row| {
+                let count = counts.get_mut(row);
+                match (op, all, count) {
+                    (SetOperator::Intersect, true, Some(n)) if *n > 0 => {
+                        *n -= 1;
+                        true
+                    }
+                    (SetOperator::Except, true, Some(n)) if *n > 0 => {
+                        *n -= 1;
+                        false
+                    }
+                    (SetOperator::Intersect, true, _) => false,
+                    (SetOperator::Except, true, _) => true,
+                    (SetOperator::Intersect, false, found) => {
+                        found.is_some() && emitted.insert(row.clone())
+                    }
+                    (SetOperator::Except, false, found) => {
+                        found.is_none() && emitted.insert(row.clone())
+                    }
+                }
+            })
+            .collect()
+    }
+
+    fn insert_schema() -> Schema {
+        Schema::new(vec![Column {
+            name: "inserted".to_string(),
+            data_type: DataType::Integer,
+            nullable: false,
+        }])
+    }
+
+    // Every row is evaluated and checked against the table schema before
+    // any is written, so a bad row leaves the table unchanged.
+    fn execute_insert<D: DiskManager>(
+        &self,
+        file_id: u32,
+        schema: &Schema,
+        rows: &[Vec<Expression>],
+        disk_manager: &mut D,
+    ) -> anyhow::Result<usize> {
+        let empty = Schema::new(Vec::new());
+        let mut tuples = Vec::with_capacity(rows.len());
+        for row in rows {
+            let values = row
+                .iter()
+                .map(|expr| self.evaluate_expression_with_schema(expr, &Vec::new(), &empty))
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            let inline = Self::encode_row(&values, schema)?;
+            tuples.push((values, inline));
+        }
+
+        let heap = HeapFile::new(file_id);
+        for (values, inline) in tuples.iter() {
+            let tuple = Self::toast_row(values, schema, inline, disk_manager)?;
+            heap.insert(disk_manager, &tuple)?;
+        }
+        Ok(tuples.len())
+    }
+
+    // Every row is encoded before the table is created, so a row that does
+    // not fit leaves the catalog untouched.
+    fn execute_select_into<D: DiskManager>(
+        &self,
+        table_name: &str,
+        result: QueryResult,
+        catalog: &mut Catalog,
+        disk_manager: &mut D,
+    ) -> anyhow::Result<usize> {
+        let schema = result.schema;
+        let tuples = result
+            .rows
+            .into_iter()
+            .map(|values| {
+                let inline = Self::encode_row(&values, &schema)?;
+                Ok((values, inline))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let file_id = catalog.create_table(disk_manager, table_name, schema.clone())?;
+        let heap = HeapFile::new(file_id);
+        for (values, inline) in tuples.iter() {
+            let tuple = Self::toast_row(values, &schema, inline, disk_manager)?;
+            heap.insert(disk_manager, &tuple)?;
+        }
+        Ok(tuples.len())
+    }
+
+    // Checks `values` against the column types and varchar widths of a table
+    // and returns the tuple with every varchar inline.
+    fn encode_row(values: &[Value], schema: &Schema) -> anyhow::Result<Vec<u8>> {
+        for (value, column) in values.iter().zip(&schema.columns) {
+            if let (DataType::Varchar(width), Value::Varchar(s)) = (&column.data_type, value)
+                && s.chars().count() > *width
+            {
+                anyhow::bail!(
+                    "Value of {} characters is too long for column '{}' of type {:?}",
+                    s.chars().count(),
+                    column.name,
+                    column.data_type
+                );
+            }
+        }
+        tuple::serialize_row(values, schema)
+    }
+
+    // Re-encodes a row from `encode_row` with varchars over TOAST_THRESHOLD
+    // bytes moved out of line, or returns `inline` when there are none.
+    fn toast_row<D: DiskManager>(
+        values: &[Value],
+        schema: &Schema,
+        inline: &[u8],
+        disk_manager: &mut D,
+    ) -> anyhow::Result<Vec<u8>> {
+        let oversized = values
+            .iter()
+            .any(|v| matches!(v, Value::Varchar(s) if s.len() > TOAST_THRESHOLD));
+        if !oversized {
+            return Ok(inline.to_vec());
+        }
+        let toast = ToastFile::new(TOAST_FILE_ID);
+        tuple::serialize_row_with(values, schema, &mut |s| {
+            if s.len() > TOAST_THRESHOLD {
+                toast.store(disk_manager, s.as_bytes()).map(Some)
+            } else {
+                Ok(None)
+            }
+        })
+    }
+
+    fn update_schema() -> Schema {
+        Schema::new(vec![Column {
+            name: "updated".to_string(),
+            data_type: DataType::Integer,
+            nullable: false,
+        }])
+    }
+
+    // New rows are computed from the old ones and checked for every page
+    // before anything is written. A tuple whose encoding keeps its length is
+    // overwritten in place; any other is tombstoned and reinserted once the
+    // scan is done, so it is not visited twice. The old row's out-of-line
+    // values are freed once the page no longer points to them.
+    fn execute_update<D: DiskManager>(
+        &self,
+        file_id: u32,
+        schema: &Schema,
+        assignments: &[(usize, Expression)],
+        predicate: Option<&Expression>,
+        disk_manager: &mut D,
+    ) -> anyhow::Result<usize> {
+        let toast = ToastFile::new(TOAST_FILE_ID);
+        let mut changes = Vec::new();
+        for page_no in 0..disk_manager.page_count(file_id)? {
+            let heap_page = HeapPage {
+                page: disk_manager.read_page(PageId::new(file_id, page_no))?,
+            };
+            for (slot_no, tuple_data) in heap_page.tuples() {
+                let row = tuple::deserialize_row_with(tuple_data, schema, &|pointer| {
+                    toast.fetch(&*disk_manager, pointer)
+                })?;
+                if let Some(predicate) = predicate
+                    && !self.evaluate_predicate_with_schema(predicate, &row, schema)?
+                {
+                    continue;
+                }
+                let mut values = row.clone();
+                for (index, expr) in assignments {
+                    values[*index] = self.evaluate_expression_with_schema(expr, &row, schema)?;
+                }
+                let inline = Self::encode_row(&values, schema)?;
+                let toasted = tuple::toast_pointers(tuple_data, schema)?;
+                changes.push((page_no, slot_no, tuple_data.len(), toasted, values, inline));
+            }
+        }
+
+        let mut reinserts = Vec::new();
+        let mut unreferenced = Vec::new();
+        let mut page: Option<HeapPage> = None;
+        for (page_no, slot_no, old_len, toasted, values, inline) in &changes {
+            let pid = PageId::new(file_id, *page_no);
+            if page.as_ref().is_none_or(|p| p.page.page_id() != pid) {
+                if let Some(done) = page.take() {
+                    disk_manager.write_page(&done.page)?;
+                    for pointer in unreferenced.drain(..) {
+                        toast.free(disk_manager, pointer)?;
+                    }
+                }
+                page = Some(HeapPage {
+                    page: disk_manager.read_page(pid)?,
+                });
+            }
+            let tuple = Self::toast_row(values, schema, inline, disk_manager)?;
+            let heap_page = page.as_mut().expect("page was just loaded");
+            if tuple.len() == *old_len {
+                heap_page.replace_tuple(*slot_no, &tuple)?;
+            } else {
+                heap_page.delete_tuple(*slot_no)?;
+                reinserts.push(tuple);
+            }
+            unreferenced.extend_from_slice(toasted);
+        }
+        if let Some(done) = page {
+            disk_manager.write_page(&done.page)?;
+            for pointer in unreferenced.drain(..) {
+                toast.free(disk_manager, pointer)?;
+            }
+        }
+
+        let heap = HeapFile::new(file_id);
+        for tuple in &reinserts {
+            heap.insert(disk_manager, tuple)?;
+        }
+        Ok(changes.len())
+    }
+
+    fn delete_schema() -> Schema {
+        Schema::new(vec![Column {
+            name: "deleted".to_string(),
+            data_type: DataType::Integer,
+            nullable: false,
+        }])
+    }
+
+    // Only pages that had a row deleted are written back. Out-of-line values
+    // of the deleted rows are freed after their page is written.
+    fn execute_delete<D: DiskManager>(
+        &self,
+        file_id: u32,
+        schema: &Schema,
+        predicate: Option<&Expression>,
+        disk_manager: &mut D,
+    ) -> anyhow::Result<usize> {
+        let toast = ToastFile::new(TOAST_FILE_ID);
+        let mut deleted = 0;
+        for page_no in 0..disk_manager.page_count(file_id)? {
+            let mut heap_page = HeapPage {
+                page: disk_manager.read_page(PageId::new(file_id, page_no))?,
+            };
+            let mut matches = Vec::new();
+            let mut unreferenced = Vec::new();
+            for (slot_no, tuple_data) in heap_page.tuples() {
+                let keep = match predicate {
+                    None => false,
+                    Some(predicate) => {
+                        let row = tuple::deserialize_row_with(tuple_data, schema, &|pointer| {
+                            toast.fetch(&*disk_manager, pointer)
+                        })?;
+                        !self.evaluate_predicate_with_schema(predicate, &row, schema)?
+                    }
+                };
+                if !keep {
+                    matches.push(slot_no);
+                    unreferenced.extend(tuple::toast_pointers(tuple_data, schema)?);
+                }
+            }
+            if matches.is_empty() {
+                continue;
+            }
+            for slot_no in &matches {
+                heap_page.delete_tuple(*slot_no)?;
+            }
+            disk_manager.write_page(&heap_page.page)?;
+            for pointer in unreferenced {
+                toast.free(disk_manager, pointer)?;
+            }
+            deleted += matches.len();
+        }
+        Ok(deleted)
+    }
+
+    fn explain_schema() -> Schema {
+        Schema::new(vec![Column {
+            name: "QUERY PLAN".to_string(),
+            data_type: DataType::Varchar(255),
+            nullable: false,
+        }])
+    }
+
+    fn explain_lines(
+        plan: &PhysicalPlan,
+        depth: usize,
+        stats: &mut impl Iterator<Item = NodeStats>,
+        lines: &mut Vec<String>,
+    ) {
+        let mut line = if depth == 0 {
+            plan.label()
+        } else {
+            format!("{}-> {}", "  ".repeat(depth), plan.label())
+        };
+        if let Some(node) = stats.next() {
+            line.push_str(&format!(
+                " (rows={} time={:.3}ms)",
+                node.rows,
+                node.elapsed.as_secs_f64() * 1000.0
+            ));
+        }
+        lines.push(line);
+        for input in plan.inputs() {
+            Self::explain_lines(input, depth + 1, stats, lines);
+        }
+    }
+
+    // Plans made only of row-at-a-time operators over a scan can be run one
+    // row at a time, which lets a LIMIT stop reading pages early.
+    fn is_streamable(plan: &PhysicalPlan) -> bool {
+        match plan {
+            PhysicalPlan::SeqScan { .. } => true,
+            PhysicalPlan::Values { .. }
+            | PhysicalPlan::GenerateSeries { .. }
+            | PhysicalPlan::Insert { .. }
+            | PhysicalPlan::SelectInto { .. }
+            | PhysicalPlan::Update { .. }
+            | PhysicalPlan::Delete { .. }
+            | PhysicalPlan::LimitPercent { .. }
+            | PhysicalPlan::Sort { .. }
+            | PhysicalPlan::Aggregate { .. }
+            | PhysicalPlan::Distinct { .. }
+            | PhysicalPlan::SetOperation { .. }
+            | PhysicalPlan::NestedLoopJoin { .. }
+            | PhysicalPlan::Explain { .. } => false,
+            PhysicalPlan::Compute { input, .. }
+            | PhysicalPlan::Projection { input, .. }
+            | PhysicalPlan::Filter { input, .. }
+            | PhysicalPlan::RowNumber { input }
+            | PhysicalPlan::Limit { input, .. } => Self::is_streamable(input),
+        }
+    }
+
+    fn execute_streaming<D: DiskManager>(
+        &self,
+        plan: PhysicalPlan,
+        disk_manager: &mut D,
+    ) -> anyhow::Result<QueryResult> {
+        let schema = self.describe(&plan)?;
+        let mut rows = Vec::new();
+        self.stream_rows(&plan, disk_manager, &mut |row| {
+            rows.push(row);
+            Ok(())
+        })?;
+        Ok(QueryResult { rows, schema })
+    }
+
+    // Runs a plan that `is_streamable` accepts, passing each output row to
+    // `on_row` as it is produced.
+    fn stream_rows<D: DiskManager>(
+        &self,
+        plan: &PhysicalPlan,
+        disk_manager: &mut D,
+        on_row: &mut dyn FnMut(Row) -> anyhow::Result<()>,
+    ) -> anyhow::Result<()> {
+        // Collect the operators from the scan upwards along with the schema
+        // each one reads from.
+        let mut stages = Vec::new();
+        let mut node = plan;
+        let (file_id, scan_schema) = loop {
+            match node {
+                PhysicalPlan::SeqScan {
+                    file_id, schema, ..
+                } => break (*file_id, schema),
+                PhysicalPlan::Compute { input, .. }
+                | PhysicalPlan::Projection { input, .. }
+                | PhysicalPlan::Filter { input, .. }
+                | PhysicalPlan::RowNumber { input }
+                | PhysicalPlan::Limit { input, .. } => {
+                    stages.push((node, self.describe(input)?, 0usize));
+                    node = input;
+                }
+                PhysicalPlan::Values { .. }
+                | PhysicalPlan::GenerateSeries { .. }
+                | PhysicalPlan::Insert { .. }
+                | PhysicalPlan::SelectInto { .. }
+                | PhysicalPlan::Update { .. }
+                | PhysicalPlan::Delete { .. }
+                | PhysicalPlan::Sort { .. }
+                | PhysicalPlan::Aggregate { .. }
+                | PhysicalPlan::Distinct { .. }
+                | PhysicalPlan::LimitPercent { .. }
+                | PhysicalPlan::SetOperation { .. }
+                | PhysicalPlan::NestedLoopJoin { .. }
+                | PhysicalPlan::Explain { .. } => {
+                    anyhow::bail!("{} cannot be streamed", node.label())
+                }
+            }
+        };
+        stages.reverse();
+
+        if stages
+            .iter()
+            .any(|(stage, _, _)| matches!(stage, PhysicalPlan::Limit { limit: 0, .. }))
+        {
+            return Ok(());
+        }
+
+        let toast = ToastFile::new(TOAST_FILE_ID);
+        'pages: for pid in disk_manager.page_ids(file_id)? {
+            let heap_page = HeapPage {
+                page: disk_manager.read_page(pid)?,
+            };
+            'tuples: for (_, tuple_data) in heap_page.tuples() {
+                let mut row = tuple::deserialize_row_with(tuple_data, scan_schema, &|pointer| {
+                    toast.fetch(&*disk_manager, pointer)
+                })?;
+                let mut exhausted = false;
+                for (stage, schema, produced) in stages.iter_mut() {
+                    match stage {
+                        PhysicalPlan::Compute { exprs, .. } => {
+                            for expr in exprs {
+                                let value =
+                                    self.evaluate_expression_with_schema(expr, &row, schema)?;
+                                row.push(value);
+                            }
+                        }
+                        PhysicalPlan::Projection { exprs, .. } => {
+                            row = exprs
+                                .iter()
+                                .map(|expr| {
+                                    self.evaluate_expression_with_schema(expr, &row, schema)
+                                })
+                                .collect::<anyhow::Result<_>>()?;
+                        }
+                        PhysicalPlan::Filter { predicate, .. } => {
+                            if !self.evaluate_predicate_with_schema(predicate, &row, schema)? {
+                                continue 'tuples;
+                            }
+                        }
+                        PhysicalPlan::RowNumber { .. } => {
+                            *produced += 1;
+                            row.push(Value::Integer(*produced as i32));
+                        }
+                        PhysicalPlan::Limit { limit, .. } => {
+                            *produced += 1;
+                            exhausted |= *produced >= *limit as usize;
+                        }
+                        PhysicalPlan::SeqScan { .. }
+                        | PhysicalPlan::Values { .. }
+                        | PhysicalPlan::GenerateSeries { .. }
+                        | PhysicalPlan::Insert { .. }
+                        | PhysicalPlan::SelectInto { .. }
+                        | PhysicalPlan::Update { .. }
+                        | PhysicalPlan::Delete { .. }
+                        | PhysicalPlan::LimitPercent { .. }
+                        | PhysicalPlan::Sort { .. }
+                        | PhysicalPlan::Aggregate { .. }
+                        | PhysicalPlan::Distinct { .. }
+                        | PhysicalPlan::SetOperation { .. }
+                        | PhysicalPlan::NestedLoopJoin { .. }
+                        | PhysicalPlan::Explain { .. } => {
+                            anyhow::bail!("{} cannot be streamed", stage.label())
+                        }
+                    }
+                }
+                on_row(row)?;
+                if exhausted {
+                    break 'pages;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Returns each distinct value of `column` in the table stored in
+    // `file_id`, in the order first seen, with NULL counted as one value.
+    // Rows are scanned one page at a time and only the distinct values are
+    // kept in memory.
+    pub fn distinct_values<D: DiskManager>(
+        &self,
+        file_id: u32,
+        schema: &Schema,
+        column: &str,
+        disk_manager: &mut D,
+    ) -> anyhow::Result<Vec<Value>> {
+        let index = schema
+            .columns
+            .iter()
+            .position(|col| col.name == column)
+            .ok_or_else(|| anyhow::anyhow!("Column '{}' not found in schema", column))?;
+        let toast = ToastFile::new(TOAST_FILE_ID);
+        let mut seen = HashSet::new();
+        let mut values = Vec::new();
+
+        for pid in disk_manager.page_ids(file_id)? {
+            let heap_page = HeapPage {
+                page: disk_manager.read_page(pid)?,
+            };
+            for (_, tuple_data) in heap_page.tuples() {
+                let mut row = tuple::deserialize_row_with(tuple_data, schema, &|pointer| {
+                    toast.fetch(&*disk_manager, pointer)
+                })?;
+                let value = row.swap_remove(index);
+                if seen.insert(value.clone()) {
+                    values.push(value);
+                }
+            }
+        }
+        Ok(values)
+    }
+
+    fn execute_seq_scan<D: DiskManager>(
+        &self,
+        file_id: u32,
+        schema: &Schema,
+        disk_manager: &mut D,
+    ) -> anyhow::Result<Vec<Row>> {
+        let mut rows = Vec::new();
+        let toast = ToastFile::new(TOAST_FILE_ID);
+
+        for pid in disk_manager.page_ids(file_id)? {
+            let heap_page = HeapPage {
+                page: disk_manager.read_page(pid)?,
+            };
+            for (_, tuple_data) in heap_page.tuples() {
+                rows.push(tuple::deserialize_row_with(
+                    tuple_data,
+                    schema,
+                    &|pointer| toast.fetch(&*disk_manager, pointer),
+                )?);
+            }
+        }
+
+        Ok(rows)
+    }
+
+    fn execute_nested_loop_join(
+        &self,
+        condition: &Expression,
+        left: &[Row],
+        right: &[Row],
+        schema: &Schema,
+    ) -> anyhow::Result<Vec<Row>> {
+        let mut rows = Vec::new();
+        for left_row in left {
+            for right_row in right {
+                let mut row = left_row.clone();
+                row.extend(right_row.iter().cloned());
+                if self.evaluate_predicate_with_schema(condition, &row, schema)? {
+                    rows.push(row);
+                }
+            }
+        }
+        Ok(rows)
+    }
+
+    // A NULL bound or step yields no rows, as in PostgreSQL.
+    fn execute_generate_series(
+        &self,
+        start: &Expression,
+        stop: &Expression,
+        step: &Expression,
+    ) -> anyhow::Result<Vec<Row>> {
+        let empty = Schema::new(Vec::new());
+        let mut bounds = Vec::with_capacity(3);
+        for expr in [start, stop, step] {
+            match self.evaluate_expression_with_schema(expr, &Vec::new(), &empty)? {
+                Value::Integer(n) => bounds.push(i64::from(n)),
+                Value::Null => return Ok(Vec::new()),
+                other => anyhow::bail!("GENERATE_SERIES expects integers, got {:?}", other),
+            }
+        }
+        let (start, stop, step) = (bounds[0], bounds[1], bounds[2]);
+        if step == 0 {
+            anyhow::bail!("GENERATE_SERIES step cannot be zero");
+        }
+
+        let mut rows = Vec::new();
+        let mut n = start;
+        while (step > 0 && n <= stop) || (step < 0 && n >= stop) {
+            rows.push(vec![Value::Integer(n as i32)]);
+            n += step;
+        }
+        Ok(rows)
+    }
+
+    // Evaluates every key once per row, then sorts stably so that rows with
+    // equal keys keep their input order.
+    fn execute_sort(
+        &self,
+        keys: &[(Expression, bool)],
+        rows: Vec<Row>,
+        schema: &Schema,
+    ) -> anyhow::Result<Vec<Row>> {
+        let mut keyed = rows
+            .into_iter()
+            .map(|row| {
+                let values = keys
+                    .iter()
+                    .map(|(expr, _)| self.evaluate_expression_with_schema(expr, &row, schema))
+                    .collect::<anyhow::Result<Vec<_>>>()?;
+                Ok((values, row))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let mut error = None;
+        keyed.sort_by(|(a, _), (b, _)| {
+            for ((l, r), (_, desc)) in a.iter().zip(b).zip(keys) {
+                let ordering = match (l, r) {
+                    (Value::Null, Value::Null) => Ordering::Equal,
+                    (Value::Null, _) => return Ordering::Greater,
+                    (_, Value::Null) => return Ordering::Less,
+                    _ => match l.compare(r) {
+                        Ok(ordering) if *desc => ordering.reverse(),
+                        Ok(ordering) => ordering,
+                        Err(e) => {
+                            error.get_or_insert(e);
+                            Ordering::Equal
+                        }
+                    },
+                };
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+            Ordering::Equal
+        });
+        if let Some(e) = error {
+            return Err(e);
+        }
+
+        Ok(keyed.into_iter().map(|(_, row)| row).collect())
+    }
+
+    // Groups are emitted in the order their first row was seen. Group keys
+    // compare NULLs as equal, so all NULL keys form one group.
+    fn execute_aggregate(
+        &self,
+        aggregates: &[Expression],
+        group_by: &[Expression],
+        rows: Vec<Row>,
+        schema: &Schema,
+    ) -> anyhow::Result<Vec<Row>> {
+        let mut groups: Vec<(Row, Vec<Accumulator>)> = Vec::new();
+        let mut index: HashMap<Row, usize> = HashMap::new();
+        let new_accumulators = || {
+            aggregates
+                .iter()
+                .map(|expr| match expr {
+                    Expression::Aggregate { func, .. } => Ok(Accumulator::new(*func)),
+                    _ => anyhow::bail!("{} is not an aggregate function call", expr),
+                })
+                .collect::<anyhow::Result<Vec<_>>>()
+        };
+        if group_by.is_empty() {
+            groups.push((Vec::new(), new_accumulators()?));
+        }
+
+        for row in rows {
+            let key = group_by
+                .iter()
+                .map(|expr| self.evaluate_expression_with_schema(expr, &row, schema))
+                .collect::<anyhow::Result<Row>>()?;
+            let group = match index.get(&key) {
+                Some(&group) => group,
+                None if group_by.is_empty() => 0,
+                None => {
+                    index.insert(key.clone(), groups.len());
+                    groups.push((key, new_accumulators()?));
+                    groups.len() - 1
+                }
+            };
+            for (expr, accumulator) in aggregates.iter().zip(&mut groups[group].1) {
+                let value = match expr {
+                    Expression::Aggregate { arg: Some(arg), .. } => {
+                        self.evaluate_expression_with_schema(arg, &row, schema)?
+                    }
+                    // COUNT(*) counts every row
+                    _ => Value::Boolean(true),
+                };
+                accumulator.add(value)?;
+            }
+        }
+
+        groups
+            .into_iter()
+            .map(|(mut row, accumulators)| {
+                for accumulator in accumulators {
+                    row.push(accumulator.finish()?);
+                }
+                Ok(row)
+            })
+            .collect()
+    }
+
+    fn aggregate_schema(
+        &self,
+        aggregates: &[Expression],
+        group_by: &[Expression],
+        input_schema: &Schema,
+    ) -> anyhow::Result<Schema> {
+        let mut columns = Vec::new();
+        for (expr, name) in group_by.iter().zip(group_column_names(group_by)) {
+            columns.push(Column {
+                name,
+                data_type: self.infer_expression_type(expr, input_schema)?,
+                nullable: true,
+            });
+        }
+        for expr in aggregates {
+            let Expression::Aggregate { func, arg } = expr else {
+                anyhow::bail!("{} is not an aggregate function call", expr);
+            };
+            let arg_type = match arg {
+                Some(arg) => self.infer_expression_type(arg, input_schema)?,
+                None => DataType::Integer,
+            };
+            let data_type = match func {
+                AggregateFunction::Count => DataType::Integer,
+                AggregateFunction::Sum | AggregateFunction::Avg => match arg_type {
+                    DataType::Integer | DataType::Null => DataType::Integer,
+                    other => anyhow::bail!("{} expects an integer argument, got {:?}", func, other),
+                },
+                AggregateFunction::Min | AggregateFunction::Max => arg_type,
+            };
+            columns.push(Column {
+                name: expr.to_string(),
+                data_type,
+                nullable: *func != AggregateFunction::Count,
+            });
         }
+        Ok(Schema::new(columns))
     }
 
-    fn execute_seq_scan<D: DiskManager>(
+    fn execute_compute(
         &self,
-        _table_name: &str,
-        schema: &Schema,
-        disk_manager: &mut D,
-    ) -> anyhow::Result<Vec<Row>> {
-        use crate::page::page_id::PageId;
-
-        let mut rows = Vec::new();
-        let file_id = 1; // TODO: Look up actual file_id for table
-        let mut page_no = 0;
-
-        loop {
-            let pid = PageId::new(file_id, page_no);
-
-            match disk_manager.read_page(pid) {
-                Ok(page) => {
-                    let heap_page = HeapPage { page };
-                    for slot_no in 0..heap_page.slot_count() {
-                        if let Some(tuple_data) = heap_page.read_tuple(slot_no) {
-                            let row = self.deserialize_row(&tuple_data, schema)?;
-                            rows.push(row);
-                        }
-                    }
+        exprs: &[Expression],
+        input_rows: Vec<Row>,
+        input_schema: Schema,
+    ) -> anyhow::Result<(Vec<Row>, Schema)> {
+        let mut result_rows = Vec::with_capacity(input_rows.len());
 
-                    page_no += 1;
-                }
-                Err(_) => {
-                    break; // No more pages
-                }
+        for mut row in input_rows {
+            for expr in exprs {
+                let value = self.evaluate_expression_with_schema(expr, &row, &input_schema)?;
+                row.push(value);
             }
+            result_rows.push(row);
         }
 
-        Ok(rows)
+        let schema = self.create_compute_schema(exprs, input_schema)?;
+        Ok((result_rows, schema))
+    }
+
+    fn create_compute_schema(
+        &self,
+        exprs: &[Expression],
+        input_schema: Schema,
+    ) -> anyhow::Result<Schema> {
+        let computed = self.create_projection_schema(exprs, &[], &input_schema)?;
+        let mut schema = input_schema;
+        for (i, mut column) in computed.columns.into_iter().enumerate() {
+            column.name = format!("{}{}", COMPUTED_COLUMN_PREFIX, i);
+            schema.columns.push(column);
+        }
+        Ok(schema)
     }
 
     fn execute_projection_with_schema(
         &self,
         exprs: &[Expression],
+        aliases: &[Option<String>],
         input_rows: Vec<Row>,
         input_schema: &Schema,
     ) -> anyhow::Result<(Vec<Row>, Schema)> {
@@ -114,7 +1328,7 @@ impl QueryExecutor {
         }
 
         // Create output schema based on expressions
-        let output_schema = self.create_projection_schema(exprs, input_schema)?;
+        let output_schema = self.create_projection_schema(exprs, aliases, input_schema)?;
 
         Ok((result_rows, output_schema))
     }
@@ -122,40 +1336,12 @@ impl QueryExecutor {
     fn create_projection_schema(
         &self,
         exprs: &[Expression],
+        aliases: &[Option<String>],
         input_schema: &Schema,
     ) -> anyhow::Result<Schema> {
-        use crate::query::types::{Column, DataType};
-
         let mut columns = Vec::new();
-
-        for expr in exprs {
-            let (name, data_type) = match expr {
-                Expression::Column { name } => {
-                    // Find the column in input schema
-                    let input_col = input_schema
-                        .columns
-                        .iter()
-                        .find(|col| &col.name == name)
-                        .ok_or_else(|| {
-                            anyhow::anyhow!("Column '{}' not found in input schema", name)
-                        })?;
-                    (name.clone(), input_col.data_type.clone())
-                }
-                Expression::Literal { value } => {
-                    let data_type = match value {
-                        crate::query::types::Value::Integer(_) => DataType::Integer,
-                        crate::query::types::Value::Varchar(_) => DataType::Varchar(255),
-                        crate::query::types::Value::Boolean(_) => DataType::Boolean,
-                        crate::query::types::Value::Null => DataType::Varchar(255), // Default for nulls
-                    };
-                    ("literal".to_string(), data_type)
-                }
-                Expression::BinaryOp { .. } => {
-                    // For now, assume binary ops produce integers (simplification)
-                    ("expr".to_string(), DataType::Integer)
-                }
-            };
-
+        for (expr, name) in exprs.iter().zip(projection_column_names(exprs, aliases)) {
+            let data_type = self.infer_expression_type(expr, input_schema)?;
             columns.push(Column {
                 name,
                 data_type,
@@ -166,54 +1352,66 @@ impl QueryExecutor {
         Ok(Schema::new(columns))
     }
 
-    fn execute_limit(&self, limit: u32, input_rows: Vec<Row>) -> Vec<Row> {
-        input_rows.into_iter().take(limit as usize).collect()
-    }
-
-    fn deserialize_row(&self, data: &[u8], schema: &Schema) -> anyhow::Result<Row> {
-        let mut row = Vec::new();
-        let mut offset = 0;
-
-        for column in &schema.columns {
-            let value = match &column.data_type {
-                crate::query::types::DataType::Integer => {
-                    if offset + 4 > data.len() {
-                        anyhow::bail!("Not enough data for integer column");
-                    }
-                    let bytes = &data[offset..offset + 4];
-                    let val = i32::from_le_bytes(bytes.try_into()?);
-                    offset += 4;
-                    Value::Integer(val)
-                }
-                crate::query::types::DataType::Varchar(_) => {
-                    if offset + 4 > data.len() {
-                        anyhow::bail!("Not enough data for varchar length");
-                    }
-                    let len_bytes = &data[offset..offset + 4];
-                    let len = u32::from_le_bytes(len_bytes.try_into()?) as usize;
-                    offset += 4;
+    // NULL literals have their own type so that they take on the type of the
+    // other operand (`NULL + 1` is an integer) instead of defaulting to varchar.
+    fn infer_expression_type(
+        &self,
+        expr: &Expression,
+        input_schema: &Schema,
+    ) -> anyhow::Result<DataType> {
+        use crate::query::ast::BinaryOperator;
 
-                    if offset + len > data.len() {
-                        anyhow::bail!("Not enough data for varchar content");
-                    }
-                    let string_bytes = &data[offset..offset + len];
-                    let s = String::from_utf8(string_bytes.to_vec())?;
-                    offset += len;
-                    Value::Varchar(s)
-                }
-                crate::query::types::DataType::Boolean => {
-                    if offset + 1 > data.len() {
-                        anyhow::bail!("Not enough data for boolean column");
+        match expr {
+            Expression::Column { name, .. } => input_schema
+                .find_column(name)
+                .map(|col| col.data_type.clone())
+                .ok_or_else(|| anyhow::anyhow!("Column '{}' not found in input schema", name)),
+            Expression::Literal { value } => Ok(match value {
+                Value::Integer(_) => DataType::Integer,
+                Value::Varchar(_) => DataType::Varchar(255),
+                Value::Boolean(_) => DataType::Boolean,
+                Value::Null => DataType::Null,
+            }),
+            Expression::Parameter { index } => {
+                anyhow::bail!("Parameter ${} has not been bound", index)
+            }
+            Expression::Aggregate { .. } => input_schema
+                .find_column(&expr.to_string())
+                .map(|col| col.data_type.clone())
+                .ok_or_else(|| anyhow::anyhow!("Aggregate {} is not allowed here", expr)),
+            Expression::BinaryOp { left, op, right } => match op {
+                BinaryOperator::Add
+                | BinaryOperator::Sub
+                | BinaryOperator::Mul
+                | BinaryOperator::Div
+                | BinaryOperator::Mod => match self.infer_expression_type(left, input_schema)? {
+                    DataType::Null => self.infer_expression_type(right, input_schema),
+                    data_type => Ok(data_type),
+                },
+                _ => Ok(DataType::Boolean),
+            },
+            Expression::InList { .. } | Expression::Not(_) | Expression::Between { .. } => {
+                Ok(DataType::Boolean)
+            }
+            Expression::Function { args, .. } => {
+                // GREATEST/LEAST take the type of their first typed argument
+                for arg in args {
+                    match self.infer_expression_type(arg, input_schema)? {
+                        DataType::Null => continue,
+                        data_type => return Ok(data_type),
                     }
-                    let val = data[offset] != 0;
-                    offset += 1;
-                    Value::Boolean(val)
                 }
-            };
-            row.push(value);
+                Ok(if args.is_empty() {
+                    DataType::Integer
+                } else {
+                    DataType::Null
+                })
+            }
         }
+    }
 
-        Ok(row)
+    fn execute_limit(&self, limit: u32, input_rows: Vec<Row>) -> Vec<Row> {
+        input_rows.into_iter().take(limit as usize).collect()
     }
 
     fn execute_filter_with_schema(
@@ -233,29 +1431,21 @@ impl QueryExecutor {
         Ok(result_rows)
     }
 
+    // A NULL predicate filters the row out, as FALSE does.
     fn evaluate_predicate_with_schema(
         &self,
         expr: &Expression,
         row: &Row,
         schema: &Schema,
     ) -> anyhow::Result<bool> {
-        match expr {
-            Expression::Literal { value } => match value {
-                Value::Boolean(b) => Ok(*b),
-                _ => anyhow::bail!("Non-boolean literal in predicate"),
-            },
-            Expression::Column { name } => {
-                let value = self.lookup_column_value(name, row, schema)?;
-                match value {
-                    Value::Boolean(b) => Ok(b),
-                    _ => anyhow::bail!("Column reference in predicate must evaluate to boolean"),
-                }
-            }
-            Expression::BinaryOp { left, op, right } => {
-                let left_val = self.evaluate_expression_with_schema(left, row, schema)?;
-                let right_val = self.evaluate_expression_with_schema(right, row, schema)?;
-                self.evaluate_binary_op(&left_val, op, &right_val)
-            }
+        match self.evaluate_expression_with_schema(expr, row, schema)? {
+            Value::Boolean(b) => Ok(b),
+            Value::Null => Ok(false),
+            other => anyhow::bail!(
+                "Predicate {} must evaluate to boolean, got {:?}",
+                expr,
+                other
+            ),
         }
     }
 
@@ -267,13 +1457,346 @@ impl QueryExecutor {
     ) -> anyhow::Result<Value> {
         match expr {
             Expression::Literal { value } => Ok(value.clone()),
-            Expression::Column { name } => self.lookup_column_value(name, row, schema),
+            Expression::Parameter { index } => {
+                anyhow::bail!("Parameter ${} has not been bound", index)
+            }
+            Expression::Column { name, .. } => self.lookup_column_value(name, row, schema),
+            // Computed by the Aggregate node below and read back by name
+            Expression::Aggregate { .. } => {
+                let name = expr.to_string();
+                if schema.find_column(&name).is_none() {
+                    anyhow::bail!("Aggregate {} is not allowed here", name);
+                }
+                self.lookup_column_value(&name, row, schema)
+            }
             Expression::BinaryOp { left, op, right } => {
                 let left_val = self.evaluate_expression_with_schema(left, row, schema)?;
                 let right_val = self.evaluate_expression_with_schema(right, row, schema)?;
                 self.evaluate_binary_op_value(&left_val, op, &right_val)
             }
+            Expression::Function { name, args } => {
+                let values = args
+                    .iter()
+                    .map(|arg| self.evaluate_expression_with_schema(arg, row, schema))
+                    .collect::<anyhow::Result<Vec<_>>>()?;
+                self.evaluate_function(name, values)
+            }
+            Expression::InList {
+                expr,
+                list,
+                negated,
+            } => {
+                let value = self.evaluate_expression_with_schema(expr, row, schema)?;
+                let list = list
+                    .iter()
+                    .map(|item| self.evaluate_expression_with_schema(item, row, schema))
+                    .collect::<anyhow::Result<Vec<_>>>()?;
+                Ok(match self.evaluate_in_list(&value, &list)? {
+                    Value::Boolean(found) => Value::Boolean(found != *negated),
+                    other => other,
+                })
+            }
+            Expression::Not(expr) => {
+                match self.evaluate_expression_with_schema(expr, row, schema)? {
+                    Value::Boolean(b) => Ok(Value::Boolean(!b)),
+                    Value::Null => Ok(Value::Null),
+                    other => anyhow::bail!("NOT expects a boolean, got {:?}", other),
+                }
+            }
+            Expression::Between {
+                expr,
+                low,
+                high,
+                negated,
+                symmetric,
+            } => {
+                let value = self.evaluate_expression_with_schema(expr, row, schema)?;
+                let low = self.evaluate_expression_with_schema(low, row, schema)?;
+                let high = self.evaluate_expression_with_schema(high, row, schema)?;
+                Ok(
+                    match self.evaluate_between(&value, &low, &high, *symmetric)? {
+                        Value::Boolean(within) => Value::Boolean(within != *negated),
+                        other => other,
+                    },
+                )
+            }
+        }
+    }
+
+    // `low <= value AND value <= high`; the symmetric form also accepts the
+    // bounds swapped, as `... OR (high <= value AND value <= low)`.
+    fn evaluate_between(
+        &self,
+        value: &Value,
+        low: &Value,
+        high: &Value,
+        symmetric: bool,
+    ) -> anyhow::Result<Value> {
+        use crate::query::ast::BinaryOperator;
+
+        let within = |low: &Value, high: &Value| {
+            let above = self.evaluate_binary_op_value(low, &BinaryOperator::Le, value)?;
+            let below = self.evaluate_binary_op_value(value, &BinaryOperator::Le, high)?;
+            self.evaluate_binary_op_value(&above, &BinaryOperator::And, &below)
+        };
+        let forward = within(low, high)?;
+        if !symmetric {
+            return Ok(forward);
+        }
+        let backward = within(high, low)?;
+        self.evaluate_binary_op_value(&forward, &BinaryOperator::Or, &backward)
+    }
+
+    // TRUE if `value` equals an item of `list`. Otherwise NULL if any of the
+    // comparisons was NULL, since a NULL item might have been a match, and
+    // FALSE if none were.
+    fn evaluate_in_list(&self, value: &Value, list: &[Value]) -> anyhow::Result<Value> {
+        use crate::query::ast::BinaryOperator;
+
+        let mut result = Value::Boolean(false);
+        for item in list {
+            match self.evaluate_binary_op_value(value, &BinaryOperator::Eq, item)? {
+                Value::Boolean(true) => return Ok(Value::Boolean(true)),
+                Value::Null => result = Value::Null,
+                _ => {}
+            }
+        }
+        Ok(result)
+    }
+
+    fn evaluate_function(&self, name: &str, args: Vec<Value>) -> anyhow::Result<Value> {
+        let upper = name.to_ascii_uppercase();
+        match upper.as_str() {
+            "GREATEST" => self.evaluate_extremum(name, args, Ordering::Greater),
+            "LEAST" => self.evaluate_extremum(name, args, Ordering::Less),
+            "MOD" => match self.integer_args::<2>(name, &args)? {
+                Some([l, r]) => Ok(Value::Integer(self.evaluate_mod(l, r)?)),
+                None => Ok(Value::Null),
+            },
+            "POWER" => match self.integer_args::<2>(name, &args)? {
+                Some([base, exp]) => {
+                    // There is no floating point type, so only non-negative
+                    // integer exponents can be represented.
+                    let exp = u32::try_from(exp).map_err(|_| {
+                        anyhow::anyhow!("POWER exponent must be non-negative, got {}", exp)
+                    })?;
+                    let result = base
+                        .checked_pow(exp)
+                        .ok_or_else(|| anyhow::anyhow!("Integer out of range in POWER"))?;
+                    Ok(Value::Integer(result))
+                }
+                None => Ok(Value::Null),
+            },
+            "TRIM" => self.evaluate_trim(name, &args, true, true),
+            "LTRIM" => self.evaluate_trim(name, &args, true, false),
+            "RTRIM" => self.evaluate_trim(name, &args, false, true),
+            "SUBSTRING" | "SUBSTR" => self.evaluate_substring(name, &args),
+            _ => {
+                let registered = self
+                    .functions
+                    .get(&upper)
+                    .ok_or_else(|| anyhow::anyhow!("Unknown function '{}'", name))?;
+                if args.len() != registered.arity {
+                    anyhow::bail!(
+                        "Function '{}' expects {} argument(s), got {}",
+                        name,
+                        registered.arity,
+                        args.len()
+                    );
+                }
+                (registered.func)(&args)
+            }
+        }
+    }
+
+    // Strips whitespace, or any of the characters in the optional second
+    // argument, from the requested ends of a string.
+    fn evaluate_trim(
+        &self,
+        name: &str,
+        args: &[Value],
+        leading: bool,
+        trailing: bool,
+    ) -> anyhow::Result<Value> {
+        let (string, characters) = match args {
+            [string] => (string, None),
+            [string, characters] => (string, Some(characters)),
+            _ => anyhow::bail!("{} expects 1 or 2 arguments, got {}", name, args.len()),
+        };
+        let characters: Option<Vec<char>> = match characters {
+            None => None,
+            Some(Value::Varchar(c)) => Some(c.chars().collect()),
+            Some(Value::Null) => return Ok(Value::Null),
+            Some(other) => anyhow::bail!("{} characters must be a string, got {:?}", name, other),
+        };
+        let string = match string {
+            Value::Varchar(s) => s.as_str(),
+            Value::Null => return Ok(Value::Null),
+            other => anyhow::bail!("{} expects a string, got {:?}", name, other),
+        };
+
+        let strip = |c: char| match &characters {
+            Some(set) => set.contains(&c),
+            None => c.is_whitespace(),
+        };
+        let mut trimmed = string;
+        if leading {
+            trimmed = trimmed.trim_start_matches(strip);
+        }
+        if trailing {
+            trimmed = trimmed.trim_end_matches(strip);
+        }
+        Ok(Value::Varchar(trimmed.to_string()))
+    }
+
+    // SUBSTRING(string, start [, length]). Positions are 1-based like the
+    // SQL standard: characters from `start` up to but not including
+    // `start + length`, clamped to the bounds of the string.
+    fn evaluate_substring(&self, name: &str, args: &[Value]) -> anyhow::Result<Value> {
+        let (string, bounds) = match args {
+            [string, rest @ ..] if (1..=2).contains(&rest.len()) => (string, rest),
+            _ => anyhow::bail!("{} expects 2 or 3 arguments, got {}", name, args.len()),
+        };
+        let string = match string {
+            Value::Varchar(s) => s,
+            Value::Null => return Ok(Value::Null),
+            other => anyhow::bail!("{} expects a string, got {:?}", name, other),
+        };
+        let (start, end) = match *bounds {
+            [ref start] => match self.integer_args::<1>(name, std::slice::from_ref(start))? {
+                Some([start]) => (i64::from(start), None),
+                None => return Ok(Value::Null),
+            },
+            _ => match self.integer_args::<2>(name, bounds)? {
+                Some([start, length]) => {
+                    if length < 0 {
+                        anyhow::bail!("{} length must be non-negative, got {}", name, length);
+                    }
+                    (i64::from(start), Some(i64::from(start) + i64::from(length)))
+                }
+                None => return Ok(Value::Null),
+            },
+        };
+
+        let skip = usize::try_from(start.max(1) - 1).unwrap_or(usize::MAX);
+        let take = match end {
+            Some(end) => usize::try_from((end - start.max(1)).max(0)).unwrap_or(usize::MAX),
+            None => usize::MAX,
+        };
+        Ok(Value::Varchar(
+            string.chars().skip(skip).take(take).collect(),
+        ))
+    }
+
+    // Checks that a numeric function got exactly N integer arguments. Returns
+    // None if any of them is NULL, which makes the function's result NULL.
+    fn integer_args<const N: usize>(
+        &self,
+        name: &str,
+        args: &[Value],
+    ) -> anyhow::Result<Option<[i32; N]>> {
+        if args.len() != N {
+            anyhow::bail!("{} expects {} argument(s), got {}", name, N, args.len());
+        }
+        let mut ints = [0; N];
+        for (slot, arg) in ints.iter_mut().zip(args) {
+            *slot = match arg {
+                Value::Integer(i) => *i,
+                Value::Null => return Ok(None),
+                other => anyhow::bail!("{} expects integer arguments, got {:?}", name, other),
+            };
+        }
+        Ok(Some(ints))
+    }
+
+    fn evaluate_mod(&self, l: i32, r: i32) -> anyhow::Result<i32> {
+        if r == 0 {
+            anyhow::bail!("Division by zero");
+        }
+        l.checked_rem(r)
+            .ok_or_else(|| anyhow::anyhow!("Integer out of range in modulo"))
+    }
+
+    // Matches all of `value` against a LIKE pattern, where `%` stands for any
+    // run of characters and `_` for exactly one. A backslash makes the next
+    // character literal.
+    fn evaluate_like(&self, value: &str, pattern: &str) -> bool {
+        enum Token {
+            Any,
+            One,
+            Char(char),
+        }
+        let mut tokens = Vec::new();
+        let mut chars = pattern.chars();
+        while let Some(c) = chars.next() {
+            tokens.push(match c {
+                '%' => Token::Any,
+                '_' => Token::One,
+                '\\' => Token::Char(chars.next().unwrap_or('\\')),
+                c => Token::Char(c),
+            });
+        }
+
+        // Greedy matching that falls back to the most recent `%` and lets it
+        // swallow one more character whenever the rest fails to match.
+        let value: Vec<char> = value.chars().collect();
+        let (mut v, mut t) = (0, 0);
+        let mut fallback = None;
+        while v < value.len() {
+            match tokens.get(t) {
+                Some(Token::Any) => {
+                    fallback = Some((t, v));
+                    t += 1;
+                    continue;
+                }
+                Some(Token::One) => {
+                    v += 1;
+                    t += 1;
+                    continue;
+                }
+                Some(Token::Char(c)) if *c == value[v] => {
+                    v += 1;
+                    t += 1;
+                    continue;
+                }
+                _ => {}
+            }
+            match fallback {
+                Some((any, consumed)) => {
+                    fallback = Some((any, consumed + 1));
+                    t = any + 1;
+                    v = consumed + 1;
+                }
+                None => return false,
+            }
+        }
+        tokens[t..].iter().all(|token| matches!(token, Token::Any))
+    }
+
+    // Like PostgreSQL, GREATEST/LEAST ignore NULL arguments and only return
+    // NULL when every argument is NULL.
+    fn evaluate_extremum(
+        &self,
+        name: &str,
+        args: Vec<Value>,
+        wanted: Ordering,
+    ) -> anyhow::Result<Value> {
+        if args.is_empty() {
+            anyhow::bail!("{} requires at least one argument", name);
+        }
+
+        let mut best: Option<Value> = None;
+        for value in args {
+            if value == Value::Null {
+                continue;
+            }
+            best = match best {
+                Some(current) if value.compare(&current)? != wanted => Some(current),
+                _ => Some(value),
+            };
         }
+
+        Ok(best.unwrap_or(Value::Null))
     }
 
     fn lookup_column_value(
@@ -295,64 +1818,42 @@ impl QueryExecutor {
             .ok_or_else(|| anyhow::anyhow!("Row has fewer columns than schema indicates"))
     }
 
-    fn evaluate_binary_op(
+    fn evaluate_binary_op_value(
         &self,
         left: &Value,
         op: &crate::query::ast::BinaryOperator,
         right: &Value,
-    ) -> anyhow::Result<bool> {
+    ) -> anyhow::Result<Value> {
         use crate::query::ast::BinaryOperator;
 
-        match (left, right) {
-            (Value::Integer(l), Value::Integer(r)) => Ok(match op {
-                BinaryOperator::Eq => l == r,
-                BinaryOperator::Ne => l != r,
-                BinaryOperator::Lt => l < r,
-                BinaryOperator::Le => l <= r,
-                BinaryOperator::Gt => l > r,
-                BinaryOperator::Ge => l >= r,
-                _ => anyhow::bail!(
-                    "Operator {:?} not supported for integers in boolean context",
-                    op
-                ),
-            }),
-            (Value::Varchar(l), Value::Varchar(r)) => Ok(match op {
-                BinaryOperator::Eq => l == r,
-                BinaryOperator::Ne => l != r,
-                BinaryOperator::Lt => l < r,
-                BinaryOperator::Le => l <= r,
-                BinaryOperator::Gt => l > r,
-                BinaryOperator::Ge => l >= r,
-                _ => anyhow::bail!(
-                    "Operator {:?} not supported for strings in boolean context",
-                    op
-                ),
-            }),
-            (Value::Boolean(l), Value::Boolean(r)) => Ok(match op {
-                BinaryOperator::Eq => l == r,
-                BinaryOperator::Ne => l != r,
-                BinaryOperator::And => *l && *r,
-                BinaryOperator::Or => *l || *r,
-                _ => anyhow::bail!("Operator {:?} not supported for booleans", op),
-            }),
-            _ => anyhow::bail!(
-                "Cannot compare {:?} and {:?} with operator {:?}",
-                left,
-                right,
-                op
-            ),
+        // AND and OR follow Kleene logic: NULL is an unknown truth value, so
+        // FALSE AND NULL is FALSE and TRUE OR NULL is TRUE. Any other operator
+        // with a NULL operand yields NULL.
+        // IS and IS NOT compare NULLs like any other value and never yield
+        // NULL themselves.
+        if let BinaryOperator::Is | BinaryOperator::IsNot = op {
+            return Ok(Value::Boolean(
+                (left == right) == matches!(op, BinaryOperator::Is),
+            ));
         }
-    }
 
-    fn evaluate_binary_op_value(
-        &self,
-        left: &Value,
-        op: &crate::query::ast::BinaryOperator,
-        right: &Value,
-    ) -> anyhow::Result<Value> {
-        use crate::query::ast::BinaryOperator;
+        if let BinaryOperator::And | BinaryOperator::Or = op {
+            let truth = |value: &Value| match value {
+                Value::Boolean(b) => Ok(Some(*b)),
+                Value::Null => Ok(None),
+                other => anyhow::bail!("Operator {:?} not supported for {:?}", op, other),
+            };
+            let (l, r) = (truth(left)?, truth(right)?);
+            let dominant = matches!(op, BinaryOperator::Or);
+            return Ok(match (l, r) {
+                (Some(b), _) | (_, Some(b)) if b == dominant => Value::Boolean(dominant),
+                (Some(_), Some(_)) => Value::Boolean(!dominant),
+                _ => Value::Null,
+            });
+        }
 
         match (left, right) {
+            (Value::Null, _) | (_, Value::Null) => Ok(Value::Null),
             (Value::Integer(l), Value::Integer(r)) => Ok(match op {
                 BinaryOperator::Add => Value::Integer(l + r),
                 BinaryOperator::Sub => Value::Integer(l - r),
@@ -363,6 +1864,7 @@ impl QueryExecutor {
                     }
                     Value::Integer(l / r)
                 }
+                BinaryOperator::Mod => Value::Integer(self.evaluate_mod(*l, *r)?),
                 BinaryOperator::Eq => Value::Boolean(l == r),
                 BinaryOperator::Ne => Value::Boolean(l != r),
                 BinaryOperator::Lt => Value::Boolean(l < r),
@@ -373,6 +1875,7 @@ impl QueryExecutor {
             }),
             (Value::Varchar(l), Value::Varchar(r)) => Ok(match op {
                 BinaryOperator::Add => Value::Varchar(format!("{}{}", l, r)),
+                BinaryOperator::Like => Value::Boolean(self.evaluate_like(l, r)),
                 BinaryOperator::Eq => Value::Boolean(l == r),
                 BinaryOperator::Ne => Value::Boolean(l != r),
                 BinaryOperator::Lt => Value::Boolean(l < r),
@@ -382,10 +1885,12 @@ impl QueryExecutor {
                 _ => anyhow::bail!("Operator {:?} not supported for strings", op),
             }),
             (Value::Boolean(l), Value::Boolean(r)) => Ok(match op {
-                BinaryOperator::And => Value::Boolean(*l && *r),
-                BinaryOperator::Or => Value::Boolean(*l || *r),
                 BinaryOperator::Eq => Value::Boolean(l == r),
                 BinaryOperator::Ne => Value::Boolean(l != r),
+                BinaryOperator::Lt => Value::Boolean(l < r),
+                BinaryOperator::Le => Value::Boolean(l <= r),
+                BinaryOperator::Gt => Value::Boolean(l > r),
+                BinaryOperator::Ge => Value::Boolean(l >= r),
                 _ => anyhow::bail!("Operator {:?} not supported for booleans", op),
             }),
             _ => anyhow::bail!(
@@ -415,7 +1920,336 @@ mod tests {
     #[test]
     fn test_query_executor_creation() {
         let executor = QueryExecutor::new();
-        assert!(std::ptr::addr_of!(executor) as *const _ != std::ptr::null());
+        assert!(!std::ptr::addr_of!(executor).is_null());
+    }
+
+    #[test]
+    fn test_greatest_and_least() -> anyhow::Result<()> {
+        let executor = QueryExecutor::new();
+        let schema = Schema::new(vec![]);
+        let eval =
+            |expr: Expression| executor.evaluate_expression_with_schema(&expr, &vec![], &schema);
+
+        let ints = vec![
+            Expression::integer(3),
+            Expression::integer(-7),
+            Expression::integer(12),
+        ];
+        assert_eq!(
+            eval(Expression::function("GREATEST", ints.clone()))?,
+            Value::Integer(12)
+        );
+        assert_eq!(
+            eval(Expression::function("least", ints))?,
+            Value::Integer(-7)
+        );
+
+        let strings = vec![
+            Expression::string("pear"),
+            Expression::string("apple"),
+            Expression::string("zucchini"),
+        ];
+        assert_eq!(
+            eval(Expression::function("GREATEST", strings.clone()))?,
+            Value::Varchar("zucchini".to_string())
+        );
+        assert_eq!(
+            eval(Expression::function("LEAST", strings))?,
+            Value::Varchar("apple".to_string())
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_greatest_and_least_nulls() -> anyhow::Result<()> {
+        let executor = QueryExecutor::new();
+        let schema = Schema::new(vec![]);
+        let null = Expression::Literal { value: Value::Null };
+        let eval =
+            |expr: Expression| executor.evaluate_expression_with_schema(&expr, &vec![], &schema);
+
+        let args = vec![null.clone(), Expression::integer(4), Expression::integer(9)];
+        assert_eq!(
+            eval(Expression::function("GREATEST", args.clone()))?,
+            Value::Integer(9)
+        );
+        assert_eq!(
+            eval(Expression::function("LEAST", args))?,
+            Value::Integer(4)
+        );
+        assert_eq!(
+            eval(Expression::function("GREATEST", vec![null.clone(), null]))?,
+            Value::Null
+        );
+
+        let mixed = vec![Expression::integer(1), Expression::string("a")];
+        assert!(eval(Expression::function("GREATEST", mixed)).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_trim_functions() -> anyhow::Result<()> {
+        let executor = QueryExecutor::new();
+        let schema = Schema::new(vec![]);
+        let call = |name: &str, args: &[&str]| {
+            let args = args.iter().map(|a| Expression::string(a)).collect();
+            executor.evaluate_expression_with_schema(
+                &Expression::function(name, args),
+                &vec![],
+                &schema,
+            )
+        };
+        let text = |s: &str| Value::Varchar(s.to_string());
+
+        assert_eq!(call("TRIM", &["  both \t"])?, text("both"));
+        assert_eq!(call("LTRIM", &["  leading  "])?, text("leading  "));
+        assert_eq!(call("RTRIM", &["  trailing  "])?, text("  trailing"));
+        assert_eq!(call("TRIM", &["xxhixyx", "xy"])?, text("hi"));
+        assert_eq!(call("LTRIM", &["--a--", "-"])?, text("a--"));
+        assert_eq!(call("RTRIM", &["--a--", "-"])?, text("--a"));
+        assert_eq!(call("TRIM", &["   "])?, text(""));
+
+        let null = Expression::Literal { value: Value::Null };
+        assert_eq!(
+            executor.evaluate_expression_with_schema(
+                &Expression::function("TRIM", vec![null]),
+                &vec![],
+                &schema
+            )?,
+            Value::Null
+        );
+        assert!(
+            executor
+                .evaluate_expression_with_schema(
+                    &Expression::function("TRIM", vec![Expression::integer(1)]),
+                    &vec![],
+                    &schema
+                )
+                .is_err()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_substring() -> anyhow::Result<()> {
+        let executor = QueryExecutor::new();
+        let schema = Schema::new(vec![]);
+        let call = |name: &str, bounds: &[i32]| {
+            let mut args = vec![Expression::string("boxsql")];
+            args.extend(bounds.iter().map(|b| Expression::integer(*b)));
+            executor.evaluate_expression_with_schema(
+                &Expression::function(name, args),
+                &vec![],
+                &schema,
+            )
+        };
+        let text = |s: &str| Value::Varchar(s.to_string());
+
+        // In range
+        assert_eq!(call("SUBSTRING", &[2, 3])?, text("oxs"));
+        assert_eq!(call("SUBSTR", &[1, 3])?, text("box"));
+        // Omitted length runs to the end
+        assert_eq!(call("SUBSTRING", &[4])?, text("sql"));
+        // Out-of-range start and length are clamped
+        assert_eq!(call("SUBSTRING", &[0, 3])?, text("bo"));
+        assert_eq!(call("SUBSTRING", &[-5, 3])?, text(""));
+        assert_eq!(call("SUBSTRING", &[5, 100])?, text("ql"));
+        assert_eq!(call("SUBSTRING", &[10])?, text(""));
+        assert_eq!(call("SUBSTRING", &[2, 0])?, text(""));
+        assert!(call("SUBSTRING", &[2, -1]).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_mod_and_power() -> anyhow::Result<()> {
+        let executor = QueryExecutor::new();
+        let schema = Schema::new(vec![]);
+        let eval =
+            |expr: Expression| executor.evaluate_expression_with_schema(&expr, &vec![], &schema);
+        let call = |name: &str, a: i32, b: i32| {
+            eval(Expression::function(
+                name,
+                vec![Expression::integer(a), Expression::integer(b)],
+            ))
+        };
+
+        assert_eq!(call("MOD", 17, 5)?, Value::Integer(2));
+        assert_eq!(call("mod", -17, 5)?, Value::Integer(-2));
+        assert_eq!(
+            eval(Expression::BinaryOp {
+                left: Box::new(Expression::integer(17)),
+                op: crate::query::ast::BinaryOperator::Mod,
+                right: Box::new(Expression::integer(5)),
+            })?,
+            Value::Integer(2)
+        );
+        assert!(
+            call("MOD", 1, 0)
+                .unwrap_err()
+                .to_string()
+                .contains("Division by zero")
+        );
+        assert!(call("MOD", i32::MIN, -1).is_err());
+
+        assert_eq!(call("POWER", 2, 10)?, Value::Integer(1024));
+        assert_eq!(call("POWER", -3, 3)?, Value::Integer(-27));
+        assert_eq!(call("POWER", 7, 0)?, Value::Integer(1));
+        assert!(
+            call("POWER", 2, 31)
+                .unwrap_err()
+                .to_string()
+                .contains("out of range")
+        );
+        assert!(call("POWER", 2, -1).is_err());
+
+        let null = Expression::Literal { value: Value::Null };
+        assert_eq!(
+            eval(Expression::function(
+                "POWER",
+                vec![null, Expression::integer(2)]
+            ))?,
+            Value::Null
+        );
+        assert!(eval(Expression::function("MOD", vec![Expression::integer(1)])).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_null_projection_types() -> anyhow::Result<()> {
+        let executor = QueryExecutor::new();
+        let schema = Schema::new(vec![Column {
+            name: "flag".to_string(),
+            data_type: DataType::Boolean,
+            nullable: true,
+        }]);
+        let null = || Expression::Literal { value: Value::Null };
+        let data_types = |exprs: &[Expression]| -> anyhow::Result<Vec<DataType>> {
+            Ok(executor
+                .create_projection_schema(exprs, &[], &schema)?
+                .columns
+                .into_iter()
+                .map(|col| col.data_type)
+                .collect())
+        };
+
+        assert_eq!(data_types(&[null()])?, vec![DataType::Null]);
+        assert_eq!(
+            data_types(&[
+                Expression::add(null(), Expression::integer(1)),
+                Expression::add(Expression::string("a"), null()),
+                Expression::eq(null(), Expression::integer(1)),
+                Expression::function("GREATEST", vec![null(), Expression::column("flag")]),
+            ])?,
+            vec![
+                DataType::Integer,
+                DataType::Varchar(255),
+                DataType::Boolean,
+                DataType::Boolean,
+            ]
+        );
+
+        let projected = executor.create_projection_schema(&[null()], &[], &schema)?;
+        assert!(projected.columns[0].nullable);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_three_valued_logic() -> anyhow::Result<()> {
+        use crate::query::ast::BinaryOperator::{self, *};
+        let executor = QueryExecutor::new();
+        let eval =
+            |l: Value, op: BinaryOperator, r: Value| executor.evaluate_binary_op_value(&l, &op, &r);
+        let (t, f, n) = (Value::Boolean(true), Value::Boolean(false), Value::Null);
+
+        assert_eq!(eval(f.clone(), And, n.clone())?, f);
+        assert_eq!(eval(n.clone(), And, f.clone())?, f);
+        assert_eq!(eval(t.clone(), And, n.clone())?, n);
+        assert_eq!(eval(t.clone(), And, t.clone())?, t);
+        assert_eq!(eval(t.clone(), Or, n.clone())?, t);
+        assert_eq!(eval(n.clone(), Or, t.clone())?, t);
+        assert_eq!(eval(f.clone(), Or, n.clone())?, n);
+        assert_eq!(eval(f.clone(), Or, f.clone())?, f);
+        assert_eq!(eval(n.clone(), Or, n.clone())?, n);
+
+        assert_eq!(eval(Value::Integer(1), Add, n.clone())?, n);
+        assert_eq!(eval(n.clone(), Eq, n.clone())?, n);
+        assert_eq!(eval(Value::Varchar("a".to_string()), Lt, n.clone())?, n);
+        assert!(eval(Value::Integer(1), And, n.clone()).is_err());
+
+        assert_eq!(eval(n.clone(), Is, n.clone())?, t);
+        assert_eq!(eval(n.clone(), IsNot, n.clone())?, f);
+        assert_eq!(eval(Value::Varchar("a".to_string()), Is, n.clone())?, f);
+        assert_eq!(eval(Value::Boolean(false), IsNot, n)?, t);
+        Ok(())
+    }
+
+    #[test]
+    fn test_like_patterns() -> anyhow::Result<()> {
+        let executor = QueryExecutor::new();
+        let like = |value: &str, pattern: &str| executor.evaluate_like(value, pattern);
+
+        // Prefix, suffix and contains
+        assert!(like("Alice", "A%"));
+        assert!(!like("Bob", "A%"));
+        assert!(like("Alice", "%ce"));
+        assert!(!like("Alice", "%ca"));
+        assert!(like("Alice", "%li%"));
+        assert!(like("Alice", "%%"));
+        assert!(!like("Alice", "%x%"));
+
+        // `_` is exactly one character, and the whole string must match
+        assert!(like("cat", "c_t"));
+        assert!(like("cät", "c_t"));
+        assert!(!like("cart", "c_t"));
+        assert!(!like("cat", "ca"));
+        assert!(like("", "%"));
+        assert!(!like("", "_"));
+        assert!(like("mississippi", "m%iss%pi"));
+        assert!(like("abcbc", "%bc"));
+
+        // Escaped wildcards only match themselves
+        assert!(like("100%", "100\\%"));
+        assert!(!like("1000", "100\\%"));
+        assert!(like("a_b", "a\\_b"));
+        assert!(!like("axb", "a\\_b"));
+        assert!(like("a\\b", "a\\\\b"));
+
+        let err = executor
+            .evaluate_binary_op_value(
+                &Value::Integer(1),
+                &crate::query::ast::BinaryOperator::Like,
+                &Value::Integer(1),
+            )
+            .unwrap_err();
+        assert!(err.to_string().contains("not supported"), "{}", err);
+        Ok(())
+    }
+
+    #[test]
+    fn test_in_list_with_nulls() -> anyhow::Result<()> {
+        let executor = QueryExecutor::new();
+        let (one, two, null) = (Value::Integer(1), Value::Integer(2), Value::Null);
+
+        let in_list = |value: &Value, list: Vec<Value>| executor.evaluate_in_list(value, &list);
+        assert_eq!(
+            in_list(&one, vec![two.clone(), one.clone()])?,
+            Value::Boolean(true)
+        );
+        assert_eq!(in_list(&one, vec![two.clone()])?, Value::Boolean(false));
+        assert_eq!(
+            in_list(&one, vec![null.clone(), one.clone()])?,
+            Value::Boolean(true)
+        );
+        assert_eq!(in_list(&one, vec![two.clone(), null.clone()])?, Value::Null);
+        assert_eq!(in_list(&null, vec![one.clone()])?, Value::Null);
+        assert!(in_list(&one, vec![Value::Varchar("1".to_string())]).is_err());
+        Ok(())
     }
 
     #[test]
@@ -431,6 +2265,7 @@ mod tests {
 
         let plan = PhysicalPlan::SeqScan {
             table_name: "test".to_string(),
+            file_id: 1,
             schema: schema.clone(),
         };
 
@@ -2,13 +2,22 @@
 mod tests {
     use tempfile::TempDir;
 
+    use crate::catalog::{Catalog, users_catalog};
+    use crate::disk::buffer_pool::BufferPool;
     use crate::disk::disk_manager::DiskManager;
     use crate::disk::file_system::FsDiskManager;
+    use crate::disk::generic::GenericDiskManager;
+    use crate::heap::heap_file::HeapFile;
     use crate::heap::heap_page::HeapPage;
-    use crate::query::executor::QueryExecutor;
+    use crate::heap::toast::TOAST_FILE_ID;
+    use crate::page::page_id::PageId;
+    use crate::query::executor::{QueryExecutor, QueryResult};
     use crate::query::parser::parse_sql;
     use crate::query::planner::QueryPlanner;
-    use crate::query::types::Value;
+    use crate::query::session::SessionConfig;
+    use crate::query::tuple::serialize_row;
+    use crate::query::types::{Column, DataType, Schema, Value};
+    use crate::wal::WalDiskManager;
 
     fn create_test_data(dm: &mut FsDiskManager) -> anyhow::Result<()> {
         let pid = dm.allocate_page(1)?;
@@ -40,7 +49,8 @@ mod tests {
 
         let sql = "SELECT * FROM users";
         let stmt = parse_sql(sql)?;
-        let planner = QueryPlanner::new();
+        let catalog = users_catalog();
+        let planner = QueryPlanner::new(&catalog);
         let plan = planner.plan(&stmt)?;
         let executor = QueryExecutor::new();
         let result = executor.execute(plan, &mut dm)?;
@@ -68,7 +78,8 @@ mod tests {
 
         let sql = "SELECT * FROM users LIMIT 3";
         let stmt = parse_sql(sql)?;
-        let planner = QueryPlanner::new();
+        let catalog = users_catalog();
+        let planner = QueryPlanner::new(&catalog);
         let plan = planner.plan(&stmt)?;
         let executor = QueryExecutor::new();
         let result = executor.execute(plan, &mut dm)?;
@@ -78,6 +89,163 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_describe_query() -> anyhow::Result<()> {
+        use crate::query::types::{Column, DataType};
+
+        let temp_dir = TempDir::new()?;
+        let mut dm = FsDiskManager::new(temp_dir.path().to_str().unwrap())?;
+
+        let catalog = users_catalog();
+        let planner = QueryPlanner::new(&catalog);
+        let executor = QueryExecutor::new();
+
+        let plan = planner.plan(&parse_sql("SELECT * FROM users WHERE id > 2 LIMIT 1")?)?;
+        let schema = executor.describe(&plan)?;
+        assert_eq!(
+            schema.columns,
+            vec![
+                Column {
+                    name: "id".to_string(),
+                    data_type: DataType::Integer,
+                    nullable: false,
+                },
+                Column {
+                    name: "name".to_string(),
+                    data_type: DataType::Varchar(255),
+                    nullable: true,
+                },
+            ]
+        );
+
+        let plan = planner.plan(&parse_sql(
+            "SELECT name, id + 1, id > 3, GREATEST(name, 'm') FROM users WHERE id + 1 > 2",
+        )?)?;
+        let schema = executor.describe(&plan)?;
+        let described: Vec<(String, DataType)> = schema
+            .columns
+            .iter()
+            .map(|col| (col.name.clone(), col.data_type.clone()))
+            .collect();
+        assert_eq!(
+            described,
+            vec![
+                ("name".to_string(), DataType::Varchar(255)),
+                ("expr".to_string(), DataType::Integer),
+                ("expr:1".to_string(), DataType::Boolean),
+                ("greatest".to_string(), DataType::Varchar(255)),
+            ]
+        );
+
+        // Describing never touches the data files
+        assert_eq!(std::fs::read_dir(temp_dir.path())?.count(), 0);
+
+        create_test_data(&mut dm)?;
+        let result = executor.execute(plan, &mut dm)?;
+        assert_eq!(result.schema, schema);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_cursor_pagination() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let mut dm = FsDiskManager::new(temp_dir.path().to_str().unwrap())?;
+
+        create_test_data(&mut dm)?;
+
+        let catalog = users_catalog();
+        let planner = QueryPlanner::new(&catalog);
+        let plan = planner.plan(&parse_sql("SELECT name FROM users")?)?;
+        let mut cursor = QueryExecutor::new().open_cursor(plan, &mut dm)?;
+
+        assert_eq!(cursor.len(), 5);
+        cursor.seek(3)?;
+        assert_eq!(
+            cursor.next(),
+            Some(vec![Value::Varchar("user_3".to_string())])
+        );
+        assert_eq!(cursor.position(), 4);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_registered_function() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let mut dm = FsDiskManager::new(temp_dir.path().to_str().unwrap())?;
+
+        create_test_data(&mut dm)?;
+
+        let mut executor = QueryExecutor::new();
+        executor.register_function("double", 1, |args| match &args[0] {
+            Value::Integer(i) => Ok(Value::Integer(i * 2)),
+            other => anyhow::bail!("double expects an integer, got {:?}", other),
+        });
+
+        let catalog = users_catalog();
+        let planner = QueryPlanner::new(&catalog);
+        let plan = planner.plan(&parse_sql("SELECT id, DOUBLE(id) FROM users")?)?;
+        let result = executor.execute(plan, &mut dm)?;
+
+        assert_eq!(result.rows.len(), 5);
+        assert_eq!(result.schema.columns[1].name, "double");
+        for (i, row) in result.rows.iter().enumerate() {
+            assert_eq!(row[1], Value::Integer(i as i32 * 2));
+        }
+
+        let plan = planner.plan(&parse_sql("SELECT double(id, id) FROM users")?)?;
+        let err = executor.execute(plan, &mut dm).err().unwrap();
+        assert!(err.to_string().contains("expects 1 argument(s), got 2"));
+
+        let plan = planner.plan(&parse_sql("SELECT triple(id) FROM users")?)?;
+        assert!(executor.execute(plan, &mut dm).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_repeated_subexpression_query() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let mut dm = FsDiskManager::new(temp_dir.path().to_str().unwrap())?;
+
+        create_test_data(&mut dm)?;
+
+        let catalog = users_catalog();
+        let planner = QueryPlanner::new(&catalog);
+        let executor = QueryExecutor::new();
+
+        let plan = planner.plan(&parse_sql(
+            "SELECT id * 10, id FROM users WHERE id * 10 >= 20",
+        )?)?;
+        let result = executor.execute(plan, &mut dm)?;
+        assert_eq!(result.schema.columns[0].name, "expr");
+        assert_eq!(result.schema.columns[1].name, "id");
+        assert_eq!(
+            result.rows,
+            vec![
+                vec![Value::Integer(20), Value::Integer(2)],
+                vec![Value::Integer(30), Value::Integer(3)],
+                vec![Value::Integer(40), Value::Integer(4)],
+            ]
+        );
+
+        let plan = planner.plan(&parse_sql(
+            "SELECT * FROM users WHERE id + 1 = 2 OR id + 1 = 5",
+        )?)?;
+        let result = executor.execute(plan, &mut dm)?;
+        assert_eq!(result.schema.columns.len(), 2);
+        assert_eq!(
+            result.rows,
+            vec![
+                vec![Value::Integer(1), Value::Varchar("user_1".to_string())],
+                vec![Value::Integer(4), Value::Varchar("user_4".to_string())],
+            ]
+        );
+
+        Ok(())
+    }
+
     #[test]
     fn test_parser_error_handling() {
         let invalid_sql = "INVALID GARBAGE";
@@ -103,7 +271,8 @@ mod tests {
         let stmt = parse_sql(sql);
         assert!(stmt.is_ok());
 
-        let planner = QueryPlanner::new();
+        let catalog = users_catalog();
+        let planner = QueryPlanner::new(&catalog);
         let result = planner.plan(&stmt.unwrap());
         assert!(result.is_err());
     }
@@ -118,6 +287,7 @@ mod tests {
                 assert_eq!(select.select_list.len(), 1);
                 assert!(select.from.is_none());
             }
+            other => panic!("Expected SELECT, got {:?}", other),
         }
 
         Ok(())
@@ -132,6 +302,7 @@ mod tests {
             crate::query::ast::Statement::Select(select) => {
                 assert_eq!(select.select_list.len(), 4);
             }
+            other => panic!("Expected SELECT, got {:?}", other),
         }
 
         Ok(())
@@ -147,8 +318,2031 @@ mod tests {
                 assert!(select.where_clause.is_some());
                 assert_eq!(select.from, Some("users".to_string()));
             }
+            other => panic!("Expected SELECT, got {:?}", other),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_limit_percent() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let mut dm = FsDiskManager::new(temp_dir.path().to_str().unwrap())?;
+        create_test_data(&mut dm)?;
+        create_test_data(&mut dm)?;
+
+        let catalog = users_catalog();
+        let planner = QueryPlanner::new(&catalog);
+        let executor = QueryExecutor::new();
+        let count = |dm: &mut FsDiskManager, sql: &str| -> anyhow::Result<usize> {
+            let plan = planner.plan(&parse_sql(sql)?)?;
+            Ok(executor.execute(plan, dm)?.rows.len())
+        };
+
+        assert_eq!(count(&mut dm, "SELECT * FROM users LIMIT 50 PERCENT")?, 5);
+        // Fractional row counts round up
+        assert_eq!(count(&mut dm, "SELECT * FROM users LIMIT 15 PERCENT")?, 2);
+        assert_eq!(
+            count(&mut dm, "SELECT * FROM users WHERE id < 2 LIMIT 25 PERCENT")?,
+            1
+        );
+        assert_eq!(count(&mut dm, "SELECT * FROM users LIMIT 0 PERCENT")?, 0);
+        assert_eq!(count(&mut dm, "SELECT * FROM users LIMIT 200 PERCENT")?, 10);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_identifier_case_resolution() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let mut dm = FsDiskManager::new(temp_dir.path().to_str().unwrap())?;
+        create_test_data(&mut dm)?;
+
+        let catalog = users_catalog();
+        let planner = QueryPlanner::new(&catalog);
+        let executor = QueryExecutor::new();
+        let run = |planner: &QueryPlanner, dm: &mut FsDiskManager, sql: &str| {
+            let plan = planner.plan(&parse_sql(sql)?)?;
+            executor.execute(plan, dm)
+        };
+
+        // Unquoted names resolve regardless of case
+        let result = run(&planner, &mut dm, "SELECT NAME, Id FROM users WHERE iD = 3")?;
+        assert_eq!(result.schema.columns[0].name, "name");
+        assert_eq!(result.schema.columns[1].name, "id");
+        assert_eq!(
+            result.rows,
+            vec![vec![
+                Value::Varchar("user_3".to_string()),
+                Value::Integer(3)
+            ]]
+        );
+
+        // Quoted names are case-sensitive
+        let result = run(
+            &planner,
+            &mut dm,
+            "SELECT \"name\" FROM users WHERE \"id\" = 3",
+        )?;
+        assert_eq!(result.rows.len(), 1);
+        assert!(run(&planner, &mut dm, "SELECT \"Name\" FROM users").is_err());
+
+        let catalog = users_catalog();
+        let planner = QueryPlanner::with_config(
+            &catalog,
+            SessionConfig {
+                case_insensitive_identifiers: false,
+                ..SessionConfig::default()
+            },
+        );
+        assert!(run(&planner, &mut dm, "SELECT NAME FROM users").is_err());
+        assert_eq!(
+            run(&planner, &mut dm, "SELECT name FROM users")?.rows.len(),
+            5
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_row_number() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let mut dm = FsDiskManager::new(temp_dir.path().to_str().unwrap())?;
+        create_test_data(&mut dm)?;
+
+        let catalog = users_catalog();
+        let planner = QueryPlanner::new(&catalog);
+        let executor = QueryExecutor::new();
+        let run = |dm: &mut FsDiskManager, sql: &str| {
+            let plan = planner.plan(&parse_sql(sql)?)?;
+            executor.execute(plan, dm)
+        };
+
+        let result = run(
+            &mut dm,
+            "SELECT ROW_NUMBER() OVER (), id FROM users WHERE id > 1",
+        )?;
+        assert_eq!(result.schema.columns[0].name, "row_number");
+        let numbers: Vec<Value> = result.rows.iter().map(|row| row[0].clone()).collect();
+        assert_eq!(numbers, (1..=3).map(Value::Integer).collect::<Vec<_>>());
+        assert_eq!(result.rows[0][1], Value::Integer(2));
+
+        // Numbering follows the limited output, streamed or not
+        for sql in [
+            "SELECT name, row_number() * 10 FROM users LIMIT 2",
+            "SELECT name, row_number() * 10 FROM users LIMIT 40 PERCENT",
+        ] {
+            let result = run(&mut dm, sql)?;
+            assert_eq!(
+                result.rows,
+                vec![
+                    vec![Value::Varchar("user_0".to_string()), Value::Integer(10)],
+                    vec![Value::Varchar("user_1".to_string()), Value::Integer(20)],
+                ]
+            );
+        }
+
+        // Repeated expressions using row numbers are not precomputed
+        let result = run(
+            &mut dm,
+            "SELECT row_number() + 1, row_number() + 1 FROM users LIMIT 1",
+        )?;
+        assert_eq!(
+            result.rows,
+            vec![vec![Value::Integer(2), Value::Integer(2)]]
+        );
+
+        assert!(run(&mut dm, "SELECT * FROM users WHERE ROW_NUMBER() = 1").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_session_max_rows() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let mut dm = FsDiskManager::new(temp_dir.path().to_str().unwrap())?;
+        create_test_data(&mut dm)?;
+
+        let config = SessionConfig {
+            max_rows: Some(3),
+            ..SessionConfig::default()
+        };
+        let catalog = users_catalog();
+        let planner = QueryPlanner::with_config(&catalog, config.clone());
+        let executor = QueryExecutor::with_config(config);
+        assert_eq!(executor.config().max_rows, Some(3));
+
+        let plan = planner.plan(&parse_sql("SELECT * FROM users")?)?;
+        assert_eq!(executor.execute(plan, &mut dm)?.rows.len(), 3);
+
+        // A smaller LIMIT in the query still applies
+        let plan = planner.plan(&parse_sql("SELECT * FROM users LIMIT 2")?)?;
+        assert_eq!(executor.execute(plan, &mut dm)?.rows.len(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_repeated_column_projection() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let mut dm = FsDiskManager::new(temp_dir.path().to_str().unwrap())?;
+        create_test_data(&mut dm)?;
+
+        let catalog = users_catalog();
+        let planner = QueryPlanner::new(&catalog);
+        let executor = QueryExecutor::new();
+        let plan = planner.plan(&parse_sql("SELECT id, id, name, id FROM users LIMIT 1")?)?;
+        assert_eq!(
+            executor.describe(&plan)?,
+            executor.execute(plan.clone(), &mut dm)?.schema
+        );
+        let result = executor.execute(plan, &mut dm)?;
+
+        let names: Vec<_> = result.schema.columns.iter().map(|c| &c.name).collect();
+        assert_eq!(names, vec!["id", "id:1", "name", "id:2"]);
+        assert_eq!(
+            result.rows,
+            vec![vec![
+                Value::Integer(0),
+                Value::Integer(0),
+                Value::Varchar("user_0".to_string()),
+                Value::Integer(0),
+            ]]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_concurrent_reads_through_shared_handle() -> anyhow::Result<()> {
+        use crate::disk::read_handle::ReadHandle;
+        use std::sync::{Arc, RwLock};
+
+        let temp_dir = TempDir::new()?;
+        let mut dm = FsDiskManager::new(temp_dir.path())?;
+        create_test_data(&mut dm)?;
+        let shared = Arc::new(RwLock::new(dm));
+        let handle = ReadHandle::new(Arc::clone(&shared));
+
+        let readers: Vec<_> = (0..4)
+            .map(|i| {
+                let mut handle = handle.clone();
+                std::thread::spawn(move || -> anyhow::Result<Vec<Vec<Value>>> {
+                    let sql = format!("SELECT id FROM users WHERE id >= {}", i);
+                    let plan = QueryPlanner::new(&users_catalog()).plan(&parse_sql(&sql)?)?;
+                    Ok(QueryExecutor::new().execute(plan, &mut handle)?.rows)
+                })
+            })
+            .collect();
+        for (i, reader) in readers.into_iter().enumerate() {
+            let rows = reader.join().unwrap()?;
+            let expected: Vec<_> = (i as i32..5).map(|id| vec![Value::Integer(id)]).collect();
+            assert_eq!(rows, expected);
+        }
+
+        // Writes go through the owner of the lock, not the handles
+        let mut reader = handle.clone();
+        assert!(reader.allocate_page(1).is_err());
+        shared.write().unwrap().allocate_page(1)?;
+        assert_eq!(reader.page_count(1)?, 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_intersect_and_except() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let mut dm = FsDiskManager::new(temp_dir.path())?;
+        create_test_data(&mut dm)?;
+
+        let catalog = users_catalog();
+        let planner = QueryPlanner::new(&catalog);
+        let executor = QueryExecutor::new();
+        let mut ids = |sql: &str| -> anyhow::Result<Vec<i32>> {
+            let plan = planner.plan(&parse_sql(sql)?)?;
+            let result = executor.execute(plan, &mut dm)?;
+            Ok(result
+                .rows
+                .into_iter()
+                .map(|row| match row[0] {
+                    Value::Integer(id) => id,
+                    ref other => panic!("Expected integer, got {:?}", other),
+                })
+                .collect())
+        };
+
+        assert_eq!(
+            ids("SELECT id FROM users WHERE id > 1 INTERSECT VALUES (4), (2), (9), (2)")?,
+            vec![2, 4]
+        );
+        assert_eq!(
+            ids("SELECT id FROM users EXCEPT VALUES (1), (3)")?,
+            vec![0, 2, 4]
+        );
+        assert_eq!(
+            ids("VALUES (1), (1), (1), (2) INTERSECT ALL VALUES (1), (1)")?,
+            vec![1, 1]
+        );
+        assert_eq!(
+            ids("VALUES (1), (1), (1), (2) EXCEPT ALL VALUES (1), (2)")?,
+            vec![1, 1]
+        );
+        assert_eq!(ids("VALUES (1), (1), (2) EXCEPT VALUES (3)")?, vec![1, 2]);
+
+        let mismatched = [
+            "SELECT id FROM users INTERSECT SELECT name FROM users",
+            "SELECT id FROM users EXCEPT SELECT id, name FROM users",
+        ];
+        for sql in mismatched {
+            let plan = planner.plan(&parse_sql(sql)?)?;
+            assert!(executor.describe(&plan).is_err(), "{}", sql);
+            assert!(executor.execute(plan, &mut dm).is_err(), "{}", sql);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_order_by() -> anyhow::Result<()> {
+        use crate::heap::heap_file::HeapFile;
+        use crate::query::ast::Expression;
+        use crate::query::planner::PhysicalPlan;
+        use crate::query::tuple::serialize_row;
+
+        let temp_dir = TempDir::new()?;
+        let mut dm = FsDiskManager::new(temp_dir.path())?;
+        let catalog = users_catalog();
+        let planner = QueryPlanner::new(&catalog);
+        let executor = QueryExecutor::new();
+
+        let schema = executor.describe(&planner.plan(&parse_sql("SELECT * FROM users")?)?)?;
+        let heap = HeapFile::new(1);
+        for (id, name) in [(0, "b"), (1, "a"), (2, "b"), (3, "a"), (4, "c")] {
+            let row = vec![Value::Integer(id), Value::Varchar(name.to_string())];
+            heap.insert(&mut dm, &serialize_row(&row, &schema)?)?;
+        }
+
+        let mut ids = |sql: &str| -> anyhow::Result<Vec<i32>> {
+            let result = executor.execute(planner.plan(&parse_sql(sql)?)?, &mut dm)?;
+            Ok(result
+                .rows
+                .iter()
+                .map(|row| match row[0] {
+                    Value::Integer(id) => id,
+                    ref other => panic!("Expected integer, got {:?}", other),
+                })
+                .collect())
+        };
+
+        // Ties keep their scan order in either direction
+        assert_eq!(
+            ids("SELECT * FROM users ORDER BY name")?,
+            vec![1, 3, 0, 2, 4]
+        );
+        assert_eq!(
+            ids("SELECT * FROM users ORDER BY name DESC")?,
+            vec![4, 0, 2, 1, 3]
+        );
+        assert_eq!(
+            ids("SELECT * FROM users ORDER BY name ASC, id DESC")?,
+            vec![3, 1, 2, 0, 4]
+        );
+        assert_eq!(
+            ids("SELECT id FROM users WHERE id > 0 ORDER BY id % 2, name LIMIT 3")?,
+            vec![2, 4, 1]
+        );
+
+        // Sort keys do not have to be selected
+        let plan = planner.plan(&parse_sql(
+            "SELECT name FROM users ORDER BY id DESC LIMIT 2",
+        )?)?;
+        let result = executor.execute(plan, &mut dm)?;
+        assert_eq!(
+            result.rows,
+            vec![
+                vec![Value::Varchar("c".to_string())],
+                vec![Value::Varchar("a".to_string())],
+            ]
+        );
+
+        // NULLs sort last whichever the direction
+        for desc in [false, true] {
+            let plan = PhysicalPlan::Sort {
+                keys: vec![(Expression::column("column1"), desc)],
+                input: Box::new(planner.plan(&parse_sql("VALUES (NULL), (1), (2)")?)?),
+            };
+            let result = executor.execute(plan, &mut dm)?;
+            assert_eq!(result.rows[2], vec![Value::Null]);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_values_statement() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let mut dm = FsDiskManager::new(temp_dir.path().to_str().unwrap())?;
+
+        let catalog = users_catalog();
+        let planner = QueryPlanner::new(&catalog);
+        let executor = QueryExecutor::new();
+        let plan = planner.plan(&parse_sql("VALUES (1, 'a'), (2, 'b')")?)?;
+        let result = executor.execute(plan, &mut dm)?;
+
+        let names: Vec<_> = result.schema.columns.iter().map(|c| &c.name).collect();
+        assert_eq!(names, vec!["column1", "column2"]);
+        assert_eq!(
+            result.rows,
+            vec![
+                vec![Value::Integer(1), Value::Varchar("a".to_string())],
+                vec![Value::Integer(2), Value::Varchar("b".to_string())],
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_gzip_database_matches_original() -> anyhow::Result<()> {
+        use std::io::Write;
+
+        let original_dir = TempDir::new()?;
+        let mut original = FsDiskManager::new(original_dir.path())?;
+        create_test_data(&mut original)?;
+        create_test_data(&mut original)?;
+
+        let archive_dir = TempDir::new()?;
+        let mut encoder = flate2::write::GzEncoder::new(
+            std::fs::File::create(archive_dir.path().join("base_1.db.gz"))?,
+            flate2::Compression::default(),
+        );
+        encoder.write_all(&std::fs::read(original_dir.path().join("base_1.db"))?)?;
+        encoder.finish()?;
+        let mut archived = FsDiskManager::open_gzip(archive_dir.path())?;
+
+        let catalog = users_catalog();
+        let planner = QueryPlanner::new(&catalog);
+        let executor = QueryExecutor::new();
+        for sql in [
+            "SELECT * FROM users",
+            "SELECT name FROM users WHERE id > 2 LIMIT 3",
+        ] {
+            let expected = executor.execute(planner.plan(&parse_sql(sql)?)?, &mut original)?;
+            let actual = executor.execute(planner.plan(&parse_sql(sql)?)?, &mut archived)?;
+            assert_eq!(actual.rows, expected.rows);
+        }
+
+        assert_eq!(archived.page_count(1)?, 2);
+        assert_eq!(archived.page_count(2)?, 0);
+        let page = archived.read_page(PageId::new(1, 0))?;
+        assert!(archived.write_page(&page).is_err());
+        assert!(archived.allocate_page(1).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_explain_analyze() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let mut dm = FsDiskManager::new(temp_dir.path().to_str().unwrap())?;
+        create_test_data(&mut dm)?;
+
+        let catalog = users_catalog();
+        let planner = QueryPlanner::new(&catalog);
+        let executor = QueryExecutor::new();
+        let lines = |dm: &mut FsDiskManager, sql: &str| -> anyhow::Result<Vec<String>> {
+            let result = executor.execute(planner.plan(&parse_sql(sql)?)?, dm)?;
+            assert_eq!(result.schema.columns[0].name, "QUERY PLAN");
+            Ok(result
+                .rows
+                .into_iter()
+                .map(|row| match &row[0] {
+                    Value::Varchar(line) => line.clone(),
+                    other => panic!("Expected text, got {:?}", other),
+                })
+                .collect())
+        };
+
+        let plan = lines(
+            &mut dm,
+            "EXPLAIN SELECT name FROM users WHERE id > 2 LIMIT 1",
+        )?;
+        assert_eq!(
+            plan,
+            vec![
+                "Limit: 1",
+                "  -> Projection: name",
+                "    -> Filter: (id > 2)",
+                "      -> SeqScan on users",
+            ]
+        );
+
+        let plan = lines(
+            &mut dm,
+            "EXPLAIN ANALYZE SELECT name FROM users WHERE id > 2",
+        )?;
+        assert_eq!(plan.len(), 3);
+        assert!(plan[0].starts_with("Projection: name (rows=2 time="));
+        assert!(plan[1].starts_with("  -> Filter: (id > 2) (rows=2 time="));
+        assert!(plan[2].starts_with("    -> SeqScan on users (rows=5 time="));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_limit_stops_scan_early() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let mut dm = FsDiskManager::new(temp_dir.path().to_str().unwrap())?;
+
+        for page in 0i32..50 {
+            let mut hp = HeapPage::allocate(&mut dm, 1)?;
+            for i in 0i32..10 {
+                let id = page * 10 + i;
+                let mut tuple_data = id.to_le_bytes().to_vec();
+                let name = format!("user_{}", id);
+                tuple_data.extend_from_slice(&(name.len() as u32).to_le_bytes());
+                tuple_data.extend_from_slice(name.as_bytes());
+                hp.insert_tuple(&tuple_data)?;
+            }
+            dm.write_page(&hp.page)?;
+        }
+
+        let catalog = users_catalog();
+        let planner = QueryPlanner::new(&catalog);
+        let executor = QueryExecutor::new();
+        let plan = planner.plan(&parse_sql(
+            "SELECT name FROM users WHERE id % 2 = 1 LIMIT 12",
+        )?)?;
+        dm.reset_stats();
+        let result = executor.execute(plan, &mut dm)?;
+
+        assert_eq!(result.rows.len(), 12);
+        assert_eq!(result.rows[0], vec![Value::Varchar("user_1".to_string())]);
+        assert_eq!(result.rows[11], vec![Value::Varchar("user_23".to_string())]);
+        assert_eq!(result.schema.columns[0].name, "name");
+        assert_eq!(dm.stats().page_reads, 3);
+
+        let plan = planner.plan(&parse_sql("SELECT * FROM users LIMIT 0")?)?;
+        dm.reset_stats();
+        assert!(executor.execute(plan, &mut dm)?.rows.is_empty());
+        assert_eq!(dm.stats().page_reads, 0);
+
+        // Without a LIMIT every page is read
+        let plan = planner.plan(&parse_sql("SELECT * FROM users")?)?;
+        dm.reset_stats();
+        assert_eq!(executor.execute(plan, &mut dm)?.rows.len(), 500);
+        assert_eq!(dm.stats().page_reads, 50);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_execute_each_delivers_rows_as_they_are_read() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let mut dm = FsDiskManager::new(temp_dir.path())?;
+        for page in 0i32..50 {
+            let mut hp = HeapPage::allocate(&mut dm, 1)?;
+            for i in 0i32..10 {
+                let id = page * 10 + i;
+                let mut tuple_data = id.to_le_bytes().to_vec();
+                let name = format!("user_{}", id);
+                tuple_data.extend_from_slice(&(name.len() as u32).to_le_bytes());
+                tuple_data.extend_from_slice(name.as_bytes());
+                hp.insert_tuple(&tuple_data)?;
+            }
+            dm.write_page(&hp.page)?;
+        }
+
+        let catalog = users_catalog();
+        let planner = QueryPlanner::new(&catalog);
+        let executor = QueryExecutor::new();
+        let plan = planner.plan(&parse_sql("SELECT id FROM users WHERE id % 2 = 1")?)?;
+        let mut rows = Vec::new();
+        executor.execute_each(plan, &mut dm, |row| {
+            rows.push(row);
+            Ok(())
+        })?;
+        assert_eq!(rows.len(), 250);
+        assert_eq!(rows[0], vec![Value::Integer(1)]);
+
+        // The first row arrives after one page read, and an error from the
+        // callback ends the scan there
+        let plan = planner.plan(&parse_sql("SELECT id FROM users")?)?;
+        dm.reset_stats();
+        let result = executor.execute_each(plan, &mut dm, |_| anyhow::bail!("reader went away"));
+        assert!(result.is_err());
+        assert_eq!(dm.stats().page_reads, 1);
+
+        // Plans that cannot stream still deliver their rows
+        let plan = planner.plan(&parse_sql("SELECT id FROM users ORDER BY id DESC LIMIT 2")?)?;
+        let mut rows = Vec::new();
+        executor.execute_each(plan, &mut dm, |row| {
+            rows.push(row);
+            Ok(())
+        })?;
+        assert_eq!(
+            rows,
+            vec![vec![Value::Integer(499)], vec![Value::Integer(498)]]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_tables_from_persisted_catalog() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        {
+            let mut dm = FsDiskManager::new(temp_dir.path())?;
+            let mut catalog = Catalog::new();
+            let users = users_catalog().table("users")?.schema.clone();
+            let flags = Schema::new(vec![
+                Column {
+                    name: "flag".to_string(),
+                    data_type: DataType::Boolean,
+                    nullable: false,
+                },
+                Column {
+                    name: "code".to_string(),
+                    data_type: DataType::Integer,
+                    nullable: false,
+                },
+            ]);
+            let users_file = catalog.create_table(&mut dm, "users", users.clone())?;
+            let flags_file = catalog.create_table(&mut dm, "flags", flags.clone())?;
+
+            let heap = HeapFile::new(users_file);
+            for (id, name) in [(1, "ann"), (2, "bob")] {
+                let row = [Value::Integer(id), Value::Varchar(name.to_string())];
+                heap.insert(&mut dm, &serialize_row(&row, &users)?)?;
+            }
+            let heap = HeapFile::new(flags_file);
+            for (flag, code) in [(true, 10), (false, 20), (true, 30)] {
+                let row = [Value::Boolean(flag), Value::Integer(code)];
+                heap.insert(&mut dm, &serialize_row(&row, &flags)?)?;
+            }
+        }
+
+        let mut dm = FsDiskManager::new(temp_dir.path())?;
+        let catalog = Catalog::load(&dm)?;
+        let planner = QueryPlanner::new(&catalog);
+        let executor = QueryExecutor::new();
+
+        let plan = planner.plan(&parse_sql("SELECT name FROM users WHERE id = 2")?)?;
+        let result = executor.execute(plan, &mut dm)?;
+        assert_eq!(result.rows, vec![vec![Value::Varchar("bob".to_string())]]);
+
+        let plan = planner.plan(&parse_sql("SELECT code FROM flags WHERE flag = TRUE")?)?;
+        let result = executor.execute(plan, &mut dm)?;
+        assert_eq!(
+            result.rows,
+            vec![vec![Value::Integer(10)], vec![Value::Integer(30)]]
+        );
+
+        // Unquoted names are case-insensitive by default
+        let plan = planner.plan(&parse_sql("SELECT * FROM FLAGS")?)?;
+        assert_eq!(executor.execute(plan, &mut dm)?.rows.len(), 3);
+
+        let err = planner
+            .plan(&parse_sql("SELECT * FROM orders")?)
+            .unwrap_err();
+        assert_eq!(err.to_string(), "table 'orders' not found");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_insert_then_select() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let mut dm = FsDiskManager::new(temp_dir.path())?;
+        let mut catalog = Catalog::new();
+        let users = users_catalog().table("users")?.schema.clone();
+        let file_id = catalog.create_table(&mut dm, "users", users)?;
+
+        let planner = QueryPlanner::new(&catalog);
+        let executor = QueryExecutor::new();
+        let mut run = |sql: &str| -> anyhow::Result<Vec<Vec<Value>>> {
+            let plan = planner.plan(&parse_sql(sql)?)?;
+            Ok(executor.execute(plan, &mut dm)?.rows)
+        };
+
+        assert_eq!(
+            run("INSERT INTO users (name, id) VALUES ('ann', 1), ('bob', 1 + 1)")?,
+            vec![vec![Value::Integer(2)]]
+        );
+        assert_eq!(
+            run("INSERT INTO users VALUES (3, 'cy')")?,
+            vec![vec![Value::Integer(1)]]
+        );
+        assert_eq!(
+            run("SELECT id, name FROM users")?,
+            vec![
+                vec![Value::Integer(1), Value::Varchar("ann".to_string())],
+                vec![Value::Integer(2), Value::Varchar("bob".to_string())],
+                vec![Value::Integer(3), Value::Varchar("cy".to_string())],
+            ]
+        );
+
+        // Rejected statements write nothing, even when earlier rows are valid
+        let err = run("INSERT INTO users VALUES (4, 'dee'), ('five', 'eve')").unwrap_err();
+        assert!(err.to_string().contains("Cannot store"), "{}", err);
+        let err = run("INSERT INTO users (id) VALUES (4)").unwrap_err();
+        assert_eq!(err.to_string(), "No value given for column 'name'");
+        let err = run("INSERT INTO users (id, nope) VALUES (4, 'x')").unwrap_err();
+        assert_eq!(err.to_string(), "Column 'nope' not found in table 'users'");
+        let err = run("INSERT INTO users VALUES (4)").unwrap_err();
+        assert_eq!(err.to_string(), "INSERT has 1 values but expects 2");
+        let long = format!("INSERT INTO users VALUES (4, '{}')", "x".repeat(256));
+        assert!(run(&long).unwrap_err().to_string().contains("too long"));
+        assert!(run("INSERT INTO orders VALUES (1)").is_err());
+        assert_eq!(run("SELECT * FROM users")?.len(), 3);
+
+        // Rows spill onto new pages once the last one is full
+        let name = "y".repeat(200);
+        let rows: Vec<String> = (10..60).map(|id| format!("({}, '{}')", id, name)).collect();
+        run(&format!("INSERT INTO users VALUES {}", rows.join(", ")))?;
+        assert_eq!(run("SELECT * FROM users")?.len(), 53);
+        assert!(dm.page_count(file_id)? > 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_large_varchar_stored_out_of_line() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let mut dm = FsDiskManager::new(temp_dir.path())?;
+        let mut catalog = Catalog::new();
+        let schema = Schema::new(vec![
+            Column {
+                name: "id".to_string(),
+                data_type: DataType::Integer,
+                nullable: false,
+            },
+            Column {
+                name: "body".to_string(),
+                data_type: DataType::Varchar(100_000),
+                nullable: false,
+            },
+        ]);
+        let file_id = catalog.create_table(&mut dm, "docs", schema)?;
+
+        let body: String = (0..20_000)
+            .map(|i| (b'a' + (i % 26) as u8) as char)
+            .collect();
+        let planner = QueryPlanner::new(&catalog);
+        let executor = QueryExecutor::new();
+        let sql = format!("INSERT INTO docs VALUES (1, '{}'), (2, 'short')", body);
+        executor.execute(planner.plan(&parse_sql(&sql)?)?, &mut dm)?;
+
+        let mut tuple_lens = Vec::new();
+        HeapFile::new(file_id).scan_raw(&dm, |_, _, tuple| {
+            tuple_lens.push(tuple.len());
+            Ok(())
+        })?;
+        assert_eq!(tuple_lens, vec![4 + 4 + 8, 4 + 4 + 5]);
+
+        let plan = planner.plan(&parse_sql("SELECT body FROM docs ORDER BY id")?)?;
+        let result = executor.execute(plan, &mut dm)?;
+        assert_eq!(
+            result.rows,
+            vec![
+                vec![Value::Varchar(body.clone())],
+                vec![Value::Varchar("short".to_string())],
+            ]
+        );
+
+        // The streaming scan path reads it back too
+        let plan = planner.plan(&parse_sql("SELECT body FROM docs LIMIT 1")?)?;
+        assert_eq!(
+            executor.execute(plan, &mut dm)?.rows,
+            vec![vec![Value::Varchar(body.clone())]]
+        );
+
+        // Pages of replaced and deleted values are reused, so rewriting the
+        // value does not grow the overflow file past two copies of it
+        let stored = dm.page_count(TOAST_FILE_ID)?;
+        for _ in 0..3 {
+            let plan = planner.plan(&parse_sql("UPDATE docs SET body = body WHERE id = 1")?)?;
+            executor.execute(plan, &mut dm)?;
+        }
+        assert_eq!(dm.page_count(TOAST_FILE_ID)?, stored * 2);
+        let plan = planner.plan(&parse_sql("DELETE FROM docs WHERE id = 1")?)?;
+        executor.execute(plan, &mut dm)?;
+        executor.execute(
+            planner.plan(&parse_sql(&sql.replace("(2, 'short')", "(3, 'x')"))?)?,
+            &mut dm,
+        )?;
+        assert_eq!(dm.page_count(TOAST_FILE_ID)?, stored * 2);
+        let plan = planner.plan(&parse_sql("SELECT id, body FROM docs WHERE id = 1")?)?;
+        assert_eq!(
+            executor.execute(plan, &mut dm)?.rows,
+            vec![vec![Value::Integer(1), Value::Varchar(body)]]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_delete_where() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let mut dm = FsDiskManager::new(temp_dir.path())?;
+        let mut catalog = users_catalog();
+        let users = catalog.table("users")?.schema.clone();
+        catalog.add_table("empty", 2, users)?;
+        create_test_data(&mut dm)?;
+
+        let planner = QueryPlanner::new(&catalog);
+        let executor = QueryExecutor::new();
+        let mut run = |sql: &str| -> anyhow::Result<Vec<Vec<Value>>> {
+            let plan = planner.plan(&parse_sql(sql)?)?;
+            Ok(executor.execute(plan, &mut dm)?.rows)
+        };
+
+        assert_eq!(
+            run("DELETE FROM users WHERE id % 2 = 1")?,
+            vec![vec![Value::Integer(2)]]
+        );
+        assert_eq!(
+            run("SELECT id FROM users")?,
+            vec![
+                vec![Value::Integer(0)],
+                vec![Value::Integer(2)],
+                vec![Value::Integer(4)],
+            ]
+        );
+        assert_eq!(
+            run("DELETE FROM users WHERE id > 100")?,
+            vec![vec![Value::Integer(0)]]
+        );
+
+        // A table whose file has no pages yet
+        assert_eq!(run("DELETE FROM empty")?, vec![vec![Value::Integer(0)]]);
+
+        assert!(run("DELETE FROM users WHERE nope = 1").is_err());
+        assert_eq!(run("DELETE FROM users")?, vec![vec![Value::Integer(3)]]);
+        assert!(run("SELECT * FROM users")?.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_set() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let mut dm = FsDiskManager::new(temp_dir.path())?;
+        create_test_data(&mut dm)?;
+
+        let catalog = users_catalog();
+        let planner = QueryPlanner::new(&catalog);
+        let executor = QueryExecutor::new();
+        let locations = |dm: &FsDiskManager| -> anyhow::Result<Vec<(PageId, usize)>> {
+            let mut locations = Vec::new();
+            HeapFile::new(1).scan_raw(dm, |pid, slot_no, _| {
+                locations.push((pid, slot_no));
+                Ok(())
+            })?;
+            Ok(locations)
+        };
+        let before = locations(&dm)?;
+
+        // Same-size tuples are rewritten in their slots
+        let plan = planner.plan(&parse_sql(
+            "UPDATE users SET id = id + 10, name = 'USER_X' WHERE id < 2",
+        )?)?;
+        assert_eq!(
+            executor.execute(plan, &mut dm)?.rows,
+            vec![vec![Value::Integer(2)]]
+        );
+        assert_eq!(locations(&dm)?, before);
+
+        // Growing a varchar moves the tuple to the end of the file
+        let plan = planner.plan(&parse_sql(
+            "UPDATE users SET name = 'a much longer name' WHERE id = 3",
+        )?)?;
+        assert_eq!(
+            executor.execute(plan, &mut dm)?.rows,
+            vec![vec![Value::Integer(1)]]
+        );
+        assert_ne!(locations(&dm)?, before);
+
+        let plan = planner.plan(&parse_sql("SELECT id, name FROM users")?)?;
+        let rows = executor.execute(plan, &mut dm)?.rows;
+        let text = |s: &str| Value::Varchar(s.to_string());
+        assert_eq!(
+            rows,
+            vec![
+                vec![Value::Integer(10), text("USER_X")],
+                vec![Value::Integer(11), text("USER_X")],
+                vec![Value::Integer(2), text("user_2")],
+                vec![Value::Integer(4), text("user_4")],
+                vec![Value::Integer(3), text("a much longer name")],
+            ]
+        );
+
+        // A bad value leaves every row as it was
+        let plan = planner.plan(&parse_sql("UPDATE users SET id = 'x' WHERE id > 3")?)?;
+        assert!(executor.execute(plan, &mut dm).is_err());
+        let plan = planner.plan(&parse_sql("SELECT id, name FROM users")?)?;
+        assert_eq!(executor.execute(plan, &mut dm)?.rows, rows);
+
+        assert!(
+            planner
+                .plan(&parse_sql("UPDATE users SET nope = 1")?)
+                .is_err()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_null_comparisons_filter_rows() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let mut dm = FsDiskManager::new(temp_dir.path())?;
+        create_test_data(&mut dm)?;
+
+        let catalog = users_catalog();
+        let planner = QueryPlanner::new(&catalog);
+        let executor = QueryExecutor::new();
+        let mut ids = |sql: &str| -> anyhow::Result<Vec<Value>> {
+            let plan = planner.plan(&parse_sql(sql)?)?;
+            let rows = executor.execute(plan, &mut dm)?.rows;
+            Ok(rows.into_iter().map(|row| row[0].clone()).collect())
+        };
+
+        assert!(ids("SELECT id FROM users WHERE name = NULL")?.is_empty());
+        assert!(ids("SELECT id FROM users WHERE id <> NULL")?.is_empty());
+        assert!(ids("SELECT id FROM users WHERE NULL")?.is_empty());
+        assert_eq!(
+            ids("SELECT id FROM users WHERE id = 1 OR name = NULL")?,
+            vec![Value::Integer(1)]
+        );
+        assert!(ids("SELECT id FROM users WHERE id = 1 AND name = NULL")?.is_empty());
+        assert_eq!(
+            ids("SELECT id = NULL FROM users LIMIT 1")?,
+            vec![Value::Null]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_is_null() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let mut dm = FsDiskManager::new(temp_dir.path())?;
+        let mut catalog = Catalog::new();
+        let users = users_catalog().table("users")?.schema.clone();
+        catalog.create_table(&mut dm, "users", users)?;
+
+        let planner = QueryPlanner::new(&catalog);
+        let executor = QueryExecutor::new();
+        let mut run = |sql: &str| -> anyhow::Result<Vec<Vec<Value>>> {
+            let plan = planner.plan(&parse_sql(sql)?)?;
+            Ok(executor.execute(plan, &mut dm)?.rows)
+        };
+
+        run("INSERT INTO users VALUES (1, 'ann'), (2, NULL), (3, 'cy'), (4, NULL)")?;
+        let ids = |rows: Vec<Vec<Value>>| -> Vec<Value> {
+            rows.into_iter().map(|row| row[0].clone()).collect()
+        };
+        assert_eq!(
+            ids(run("SELECT id FROM users WHERE name IS NULL")?),
+            vec![Value::Integer(2), Value::Integer(4)]
+        );
+        assert_eq!(
+            ids(run("SELECT id FROM users WHERE name IS NOT NULL")?),
+            vec![Value::Integer(1), Value::Integer(3)]
+        );
+        assert!(run("SELECT id FROM users WHERE id IS NULL")?.is_empty());
+        assert_eq!(
+            run("SELECT name IS NULL, NULL IS NOT NULL FROM users WHERE id = 2")?,
+            vec![vec![Value::Boolean(true), Value::Boolean(false)]]
+        );
+
+        // NULL makes it through UPDATE, and non-nullable columns refuse it
+        run("UPDATE users SET name = NULL WHERE id = 1")?;
+        assert_eq!(
+            ids(run("SELECT id FROM users WHERE name IS NOT NULL")?),
+            vec![Value::Integer(3)]
+        );
+        let err = run("INSERT INTO users VALUES (NULL, 'eve')").unwrap_err();
+        assert!(err.to_string().contains("Cannot store"), "{}", err);
+        Ok(())
+    }
+
+    #[test]
+    fn test_aggregates() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let mut dm = FsDiskManager::new(temp_dir.path())?;
+        let mut catalog = Catalog::new();
+        let users = users_catalog().table("users")?.schema.clone();
+        catalog.create_table(&mut dm, "users", users)?;
+
+        let planner = QueryPlanner::new(&catalog);
+        let executor = QueryExecutor::new();
+        let mut run = |sql: &str| -> anyhow::Result<Vec<Vec<Value>>> {
+            let plan = planner.plan(&parse_sql(sql)?)?;
+            Ok(executor.execute(plan, &mut dm)?.rows)
+        };
+
+        // Over no rows COUNT is 0 and everything else NULL
+        assert_eq!(
+            run("SELECT COUNT(*), SUM(id), AVG(id), MIN(id), MAX(name) FROM users")?,
+            vec![vec![
+                Value::Integer(0),
+                Value::Null,
+                Value::Null,
+                Value::Null,
+                Value::Null
+            ]]
+        );
+
+        run("INSERT INTO users VALUES (1, 'cy'), (2, NULL), (4, 'ann'), (6, 'bo')")?;
+        assert_eq!(
+            run("SELECT COUNT(*), COUNT(name), SUM(id), AVG(id), MIN(name), MAX(id) FROM users")?,
+            vec![vec![
+                Value::Integer(4),
+                Value::Integer(3),
+                Value::Integer(13),
+                Value::Integer(3),
+                Value::Varchar("ann".to_string()),
+                Value::Integer(6)
+            ]]
+        );
+        assert_eq!(
+            run("SELECT COUNT(*), MAX(id) - MIN(id), SUM(id * 2) FROM users WHERE id > 1")?,
+            vec![vec![
+                Value::Integer(3),
+                Value::Integer(4),
+                Value::Integer(24)
+            ]]
+        );
+
+        let mut err = |sql: &str| run(sql).unwrap_err().to_string();
+        assert!(err("SELECT id, COUNT(*) FROM users").contains("Column 'id'"));
+        assert!(err("SELECT id FROM users WHERE COUNT(*) > 1").contains("WHERE"));
+        assert!(err("SELECT SUM(COUNT(*)) FROM users").contains("nested"));
+        assert!(err("SELECT SUM(name) FROM users").contains("integer"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_group_by_having() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let mut dm = FsDiskManager::new(temp_dir.path())?;
+        let mut catalog = Catalog::new();
+        let users = users_catalog().table("users")?.schema.clone();
+        catalog.create_table(&mut dm, "users", users)?;
+
+        let planner = QueryPlanner::new(&catalog);
+        let executor = QueryExecutor::new();
+        let mut run = |sql: &str| -> anyhow::Result<QueryResult> {
+            let plan = planner.plan(&parse_sql(sql)?)?;
+            executor.execute(plan, &mut dm)
+        };
+
+        run(
+            "INSERT INTO users VALUES (1, 'ann'), (2, 'bo'), (3, 'ann'), (4, NULL), \
+             (5, 'ann'), (6, 'bo'), (7, NULL)",
+        )?;
+        let ann = || Value::Varchar("ann".to_string());
+        let bo = || Value::Varchar("bo".to_string());
+
+        // Groups come out in the order they were first seen, NULLs together
+        assert_eq!(
+            run("SELECT name, COUNT(*) FROM users GROUP BY name")?.rows,
+            vec![
+                vec![ann(), Value::Integer(3)],
+                vec![bo(), Value::Integer(2)],
+                vec![Value::Null, Value::Integer(2)],
+            ]
+        );
+        assert_eq!(
+            run("SELECT name, COUNT(*), SUM(id) FROM users GROUP BY name HAVING COUNT(*) > 2")?
+                .rows,
+            vec![vec![ann(), Value::Integer(3), Value::Integer(9)]]
+        );
+        assert_eq!(
+            run("SELECT COUNT(name) FROM users GROUP BY name HAVING name IS NULL")?.rows,
+            vec![vec![Value::Integer(0)]]
+        );
+        assert_eq!(
+            run("SELECT name FROM users GROUP BY name ORDER BY MAX(id) DESC")?.rows,
+            vec![vec![Value::Null], vec![bo()], vec![ann()]]
+        );
+
+        let result = run("SELECT id % 2, COUNT(*) FROM users GROUP BY id % 2 ORDER BY id % 2")?;
+        let names: Vec<&str> = result
+            .schema
+            .columns
+            .iter()
+            .map(|c| c.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["expr", "count"]);
+        assert_eq!(
+            result.rows,
+            vec![
+                vec![Value::Integer(0), Value::Integer(3)],
+                vec![Value::Integer(1), Value::Integer(4)],
+            ]
+        );
+
+        let mut err = |sql: &str| run(sql).err().unwrap().to_string();
+        assert!(err("SELECT id, COUNT(*) FROM users GROUP BY name").contains("GROUP BY"));
+        assert!(err("SELECT * FROM users GROUP BY id").contains("SELECT *"));
+        assert!(err("SELECT name FROM users GROUP BY COUNT(*)").contains("GROUP BY"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_distinct_values() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let mut dm = FsDiskManager::new(temp_dir.path())?;
+        let mut catalog = Catalog::new();
+        let users = users_catalog().table("users")?.schema.clone();
+        catalog.create_table(&mut dm, "users", users)?;
+        let executor = QueryExecutor::new();
+        let plan = QueryPlanner::new(&catalog).plan(&parse_sql(
+            "INSERT INTO users VALUES (1, 'bo'), (2, NULL), (3, 'ann'), (1, 'bo'), (5, NULL)",
+        )?)?;
+        executor.execute(plan, &mut dm)?;
+
+        let table = catalog.table("users")?;
+        assert_eq!(
+            executor.distinct_values(table.file_id, &table.schema, "name", &mut dm)?,
+            vec![
+                Value::Varchar("bo".to_string()),
+                Value::Null,
+                Value::Varchar("ann".to_string()),
+            ]
+        );
+        assert_eq!(
+            executor.distinct_values(table.file_id, &table.schema, "id", &mut dm)?,
+            vec![
+                Value::Integer(1),
+                Value::Integer(2),
+                Value::Integer(3),
+                Value::Integer(5),
+            ]
+        );
+        assert!(
+            executor
+                .distinct_values(table.file_id, &table.schema, "age", &mut dm)
+                .is_err()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_select_distinct() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let mut dm = FsDiskManager::new(temp_dir.path())?;
+        let mut catalog = Catalog::new();
+        let users = users_catalog().table("users")?.schema.clone();
+        catalog.create_table(&mut dm, "users", users)?;
+
+        let planner = QueryPlanner::new(&catalog);
+        let executor = QueryExecutor::new();
+        let mut run = |sql: &str| -> anyhow::Result<Vec<Vec<Value>>> {
+            let plan = planner.plan(&parse_sql(sql)?)?;
+            Ok(executor.execute(plan, &mut dm)?.rows)
+        };
+
+        run(
+            "INSERT INTO users VALUES (1, 'bo'), (2, NULL), (3, 'ann'), (4, 'bo'), \
+             (5, NULL), (6, 'ann'), (7, 'cy')",
+        )?;
+        assert_eq!(run("SELECT name FROM users")?.len(), 7);
+        let names = run("SELECT DISTINCT name FROM users")?;
+        assert_eq!(names.len(), 4);
+        assert_eq!(
+            names,
+            vec![
+                vec![Value::Varchar("bo".to_string())],
+                vec![Value::Null],
+                vec![Value::Varchar("ann".to_string())],
+                vec![Value::Varchar("cy".to_string())],
+            ]
+        );
+
+        // Whole rows are compared, and LIMIT counts distinct rows
+        assert_eq!(run("SELECT DISTINCT id, name FROM users")?.len(), 7);
+        assert_eq!(
+            run("SELECT DISTINCT name FROM users WHERE name IS NOT NULL ORDER BY name LIMIT 2")?,
+            vec![
+                vec![Value::Varchar("ann".to_string())],
+                vec![Value::Varchar("bo".to_string())],
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_select_aliases() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let mut dm = FsDiskManager::new(temp_dir.path())?;
+        create_test_data(&mut dm)?;
+        let catalog = users_catalog();
+        let planner = QueryPlanner::new(&catalog);
+        let executor = QueryExecutor::new();
+        let mut run = |sql: &str| -> anyhow::Result<QueryResult> {
+            let plan = planner.plan(&parse_sql(sql)?)?;
+            executor.execute(plan, &mut dm)
+        };
+        let names = |result: &QueryResult| -> Vec<String> {
+            result
+                .schema
+                .columns
+                .iter()
+                .map(|c| c.name.clone())
+                .collect()
+        };
+
+        let result = run("SELECT id AS user_id, name AS full_name FROM users WHERE id < 2")?;
+        assert_eq!(names(&result), vec!["user_id", "full_name"]);
+        assert_eq!(
+            result.rows,
+            vec![
+                vec![Value::Integer(0), Value::Varchar("user_0".to_string())],
+                vec![Value::Integer(1), Value::Varchar("user_1".to_string())],
+            ]
+        );
+
+        let result = run(
+            "SELECT id * 2 doubled, 1 one, COUNT(*) AS total, name FROM users GROUP BY id, name LIMIT 1",
+        )?;
+        assert_eq!(names(&result), vec!["doubled", "one", "total", "name"]);
+
+        // A projection that only renames columns is kept
+        let result = run("SELECT id AS name, name AS id FROM users LIMIT 1")?;
+        assert_eq!(names(&result), vec!["name", "id"]);
+        assert_eq!(
+            result.rows,
+            vec![vec![
+                Value::Integer(0),
+                Value::Varchar("user_0".to_string())
+            ]]
+        );
+        let result = run("EXPLAIN SELECT id AS user_id, name FROM users")?;
+        assert_eq!(
+            result.rows[0],
+            vec![Value::Varchar(
+                "Projection: id AS user_id, name".to_string()
+            )]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_order_by_select_alias() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let mut dm = FsDiskManager::new(temp_dir.path())?;
+        create_test_data(&mut dm)?;
+        let catalog = users_catalog();
+        let planner = QueryPlanner::new(&catalog);
+        let executor = QueryExecutor::new();
+        let mut run = |sql: &str| -> anyhow::Result<QueryResult> {
+            let plan = planner.plan(&parse_sql(sql)?)?;
+            executor.execute(plan, &mut dm)
+        };
+
+        let result = run("SELECT id AS x FROM users ORDER BY x DESC LIMIT 2")?;
+        assert_eq!(
+            result.rows,
+            vec![vec![Value::Integer(4)], vec![Value::Integer(3)]]
+        );
+
+        let result = run("SELECT COUNT(*) AS c FROM users ORDER BY c")?;
+        assert_eq!(result.rows, vec![vec![Value::Integer(5)]]);
+
+        let result = run(
+            "SELECT id % 2 AS parity, COUNT(*) AS c FROM users GROUP BY id % 2 ORDER BY c DESC",
+        )?;
+        assert_eq!(
+            result.rows,
+            vec![
+                vec![Value::Integer(0), Value::Integer(3)],
+                vec![Value::Integer(1), Value::Integer(2)],
+            ]
+        );
+
+        // The alias shadows the column it renames to
+        let result = run("SELECT name AS id, 10 - id AS name FROM users ORDER BY id DESC LIMIT 1")?;
+        assert_eq!(
+            result.rows,
+            vec![vec![
+                Value::Varchar("user_4".to_string()),
+                Value::Integer(6)
+            ]]
+        );
+
+        // A quoted alias is matched by the same quoted name
+        let result =
+            run("SELECT name AS \"Full Name\" FROM users ORDER BY \"Full Name\" DESC LIMIT 1")?;
+        assert_eq!(result.schema.columns[0].name, "Full Name");
+        assert_eq!(
+            result.rows,
+            vec![vec![Value::Varchar("user_4".to_string())]]
+        );
+        assert!(run("SELECT name AS \"Full Name\" FROM users ORDER BY \"full name\"").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_order_by_position() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let mut dm = FsDiskManager::new(temp_dir.path())?;
+        create_test_data(&mut dm)?;
+        let catalog = users_catalog();
+        let planner = QueryPlanner::new(&catalog);
+        let executor = QueryExecutor::new();
+        let mut run = |sql: &str| -> anyhow::Result<QueryResult> {
+            let plan = planner.plan(&parse_sql(sql)?)?;
+            executor.execute(plan, &mut dm)
+        };
+
+        assert_eq!(
+            run("SELECT name, 10 - id FROM users ORDER BY 2 LIMIT 2")?.rows,
+            vec![
+                vec![Value::Varchar("user_4".to_string()), Value::Integer(6)],
+                vec![Value::Varchar("user_3".to_string()), Value::Integer(7)],
+            ]
+        );
+        assert_eq!(
+            run("SELECT * FROM users ORDER BY 1 DESC LIMIT 1")?.rows,
+            vec![vec![
+                Value::Integer(4),
+                Value::Varchar("user_4".to_string())
+            ]]
+        );
+        assert!(run("SELECT id FROM users ORDER BY 2").is_err());
+        assert!(run("SELECT id FROM users ORDER BY 0").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_common_subexpressions_only_see_filtered_rows() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let mut dm = FsDiskManager::new(temp_dir.path())?;
+        create_test_data(&mut dm)?;
+        let catalog = users_catalog();
+        let planner = QueryPlanner::new(&catalog);
+        let mut executor = QueryExecutor::new();
+        let calls = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let counter = calls.clone();
+        executor.register_function("tick", 1, move |args| {
+            counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Ok(args[0].clone())
+        });
+        let mut run = |sql: &str| -> anyhow::Result<QueryResult> {
+            let plan = planner.plan(&parse_sql(sql)?)?;
+            executor.execute(plan, &mut dm)
+        };
+
+        // id 0 would divide by zero if the repeated 10 / id were computed
+        // before the WHERE clause removed it
+        assert_eq!(
+            run("SELECT 10 / id, 10 / id FROM users WHERE id <> 0 ORDER BY id LIMIT 1")?.rows,
+            vec![vec![Value::Integer(10), Value::Integer(10)]]
+        );
+        assert_eq!(
+            run("SELECT 10 / id AS x FROM users WHERE id > 0 ORDER BY x")?.rows,
+            vec![
+                vec![Value::Integer(2)],
+                vec![Value::Integer(3)],
+                vec![Value::Integer(5)],
+                vec![Value::Integer(10)],
+            ]
+        );
+
+        // A registered function runs once per call, not once per distinct call
+        run("SELECT tick(id) + 1, tick(id) + 1 FROM users")?;
+        assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 10);
+        Ok(())
+    }
+
+    #[test]
+    fn test_where_like() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let mut dm = FsDiskManager::new(temp_dir.path())?;
+        create_test_data(&mut dm)?;
+
+        let catalog = users_catalog();
+        let planner = QueryPlanner::new(&catalog);
+        let executor = QueryExecutor::new();
+        let mut ids = |sql: &str| -> anyhow::Result<Vec<Value>> {
+            let plan = planner.plan(&parse_sql(sql)?)?;
+            let rows = executor.execute(plan, &mut dm)?.rows;
+            Ok(rows.into_iter().map(|row| row[0].clone()).collect())
+        };
+
+        assert_eq!(
+            ids("SELECT id FROM users WHERE name LIKE 'user%'")?.len(),
+            5
+        );
+        assert_eq!(
+            ids("SELECT id FROM users WHERE name LIKE '%3'")?,
+            vec![Value::Integer(3)]
+        );
+        assert_eq!(
+            ids("SELECT id FROM users WHERE name LIKE 'u__r\\_1' OR name LIKE '%r_4%'")?,
+            vec![Value::Integer(1), Value::Integer(4)]
+        );
+        assert!(ids("SELECT id FROM users WHERE name LIKE 'user'")?.is_empty());
+        assert!(ids("SELECT id FROM users WHERE id LIKE '1'").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_select_into_creates_table() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let mut dm = FsDiskManager::new(temp_dir.path())?;
+        let mut catalog = Catalog::new();
+        let users = users_catalog().table("users")?.schema.clone();
+        catalog.create_table(&mut dm, "users", users.clone())?;
+
+        let executor = QueryExecutor::new();
+        let mut run = |sql: &str| -> anyhow::Result<Vec<Vec<Value>>> {
+            let plan = QueryPlanner::new(&catalog).plan(&parse_sql(sql)?)?;
+            Ok(executor
+                .execute_with_catalog(plan, &mut catalog, &mut dm)?
+                .rows)
+        };
+
+        run("INSERT INTO users VALUES (1, 'ann'), (2, 'bob'), (3, NULL), (4, 'dee')")?;
+        assert_eq!(
+            run("SELECT id, name INTO later_users FROM users WHERE id > 1 AND name IS NOT NULL")?,
+            vec![vec![Value::Integer(2)]]
+        );
+        // The new table can be queried straight away with the same catalog
+        assert_eq!(
+            run("SELECT id, name FROM later_users")?,
+            vec![
+                vec![Value::Integer(2), Value::Varchar("bob".to_string())],
+                vec![Value::Integer(4), Value::Varchar("dee".to_string())],
+            ]
+        );
+
+        // Computed columns get inferred types
+        run("SELECT id * 10, name INTO scaled FROM users WHERE id < 3")?;
+        assert_eq!(
+            run("SELECT name FROM scaled")?,
+            vec![
+                vec![Value::Varchar("ann".to_string())],
+                vec![Value::Varchar("bob".to_string())],
+            ]
+        );
+
+        let err = run("SELECT id INTO later_users FROM users").unwrap_err();
+        assert_eq!(err.to_string(), "table 'later_users' already exists");
+        assert!(run("SELECT id INTO x FROM users INTERSECT SELECT id FROM users").is_err());
+
+        // Both tables were saved to the catalog on disk too
+        let saved = Catalog::load(&dm)?;
+        let later = saved.table("later_users")?;
+        assert_eq!(later.schema, users);
+        assert_eq!(later.file_id, 2);
+        let scaled = &saved.table("scaled")?.schema;
+        assert_eq!(scaled.columns[0].data_type, DataType::Integer);
+        assert!(saved.table("x").is_err());
+
+        // A table the caller has added but not yet saved keeps its file id
+        catalog.add_table("pending", 4, users.clone())?;
+        let plan =
+            QueryPlanner::new(&catalog).plan(&parse_sql("SELECT id INTO ids FROM users")?)?;
+        executor.execute_with_catalog(plan, &mut catalog, &mut dm)?;
+        assert_eq!(catalog.table("ids")?.file_id, 5);
+
+        // Without a catalog to add it to, SELECT INTO is refused
+        let plan =
+            QueryPlanner::new(&catalog).plan(&parse_sql("SELECT id INTO more FROM users")?)?;
+        assert!(executor.execute(plan, &mut dm).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_where_in_list() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let mut dm = FsDiskManager::new(temp_dir.path())?;
+        create_test_data(&mut dm)?;
+
+        let catalog = users_catalog();
+        let planner = QueryPlanner::new(&catalog);
+        let executor = QueryExecutor::new();
+        let mut ids = |sql: &str| -> anyhow::Result<Vec<Value>> {
+            let plan = planner.plan(&parse_sql(sql)?)?;
+            let rows = executor.execute(plan, &mut dm)?.rows;
+            Ok(rows.into_iter().map(|row| row[0].clone()).collect())
+        };
+        let ints = |ids: &[i32]| -> Vec<Value> { ids.iter().map(|&i| Value::Integer(i)).collect() };
+
+        assert_eq!(
+            ids("SELECT id FROM users WHERE id IN (1, 3, 5)")?,
+            ints(&[1, 3])
+        );
+        assert_eq!(
+            ids("SELECT id FROM users WHERE id NOT IN (1, 3, 5)")?,
+            ints(&[0, 2, 4])
+        );
+        assert_eq!(
+            ids("SELECT id FROM users WHERE name IN ('user_4', 'nobody', 'user_' + '0')")?,
+            ints(&[0, 4])
+        );
+        assert_eq!(
+            ids("SELECT id FROM users WHERE name NOT IN ('user_4') AND id IN (id - 1, 4, 2)")?,
+            ints(&[2])
+        );
+
+        // A NULL in the list makes NOT IN unknown for every non-matching row
+        assert_eq!(
+            ids("SELECT id FROM users WHERE id IN (2, NULL)")?,
+            ints(&[2])
+        );
+        assert!(ids("SELECT id FROM users WHERE id NOT IN (2, NULL)")?.is_empty());
+        assert!(ids("SELECT id FROM users WHERE id IN ('a')").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_order_by_boolean() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let mut dm = FsDiskManager::new(temp_dir.path())?;
+        let mut catalog = Catalog::new();
+        let accounts = Schema::new(vec![
+            Column {
+                name: "id".to_string(),
+                data_type: DataType::Integer,
+                nullable: false,
+            },
+            Column {
+                name: "active".to_string(),
+                data_type: DataType::Boolean,
+                nullable: false,
+            },
+        ]);
+        catalog.create_table(&mut dm, "accounts", accounts)?;
+
+        let planner = QueryPlanner::new(&catalog);
+        let executor = QueryExecutor::new();
+        let mut run = |sql: &str| -> anyhow::Result<Vec<Vec<Value>>> {
+            let plan = planner.plan(&parse_sql(sql)?)?;
+            Ok(executor.execute(plan, &mut dm)?.rows)
+        };
+        run("INSERT INTO accounts VALUES (1, true), (2, false), (3, true), (4, false)")?;
+        let ids = |rows: Vec<Vec<Value>>| -> Vec<Value> {
+            rows.into_iter().map(|row| row[0].clone()).collect()
+        };
+        let ints = |ids: &[i32]| -> Vec<Value> { ids.iter().map(|&i| Value::Integer(i)).collect() };
+
+        // false sorts before true
+        assert_eq!(
+            ids(run("SELECT id FROM accounts ORDER BY active, id")?),
+            ints(&[2, 4, 1, 3])
+        );
+        assert_eq!(
+            ids(run("SELECT id FROM accounts ORDER BY active DESC, id")?),
+            ints(&[1, 3, 2, 4])
+        );
+        assert_eq!(
+            ids(run("SELECT id FROM accounts WHERE active > false")?),
+            ints(&[1, 3])
+        );
+        assert_eq!(
+            run("SELECT GREATEST(active, false), LEAST(active, true) FROM accounts WHERE id = 2")?,
+            vec![vec![Value::Boolean(false), Value::Boolean(false)]]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_where_between() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let mut dm = FsDiskManager::new(temp_dir.path())?;
+        create_test_data(&mut dm)?;
+
+        let catalog = users_catalog();
+        let planner = QueryPlanner::new(&catalog);
+        let executor = QueryExecutor::new();
+        let mut ids = |sql: &str| -> anyhow::Result<Vec<Value>> {
+            let plan = planner.plan(&parse_sql(sql)?)?;
+            let rows = executor.execute(plan, &mut dm)?.rows;
+            Ok(rows.into_iter().map(|row| row[0].clone()).collect())
+        };
+        let ints = |ids: &[i32]| -> Vec<Value> { ids.iter().map(|&i| Value::Integer(i)).collect() };
+
+        // Both bounds are inclusive
+        assert_eq!(
+            ids("SELECT id FROM users WHERE id BETWEEN 1 AND 3")?,
+            ints(&[1, 2, 3])
+        );
+        assert_eq!(
+            ids("SELECT id FROM users WHERE id NOT BETWEEN 1 AND 3")?,
+            ints(&[0, 4])
+        );
+        assert!(ids("SELECT id FROM users WHERE id BETWEEN 3 AND 1")?.is_empty());
+        assert_eq!(
+            ids("SELECT id FROM users WHERE id BETWEEN 2 AND 2 AND name <> 'x'")?,
+            ints(&[2])
+        );
+
+        assert_eq!(
+            ids("SELECT id FROM users WHERE name BETWEEN 'user_1' AND 'user_3'")?,
+            ints(&[1, 2, 3])
+        );
+        assert_eq!(
+            ids("SELECT id FROM users WHERE name BETWEEN 'user_2a' AND 'user_9'")?,
+            ints(&[3, 4])
+        );
+
+        // An unknown bound is only decisive when the other bound rules the row out
+        assert_eq!(
+            ids("SELECT id FROM users WHERE id NOT BETWEEN NULL AND 1")?,
+            ints(&[2, 3, 4])
+        );
+        assert!(ids("SELECT id FROM users WHERE id BETWEEN 'a' AND 'b'").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_where_not() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let mut dm = FsDiskManager::new(temp_dir.path())?;
+        create_test_data(&mut dm)?;
+
+        let catalog = users_catalog();
+        let planner = QueryPlanner::new(&catalog);
+        let executor = QueryExecutor::new();
+        let mut ids = |sql: &str| -> anyhow::Result<Vec<Value>> {
+            let plan = planner.plan(&parse_sql(sql)?)?;
+            let rows = executor.execute(plan, &mut dm)?.rows;
+            Ok(rows.into_iter().map(|row| row[0].clone()).collect())
+        };
+        let ints = |ids: &[i32]| -> Vec<Value> { ids.iter().map(|&i| Value::Integer(i)).collect() };
+
+        assert_eq!(
+            ids("SELECT id FROM users WHERE NOT (id = 2)")?,
+            ints(&[0, 1, 3, 4])
+        );
+        // NOT applies to the comparison only, before AND and OR combine
+        assert_eq!(
+            ids("SELECT id FROM users WHERE NOT id < 2 AND id < 4")?,
+            ints(&[2, 3])
+        );
+        assert_eq!(
+            ids("SELECT id FROM users WHERE id = 0 OR NOT id < 4 AND NOT id = 3")?,
+            ints(&[0, 4])
+        );
+        assert_eq!(
+            ids("SELECT id FROM users WHERE NOT (id = 0 OR id > 2)")?,
+            ints(&[1, 2])
+        );
+
+        // NOT NULL stays unknown
+        assert!(ids("SELECT id FROM users WHERE NOT (id = NULL)")?.is_empty());
+        assert!(ids("SELECT id FROM users WHERE NOT name").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_where_between_symmetric() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let mut dm = FsDiskManager::new(temp_dir.path())?;
+        create_test_data(&mut dm)?;
+
+        let catalog = users_catalog();
+        let planner = QueryPlanner::new(&catalog);
+        let executor = QueryExecutor::new();
+        let mut ids = |sql: &str| -> anyhow::Result<Vec<Value>> {
+            let plan = planner.plan(&parse_sql(sql)?)?;
+            let rows = executor.execute(plan, &mut dm)?.rows;
+            Ok(rows.into_iter().map(|row| row[0].clone()).collect())
+        };
+        let ints = |ids: &[i32]| -> Vec<Value> { ids.iter().map(|&i| Value::Integer(i)).collect() };
+
+        // Reversed bounds match nothing unless SYMMETRIC
+        assert!(ids("SELECT id FROM users WHERE id BETWEEN 3 AND 1")?.is_empty());
+        assert_eq!(
+            ids("SELECT id FROM users WHERE id BETWEEN SYMMETRIC 3 AND 1")?,
+            ints(&[1, 2, 3])
+        );
+        assert_eq!(
+            ids("SELECT id FROM users WHERE id BETWEEN SYMMETRIC 1 AND 3")?,
+            ints(&[1, 2, 3])
+        );
+        assert_eq!(
+            ids("SELECT id FROM users WHERE id NOT BETWEEN SYMMETRIC 3 AND 1")?,
+            ints(&[0, 4])
+        );
+        assert_eq!(
+            ids("SELECT id FROM users WHERE name BETWEEN SYMMETRIC 'user_4' AND 'user_3'")?,
+            ints(&[3, 4])
+        );
+        assert_eq!(
+            ids("SELECT id FROM users WHERE id BETWEEN SYMMETRIC 4 - id AND 3")?,
+            ints(&[2, 3])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_generate_series() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let mut dm = FsDiskManager::new(temp_dir.path())?;
+        let catalog = Catalog::new();
+        let planner = QueryPlanner::new(&catalog);
+        let executor = QueryExecutor::new();
+        let mut run = |sql: &str| -> anyhow::Result<Vec<i32>> {
+            let plan = planner.plan(&parse_sql(sql)?)?;
+            Ok(executor
+                .execute(plan, &mut dm)?
+                .rows
+                .into_iter()
+                .map(|row| match row[0] {
+                    Value::Integer(n) => n,
+                    ref other => panic!("Expected integer, got {:?}", other),
+                })
+                .collect())
+        };
+
+        assert_eq!(
+            run("SELECT * FROM generate_series(1, 10)")?,
+            (1..=10).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            run("SELECT * FROM GENERATE_SERIES(10, 1, -3)")?,
+            vec![10, 7, 4, 1]
+        );
+        assert_eq!(
+            run("SELECT * FROM generate_series(0, 9, 4)")?,
+            vec![0, 4, 8]
+        );
+        // A range running against the step is empty
+        assert!(run("SELECT * FROM generate_series(5, 1)")?.is_empty());
+        assert!(run("SELECT * FROM generate_series(1, 5, -1)")?.is_empty());
+
+        // The series is an ordinary input to the rest of the query
+        assert_eq!(
+            run("SELECT generate_series * 2 FROM generate_series(1, 10) \
+                 WHERE generate_series > 7 ORDER BY generate_series DESC LIMIT 2")?,
+            vec![20, 18]
+        );
+        assert_eq!(
+            run("SELECT COUNT(*) FROM generate_series(1, 100)")?,
+            vec![100]
+        );
+
+        assert!(run("SELECT * FROM generate_series(1, 10, 0)").is_err());
+        assert!(run("SELECT * FROM generate_series(1)").is_err());
+        assert!(run("SELECT * FROM no_such_function(1, 2)").is_err());
+
+        let plan = planner.plan(&parse_sql("SELECT * FROM generate_series(1, 2)")?)?;
+        assert_eq!(executor.describe(&plan)?.columns[0].name, "generate_series");
+        Ok(())
+    }
+
+    #[test]
+    fn test_inner_join() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let mut dm = FsDiskManager::new(temp_dir.path())?;
+        let mut catalog = Catalog::new();
+        let users = users_catalog().table("users")?.schema.clone();
+        catalog.create_table(&mut dm, "users", users)?;
+        let orders = Schema::new(vec![
+            Column {
+                name: "id".to_string(),
+                data_type: DataType::Integer,
+                nullable: false,
+            },
+            Column {
+                name: "user_id".to_string(),
+                data_type: DataType::Integer,
+                nullable: false,
+            },
+            Column {
+                name: "total".to_string(),
+                data_type: DataType::Integer,
+                nullable: false,
+            },
+        ]);
+        catalog.create_table(&mut dm, "orders", orders)?;
+
+        let planner = QueryPlanner::new(&catalog);
+        let executor = QueryExecutor::new();
+        let mut run = |sql: &str| -> anyhow::Result<QueryResult> {
+            let plan = planner.plan(&parse_sql(sql)?)?;
+            executor.execute(plan, &mut dm)
+        };
+
+        run("INSERT INTO users VALUES (1, 'ann'), (2, 'bo'), (3, 'cy')")?;
+        run("INSERT INTO orders VALUES (10, 1, 5), (11, 2, 7), (12, 1, 9), (13, 4, 1)")?;
+
+        let result = run("SELECT * FROM users JOIN orders ON users.id = orders.user_id")?;
+        let names: Vec<&str> = result
+            .schema
+            .columns
+            .iter()
+            .map(|col| col.name.as_str())
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                "users.id",
+                "users.name",
+                "orders.id",
+                "orders.user_id",
+                "orders.total"
+            ]
+        );
+        // cy has no orders and order 13 has no user
+        assert_eq!(result.rows.len(), 3);
+        assert_eq!(
+            result.rows[0],
+            vec![
+                Value::Integer(1),
+                Value::Varchar("ann".to_string()),
+                Value::Integer(10),
+                Value::Integer(1),
+                Value::Integer(5),
+            ]
+        );
+
+        // Unqualified names resolve when only one table has the column
+        assert_eq!(
+            run(
+                "SELECT name, total FROM users INNER JOIN orders ON users.id = user_id \
+                 WHERE total > 5 ORDER BY total DESC"
+            )?
+            .rows,
+            vec![
+                vec![Value::Varchar("ann".to_string()), Value::Integer(9)],
+                vec![Value::Varchar("bo".to_string()), Value::Integer(7)],
+            ]
+        );
+        assert_eq!(
+            run(
+                "SELECT name, SUM(total) FROM users JOIN orders ON users.id = user_id \
+                 GROUP BY name ORDER BY name"
+            )?
+            .rows,
+            vec![
+                vec![Value::Varchar("ann".to_string()), Value::Integer(14)],
+                vec![Value::Varchar("bo".to_string()), Value::Integer(7)],
+            ]
+        );
+
+        let Err(err) = run("SELECT id FROM users JOIN orders ON users.id = user_id") else {
+            panic!("Expected an ambiguous column error");
+        };
+        assert!(err.to_string().contains("ambiguous"), "{}", err);
+        assert!(run("SELECT * FROM users JOIN users ON users.id = users.id").is_err());
+
+        // Aliases tell the two sides of a self-join apart
+        let result = run("SELECT * FROM users a JOIN users b ON a.id = b.id")?;
+        let names: Vec<&str> = result
+            .schema
+            .columns
+            .iter()
+            .map(|col| col.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["a.id", "a.name", "b.id", "b.name"]);
+        assert_eq!(result.rows.len(), 3);
+        assert_eq!(
+            run(
+                "SELECT b.name FROM users AS a JOIN users AS b ON b.id = a.id + 1 \
+                 WHERE a.name = 'ann'"
+            )?
+            .rows,
+            vec![vec![Value::Varchar("bo".to_string())]]
+        );
+        assert_eq!(
+            run("SELECT u.name FROM users u WHERE u.id = 3")?.rows,
+            vec![vec![Value::Varchar("cy".to_string())]]
+        );
+        assert!(run("SELECT * FROM users a JOIN orders a ON a.id = a.user_id").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_expressions_over_join() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let mut dm = FsDiskManager::new(temp_dir.path())?;
+        let mut catalog = Catalog::new();
+        let columns = |value: &str| {
+            Schema::new(vec![
+                Column {
+                    name: "id".to_string(),
+                    data_type: DataType::Integer,
+                    nullable: false,
+                },
+                Column {
+                    name: value.to_string(),
+                    data_type: DataType::Integer,
+                    nullable: false,
+                },
+            ])
+        };
+        catalog.create_table(&mut dm, "a", columns("x"))?;
+        catalog.create_table(&mut dm, "b", columns("y"))?;
+
+        let planner = QueryPlanner::new(&catalog);
+        let executor = QueryExecutor::new();
+        let mut run = |sql: &str| -> anyhow::Result<Vec<Vec<Value>>> {
+            let plan = planner.plan(&parse_sql(sql)?)?;
+            Ok(executor.execute(plan, &mut dm)?.rows)
+        };
+        let ints = |rows: &[&[i32]]| -> Vec<Vec<Value>> {
+            rows.iter()
+                .map(|row| row.iter().map(|&n| Value::Integer(n)).collect())
+                .collect()
+        };
+
+        run("INSERT INTO a VALUES (1, 10), (2, 20), (3, 30)")?;
+        run("INSERT INTO b VALUES (1, 4), (2, 50), (4, 7)")?;
+
+        assert_eq!(
+            run("SELECT a.x + b.y, a.x * b.y - a.id, GREATEST(a.x, b.y) \
+                 FROM a JOIN b ON a.id = b.id ORDER BY a.id")?,
+            ints(&[&[14, 39, 10], &[70, 998, 50]])
+        );
+        // A sum repeated across the select list and WHERE
+        assert_eq!(
+            run(
+                "SELECT a.x + b.y, (a.x + b.y) * 2 FROM a JOIN b ON a.id = b.id \
+                 WHERE a.x + b.y > 20"
+            )?,
+            ints(&[&[70, 140]])
+        );
+        // Unqualified columns mix with qualified ones
+        assert_eq!(
+            run("SELECT x - y FROM a JOIN b ON a.id = b.id AND x > b.y ORDER BY x")?,
+            ints(&[&[6]])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_scans_through_buffer_pool() -> anyhow::Result<()> {
+        let disk = GenericDiskManager::new(|_| Ok(std::io::Cursor::new(Vec::new())));
+        let mut pool = BufferPool::new(disk, 16);
+        let mut catalog = Catalog::new();
+        let users = users_catalog().table("users")?.schema.clone();
+        catalog.create_table(&mut pool, "users", users)?;
+
+        let planner = QueryPlanner::new(&catalog);
+        let executor = QueryExecutor::new();
+        let run = |pool: &mut BufferPool<_>, sql: &str| -> anyhow::Result<usize> {
+            let plan = planner.plan(&parse_sql(sql)?)?;
+            Ok(executor.execute(plan, pool)?.rows.len())
+        };
+
+        run(&mut pool, "INSERT INTO users VALUES (1, 'ann'), (2, 'bo')")?;
+        pool.flush()?;
+        pool.disk().reset_stats();
+        assert_eq!(run(&mut pool, "SELECT * FROM users")?, 2);
+        assert_eq!(run(&mut pool, "SELECT name FROM users WHERE id = 2")?, 1);
+        // The inserted pages never left the pool, so no scan went to disk
+        assert_eq!(pool.disk().stats().page_reads, 0);
+
+        // A pool too small for the table still returns every row
+        let disk = pool.into_inner()?;
+        let mut small = BufferPool::new(disk, 1);
+        assert_eq!(run(&mut small, "SELECT * FROM users")?, 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_dml_is_recovered_from_the_wal() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let dir = temp_dir.path();
+        let users = users_catalog().table("users")?.schema.clone();
+        let row = |id: i32, name: &str| vec![Value::Integer(id), Value::Varchar(name.to_string())];
+
+        {
+            // Buffered pages only reach the data file on sync, so dropping
+            // the manager without one is a crash after the log was written
+            let mut dm = WalDiskManager::open(dir, FsDiskManager::new_buffered(dir)?)?;
+            let mut catalog = Catalog::new();
+            catalog.create_table(&mut dm, "users", users)?;
+            let planner = QueryPlanner::new(&catalog);
+            let executor = QueryExecutor::new();
+            let mut run = |sql: &str| -> anyhow::Result<Vec<Vec<Value>>> {
+                let plan = planner.plan(&parse_sql(sql)?)?;
+                Ok(executor.execute(plan, &mut dm)?.rows)
+            };
+            run("INSERT INTO users VALUES (1, 'ann'), (2, 'bo')")?;
+            run("INSERT INTO users VALUES (3, 'cy')")?;
+            run("UPDATE users SET name = 'bea' WHERE id = 2")?;
+            run("DELETE FROM users WHERE id = 1")?;
+            assert_eq!(
+                run("SELECT id, name FROM users ORDER BY id")?,
+                vec![row(2, "bea"), row(3, "cy")]
+            );
+        }
+
+        // Only the catalog and the empty table page were synced
+        let mut dm = FsDiskManager::new(dir)?;
+        let catalog = Catalog::load(&dm)?;
+        let planner = QueryPlanner::new(&catalog);
+        let executor = QueryExecutor::new();
+        let plan = planner.plan(&parse_sql("SELECT id, name FROM users ORDER BY id")?)?;
+        assert!(executor.execute(plan.clone(), &mut dm)?.rows.is_empty());
+
+        // Opening through the log replays the lost writes
+        let mut dm = WalDiskManager::open(dir, dm)?;
+        assert_eq!(
+            executor.execute(plan.clone(), &mut dm)?.rows,
+            vec![row(2, "bea"), row(3, "cy")]
+        );
+        // and writes them back to the data file
+        let mut dm = FsDiskManager::new(dir)?;
+        assert_eq!(
+            executor.execute(plan, &mut dm)?.rows,
+            vec![row(2, "bea"), row(3, "cy")]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_order_by_is_stable() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let mut dm = FsDiskManager::new(temp_dir.path())?;
+        let mut catalog = Catalog::new();
+        let users = users_catalog().table("users")?.schema.clone();
+        catalog.create_table(&mut dm, "users", users)?;
+
+        let planner = QueryPlanner::new(&catalog);
+        let executor = QueryExecutor::new();
+        let mut run = |sql: &str| -> anyhow::Result<Vec<Vec<Value>>> {
+            let plan = planner.plan(&parse_sql(sql)?)?;
+            Ok(executor.execute(plan, &mut dm)?.rows)
+        };
+
+        // Enough rows to span several pages, with only three distinct keys
+        let values: Vec<String> = (0..1000)
+            .map(|i| format!("({}, 'key{}')", i, i % 3))
+            .collect();
+        run(&format!("INSERT INTO users VALUES {}", values.join(", ")))?;
+        let ids = |rows: Vec<Vec<Value>>| -> Vec<i32> {
+            rows.into_iter()
+                .map(|row| match row[0] {
+                    Value::Integer(id) => id,
+                    ref other => panic!("Expected integer, got {:?}", other),
+                })
+                .collect()
+        };
+        let scan_order = ids(run("SELECT id FROM users")?);
+
+        // Rows with equal keys keep their scan order, whichever way the key sorts
+        for (sql, keys) in [
+            ("SELECT id FROM users ORDER BY name", [0, 1, 2]),
+            ("SELECT id FROM users ORDER BY name DESC", [2, 1, 0]),
+        ] {
+            let expected: Vec<i32> = keys
+                .iter()
+                .flat_map(|&key| scan_order.iter().copied().filter(move |id| id % 3 == key))
+                .collect();
+            assert_eq!(ids(run(sql)?), expected, "{}", sql);
         }
 
+        // Pages of a sorted result line up with the full ordering
+        let all = ids(run("SELECT id FROM users ORDER BY name")?);
+        assert_eq!(
+            ids(run("SELECT id FROM users ORDER BY name LIMIT 10")?),
+            all[..10]
+        );
         Ok(())
     }
 }
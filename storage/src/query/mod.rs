@@ -1,7 +1,13 @@
 pub mod ast;
+pub mod columnar;
+pub mod cursor;
 pub mod executor;
+pub mod optimizer;
 pub mod parser;
+pub mod plan_cache;
 pub mod planner;
+pub mod session;
+pub mod tuple;
 pub mod types;
 
 #[cfg(test)]
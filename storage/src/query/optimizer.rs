@@ -0,0 +1,238 @@
+use crate::query::ast::Expression;
+use crate::query::planner::{
+    COMPUTED_COLUMN_PREFIX, PhysicalPlan, ROW_NUMBER_COLUMN, group_column_names,
+    projection_column_names,
+};
+
+// Rewrites applied to every plan after planning. Each one leaves the rows
+// and column names the plan produces unchanged.
+pub fn optimize(plan: PhysicalPlan) -> PhysicalPlan {
+    remove_redundant_projections(plan)
+}
+
+// Folds a projection into the projection below it when it only picks that
+// projection's columns, and drops projections that reproduce their input
+// column for column.
+fn remove_redundant_projections(plan: PhysicalPlan) -> PhysicalPlan {
+    let plan = plan.map_inputs(&mut remove_redundant_projections);
+    let PhysicalPlan::Projection {
+        exprs,
+        aliases,
+        input,
+    } = plan
+    else {
+        return plan;
+    };
+
+    let (exprs, aliases, input) = match *input {
+        PhysicalPlan::Projection {
+            exprs: inner,
+            aliases: inner_aliases,
+            input: inner_input,
+        } => match merge_projections(&exprs, &aliases, &inner, &inner_aliases) {
+            Some((merged, merged_aliases)) => (merged, merged_aliases, inner_input),
+            None => (
+                exprs,
+                aliases,
+                Box::new(PhysicalPlan::Projection {
+                    exprs: inner,
+                    aliases: inner_aliases,
+                    input: inner_input,
+                }),
+            ),
+        },
+        input => (exprs, aliases, Box::new(input)),
+    };
+
+    if is_identity(&exprs, &aliases, &input) {
+        *input
+    } else {
+        PhysicalPlan::Projection {
+            exprs,
+            aliases,
+            input,
+        }
+    }
+}
+
+// `outer` rewritten to read from the input of `inner` directly, if every
+// outer expression is a reference to one of inner's columns and the merged
+// projection keeps the outer column names.
+fn merge_projections(
+    outer: &[Expression],
+    outer_aliases: &[Option<String>],
+    inner: &[Expression],
+    inner_aliases: &[Option<String>],
+) -> Option<(Vec<Expression>, Vec<Option<String>>)> {
+    let inner_names = projection_column_names(inner, inner_aliases);
+    let (merged, merged_aliases): (Vec<_>, Vec<_>) = outer
+        .iter()
+        .enumerate()
+        .map(|(i, expr)| match expr {
+            Expression::Column { name, .. } => {
+                let j = inner_names
+                    .iter()
+                    .position(|inner_name| inner_name == name)?;
+                let alias = outer_aliases.get(i).cloned().flatten();
+                Some((
+                    inner[j].clone(),
+                    alias.or_else(|| inner_aliases.get(j).cloned().flatten()),
+                ))
+            }
+            _ => None,
+        })
+        .collect::<Option<Vec<_>>>()?
+        .into_iter()
+        .unzip();
+    let merged_aliases = if merged_aliases.iter().all(Option::is_none) {
+        Vec::new()
+    } else {
+        merged_aliases
+    };
+    (projection_column_names(&merged, &merged_aliases)
+        == projection_column_names(outer, outer_aliases))
+    .then_some((merged, merged_aliases))
+}
+
+fn is_identity(exprs: &[Expression], aliases: &[Option<String>], input: &PhysicalPlan) -> bool {
+    let Some(input_names) = output_names(input) else {
+        return false;
+    };
+    let referenced: Vec<&str> = exprs
+        .iter()
+        .filter_map(|expr| match expr {
+            Expression::Column { name, .. } => Some(name.as_str()),
+            _ => None,
+        })
+        .collect();
+    referenced.len() == exprs.len()
+        && referenced == input_names
+        && projection_column_names(exprs, aliases) == input_names
+}
+
+// Column names `plan` produces, where they can be worked out without
+// resolving types.
+fn output_names(plan: &PhysicalPlan) -> Option<Vec<String>> {
+    match plan {
+        PhysicalPlan::SeqScan { schema, .. } | PhysicalPlan::Values { schema, .. } => {
+            Some(schema.columns.iter().map(|c| c.name.clone()).collect())
+        }
+        PhysicalPlan::GenerateSeries { .. } => Some(vec!["generate_series".to_string()]),
+        PhysicalPlan::Projection { exprs, aliases, .. } => {
+            Some(projection_column_names(exprs, aliases))
+        }
+        PhysicalPlan::Compute { exprs, input } => {
+            let mut names = output_names(input)?;
+            names.extend((0..exprs.len()).map(|i| format!("{}{}", COMPUTED_COLUMN_PREFIX, i)));
+            Some(names)
+        }
+        PhysicalPlan::Aggregate {
+            aggregates,
+            group_by,
+            ..
+        } => {
+            let mut names = group_column_names(group_by);
+            names.extend(aggregates.iter().map(|expr| expr.to_string()));
+            Some(names)
+        }
+        PhysicalPlan::RowNumber { input } => {
+            let mut names = output_names(input)?;
+            names.push(ROW_NUMBER_COLUMN.to_string());
+            Some(names)
+        }
+        PhysicalPlan::Filter { input, .. }
+        | PhysicalPlan::Sort { input, .. }
+        | PhysicalPlan::Distinct { input }
+        | PhysicalPlan::Limit { input, .. }
+        | PhysicalPlan::LimitPercent { input, .. } => output_names(input),
+        PhysicalPlan::SetOperation { left, .. } => output_names(left),
+        PhysicalPlan::NestedLoopJoin { left, right, .. } => {
+            let mut names = output_names(left)?;
+            names.extend(output_names(right)?);
+            Some(names)
+        }
+        PhysicalPlan::Explain { .. }
+        | PhysicalPlan::Insert { .. }
+        | PhysicalPlan::Update { .. }
+        | PhysicalPlan::Delete { .. }
+        | PhysicalPlan::SelectInto { .. } => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::catalog::users_catalog;
+    use crate::query::parser::parse_sql;
+    use crate::query::planner::QueryPlanner;
+
+    fn projections(plan: &PhysicalPlan) -> usize {
+        let mut count = 0;
+        plan.walk(&mut |node| {
+            if let PhysicalPlan::Projection { .. } = node {
+                count += 1;
+            }
+        });
+        count
+    }
+
+    fn scan() -> PhysicalPlan {
+        let catalog = users_catalog();
+        let table = catalog.table("users").unwrap();
+        PhysicalPlan::SeqScan {
+            table_name: "users".to_string(),
+            file_id: table.file_id,
+            schema: table.schema.clone(),
+        }
+    }
+
+    fn project(names: &[&str], input: PhysicalPlan) -> PhysicalPlan {
+        PhysicalPlan::Projection {
+            exprs: names.iter().map(|name| Expression::column(name)).collect(),
+            aliases: Vec::new(),
+            input: Box::new(input),
+        }
+    }
+
+    #[test]
+    fn identity_projection_is_removed() -> anyhow::Result<()> {
+        let catalog = users_catalog();
+        let planner = QueryPlanner::new(&catalog);
+
+        let plan = planner.plan(&parse_sql("SELECT id, name FROM users WHERE id > 1")?)?;
+        assert_eq!(projections(&plan), 0);
+        assert!(matches!(plan, PhysicalPlan::Filter { .. }));
+
+        // Reordering or dropping columns is real work
+        let plan = planner.plan(&parse_sql("SELECT name, id FROM users")?)?;
+        assert_eq!(projections(&plan), 1);
+        let plan = planner.plan(&parse_sql("SELECT id FROM users")?)?;
+        assert_eq!(projections(&plan), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn adjacent_projections_are_collapsed() {
+        let plan = project(&["name"], project(&["name", "id"], scan()));
+        let PhysicalPlan::Projection { exprs, input, .. } = optimize(plan) else {
+            panic!("Expected a projection");
+        };
+        assert_eq!(exprs, vec![Expression::column("name")]);
+        assert!(matches!(*input, PhysicalPlan::SeqScan { .. }));
+
+        // Both collapse away when the result is the scan's own columns
+        let plan = project(&["id", "name"], project(&["id", "name"], scan()));
+        assert!(matches!(optimize(plan), PhysicalPlan::SeqScan { .. }));
+
+        // An outer projection that computes something stays separate
+        let plan = PhysicalPlan::Projection {
+            exprs: vec![Expression::add(
+                Expression::column("id"),
+                Expression::integer(1),
+            )],
+            aliases: Vec::new(),
+            input: Box::new(project(&["id"], scan())),
+        };
+        assert_eq!(projections(&optimize(plan)), 2);
+    }
+}
@@ -1,45 +1,323 @@
 use nom::{
     IResult,
     branch::alt,
-    bytes::complete::{tag, tag_no_case, take_while1},
+    bytes::complete::{is_not, tag, tag_no_case, take_while1},
     character::complete::{char, digit1, multispace0, multispace1},
-    combinator::{map, opt, recognize},
-    multi::separated_list1,
+    combinator::{map, not, opt, recognize},
+    error::ErrorKind,
+    multi::{many0, separated_list0, separated_list1},
     sequence::{delimited, preceded, terminated, tuple},
 };
 
-use crate::query::ast::{BinaryOperator, Expression, SelectItem, SelectStatement, Statement};
+use crate::query::ast::{
+    AggregateFunction, BinaryOperator, ColumnName, Expression, Join, SelectItem, SelectStatement,
+    SetOperator, Statement,
+};
 use crate::query::types::Value;
 
+// A statement in a script that failed to parse.
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+#[error("statement {index} (byte {offset}): {message}")]
+pub struct ParseError {
+    // Position of the statement in the script, counting from 0
+    pub index: usize,
+    // Byte offset of the statement's first non-blank character
+    pub offset: usize,
+    pub message: String,
+}
+
+// Anything after the statement other than whitespace and one `;` is an
+// error, so a misspelled clause is not silently dropped.
 pub fn parse_sql(input: &str) -> anyhow::Result<Statement> {
-    let (_remaining, stmt) = statement(input).map_err(|e| anyhow::anyhow!("Parse error: {}", e))?;
+    let (rest, stmt) = statement(input).map_err(|e| anyhow::anyhow!(error_message(e)))?;
+    let rest = rest.trim_start();
+    let rest = rest.strip_prefix(';').unwrap_or(rest).trim();
+    if !rest.is_empty() {
+        anyhow::bail!("Parse error: unexpected input '{}'", rest);
+    }
     Ok(stmt)
 }
 
+// Parses every `;`-separated statement in `input`, carrying on past
+// failures so that all of a script's errors are reported in one pass.
+// As with `parse_sql`, a statement followed by unparsed text is an error.
+pub fn parse_script_collect(input: &str) -> (Vec<Statement>, Vec<ParseError>) {
+    let mut statements = Vec::new();
+    let mut errors = Vec::new();
+    for (index, (offset, text)) in split_statements(input).into_iter().enumerate() {
+        let message = match statement(text) {
+            Ok((rest, stmt)) if rest.trim().is_empty() => {
+                statements.push(stmt);
+                continue;
+            }
+            Ok((rest, _)) => format!("Parse error: unexpected input '{}'", rest.trim()),
+            Err(e) => error_message(e),
+        };
+        errors.push(ParseError {
+            index,
+            offset,
+            message,
+        });
+    }
+    (statements, errors)
+}
+
+fn error_message(e: nom::Err<nom::error::Error<&str>>) -> String {
+    match e {
+        nom::Err::Failure(err) if err.code == ErrorKind::Escaped => format!(
+            "Parse error: invalid escape sequence '{}'",
+            err.input.chars().take(2).collect::<String>()
+        ),
+        e => format!("Parse error: {}", e),
+    }
+}
+
+// Splits a script at semicolons outside string literals and quoted
+// identifiers, dropping blank statements. Each piece comes with the byte
+// offset in `input` of its first non-blank character.
+pub fn split_statements(input: &str) -> Vec<(usize, &str)> {
+    split_at_semicolons(input)
+        .into_iter()
+        .filter(|(_, text)| !text.trim().is_empty())
+        .map(|(start, text)| {
+            let trimmed = text.trim_start();
+            (start + text.len() - trimmed.len(), trimmed)
+        })
+        .collect()
+}
+
+// Whether `input` holds at least one statement and ends with a semicolon
+// outside any quotes, so a line-based reader can stop collecting input.
+pub fn is_complete_script(input: &str) -> bool {
+    let pieces = split_at_semicolons(input);
+    pieces.len() > 1
+        && pieces
+            .last()
+            .is_some_and(|(_, rest)| rest.trim().is_empty())
+        && pieces.iter().any(|(_, text)| !text.trim().is_empty())
+}
+
+// Every piece between unquoted semicolons, including blank ones and the
+// unterminated text after the last semicolon.
+fn split_at_semicolons(input: &str) -> Vec<(usize, &str)> {
+    let mut pieces = Vec::new();
+    let mut start = 0;
+    let mut quote: Option<char> = None;
+    let mut escapes = false;
+    let mut prev = ['\0', '\0'];
+    let mut chars = input.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match (quote, c) {
+            (Some('\''), '\\') if escapes => {
+                chars.next();
+            }
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '\'' | '"') => {
+                quote = Some(c);
+                // E'...' escape strings, but not an identifier ending in e
+                escapes = c == '\''
+                    && prev[1].eq_ignore_ascii_case(&'e')
+                    && !(prev[0].is_alphanumeric() || prev[0] == '_');
+            }
+            (None, ';') => {
+                pieces.push((start, &input[start..i]));
+                start = i + 1;
+            }
+            (None, _) => {}
+        }
+        prev = [prev[1], c];
+    }
+    pieces.push((start, &input[start..]));
+    pieces
+}
+
 fn statement(input: &str) -> IResult<&str, Statement> {
-    preceded(multispace0, alt((select_statement,)))(input)
+    preceded(
+        multispace0,
+        alt((
+            explain_statement,
+            insert_statement,
+            update_statement,
+            delete_statement,
+            set_expression,
+        )),
+    )(input)
+}
+
+fn explain_statement(input: &str) -> IResult<&str, Statement> {
+    let (input, _) = terminated(tag_no_case("explain"), multispace1)(input)?;
+    let (input, analyze) = opt(terminated(tag_no_case("analyze"), multispace1))(input)?;
+    let (input, statement) = set_expression(input)?;
+    Ok((
+        input,
+        Statement::Explain {
+            analyze: analyze.is_some(),
+            statement: Box::new(statement),
+        },
+    ))
+}
+
+// INTERSECT binds tighter than EXCEPT; both are left-associative.
+fn set_expression(input: &str) -> IResult<&str, Statement> {
+    set_operation_chain(input, SetOperator::Except, "except", intersect_expression)
+}
+
+fn intersect_expression(input: &str) -> IResult<&str, Statement> {
+    set_operation_chain(input, SetOperator::Intersect, "intersect", query_term)
+}
+
+fn set_operation_chain<'a>(
+    input: &'a str,
+    op: SetOperator,
+    keyword: &'static str,
+    operand: fn(&'a str) -> IResult<&'a str, Statement>,
+) -> IResult<&'a str, Statement> {
+    let (input, first) = operand(input)?;
+    let (input, rest) = many0(tuple((
+        preceded(
+            terminated(tag_no_case(keyword), multispace1),
+            opt(terminated(tag_no_case("all"), multispace1)),
+        ),
+        operand,
+    )))(input)?;
+
+    let statement = rest
+        .into_iter()
+        .fold(first, |left, (all, right)| Statement::SetOperation {
+            op,
+            all: all.is_some(),
+            left: Box::new(left),
+            right: Box::new(right),
+        });
+    Ok((input, statement))
+}
+
+fn query_term(input: &str) -> IResult<&str, Statement> {
+    alt((select_statement, values_statement))(input)
 }
 
 fn select_statement(input: &str) -> IResult<&str, Statement> {
     let (input, _) = tag_no_case("select")(input)?;
     let (input, _) = multispace1(input)?;
+    let (input, distinct) = opt(terminated(keyword("distinct"), multispace1))(input)?;
     let (input, select_list) = select_list(input)?;
-    let (input, from) = opt(from_clause)(input)?;
+    let (input, into) = opt(into_clause)(input)?;
+    let (input, from) = opt(select_from_clause)(input)?;
+    let (from, from_alias, table_function) = match from {
+        Some(FromItem::Table(table, alias)) => (Some(table), alias, None),
+        Some(FromItem::Function(function)) => (None, None, Some(Box::new(function))),
+        None => (None, None, None),
+    };
+    let (input, joins) = many0(join_clause)(input)?;
     let (input, where_clause) = opt(where_clause)(input)?;
+    let (input, group_by) = opt(group_by_clause)(input)?;
+    let (input, having) = opt(having_clause)(input)?;
+    let (input, order_by) = opt(order_by_clause)(input)?;
     let (input, limit) = opt(limit_clause)(input)?;
     let (input, _) = multispace0(input)?;
+    let (limit, limit_percent) = match limit {
+        Some((limit, percent)) => (Some(limit), percent),
+        None => (None, false),
+    };
 
     Ok((
         input,
         Statement::Select(SelectStatement {
+            distinct: distinct.is_some(),
             select_list,
+            into,
             from,
+            from_alias,
+            table_function,
+            joins,
             where_clause,
+            group_by: group_by.unwrap_or_default(),
+            having,
+            order_by: order_by.unwrap_or_default(),
             limit,
+            limit_percent,
         }),
     ))
 }
 
+fn values_statement(input: &str) -> IResult<&str, Statement> {
+    map(values_rows, Statement::Values)(input)
+}
+
+fn values_rows(input: &str) -> IResult<&str, Vec<Vec<Expression>>> {
+    let (input, _) = terminated(tag_no_case("values"), multispace0)(input)?;
+    let row = delimited(
+        terminated(char('('), multispace0),
+        separated_list1(delimited(multispace0, char(','), multispace0), expression),
+        preceded(multispace0, char(')')),
+    );
+    let (input, rows) =
+        separated_list1(delimited(multispace0, char(','), multispace0), row)(input)?;
+    let (input, _) = multispace0(input)?;
+    Ok((input, rows))
+}
+
+fn insert_statement(input: &str) -> IResult<&str, Statement> {
+    let (input, _) = terminated(tag_no_case("insert"), multispace1)(input)?;
+    let (input, _) = terminated(tag_no_case("into"), multispace1)(input)?;
+    let (input, table) = terminated(identifier, multispace0)(input)?;
+    let (input, columns) = opt(terminated(
+        delimited(
+            terminated(char('('), multispace0),
+            separated_list1(delimited(multispace0, char(','), multispace0), column_name),
+            preceded(multispace0, char(')')),
+        ),
+        multispace0,
+    ))(input)?;
+    let (input, rows) = values_rows(input)?;
+    Ok((
+        input,
+        Statement::Insert {
+            table,
+            columns,
+            rows,
+        },
+    ))
+}
+
+fn update_statement(input: &str) -> IResult<&str, Statement> {
+    let (input, _) = terminated(tag_no_case("update"), multispace1)(input)?;
+    let (input, table) = terminated(identifier, multispace1)(input)?;
+    let (input, _) = terminated(tag_no_case("set"), multispace1)(input)?;
+    let (input, assignments) = separated_list1(
+        delimited(multispace0, char(','), multispace0),
+        tuple((
+            terminated(column_name, delimited(multispace0, char('='), multispace0)),
+            expression,
+        )),
+    )(input)?;
+    let (input, where_clause) = opt(where_clause)(input)?;
+    let (input, _) = multispace0(input)?;
+    Ok((
+        input,
+        Statement::Update {
+            table,
+            assignments,
+            where_clause,
+        },
+    ))
+}
+
+fn delete_statement(input: &str) -> IResult<&str, Statement> {
+    let (input, _) = tag_no_case("delete")(input)?;
+    let (input, table) = from_clause(input)?;
+    let (input, where_clause) = opt(where_clause)(input)?;
+    let (input, _) = multispace0(input)?;
+    Ok((
+        input,
+        Statement::Delete {
+            table,
+            where_clause,
+        },
+    ))
+}
+
 fn select_list(input: &str) -> IResult<&str, Vec<SelectItem>> {
     separated_list1(delimited(multispace0, char(','), multispace0), select_item)(input)
 }
@@ -47,13 +325,77 @@ fn select_list(input: &str) -> IResult<&str, Vec<SelectItem>> {
 fn select_item(input: &str) -> IResult<&str, SelectItem> {
     alt((
         map(char('*'), |_| SelectItem::Wildcard),
-        map(expression, |expr| SelectItem::Expression {
-            expr,
-            alias: None,
+        map(tuple((expression, opt(alias))), |(expr, alias)| {
+            SelectItem::Expression { expr, alias }
         }),
     ))(input)
 }
 
+// Words that can follow a select item, so are not taken as a bare alias
+const RESERVED_AFTER_SELECT_ITEM: &[&str] = &[
+    "from",
+    "into",
+    "where",
+    "group",
+    "having",
+    "order",
+    "limit",
+    "intersect",
+    "except",
+];
+
+// Words that can follow a table in FROM or JOIN, so are not taken as an alias
+const RESERVED_AFTER_TABLE: &[&str] = &[
+    "inner",
+    "join",
+    "on",
+    "where",
+    "group",
+    "having",
+    "order",
+    "limit",
+    "intersect",
+    "except",
+];
+
+// `AS alias` or just `alias`. A quoted alias keeps its exact spelling.
+fn alias(input: &str) -> IResult<&str, String> {
+    alias_unless(input, RESERVED_AFTER_SELECT_ITEM)
+}
+
+fn table_alias(input: &str) -> IResult<&str, String> {
+    alias_unless(input, RESERVED_AFTER_TABLE)
+}
+
+fn alias_unless<'a>(input: &'a str, reserved: &[&str]) -> IResult<&'a str, String> {
+    let (input, _) = multispace1(input)?;
+    let (input, as_keyword) = opt(terminated(keyword("as"), multispace1))(input)?;
+    let (rest, name) = column_name(input)?;
+    if as_keyword.is_none()
+        && !name.quoted
+        && reserved
+            .iter()
+            .any(|word| name.name.eq_ignore_ascii_case(word))
+    {
+        return Err(nom::Err::Error(nom::error::Error::new(
+            input,
+            ErrorKind::Tag,
+        )));
+    }
+    Ok((rest, name.name))
+}
+
+fn into_clause(input: &str) -> IResult<&str, String> {
+    let (input, _) = preceded(multispace1, keyword("into"))(input)?;
+    let (input, _) = multispace1(input)?;
+    identifier(input)
+}
+
+enum FromItem {
+    Table(String, Option<String>),
+    Function(Expression),
+}
+
 fn from_clause(input: &str) -> IResult<&str, String> {
     let (input, _) = preceded(multispace1, tag_no_case("from"))(input)?;
     let (input, _) = multispace1(input)?;
@@ -61,20 +403,91 @@ fn from_clause(input: &str) -> IResult<&str, String> {
     Ok((input, table))
 }
 
+// SELECT also accepts a table function call where a table name would go
+fn select_from_clause(input: &str) -> IResult<&str, FromItem> {
+    let (input, _) = preceded(multispace1, tag_no_case("from"))(input)?;
+    let (input, _) = multispace1(input)?;
+    alt((
+        map(function_call, FromItem::Function),
+        map(tuple((identifier, opt(table_alias))), |(table, alias)| {
+            FromItem::Table(table, alias)
+        }),
+    ))(input)
+}
+
+fn join_clause(input: &str) -> IResult<&str, Join> {
+    let (input, _) = opt(preceded(multispace1, keyword("inner")))(input)?;
+    let (input, _) = preceded(multispace1, keyword("join"))(input)?;
+    let (input, table) = preceded(multispace1, identifier)(input)?;
+    let (input, alias) = opt(table_alias)(input)?;
+    let (input, _) = preceded(multispace1, keyword("on"))(input)?;
+    let (input, condition) = preceded(multispace1, expression)(input)?;
+    Ok((
+        input,
+        Join {
+            table,
+            alias,
+            condition,
+        },
+    ))
+}
+
 fn where_clause(input: &str) -> IResult<&str, Expression> {
     let (input, _) = preceded(multispace1, tag_no_case("where"))(input)?;
     let (input, _) = multispace1(input)?;
     expression(input)
 }
 
-fn limit_clause(input: &str) -> IResult<&str, u32> {
+fn group_by_clause(input: &str) -> IResult<&str, Vec<Expression>> {
+    let (input, _) = preceded(multispace1, tag_no_case("group"))(input)?;
+    let (input, _) = preceded(multispace1, tag_no_case("by"))(input)?;
+    let (input, _) = multispace1(input)?;
+    separated_list1(delimited(multispace0, char(','), multispace0), expression)(input)
+}
+
+fn having_clause(input: &str) -> IResult<&str, Expression> {
+    let (input, _) = preceded(multispace1, keyword("having"))(input)?;
+    let (input, _) = multispace1(input)?;
+    expression(input)
+}
+
+fn order_by_clause(input: &str) -> IResult<&str, Vec<(Expression, bool)>> {
+    let (input, _) = preceded(multispace1, tag_no_case("order"))(input)?;
+    let (input, _) = preceded(multispace1, tag_no_case("by"))(input)?;
+    let (input, _) = multispace1(input)?;
+    separated_list1(
+        delimited(multispace0, char(','), multispace0),
+        tuple((
+            expression,
+            map(
+                opt(preceded(
+                    multispace1,
+                    alt((keyword("asc"), keyword("desc"))),
+                )),
+                |direction| direction.is_some_and(|d: &str| d.eq_ignore_ascii_case("desc")),
+            ),
+        )),
+    )(input)
+}
+
+fn limit_clause(input: &str) -> IResult<&str, (u32, bool)> {
     let (input, _) = preceded(multispace1, tag_no_case("limit"))(input)?;
     let (input, _) = multispace1(input)?;
     let (input, num) = digit1(input)?;
-    let limit = num.parse().map_err(|_| {
-        nom::Err::Error(nom::error::Error::new(input, nom::error::ErrorKind::Digit))
-    })?;
-    Ok((input, limit))
+    let limit = num
+        .parse()
+        .map_err(|_| nom::Err::Error(nom::error::Error::new(input, ErrorKind::Digit)))?;
+    let (input, percent) = opt(preceded(multispace1, tag_no_case("percent")))(input)?;
+    Ok((input, (limit, percent.is_some())))
+}
+
+// Matches `word` only as a whole word, so "or" does not match the start of
+// "order".
+fn keyword<'a>(word: &'static str) -> impl FnMut(&'a str) -> IResult<&'a str, &'a str> {
+    terminated(
+        tag_no_case(word),
+        not(take_while1(|c: char| c.is_alphanumeric() || c == '_')),
+    )
 }
 
 fn expression(input: &str) -> IResult<&str, Expression> {
@@ -84,7 +497,7 @@ fn expression(input: &str) -> IResult<&str, Expression> {
 fn or_expression(input: &str) -> IResult<&str, Expression> {
     let (input, left) = and_expression(input)?;
     let (input, rights) = nom::multi::many0(tuple((
-        preceded(multispace0, tag_no_case("or")),
+        preceded(multispace0, keyword("or")),
         preceded(multispace0, and_expression),
     )))(input)?;
 
@@ -101,10 +514,10 @@ fn or_expression(input: &str) -> IResult<&str, Expression> {
 }
 
 fn and_expression(input: &str) -> IResult<&str, Expression> {
-    let (input, left) = equality_expression(input)?;
+    let (input, left) = not_expression(input)?;
     let (input, rights) = nom::multi::many0(tuple((
-        preceded(multispace0, tag_no_case("and")),
-        preceded(multispace0, equality_expression),
+        preceded(multispace0, keyword("and")),
+        preceded(multispace0, not_expression),
     )))(input)?;
 
     Ok((
@@ -119,22 +532,60 @@ fn and_expression(input: &str) -> IResult<&str, Expression> {
     ))
 }
 
+// NOT binds tighter than AND but looser than comparisons, so
+// `NOT a = b AND c` is `(NOT (a = b)) AND c`.
+fn not_expression(input: &str) -> IResult<&str, Expression> {
+    alt((
+        map(
+            preceded(terminated(keyword("not"), multispace0), not_expression),
+            |expr| Expression::Not(Box::new(expr)),
+        ),
+        equality_expression,
+    ))(input)
+}
+
 fn equality_expression(input: &str) -> IResult<&str, Expression> {
     let (input, left) = additive_expression(input)?;
-    let (input, op_right) = opt(tuple((
-        preceded(
-            multispace0,
-            alt((
-                map(tag(">="), |_| BinaryOperator::Ge),
-                map(tag("<="), |_| BinaryOperator::Le),
-                map(tag("<>"), |_| BinaryOperator::Ne),
-                map(tag("!="), |_| BinaryOperator::Ne),
-                map(tag("="), |_| BinaryOperator::Eq),
-                map(tag("<"), |_| BinaryOperator::Lt),
-                map(tag(">"), |_| BinaryOperator::Gt),
-            )),
-        ),
-        preceded(multispace0, additive_expression),
+    if let Ok((input, (negated, list))) = in_list(input) {
+        return Ok((
+            input,
+            Expression::InList {
+                expr: Box::new(left),
+                list,
+                negated,
+            },
+        ));
+    }
+    if let Ok((input, (negated, symmetric, low, high))) = between_range(input) {
+        return Ok((
+            input,
+            Expression::Between {
+                expr: Box::new(left),
+                low: Box::new(low),
+                high: Box::new(high),
+                negated,
+                symmetric,
+            },
+        ));
+    }
+    let (input, op_right) = opt(alt((
+        tuple((
+            preceded(
+                multispace0,
+                alt((
+                    map(tag(">="), |_| BinaryOperator::Ge),
+                    map(tag("<="), |_| BinaryOperator::Le),
+                    map(tag("<>"), |_| BinaryOperator::Ne),
+                    map(tag("!="), |_| BinaryOperator::Ne),
+                    map(tag("="), |_| BinaryOperator::Eq),
+                    map(tag("<"), |_| BinaryOperator::Lt),
+                    map(tag(">"), |_| BinaryOperator::Gt),
+                    map(keyword("like"), |_| BinaryOperator::Like),
+                )),
+            ),
+            preceded(multispace0, additive_expression),
+        )),
+        is_null_test,
     )))(input)?;
 
     match op_right {
@@ -150,6 +601,45 @@ fn equality_expression(input: &str) -> IResult<&str, Expression> {
     }
 }
 
+// The `[NOT] IN (expr, ...)` suffix of a comparison
+fn in_list(input: &str) -> IResult<&str, (bool, Vec<Expression>)> {
+    let (input, negated) = opt(preceded(multispace1, keyword("not")))(input)?;
+    let (input, _) = preceded(multispace0, keyword("in"))(input)?;
+    let (input, list) = preceded(
+        multispace0,
+        delimited(
+            terminated(char('('), multispace0),
+            separated_list1(delimited(multispace0, char(','), multispace0), expression),
+            preceded(multispace0, char(')')),
+        ),
+    )(input)?;
+    Ok((input, (negated.is_some(), list)))
+}
+
+// The `[NOT] BETWEEN low AND high` suffix of a comparison. The bounds are
+// additive expressions, so the AND here never starts a conjunction.
+fn between_range(input: &str) -> IResult<&str, (bool, bool, Expression, Expression)> {
+    let (input, negated) = opt(preceded(multispace1, keyword("not")))(input)?;
+    let (input, _) = preceded(multispace0, keyword("between"))(input)?;
+    let (input, symmetric) = opt(preceded(multispace1, keyword("symmetric")))(input)?;
+    let (input, low) = preceded(multispace0, additive_expression)(input)?;
+    let (input, _) = preceded(multispace0, keyword("and"))(input)?;
+    let (input, high) = preceded(multispace0, additive_expression)(input)?;
+    Ok((input, (negated.is_some(), symmetric.is_some(), low, high)))
+}
+
+// The `IS [NOT] NULL` suffix of a comparison
+fn is_null_test(input: &str) -> IResult<&str, (BinaryOperator, Expression)> {
+    let (input, _) = preceded(multispace0, keyword("is"))(input)?;
+    let (input, negated) = opt(preceded(multispace1, keyword("not")))(input)?;
+    let (input, null) = preceded(multispace1, null_literal)(input)?;
+    let op = match negated {
+        Some(_) => BinaryOperator::IsNot,
+        None => BinaryOperator::Is,
+    };
+    Ok((input, (op, null)))
+}
+
 fn additive_expression(input: &str) -> IResult<&str, Expression> {
     let (input, left) = multiplicative_expression(input)?;
     let (input, rights) = nom::multi::many0(tuple((
@@ -183,6 +673,7 @@ fn multiplicative_expression(input: &str) -> IResult<&str, Expression> {
             alt((
                 map(char('*'), |_| BinaryOperator::Mul),
                 map(char('/'), |_| BinaryOperator::Div),
+                map(char('%'), |_| BinaryOperator::Mod),
             )),
         ),
         preceded(multispace0, primary_expression),
@@ -203,6 +694,12 @@ fn multiplicative_expression(input: &str) -> IResult<&str, Expression> {
 fn primary_expression(input: &str) -> IResult<&str, Expression> {
     alt((
         literal_expression,
+        parameter,
+        trim_expression,
+        substring_expression,
+        row_number_expression,
+        aggregate_call,
+        function_call,
         column_expression,
         delimited(
             char('('),
@@ -212,8 +709,34 @@ fn primary_expression(input: &str) -> IResult<&str, Expression> {
     ))(input)
 }
 
+fn parameter(input: &str) -> IResult<&str, Expression> {
+    let (rest, digits) = preceded(char('$'), digit1)(input)?;
+    match digits.parse::<usize>() {
+        Ok(index) if index > 0 => Ok((rest, Expression::Parameter { index })),
+        _ => Err(nom::Err::Error(nom::error::Error::new(
+            input,
+            ErrorKind::Digit,
+        ))),
+    }
+}
+
 fn literal_expression(input: &str) -> IResult<&str, Expression> {
-    alt((integer_literal, string_literal, boolean_literal))(input)
+    alt((
+        integer_literal,
+        string_literal,
+        boolean_literal,
+        null_literal,
+    ))(input)
+}
+
+fn null_literal(input: &str) -> IResult<&str, Expression> {
+    map(
+        terminated(
+            tag_no_case("null"),
+            not(take_while1(|c: char| c.is_alphanumeric() || c == '_')),
+        ),
+        |_| Expression::Literal { value: Value::Null },
+    )(input)
 }
 
 fn integer_literal(input: &str) -> IResult<&str, Expression> {
@@ -224,9 +747,9 @@ fn integer_literal(input: &str) -> IResult<&str, Expression> {
     } else {
         digits.to_string()
     };
-    let value = num_str.parse::<i32>().map_err(|_| {
-        nom::Err::Error(nom::error::Error::new(input, nom::error::ErrorKind::Digit))
-    })?;
+    let value = num_str
+        .parse::<i32>()
+        .map_err(|_| nom::Err::Error(nom::error::Error::new(input, ErrorKind::Digit)))?;
     Ok((
         input,
         Expression::Literal {
@@ -235,16 +758,51 @@ fn integer_literal(input: &str) -> IResult<&str, Expression> {
     ))
 }
 
+// A doubled quote ('') stands for a literal quote. Backslash escapes (\n, \t,
+// \r, \\, \') are only recognised in PostgreSQL-style escape strings written
+// with an E prefix, e.g. E'line1\nline2'; in plain strings a backslash is
+// an ordinary character.
 fn string_literal(input: &str) -> IResult<&str, Expression> {
+    let (input, escape_string) = opt(tag_no_case("e"))(input)?;
     let (input, _) = char('\'')(input)?;
-    let (input, content) = take_while1(|c| c != '\'')(input)?;
-    let (input, _) = char('\'')(input)?;
-    Ok((
+
+    let mut content = String::new();
+    let mut chars = input.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\'' if chars.next_if(|&(_, next)| next == '\'').is_some() => content.push('\''),
+            '\'' => {
+                return Ok((
+                    &input[i + 1..],
+                    Expression::Literal {
+                        value: Value::Varchar(content),
+                    },
+                ));
+            }
+            '\\' if escape_string.is_some() => {
+                let escaped = match chars.next() {
+                    Some((_, 'n')) => '\n',
+                    Some((_, 't')) => '\t',
+                    Some((_, 'r')) => '\r',
+                    Some((_, '\\')) => '\\',
+                    Some((_, '\'')) => '\'',
+                    _ => {
+                        return Err(nom::Err::Failure(nom::error::Error::new(
+                            &input[i..],
+                            ErrorKind::Escaped,
+                        )));
+                    }
+                };
+                content.push(escaped);
+            }
+            c => content.push(c),
+        }
+    }
+
+    Err(nom::Err::Error(nom::error::Error::new(
         input,
-        Expression::Literal {
-            value: Value::Varchar(content.to_string()),
-        },
-    ))
+        ErrorKind::Char,
+    )))
 }
 
 fn boolean_literal(input: &str) -> IResult<&str, Expression> {
@@ -258,9 +816,169 @@ fn boolean_literal(input: &str) -> IResult<&str, Expression> {
     ))(input)
 }
 
-fn column_expression(input: &str) -> IResult<&str, Expression> {
+// The standard TRIM([BOTH | LEADING | TRAILING] [characters] FROM string)
+// form, rewritten into the equivalent TRIM/LTRIM/RTRIM call.
+fn trim_expression(input: &str) -> IResult<&str, Expression> {
+    let (input, _) = tag_no_case("trim")(input)?;
+    let (input, _) = preceded(multispace0, char('('))(input)?;
+    let (input, name) = opt(terminated(
+        preceded(
+            multispace0,
+            alt((
+                map(tag_no_case("both"), |_| "TRIM"),
+                map(tag_no_case("leading"), |_| "LTRIM"),
+                map(tag_no_case("trailing"), |_| "RTRIM"),
+            )),
+        ),
+        multispace1,
+    ))(input)?;
+    let from = |input| delimited(multispace0, tag_no_case("from"), multispace1)(input);
+    let (input, characters) = alt((
+        map(from, |_| None),
+        map(terminated(preceded(multispace0, expression), from), Some),
+    ))(input)?;
+    let (input, string) = expression(input)?;
+    let (input, _) = preceded(multispace0, char(')'))(input)?;
+
+    let mut args = vec![string];
+    args.extend(characters);
+    Ok((
+        input,
+        Expression::Function {
+            name: name.unwrap_or("TRIM").to_string(),
+            args,
+        },
+    ))
+}
+
+// The standard SUBSTRING(string [FROM start] [FOR length]) form, rewritten
+// into the equivalent SUBSTRING(string, start[, length]) call.
+fn substring_expression(input: &str) -> IResult<&str, Expression> {
+    let (input, _) = tag_no_case("substring")(input)?;
+    let (input, _) = preceded(multispace0, char('('))(input)?;
+    let (input, string) = preceded(multispace0, expression)(input)?;
+    let (input, start) = opt(preceded(
+        delimited(multispace1, tag_no_case("from"), multispace1),
+        expression,
+    ))(input)?;
+    let (input, length) = opt(preceded(
+        delimited(multispace1, tag_no_case("for"), multispace1),
+        expression,
+    ))(input)?;
+    if start.is_none() && length.is_none() {
+        // Plain SUBSTRING(s, start, length) is handled as an ordinary call.
+        return Err(nom::Err::Error(nom::error::Error::new(
+            input,
+            nom::error::ErrorKind::Tag,
+        )));
+    }
+    let (input, _) = preceded(multispace0, char(')'))(input)?;
+
+    let mut args = vec![string, start.unwrap_or_else(|| Expression::integer(1))];
+    args.extend(length);
+    Ok((
+        input,
+        Expression::Function {
+            name: "SUBSTRING".to_string(),
+            args,
+        },
+    ))
+}
+
+// ROW_NUMBER() with an optional empty OVER (); partitions and window
+// ordering are not supported.
+fn row_number_expression(input: &str) -> IResult<&str, Expression> {
+    let (input, _) = tag_no_case("row_number")(input)?;
+    let (input, _) = tuple((multispace0, char('('), multispace0, char(')')))(input)?;
+    let (input, _) = opt(tuple((
+        multispace1,
+        tag_no_case("over"),
+        multispace0,
+        char('('),
+        multispace0,
+        char(')'),
+    )))(input)?;
+    Ok((input, Expression::function("ROW_NUMBER", vec![])))
+}
+
+// COUNT(*) or COUNT/SUM/AVG/MIN/MAX of a single expression
+fn aggregate_call(input: &str) -> IResult<&str, Expression> {
+    let (rest, func) = alt((
+        map(keyword("count"), |_| AggregateFunction::Count),
+        map(keyword("sum"), |_| AggregateFunction::Sum),
+        map(keyword("avg"), |_| AggregateFunction::Avg),
+        map(keyword("min"), |_| AggregateFunction::Min),
+        map(keyword("max"), |_| AggregateFunction::Max),
+    ))(input)?;
+    let (rest, _) = preceded(multispace0, char('('))(rest)?;
+    let (rest, arg) = delimited(
+        multispace0,
+        alt((
+            map(char('*'), |_| None),
+            map(expression, |arg| Some(Box::new(arg))),
+        )),
+        multispace0,
+    )(rest)?;
+    let (rest, _) = char(')')(rest)?;
+    if arg.is_none() && func != AggregateFunction::Count {
+        return Err(nom::Err::Error(nom::error::Error::new(
+            input,
+            ErrorKind::Tag,
+        )));
+    }
+    Ok((rest, Expression::Aggregate { func, arg }))
+}
+
+fn function_call(input: &str) -> IResult<&str, Expression> {
     let (input, name) = identifier(input)?;
-    Ok((input, Expression::Column { name }))
+    let (input, _) = preceded(multispace0, char('('))(input)?;
+    let (input, args) = preceded(
+        multispace0,
+        separated_list0(delimited(multispace0, char(','), multispace0), expression),
+    )(input)?;
+    let (input, _) = preceded(multispace0, char(')'))(input)?;
+    Ok((input, Expression::Function { name, args }))
+}
+
+fn column_expression(input: &str) -> IResult<&str, Expression> {
+    alt((
+        map(quoted_identifier, |name| Expression::Column {
+            name,
+            quoted: true,
+        }),
+        map(qualified_identifier, |name| Expression::Column {
+            name,
+            quoted: false,
+        }),
+    ))(input)
+}
+
+fn column_name(input: &str) -> IResult<&str, ColumnName> {
+    alt((
+        map(quoted_identifier, |name| ColumnName { name, quoted: true }),
+        map(identifier, |name| ColumnName {
+            name,
+            quoted: false,
+        }),
+    ))(input)
+}
+
+// A column name, optionally qualified by its table: id or users.id
+fn qualified_identifier(input: &str) -> IResult<&str, String> {
+    let (input, name) = recognize(tuple((identifier, opt(tuple((char('.'), identifier))))))(input)?;
+    Ok((input, name.to_string()))
+}
+
+// A double-quoted identifier, without its quotes and with doubled quotes
+// inside undoubled. Callers record that it was quoted so the planner can
+// resolve it case-sensitively.
+fn quoted_identifier(input: &str) -> IResult<&str, String> {
+    let (input, name) = delimited(
+        char('"'),
+        recognize(many0(alt((is_not("\""), tag("\"\""))))),
+        char('"'),
+    )(input)?;
+    Ok((input, name.replace("\"\"", "\"")))
 }
 
 fn identifier(input: &str) -> IResult<&str, String> {
@@ -281,7 +999,9 @@ mod tests {
         let sql = "SELECT * FROM users";
         let stmt = parse_sql(sql).unwrap();
 
-        let Statement::Select(select) = stmt;
+        let Statement::Select(select) = stmt else {
+            panic!("Expected SELECT");
+        };
         assert_eq!(select.select_list, vec![SelectItem::Wildcard]);
         assert_eq!(select.from, Some("users".to_string()));
         assert!(select.where_clause.is_none());
@@ -293,7 +1013,9 @@ mod tests {
         let sql = "SELECT id, name FROM users";
         let stmt = parse_sql(sql).unwrap();
 
-        let Statement::Select(select) = stmt;
+        let Statement::Select(select) = stmt else {
+            panic!("Expected SELECT");
+        };
         assert_eq!(select.select_list.len(), 2);
         assert_eq!(select.from, Some("users".to_string()));
     }
@@ -303,7 +1025,9 @@ mod tests {
         let sql = "SELECT * FROM users WHERE id = 42";
         let stmt = parse_sql(sql).unwrap();
 
-        let Statement::Select(select) = stmt;
+        let Statement::Select(select) = stmt else {
+            panic!("Expected SELECT");
+        };
         assert!(select.where_clause.is_some());
     }
 
@@ -312,8 +1036,11 @@ mod tests {
         let sql = "SELECT * FROM users LIMIT 10";
         let stmt = parse_sql(sql).unwrap();
 
-        let Statement::Select(select) = stmt;
+        let Statement::Select(select) = stmt else {
+            panic!("Expected SELECT");
+        };
         assert_eq!(select.limit, Some(10));
+        assert!(!select.limit_percent);
     }
 
     #[test]
@@ -321,17 +1048,418 @@ mod tests {
         let sql = "SELECT 42 + 3 * 5";
         let stmt = parse_sql(sql).unwrap();
 
-        let Statement::Select(select) = stmt;
+        let Statement::Select(select) = stmt else {
+            panic!("Expected SELECT");
+        };
         assert_eq!(select.select_list.len(), 1);
         assert!(select.from.is_none());
     }
 
+    #[test]
+    fn test_trim_syntax() -> anyhow::Result<()> {
+        let parse_expr = |sql: &str| -> anyhow::Result<Expression> {
+            let Statement::Select(select) = parse_sql(sql)? else {
+                panic!("Expected SELECT");
+            };
+            match select.select_list.into_iter().next() {
+                Some(SelectItem::Expression { expr, .. }) => Ok(expr),
+                other => panic!("Expected expression item, got {:?}", other),
+            }
+        };
+        let name = || Expression::column("name");
+
+        assert_eq!(
+            parse_expr("SELECT TRIM(name) FROM users")?,
+            Expression::function("TRIM", vec![name()])
+        );
+        assert_eq!(
+            parse_expr("SELECT trim(BOTH 'x' FROM name) FROM users")?,
+            Expression::function("TRIM", vec![name(), Expression::string("x")])
+        );
+        assert_eq!(
+            parse_expr("SELECT TRIM(leading FROM name) FROM users")?,
+            Expression::function("LTRIM", vec![name()])
+        );
+        assert_eq!(
+            parse_expr("SELECT TRIM(TRAILING '-' FROM name) FROM users")?,
+            Expression::function("RTRIM", vec![name(), Expression::string("-")])
+        );
+        assert_eq!(
+            parse_expr("SELECT TRIM('ab' FROM name) FROM users")?,
+            Expression::function("TRIM", vec![name(), Expression::string("ab")])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_substring_syntax() -> anyhow::Result<()> {
+        let parse_expr = |sql: &str| -> anyhow::Result<Expression> {
+            let Statement::Select(select) = parse_sql(sql)? else {
+                panic!("Expected SELECT");
+            };
+            match select.select_list.into_iter().next() {
+                Some(SelectItem::Expression { expr, .. }) => Ok(expr),
+                other => panic!("Expected expression item, got {:?}", other),
+            }
+        };
+        let name = || Expression::column("name");
+
+        assert_eq!(
+            parse_expr("SELECT SUBSTRING(name FROM 2 FOR 3) FROM users")?,
+            Expression::function(
+                "SUBSTRING",
+                vec![name(), Expression::integer(2), Expression::integer(3)]
+            )
+        );
+        assert_eq!(
+            parse_expr("SELECT substring(name from id + 1) FROM users")?,
+            Expression::function(
+                "SUBSTRING",
+                vec![
+                    name(),
+                    Expression::add(Expression::column("id"), Expression::integer(1))
+                ]
+            )
+        );
+        assert_eq!(
+            parse_expr("SELECT SUBSTRING(name FOR 2) FROM users")?,
+            Expression::function(
+                "SUBSTRING",
+                vec![name(), Expression::integer(1), Expression::integer(2)]
+            )
+        );
+        assert_eq!(
+            parse_expr("SELECT SUBSTR(name, 2, 3) FROM users")?,
+            Expression::function(
+                "SUBSTR",
+                vec![name(), Expression::integer(2), Expression::integer(3)]
+            )
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_limit_percent() {
+        let Statement::Select(select) = parse_sql("SELECT * FROM users LIMIT 50 percent").unwrap()
+        else {
+            panic!("Expected SELECT");
+        };
+        assert_eq!(select.limit, Some(50));
+        assert!(select.limit_percent);
+    }
+
+    #[test]
+    fn test_quoted_identifier() {
+        let Statement::Select(select) =
+            parse_sql("SELECT \"Name\", \"a\"\"b\" FROM users").unwrap()
+        else {
+            panic!("Expected SELECT");
+        };
+        assert_eq!(
+            select.select_list,
+            vec![
+                SelectItem::Expression {
+                    expr: Expression::quoted_column("Name"),
+                    alias: None,
+                },
+                SelectItem::Expression {
+                    expr: Expression::quoted_column("a\"b"),
+                    alias: None,
+                },
+            ]
+        );
+        assert_eq!(Expression::quoted_column("a\"b").to_string(), "\"a\"\"b\"");
+
+        // A quoted word is an alias even where the bare word is a keyword
+        let Statement::Select(select) = parse_sql("SELECT id \"from\" FROM users").unwrap() else {
+            panic!("Expected SELECT");
+        };
+        assert_eq!(
+            select.select_list,
+            vec![SelectItem::Expression {
+                expr: Expression::column("id"),
+                alias: Some("from".to_string()),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_row_number() {
+        for sql in [
+            "SELECT ROW_NUMBER(), name FROM users",
+            "SELECT row_number() OVER () , name FROM users",
+            "SELECT ROW_NUMBER ( ) over( ), name FROM users",
+        ] {
+            let Statement::Select(select) = parse_sql(sql).unwrap() else {
+                panic!("Expected SELECT");
+            };
+            assert_eq!(
+                select.select_list[0],
+                SelectItem::Expression {
+                    expr: Expression::function("ROW_NUMBER", vec![]),
+                    alias: None,
+                }
+            );
+            assert_eq!(select.select_list.len(), 2);
+        }
+    }
+
+    #[test]
+    fn test_explain() {
+        for (sql, expected) in [
+            ("EXPLAIN SELECT * FROM users", false),
+            ("explain analyze SELECT * FROM users", true),
+        ] {
+            let Statement::Explain { analyze, statement } = parse_sql(sql).unwrap() else {
+                panic!("Expected EXPLAIN");
+            };
+            assert_eq!(analyze, expected);
+            assert_eq!(
+                *statement,
+                Statement::Select(SelectStatement::select_all_from("users"))
+            );
+        }
+    }
+
+    #[test]
+    fn test_values() {
+        let stmt = parse_sql("VALUES (1, 'a'), ( -2 , NULL )").unwrap();
+        assert_eq!(
+            stmt,
+            Statement::Values(vec![
+                vec![Expression::integer(1), Expression::string("a")],
+                vec![
+                    Expression::integer(-2),
+                    Expression::Literal { value: Value::Null }
+                ],
+            ])
+        );
+        assert!(parse_sql("VALUES ()").is_err());
+    }
+
+    #[test]
+    fn test_insert() {
+        let stmt = parse_sql("INSERT INTO users (id, \"Name\") VALUES (1, 'a'), (2, 'b')").unwrap();
+        assert_eq!(
+            stmt,
+            Statement::Insert {
+                table: "users".to_string(),
+                columns: Some(vec![
+                    ColumnName::new("id"),
+                    ColumnName {
+                        name: "Name".to_string(),
+                        quoted: true,
+                    },
+                ]),
+                rows: vec![
+                    vec![Expression::integer(1), Expression::string("a")],
+                    vec![Expression::integer(2), Expression::string("b")],
+                ],
+            }
+        );
+
+        let stmt = parse_sql("insert into users values (1 + 2, 'c')").unwrap();
+        let Statement::Insert { columns, rows, .. } = stmt else {
+            panic!("Expected INSERT");
+        };
+        assert_eq!(columns, None);
+        assert_eq!(rows.len(), 1);
+        assert!(parse_sql("INSERT INTO users").is_err());
+        assert!(parse_sql("INSERT INTO users () VALUES (1)").is_err());
+    }
+
+    #[test]
+    fn test_update() {
+        assert_eq!(
+            parse_sql("UPDATE users SET name = 'x', id=id + 1 WHERE id = 3").unwrap(),
+            Statement::Update {
+                table: "users".to_string(),
+                assignments: vec![
+                    (ColumnName::new("name"), Expression::string("x")),
+                    (
+                        ColumnName::new("id"),
+                        Expression::add(Expression::column("id"), Expression::integer(1)),
+                    ),
+                ],
+                where_clause: Some(Expression::eq(
+                    Expression::column("id"),
+                    Expression::integer(3),
+                )),
+            }
+        );
+        let Statement::Update { where_clause, .. } = parse_sql("update users set id = 0").unwrap()
+        else {
+            panic!("Expected UPDATE");
+        };
+        assert_eq!(where_clause, None);
+        assert!(parse_sql("UPDATE users SET WHERE id = 1").is_err());
+        assert!(parse_sql("UPDATE users SET name = 'zz' WHRE id = 1").is_err());
+    }
+
+    #[test]
+    fn test_delete() {
+        assert_eq!(
+            parse_sql("DELETE FROM users WHERE id = 3").unwrap(),
+            Statement::Delete {
+                table: "users".to_string(),
+                where_clause: Some(Expression::eq(
+                    Expression::column("id"),
+                    Expression::integer(3),
+                )),
+            }
+        );
+        assert_eq!(
+            parse_sql("delete from users").unwrap(),
+            Statement::Delete {
+                table: "users".to_string(),
+                where_clause: None,
+            }
+        );
+        assert!(parse_sql("DELETE users").is_err());
+        assert!(parse_sql("DELETE FROM users WHRE id = 1").is_err());
+        assert!(parse_sql("DELETE FROM users WHERE id = 1;").is_ok());
+        assert!(parse_sql("DELETE FROM users; DELETE FROM users").is_err());
+    }
+
+    #[test]
+    fn test_order_by() {
+        let Statement::Select(select) = parse_sql(
+            "SELECT * FROM users WHERE id > 1 ORDER BY id ASC, name desc, id + 1 LIMIT 3",
+        )
+        .unwrap() else {
+            panic!("Expected SELECT");
+        };
+        assert_eq!(
+            select.order_by,
+            vec![
+                (Expression::column("id"), false),
+                (Expression::column("name"), true),
+                (
+                    Expression::add(Expression::column("id"), Expression::integer(1)),
+                    false
+                ),
+            ]
+        );
+        assert_eq!(select.limit, Some(3));
+
+        let Statement::Select(select) = parse_sql("SELECT * FROM users").unwrap() else {
+            panic!("Expected SELECT");
+        };
+        assert!(select.order_by.is_empty());
+    }
+
+    #[test]
+    fn test_set_operations() {
+        let table =
+            |name: &str| Box::new(Statement::Select(SelectStatement::select_all_from(name)));
+        let stmt =
+            parse_sql("SELECT * FROM a EXCEPT ALL SELECT * FROM b intersect SELECT * FROM c")
+                .unwrap();
+        assert_eq!(
+            stmt,
+            Statement::SetOperation {
+                op: SetOperator::Except,
+                all: true,
+                left: table("a"),
+                right: Box::new(Statement::SetOperation {
+                    op: SetOperator::Intersect,
+                    all: false,
+                    left: table("b"),
+                    right: table("c"),
+                }),
+            }
+        );
+
+        let Statement::SetOperation { op, left, .. } =
+            parse_sql("VALUES (1) EXCEPT VALUES (2) EXCEPT VALUES (3)").unwrap()
+        else {
+            panic!("Expected set operation");
+        };
+        assert_eq!(op, SetOperator::Except);
+        assert!(matches!(*left, Statement::SetOperation { .. }));
+    }
+
+    #[test]
+    fn test_parse_script_collect() {
+        let script = "SELECT * FROM users;\n SELEC * FROM users;\n\
+                      SELECT 'a;b', \"x;y\", E'\\';' FROM users;\n";
+        let (statements, errors) = parse_script_collect(script);
+
+        assert_eq!(statements.len(), 2);
+        assert_eq!(
+            statements[0],
+            Statement::Select(SelectStatement::select_all_from("users"))
+        );
+        let Statement::Select(select) = &statements[1] else {
+            panic!("Expected SELECT");
+        };
+        assert_eq!(
+            select.select_list[0],
+            SelectItem::Expression {
+                expr: Expression::string("a;b"),
+                alias: None,
+            }
+        );
+        assert_eq!(
+            select.select_list[2],
+            SelectItem::Expression {
+                expr: Expression::string("';"),
+                alias: None,
+            }
+        );
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].index, 1);
+        assert_eq!(errors[0].offset, script.find("SELEC ").unwrap());
+        assert!(
+            parse_script_collect("SELECT 1 AS one garbage").1[0]
+                .message
+                .contains("garbage")
+        );
+    }
+
+    #[test]
+    fn test_is_complete_script() {
+        assert!(is_complete_script("SELECT * FROM users;"));
+        assert!(is_complete_script("INSERT INTO users\nVALUES (1, 'a');\n"));
+        assert!(is_complete_script("SELECT 1; SELECT 2;"));
+        assert!(!is_complete_script("INSERT INTO users"));
+        assert!(!is_complete_script("INSERT INTO users VALUES (1, 'a;"));
+        assert!(!is_complete_script("SELECT 1; SELECT 2"));
+        assert!(!is_complete_script(" ; "));
+        assert!(!is_complete_script(""));
+    }
+
+    #[test]
+    fn test_modulo_operator() {
+        let Statement::Select(select) = parse_sql("SELECT id % 3 * 2 FROM users").unwrap() else {
+            panic!("Expected SELECT");
+        };
+        assert_eq!(
+            select.select_list,
+            vec![SelectItem::Expression {
+                expr: Expression::BinaryOp {
+                    left: Box::new(Expression::BinaryOp {
+                        left: Box::new(Expression::column("id")),
+                        op: BinaryOperator::Mod,
+                        right: Box::new(Expression::integer(3)),
+                    }),
+                    op: BinaryOperator::Mul,
+                    right: Box::new(Expression::integer(2)),
+                },
+                alias: None,
+            }]
+        );
+    }
+
     #[test]
     fn test_string_literal() {
         let sql = "SELECT 'hello world'";
         let stmt = parse_sql(sql).unwrap();
 
-        let Statement::Select(select) = stmt;
+        let Statement::Select(select) = stmt else {
+            panic!("Expected SELECT");
+        };
         if let SelectItem::Expression { expr, .. } = &select.select_list[0] {
             if let Expression::Literal { value } = expr {
                 assert_eq!(*value, Value::Varchar("hello world".to_string()));
@@ -343,12 +1471,584 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_function_call() {
+        let sql = "SELECT GREATEST(id, 10), least( 'a' , name ) FROM users";
+        let stmt = parse_sql(sql).unwrap();
+
+        let Statement::Select(select) = stmt else {
+            panic!("Expected SELECT");
+        };
+        assert_eq!(
+            select.select_list,
+            vec![
+                SelectItem::Expression {
+                    expr: Expression::function(
+                        "GREATEST",
+                        vec![Expression::column("id"), Expression::integer(10)]
+                    ),
+                    alias: None,
+                },
+                SelectItem::Expression {
+                    expr: Expression::function(
+                        "least",
+                        vec![Expression::string("a"), Expression::column("name")]
+                    ),
+                    alias: None,
+                },
+            ]
+        );
+    }
+
+    fn parse_string_literal(sql: &str) -> anyhow::Result<Value> {
+        let Statement::Select(select) = parse_sql(sql)? else {
+            panic!("Expected SELECT");
+        };
+        match &select.select_list[0] {
+            SelectItem::Expression {
+                expr: Expression::Literal { value },
+                ..
+            } => Ok(value.clone()),
+            other => panic!("Expected literal expression, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_string_literal_quotes() -> anyhow::Result<()> {
+        assert_eq!(
+            parse_string_literal("SELECT 'it''s'")?,
+            Value::Varchar("it's".to_string())
+        );
+        assert_eq!(
+            parse_string_literal("SELECT ''")?,
+            Value::Varchar(String::new())
+        );
+        assert_eq!(
+            parse_string_literal(r"SELECT 'a\nb'")?,
+            Value::Varchar(r"a\nb".to_string())
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_escape_string_literal() -> anyhow::Result<()> {
+        assert_eq!(
+            parse_string_literal(r"SELECT E'line1\nline2'")?,
+            Value::Varchar("line1\nline2".to_string())
+        );
+        assert_eq!(
+            parse_string_literal(r"SELECT e'\t'")?,
+            Value::Varchar("\t".to_string())
+        );
+        assert_eq!(
+            parse_string_literal(r"SELECT E'\\ and \''")?,
+            Value::Varchar(r"\ and '".to_string())
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_escape_sequence() {
+        let err = parse_sql(r"SELECT E'bad\q'").unwrap_err();
+        assert!(
+            err.to_string().contains(r"invalid escape sequence '\q'"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_null_literal() -> anyhow::Result<()> {
+        assert_eq!(parse_string_literal("SELECT NULL")?, Value::Null);
+        assert_eq!(parse_string_literal("SELECT null")?, Value::Null);
+
+        let Statement::Select(select) = parse_sql("SELECT nullable FROM t")? else {
+            panic!("Expected SELECT");
+        };
+        assert_eq!(
+            select.select_list,
+            vec![SelectItem::Expression {
+                expr: Expression::column("nullable"),
+                alias: None,
+            }]
+        );
+        Ok(())
+    }
+
     #[test]
     fn test_boolean_literals() {
         let sql = "SELECT true, false";
         let stmt = parse_sql(sql).unwrap();
 
-        let Statement::Select(select) = stmt;
+        let Statement::Select(select) = stmt else {
+            panic!("Expected SELECT");
+        };
+        assert_eq!(select.select_list.len(), 2);
+    }
+
+    #[test]
+    fn test_is_null() -> anyhow::Result<()> {
+        let where_clause = |sql: &str| -> anyhow::Result<Option<Expression>> {
+            let Statement::Select(select) = parse_sql(sql)? else {
+                panic!("Expected SELECT");
+            };
+            Ok(select.where_clause)
+        };
+        let null = Expression::Literal { value: Value::Null };
+
+        assert_eq!(
+            where_clause("SELECT * FROM users WHERE name IS NULL")?,
+            Some(Expression::BinaryOp {
+                left: Box::new(Expression::column("name")),
+                op: BinaryOperator::Is,
+                right: Box::new(null.clone()),
+            })
+        );
+        assert_eq!(
+            where_clause("SELECT * FROM users WHERE name is  not\tnull AND id > 1")?,
+            Some(Expression::BinaryOp {
+                left: Box::new(Expression::BinaryOp {
+                    left: Box::new(Expression::column("name")),
+                    op: BinaryOperator::IsNot,
+                    right: Box::new(null),
+                }),
+                op: BinaryOperator::And,
+                right: Box::new(Expression::BinaryOp {
+                    left: Box::new(Expression::column("id")),
+                    op: BinaryOperator::Gt,
+                    right: Box::new(Expression::integer(1)),
+                }),
+            })
+        );
+
+        // Only NULL may follow IS
+        assert!(is_null_test(" IS 1").is_err());
+        assert!(is_null_test(" IS NOTNULL").is_err());
+        assert!(is_null_test(" ISNULL").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_like() -> anyhow::Result<()> {
+        let Statement::Select(select) = parse_sql("SELECT * FROM users WHERE name like 'A%'")?
+        else {
+            panic!("Expected SELECT");
+        };
+        assert_eq!(
+            select.where_clause,
+            Some(Expression::BinaryOp {
+                left: Box::new(Expression::column("name")),
+                op: BinaryOperator::Like,
+                right: Box::new(Expression::Literal {
+                    value: Value::Varchar("A%".to_string())
+                }),
+            })
+        );
+
+        // A column that merely starts with the keyword is still a column
+        let Statement::Select(select) = parse_sql("SELECT * FROM users WHERE name = likes")? else {
+            panic!("Expected SELECT");
+        };
+        assert_eq!(
+            select.where_clause,
+            Some(Expression::BinaryOp {
+                left: Box::new(Expression::column("name")),
+                op: BinaryOperator::Eq,
+                right: Box::new(Expression::column("likes")),
+            })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_select_into() -> anyhow::Result<()> {
+        let Statement::Select(select) =
+            parse_sql("SELECT id, name INTO active_users FROM users WHERE id > 1")?
+        else {
+            panic!("Expected SELECT");
+        };
+        assert_eq!(select.into.as_deref(), Some("active_users"));
+        assert_eq!(select.from.as_deref(), Some("users"));
         assert_eq!(select.select_list.len(), 2);
+        assert!(select.where_clause.is_some());
+
+        let Statement::Select(select) = parse_sql("SELECT 1 into one")? else {
+            panic!("Expected SELECT");
+        };
+        assert_eq!(select.into.as_deref(), Some("one"));
+        assert_eq!(select.from, None);
+
+        let Statement::Select(select) = parse_sql("SELECT intox FROM users")? else {
+            panic!("Expected SELECT");
+        };
+        assert_eq!(select.into, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_in_list() -> anyhow::Result<()> {
+        let where_clause = |sql: &str| -> anyhow::Result<Option<Expression>> {
+            let Statement::Select(select) = parse_sql(sql)? else {
+                panic!("Expected SELECT");
+            };
+            Ok(select.where_clause)
+        };
+
+        assert_eq!(
+            where_clause("SELECT * FROM users WHERE id IN (1, 3,5)")?,
+            Some(Expression::InList {
+                expr: Box::new(Expression::column("id")),
+                list: vec![
+                    Expression::integer(1),
+                    Expression::integer(3),
+                    Expression::integer(5)
+                ],
+                negated: false,
+            })
+        );
+        assert_eq!(
+            where_clause("SELECT * FROM users WHERE name not in('a') OR id = 2")?,
+            Some(Expression::BinaryOp {
+                left: Box::new(Expression::InList {
+                    expr: Box::new(Expression::column("name")),
+                    list: vec![Expression::Literal {
+                        value: Value::Varchar("a".to_string())
+                    }],
+                    negated: true,
+                }),
+                op: BinaryOperator::Or,
+                right: Box::new(Expression::BinaryOp {
+                    left: Box::new(Expression::column("id")),
+                    op: BinaryOperator::Eq,
+                    right: Box::new(Expression::integer(2)),
+                }),
+            })
+        );
+        assert!(where_clause("SELECT * FROM users WHERE id IN ()").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_aggregates() -> anyhow::Result<()> {
+        let Statement::Select(select) = parse_sql(
+            "SELECT count(*), COUNT( name ), sum(id) + 1, Avg(id), MIN(name), max(id) FROM users",
+        )?
+        else {
+            panic!("Expected SELECT");
+        };
+        let exprs: Vec<String> = select
+            .select_list
+            .iter()
+            .map(|item| match item {
+                SelectItem::Expression { expr, .. } => expr.to_string(),
+                SelectItem::Wildcard => panic!("Expected expressions"),
+            })
+            .collect();
+        assert_eq!(
+            exprs,
+            vec![
+                "COUNT(*)",
+                "COUNT(name)",
+                "(SUM(id) + 1)",
+                "AVG(id)",
+                "MIN(name)",
+                "MAX(id)"
+            ]
+        );
+
+        // Only COUNT takes `*`, and longer names are ordinary functions
+        assert!(aggregate_call("SUM(*)").is_err());
+        assert_eq!(
+            primary_expression("counter(id)")?.1,
+            Expression::function("counter", vec![Expression::column("id")])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_group_by_having() -> anyhow::Result<()> {
+        let Statement::Select(select) = parse_sql(
+            "SELECT name, COUNT(*) FROM users WHERE id > 0 GROUP BY name, id % 2 having count(*) > 1 ORDER BY name",
+        )?
+        else {
+            panic!("Expected SELECT");
+        };
+        let group_by: Vec<String> = select.group_by.iter().map(|e| e.to_string()).collect();
+        assert_eq!(group_by, vec!["name", "(id % 2)"]);
+        assert_eq!(select.having.unwrap().to_string(), "(COUNT(*) > 1)");
+        assert_eq!(select.order_by.len(), 1);
+
+        let Statement::Select(select) =
+            parse_sql("SELECT COUNT(*) FROM users HAVING COUNT(*) > 0")?
+        else {
+            panic!("Expected SELECT");
+        };
+        assert!(select.group_by.is_empty());
+        assert!(select.having.is_some());
+        Ok(())
+    }
+
+    #[test]
+    fn test_select_distinct() -> anyhow::Result<()> {
+        let Statement::Select(select) = parse_sql("SELECT distinct name FROM users")? else {
+            panic!("Expected SELECT");
+        };
+        assert!(select.distinct);
+        assert_eq!(select.select_list.len(), 1);
+
+        let Statement::Select(select) = parse_sql("SELECT distinctive FROM users")? else {
+            panic!("Expected SELECT");
+        };
+        assert!(!select.distinct);
+        assert_eq!(
+            select.select_list,
+            vec![SelectItem::Expression {
+                expr: Expression::column("distinctive"),
+                alias: None
+            }]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_select_aliases() -> anyhow::Result<()> {
+        let Statement::Select(select) = parse_sql(
+            "SELECT id AS user_id, name full_name, id + 1 as \"Next Id\", name FROM users",
+        )?
+        else {
+            panic!("Expected SELECT");
+        };
+        let aliases: Vec<Option<&str>> = select
+            .select_list
+            .iter()
+            .map(|item| match item {
+                SelectItem::Expression { alias, .. } => alias.as_deref(),
+                SelectItem::Wildcard => panic!("Expected expressions"),
+            })
+            .collect();
+        assert_eq!(
+            aliases,
+            vec![Some("user_id"), Some("full_name"), Some("Next Id"), None]
+        );
+        assert_eq!(select.from, Some("users".to_string()));
+
+        // Clause keywords are not bare aliases, but AS allows them
+        assert!(alias(" where").is_err());
+        assert_eq!(alias(" AS limit")?.1, "limit");
+        Ok(())
+    }
+
+    #[test]
+    fn test_join() -> anyhow::Result<()> {
+        let Statement::Select(select) = parse_sql(
+            "SELECT users.name, total FROM users JOIN orders ON users.id = orders.user_id \
+             INNER JOIN items ON orders.id = items.order_id WHERE total > 10",
+        )?
+        else {
+            panic!("Expected SELECT");
+        };
+        assert_eq!(select.from, Some("users".to_string()));
+        assert_eq!(
+            select.joins,
+            vec![
+                Join {
+                    table: "orders".to_string(),
+                    alias: None,
+                    condition: Expression::BinaryOp {
+                        left: Box::new(Expression::column("users.id")),
+                        op: BinaryOperator::Eq,
+                        right: Box::new(Expression::column("orders.user_id")),
+                    },
+                },
+                Join {
+                    table: "items".to_string(),
+                    alias: None,
+                    condition: Expression::BinaryOp {
+                        left: Box::new(Expression::column("orders.id")),
+                        op: BinaryOperator::Eq,
+                        right: Box::new(Expression::column("items.order_id")),
+                    },
+                },
+            ]
+        );
+        assert_eq!(
+            select.select_list[0],
+            SelectItem::Expression {
+                expr: Expression::column("users.name"),
+                alias: None
+            }
+        );
+        assert!(select.where_clause.is_some());
+        // A JOIN needs its ON condition
+        let (_, errors) = parse_script_collect("SELECT * FROM users JOIN orders");
+        assert_eq!(errors.len(), 1);
+
+        let Statement::Select(select) =
+            parse_sql("SELECT * FROM users u JOIN orders AS o ON u.id = o.user_id WHERE u.id = 1")?
+        else {
+            panic!("Expected SELECT");
+        };
+        assert_eq!(select.from_alias, Some("u".to_string()));
+        assert_eq!(select.joins[0].alias, Some("o".to_string()));
+        assert!(select.where_clause.is_some());
+        Ok(())
+    }
+
+    #[test]
+    fn test_table_function() -> anyhow::Result<()> {
+        let Statement::Select(select) =
+            parse_sql("SELECT * FROM generate_series(10, 1, -3) WHERE generate_series > 2")?
+        else {
+            panic!("Expected SELECT");
+        };
+        assert_eq!(select.from, None);
+        assert_eq!(
+            select.table_function.map(|f| *f),
+            Some(Expression::Function {
+                name: "generate_series".to_string(),
+                args: vec![
+                    Expression::integer(10),
+                    Expression::integer(1),
+                    Expression::integer(-3)
+                ],
+            })
+        );
+        assert!(select.where_clause.is_some());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parameters() -> anyhow::Result<()> {
+        let Statement::Select(select) =
+            parse_sql("SELECT * FROM users WHERE id = $1 AND name <> $12")?
+        else {
+            panic!("Expected SELECT");
+        };
+        assert_eq!(
+            select.where_clause.unwrap().to_string(),
+            "((id = $1) AND (name != $12))"
+        );
+        assert!(parameter("$0").is_err());
+        assert!(parameter("$x").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_between() -> anyhow::Result<()> {
+        let Statement::Select(select) = parse_sql(
+            "SELECT * FROM users WHERE id BETWEEN 5 AND 10 AND name NOT between 'a' and 'b'",
+        )?
+        else {
+            panic!("Expected SELECT");
+        };
+        assert_eq!(
+            select.where_clause,
+            Some(Expression::BinaryOp {
+                left: Box::new(Expression::Between {
+                    expr: Box::new(Expression::column("id")),
+                    low: Box::new(Expression::integer(5)),
+                    high: Box::new(Expression::integer(10)),
+                    negated: false,
+                    symmetric: false,
+                }),
+                op: BinaryOperator::And,
+                right: Box::new(Expression::Between {
+                    expr: Box::new(Expression::column("name")),
+                    low: Box::new(Expression::Literal {
+                        value: Value::Varchar("a".to_string())
+                    }),
+                    high: Box::new(Expression::Literal {
+                        value: Value::Varchar("b".to_string())
+                    }),
+                    negated: true,
+                    symmetric: false,
+                }),
+            })
+        );
+
+        // Bounds may be arithmetic but not boolean expressions
+        let Statement::Select(select) =
+            parse_sql("SELECT * FROM users WHERE id BETWEEN id - 1 AND id + 1 OR id = 0")?
+        else {
+            panic!("Expected SELECT");
+        };
+        assert!(matches!(
+            select.where_clause,
+            Some(Expression::BinaryOp {
+                op: BinaryOperator::Or,
+                ..
+            })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_not_precedence() -> anyhow::Result<()> {
+        let where_clause = |sql: &str| -> anyhow::Result<String> {
+            let Statement::Select(select) = parse_sql(sql)? else {
+                panic!("Expected SELECT");
+            };
+            Ok(select.where_clause.unwrap().to_string())
+        };
+
+        assert_eq!(
+            where_clause("SELECT * FROM t WHERE NOT (id = 5)")?,
+            "(NOT (id = 5))"
+        );
+        assert_eq!(
+            where_clause("SELECT * FROM t WHERE not active")?,
+            "(NOT active)"
+        );
+        assert_eq!(
+            where_clause("SELECT * FROM t WHERE NOT id = 5 AND active")?,
+            "((NOT (id = 5)) AND active)"
+        );
+        assert_eq!(
+            where_clause("SELECT * FROM t WHERE a OR NOT b AND c")?,
+            "(a OR ((NOT b) AND c))"
+        );
+        assert_eq!(
+            where_clause("SELECT * FROM t WHERE NOT NOT a AND NOT (b OR c)")?,
+            "((NOT (NOT a)) AND (NOT (b OR c)))"
+        );
+        assert_eq!(
+            where_clause("SELECT * FROM t WHERE nothing AND NOT id NOT IN (1)")?,
+            "(nothing AND (NOT (id NOT IN (1))))"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_between_symmetric() -> anyhow::Result<()> {
+        let Statement::Select(select) =
+            parse_sql("SELECT * FROM t WHERE id NOT BETWEEN symmetric 10 AND 5")?
+        else {
+            panic!("Expected SELECT");
+        };
+        assert_eq!(
+            select.where_clause,
+            Some(Expression::Between {
+                expr: Box::new(Expression::column("id")),
+                low: Box::new(Expression::integer(10)),
+                high: Box::new(Expression::integer(5)),
+                negated: true,
+                symmetric: true,
+            })
+        );
+
+        // A bound that only starts with the keyword is a column
+        let Statement::Select(select) =
+            parse_sql("SELECT * FROM t WHERE id BETWEEN symmetrical AND 5")?
+        else {
+            panic!("Expected SELECT");
+        };
+        assert!(matches!(
+            select.where_clause,
+            Some(Expression::Between {
+                symmetric: false,
+                ..
+            })
+        ));
+        Ok(())
     }
 }
@@ -0,0 +1,203 @@
+use crate::catalog::{Catalog, TableInfo};
+use crate::query::parser::parse_sql;
+use crate::query::planner::{PhysicalPlan, QueryPlanner};
+use crate::query::session::SessionConfig;
+use crate::query::types::Value;
+use std::collections::HashMap;
+
+struct CachedPlan {
+    plan: PhysicalPlan,
+    // The catalog entry of every table the plan touches when it was built,
+    // under the name the catalog has it; `None` for tables that did not exist
+    // yet (the target of SELECT INTO)
+    tables: Vec<(String, Option<TableInfo>)>,
+}
+
+impl CachedPlan {
+    fn is_current(&self, catalog: &Catalog) -> bool {
+        self.tables
+            .iter()
+            .all(|(name, info)| catalog.table(name).ok() == info.as_ref())
+    }
+}
+
+// Optimized plans for parameterized SQL, keyed by the SQL text and the
+// planner's session config. A plan is built once per query shape and has
+// the parameters bound into a copy of it on every run. Entries are rebuilt
+// when a table they touch is created, dropped or changes schema.
+#[derive(Default)]
+pub struct PlanCache {
+    plans: HashMap<(String, SessionConfig), CachedPlan>,
+    plans_built: u64,
+}
+
+impl PlanCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn plan(
+        &mut self,
+        sql: &str,
+        params: &[Value],
+        planner: &QueryPlanner,
+    ) -> anyhow::Result<PhysicalPlan> {
+        let catalog = planner.catalog();
+        let key = (sql.to_string(), planner.config().clone());
+        let cached = match self.plans.get(&key) {
+            Some(cached) if cached.is_current(catalog) => cached,
+            _ => {
+                let plan = planner.plan(&parse_sql(sql)?)?;
+                self.plans_built += 1;
+                let tables = table_names(&plan)
+                    .into_iter()
+                    .map(|name| {
+                        let info = catalog.table(&name).ok().cloned();
+                        (name, info)
+                    })
+                    .collect();
+                self.plans
+                    .entry(key)
+                    .insert_entry(CachedPlan { plan, tables })
+                    .into_mut()
+            }
+        };
+        cached.plan.clone().bind_parameters(params)
+    }
+
+    // Number of times a plan has been built instead of taken from the cache
+    pub fn plans_built(&self) -> u64 {
+        self.plans_built
+    }
+
+    pub fn len(&self) -> usize {
+        self.plans.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.plans.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.plans.clear();
+    }
+}
+
+fn table_names(plan: &PhysicalPlan) -> Vec<String> {
+    let mut names = Vec::new();
+    plan.walk(&mut |node| match node {
+        PhysicalPlan::SeqScan { table_name, .. }
+        | PhysicalPlan::Insert { table_name, .. }
+        | PhysicalPlan::Update { table_name, .. }
+        | PhysicalPlan::Delete { table_name, .. }
+        | PhysicalPlan::SelectInto { table_name, .. }
+            if !names.contains(table_name) =>
+        {
+            names.push(table_name.clone())
+        }
+        _ => {}
+    });
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::catalog::users_catalog;
+    use crate::disk::disk_manager::DiskManager;
+    use crate::disk::file_system::FsDiskManager;
+    use crate::heap::heap_page::HeapPage;
+    use crate::query::executor::QueryExecutor;
+    use crate::query::tuple::serialize_row;
+    use crate::query::types::{Column, DataType, Schema};
+    use tempfile::TempDir;
+
+    const SQL: &str = "SELECT name FROM users WHERE id = $1";
+
+    fn names(
+        cache: &mut PlanCache,
+        planner: &QueryPlanner,
+        dm: &mut FsDiskManager,
+        id: i32,
+    ) -> anyhow::Result<Vec<Value>> {
+        let plan = cache.plan(SQL, &[Value::Integer(id)], planner)?;
+        let result = QueryExecutor::new().execute(plan, dm)?;
+        Ok(result.rows.into_iter().map(|row| row[0].clone()).collect())
+    }
+
+    #[test]
+    fn parameterized_plans_are_reused() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let mut dm = FsDiskManager::new(temp_dir.path())?;
+        let catalog = users_catalog();
+        let schema = catalog.table("users")?.schema.clone();
+        let mut hp = HeapPage::allocate(&mut dm, 1)?;
+        for i in 0..3 {
+            let row = vec![Value::Integer(i), Value::Varchar(format!("user_{}", i))];
+            hp.insert_tuple(&serialize_row(&row, &schema)?)?;
+        }
+        dm.write_page(&hp.page)?;
+
+        let mut cache = PlanCache::new();
+        let planner = QueryPlanner::new(&catalog);
+        assert_eq!(
+            names(&mut cache, &planner, &mut dm, 1)?,
+            vec![Value::Varchar("user_1".to_string())]
+        );
+        assert_eq!(
+            names(&mut cache, &planner, &mut dm, 2)?,
+            vec![Value::Varchar("user_2".to_string())]
+        );
+        assert_eq!(cache.plans_built(), 1);
+        assert_eq!(cache.len(), 1);
+
+        // Too few parameters is an error, and does not cost a rebuild
+        assert!(cache.plan(SQL, &[], &planner).is_err());
+        assert_eq!(cache.plans_built(), 1);
+
+        // A schema change invalidates the entry
+        let mut columns = schema.columns.clone();
+        columns.push(Column {
+            name: "active".to_string(),
+            data_type: DataType::Boolean,
+            nullable: true,
+        });
+        let mut changed = Catalog::new();
+        changed.add_table("users", 1, Schema::new(columns))?;
+        cache.plan(SQL, &[Value::Integer(1)], &QueryPlanner::new(&changed))?;
+        assert_eq!(cache.plans_built(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn entries_track_the_catalog_name_and_config() -> anyhow::Result<()> {
+        const UPPER: &str = "SELECT name FROM USERS WHERE id = $1";
+        let catalog = users_catalog();
+        let mut cache = PlanCache::new();
+        cache.plan(UPPER, &[Value::Integer(1)], &QueryPlanner::new(&catalog))?;
+        cache.plan(UPPER, &[Value::Integer(2)], &QueryPlanner::new(&catalog))?;
+        assert_eq!(cache.plans_built(), 1);
+
+        // USERS resolved to `users`, so a change to `users` invalidates it
+        let mut columns = catalog.table("users")?.schema.columns.clone();
+        columns.push(Column {
+            name: "active".to_string(),
+            data_type: DataType::Boolean,
+            nullable: true,
+        });
+        let mut changed = Catalog::new();
+        changed.add_table("users", 1, Schema::new(columns))?;
+        cache.plan(UPPER, &[Value::Integer(1)], &QueryPlanner::new(&changed))?;
+        assert_eq!(cache.plans_built(), 2);
+
+        // A planner that matches names exactly does not reuse the plan
+        let exact = SessionConfig {
+            case_insensitive_identifiers: false,
+            ..SessionConfig::default()
+        };
+        let planner = QueryPlanner::with_config(&changed, exact);
+        assert!(cache.plan(UPPER, &[Value::Integer(1)], &planner).is_err());
+        assert_eq!(cache.len(), 1);
+        Ok(())
+    }
+}
@@ -1,5 +1,9 @@
-use crate::query::ast::{Expression, SelectStatement, Statement};
-use crate::query::types::Schema;
+use crate::catalog::{Catalog, CatalogError, TableInfo};
+use crate::query::ast::{ColumnName, Expression, Join, SelectStatement, SetOperator, Statement};
+use crate::query::optimizer;
+use crate::query::session::SessionConfig;
+use crate::query::types::{Column, DataType, Row, Schema, Value};
+use std::collections::HashMap;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum LogicalPlan {
@@ -21,61 +25,928 @@ pub enum LogicalPlan {
     },
 }
 
+// Prefix of the columns a Compute node appends to its input rows. It cannot
+// appear in a parsed identifier, so it never collides with table columns.
+pub const COMPUTED_COLUMN_PREFIX: &str = "#cse";
+pub const ROW_NUMBER_COLUMN: &str = "#row_number";
+// Prefix of the Aggregate columns holding group keys that are not plain
+// column references.
+pub const GROUP_COLUMN_PREFIX: &str = "#group";
+
+// Names of the Aggregate columns holding each group key. A plain column keeps
+// its name so it can still be selected as is.
+pub fn group_column_names(group_by: &[Expression]) -> Vec<String> {
+    group_by
+        .iter()
+        .enumerate()
+        .map(|(i, expr)| match expr {
+            Expression::Column { name, .. } => name.clone(),
+            _ => format!("{}{}", GROUP_COLUMN_PREFIX, i),
+        })
+        .collect()
+}
+
+// The schema of a GENERATE_SERIES scan.
+pub fn generate_series_schema() -> Schema {
+    Schema::new(vec![Column {
+        name: "generate_series".to_string(),
+        data_type: DataType::Integer,
+        nullable: false,
+    }])
+}
+
+// Output column names of a projection, where `aliases[i]`, if given, names
+// column i. Repeats of a name are numbered (id, id:1, id:2, ...) so every
+// column in the result can be found by name.
+pub fn projection_column_names(exprs: &[Expression], aliases: &[Option<String>]) -> Vec<String> {
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    exprs
+        .iter()
+        .enumerate()
+        .map(|(i, expr)| {
+            let name = match expr {
+                _ if aliases.get(i).is_some_and(Option::is_some) => {
+                    aliases[i].clone().unwrap_or_default()
+                }
+                // Computed columns stand in for the expression they cache
+                Expression::Column { name, .. }
+                    if name.starts_with(COMPUTED_COLUMN_PREFIX)
+                        || name.starts_with(GROUP_COLUMN_PREFIX) =>
+                {
+                    "expr".to_string()
+                }
+                Expression::Column { name, .. } if name == ROW_NUMBER_COLUMN => {
+                    "row_number".to_string()
+                }
+                Expression::Column { name, .. } => name.clone(),
+                Expression::Literal { .. } => "literal".to_string(),
+                Expression::Parameter { .. } => "parameter".to_string(),
+                Expression::BinaryOp { .. }
+                | Expression::InList { .. }
+                | Expression::Not(_)
+                | Expression::Between { .. } => "expr".to_string(),
+                Expression::Function { name, .. } => name.to_lowercase(),
+                Expression::Aggregate { func, .. } => func.to_string().to_lowercase(),
+            };
+            let repeats = seen.entry(name.clone()).or_insert(0);
+            let name = match *repeats {
+                0 => name,
+                n => format!("{}:{}", name, n),
+            };
+            *repeats += 1;
+            name
+        })
+        .collect()
+}
+
 #[derive(Debug, Clone)]
 pub enum PhysicalPlan {
     SeqScan {
         table_name: String,
+        file_id: u32,
+        schema: Schema,
+    },
+    // Literal rows from a VALUES statement
+    Values {
         schema: Schema,
+        rows: Vec<Row>,
+    },
+    // FROM GENERATE_SERIES(start, stop, step): the integers from `start` to
+    // `stop` inclusive, as a single column named generate_series
+    GenerateSeries {
+        start: Expression,
+        stop: Expression,
+        step: Expression,
+    },
+    Compute {
+        exprs: Vec<Expression>,
+        input: Box<PhysicalPlan>,
     },
+    // Pairs every row of `left` with every row of `right`, keeping the
+    // concatenated rows for which `condition` is true
+    NestedLoopJoin {
+        left: Box<PhysicalPlan>,
+        right: Box<PhysicalPlan>,
+        condition: Expression,
+    },
+    // Evaluates `exprs` over each row; `aliases` names the output columns
+    // given an AS alias and is empty when there are none
     Projection {
         exprs: Vec<Expression>,
+        aliases: Vec<Option<String>>,
         input: Box<PhysicalPlan>,
     },
     Filter {
         predicate: Expression,
         input: Box<PhysicalPlan>,
     },
+    // Orders rows by each key in turn, true meaning descending. NULLs sort
+    // last and rows with equal keys keep their input order.
+    Sort {
+        keys: Vec<(Expression, bool)>,
+        input: Box<PhysicalPlan>,
+    },
+    // One row per distinct value of `group_by`, or a single row for the
+    // whole input when it is empty, holding the group keys (named by
+    // group_column_names) and then the aggregates. Aggregate columns are
+    // named after the call so expressions above can read them back.
+    Aggregate {
+        aggregates: Vec<Expression>,
+        group_by: Vec<Expression>,
+        input: Box<PhysicalPlan>,
+    },
+    // Appends each row's 1-based position as ROW_NUMBER_COLUMN
+    RowNumber {
+        input: Box<PhysicalPlan>,
+    },
+    // Drops rows equal to an earlier row, NULLs comparing equal
+    Distinct {
+        input: Box<PhysicalPlan>,
+    },
     Limit {
         limit: u32,
         input: Box<PhysicalPlan>,
     },
+    // Keeps the first `percent`% of the input rows, rounded up
+    LimitPercent {
+        percent: u32,
+        input: Box<PhysicalPlan>,
+    },
+    // Rows of `left` that are (INTERSECT) or are not (EXCEPT) in `right`,
+    // deduplicated unless `all` is set
+    SetOperation {
+        op: SetOperator,
+        all: bool,
+        left: Box<PhysicalPlan>,
+        right: Box<PhysicalPlan>,
+    },
+    // Describes `input` instead of returning its rows; with `analyze` the
+    // input is run and each node is annotated with its row count and time
+    Explain {
+        analyze: bool,
+        input: Box<PhysicalPlan>,
+    },
+    // Sets the columns at the given schema indexes on every row matching
+    // `predicate`; the expressions read the row's old values
+    Update {
+        table_name: String,
+        file_id: u32,
+        schema: Schema,
+        assignments: Vec<(usize, Expression)>,
+        predicate: Option<Expression>,
+    },
+    // Tombstones the rows of the table's heap file that match `predicate`,
+    // or all of them without one
+    Delete {
+        table_name: String,
+        file_id: u32,
+        schema: Schema,
+        predicate: Option<Expression>,
+    },
+    // Appends `rows` to the table's heap file. Each row holds one expression
+    // per schema column, in schema order.
+    Insert {
+        table_name: String,
+        file_id: u32,
+        schema: Schema,
+        rows: Vec<Vec<Expression>>,
+    },
+    // Creates `table_name` with the output schema of `input` and inserts
+    // every row `input` produces
+    SelectInto {
+        table_name: String,
+        input: Box<PhysicalPlan>,
+    },
+}
+
+impl PhysicalPlan {
+    pub fn inputs(&self) -> Vec<&PhysicalPlan> {
+        match self {
+            PhysicalPlan::SeqScan { .. }
+            | PhysicalPlan::Values { .. }
+            | PhysicalPlan::GenerateSeries { .. }
+            | PhysicalPlan::Insert { .. }
+            | PhysicalPlan::Update { .. }
+            | PhysicalPlan::Delete { .. } => Vec::new(),
+            PhysicalPlan::Compute { input, .. }
+            | PhysicalPlan::Projection { input, .. }
+            | PhysicalPlan::Filter { input, .. }
+            | PhysicalPlan::Sort { input, .. }
+            | PhysicalPlan::Aggregate { input, .. }
+            | PhysicalPlan::RowNumber { input }
+            | PhysicalPlan::Distinct { input }
+            | PhysicalPlan::Limit { input, .. }
+            | PhysicalPlan::LimitPercent { input, .. }
+            | PhysicalPlan::Explain { input, .. }
+            | PhysicalPlan::SelectInto { input, .. } => vec![input],
+            PhysicalPlan::SetOperation { left, right, .. }
+            | PhysicalPlan::NestedLoopJoin { left, right, .. } => vec![left, right],
+        }
+    }
+
+    // One-line description of this node, without its inputs.
+    pub fn label(&self) -> String {
+        let list = |exprs: &[Expression]| {
+            let exprs: Vec<String> = exprs.iter().map(|e| e.to_string()).collect();
+            exprs.join(", ")
+        };
+        match self {
+            PhysicalPlan::SeqScan { table_name, .. } => format!("SeqScan on {}", table_name),
+            PhysicalPlan::Values { rows, .. } => format!("Values: {} rows", rows.len()),
+            PhysicalPlan::GenerateSeries { start, stop, step } => {
+                format!("GenerateSeries: {} to {} step {}", start, stop, step)
+            }
+            PhysicalPlan::Insert {
+                table_name, rows, ..
+            } => format!("Insert on {}: {} rows", table_name, rows.len()),
+            PhysicalPlan::SelectInto { table_name, .. } => format!("SelectInto: {}", table_name),
+            PhysicalPlan::Update {
+                table_name,
+                schema,
+                assignments,
+                ..
+            } => {
+                let assignments: Vec<String> = assignments
+                    .iter()
+                    .map(|(index, expr)| format!("{} = {}", schema.columns[*index].name, expr))
+                    .collect();
+                format!("Update on {}: {}", table_name, assignments.join(", "))
+            }
+            PhysicalPlan::Delete {
+                table_name,
+                predicate: Some(predicate),
+                ..
+            } => format!("Delete on {}: {}", table_name, predicate),
+            PhysicalPlan::Delete { table_name, .. } => format!("Delete on {}", table_name),
+            PhysicalPlan::Compute { exprs, .. } => format!("Compute: {}", list(exprs)),
+            PhysicalPlan::Projection { exprs, aliases, .. } => {
+                let exprs: Vec<String> = exprs
+                    .iter()
+                    .enumerate()
+                    .map(|(i, expr)| match aliases.get(i) {
+                        Some(Some(alias)) => format!("{} AS {}", expr, alias),
+                        _ => expr.to_string(),
+                    })
+                    .collect();
+                format!("Projection: {}", exprs.join(", "))
+            }
+            PhysicalPlan::Filter { predicate, .. } => format!("Filter: {}", predicate),
+            PhysicalPlan::Sort { keys, .. } => {
+                let keys: Vec<String> = keys
+                    .iter()
+                    .map(|(expr, desc)| {
+                        if *desc {
+                            format!("{} DESC", expr)
+                        } else {
+                            expr.to_string()
+                        }
+                    })
+                    .collect();
+                format!("Sort: {}", keys.join(", "))
+            }
+            PhysicalPlan::Aggregate {
+                aggregates,
+                group_by,
+                ..
+            } if group_by.is_empty() => format!("Aggregate: {}", list(aggregates)),
+            PhysicalPlan::Aggregate {
+                aggregates,
+                group_by,
+                ..
+            } => format!(
+                "Aggregate: {} GROUP BY {}",
+                list(aggregates),
+                list(group_by)
+            ),
+            PhysicalPlan::RowNumber { .. } => "RowNumber".to_string(),
+            PhysicalPlan::Distinct { .. } => "Distinct".to_string(),
+            PhysicalPlan::Limit { limit, .. } => format!("Limit: {}", limit),
+            PhysicalPlan::LimitPercent { percent, .. } => format!("Limit: {} PERCENT", percent),
+            PhysicalPlan::NestedLoopJoin { condition, .. } => {
+                format!("NestedLoopJoin on {}", condition)
+            }
+            PhysicalPlan::SetOperation { op, all, .. } => {
+                let name = match op {
+                    SetOperator::Intersect => "Intersect",
+                    SetOperator::Except => "Except",
+                };
+                if *all {
+                    format!("{} All", name)
+                } else {
+                    name.to_string()
+                }
+            }
+            PhysicalPlan::Explain { analyze: false, .. } => "Explain".to_string(),
+            PhysicalPlan::Explain { analyze: true, .. } => "Explain Analyze".to_string(),
+        }
+    }
+
+    // Visits every plan node, parents before inputs.
+    pub fn walk(&self, visit: &mut impl FnMut(&PhysicalPlan)) {
+        visit(self);
+        for input in self.inputs() {
+            input.walk(visit);
+        }
+    }
+
+    // Replaces each direct input of this node with `f(input)`.
+    pub fn map_inputs(self, f: &mut impl FnMut(PhysicalPlan) -> PhysicalPlan) -> PhysicalPlan {
+        let mut apply = |input: Box<PhysicalPlan>| Box::new(f(*input));
+        match self {
+            PhysicalPlan::SeqScan { .. }
+            | PhysicalPlan::Values { .. }
+            | PhysicalPlan::GenerateSeries { .. }
+            | PhysicalPlan::Insert { .. }
+            | PhysicalPlan::Update { .. }
+            | PhysicalPlan::Delete { .. } => self,
+            PhysicalPlan::Compute { exprs, input } => PhysicalPlan::Compute {
+                exprs,
+                input: apply(input),
+            },
+            PhysicalPlan::Projection {
+                exprs,
+                aliases,
+                input,
+            } => PhysicalPlan::Projection {
+                exprs,
+                aliases,
+                input: apply(input),
+            },
+            PhysicalPlan::Filter { predicate, input } => PhysicalPlan::Filter {
+                predicate,
+                input: apply(input),
+            },
+            PhysicalPlan::Sort { keys, input } => PhysicalPlan::Sort {
+                keys,
+                input: apply(input),
+            },
+            PhysicalPlan::Aggregate {
+                aggregates,
+                group_by,
+                input,
+            } => PhysicalPlan::Aggregate {
+                aggregates,
+                group_by,
+                input: apply(input),
+            },
+            PhysicalPlan::RowNumber { input } => PhysicalPlan::RowNumber {
+                input: apply(input),
+            },
+            PhysicalPlan::Distinct { input } => PhysicalPlan::Distinct {
+                input: apply(input),
+            },
+            PhysicalPlan::Limit { limit, input } => PhysicalPlan::Limit {
+                limit,
+                input: apply(input),
+            },
+            PhysicalPlan::LimitPercent { percent, input } => PhysicalPlan::LimitPercent {
+                percent,
+                input: apply(input),
+            },
+            PhysicalPlan::SetOperation {
+                op,
+                all,
+                left,
+                right,
+            } => PhysicalPlan::SetOperation {
+                op,
+                all,
+                left: apply(left),
+                right: apply(right),
+            },
+            PhysicalPlan::NestedLoopJoin {
+                left,
+                right,
+                condition,
+            } => PhysicalPlan::NestedLoopJoin {
+                left: apply(left),
+                right: apply(right),
+                condition,
+            },
+            PhysicalPlan::Explain { analyze, input } => PhysicalPlan::Explain {
+                analyze,
+                input: apply(input),
+            },
+            PhysicalPlan::SelectInto { table_name, input } => PhysicalPlan::SelectInto {
+                table_name,
+                input: apply(input),
+            },
+        }
+    }
+
+    // Applies `Expression::map` to every expression held by this plan and its inputs.
+    pub fn map_expressions(self, f: &mut impl FnMut(Expression) -> Expression) -> PhysicalPlan {
+        match self {
+            PhysicalPlan::SeqScan { .. } | PhysicalPlan::Values { .. } => self,
+            PhysicalPlan::GenerateSeries { start, stop, step } => PhysicalPlan::GenerateSeries {
+                start: start.map(f),
+                stop: stop.map(f),
+                step: step.map(f),
+            },
+            PhysicalPlan::Compute { exprs, input } => PhysicalPlan::Compute {
+                exprs: exprs.into_iter().map(|expr| expr.map(f)).collect(),
+                input: Box::new(input.map_expressions(f)),
+            },
+            PhysicalPlan::Projection {
+                exprs,
+                aliases,
+                input,
+            } => PhysicalPlan::Projection {
+                exprs: exprs.into_iter().map(|expr| expr.map(f)).collect(),
+                aliases,
+                input: Box::new(input.map_expressions(f)),
+            },
+            PhysicalPlan::Filter { predicate, input } => PhysicalPlan::Filter {
+                predicate: predicate.map(f),
+                input: Box::new(input.map_expressions(f)),
+            },
+            PhysicalPlan::Sort { keys, input } => PhysicalPlan::Sort {
+                keys: keys
+                    .into_iter()
+                    .map(|(expr, desc)| (expr.map(f), desc))
+                    .collect(),
+                input: Box::new(input.map_expressions(f)),
+            },
+            PhysicalPlan::Aggregate {
+                aggregates,
+                group_by,
+                input,
+            } => PhysicalPlan::Aggregate {
+                aggregates: aggregates.into_iter().map(|expr| expr.map(f)).collect(),
+                group_by: group_by.into_iter().map(|expr| expr.map(f)).collect(),
+                input: Box::new(input.map_expressions(f)),
+            },
+            PhysicalPlan::RowNumber { input } => PhysicalPlan::RowNumber {
+                input: Box::new(input.map_expressions(f)),
+            },
+            PhysicalPlan::Distinct { input } => PhysicalPlan::Distinct {
+                input: Box::new(input.map_expressions(f)),
+            },
+            PhysicalPlan::Limit { limit, input } => PhysicalPlan::Limit {
+                limit,
+                input: Box::new(input.map_expressions(f)),
+            },
+            PhysicalPlan::LimitPercent { percent, input } => PhysicalPlan::LimitPercent {
+                percent,
+                input: Box::new(input.map_expressions(f)),
+            },
+            PhysicalPlan::SetOperation {
+                op,
+                all,
+                left,
+                right,
+            } => PhysicalPlan::SetOperation {
+                op,
+                all,
+                left: Box::new(left.map_expressions(f)),
+                right: Box::new(right.map_expressions(f)),
+            },
+            PhysicalPlan::NestedLoopJoin {
+                left,
+                right,
+                condition,
+            } => PhysicalPlan::NestedLoopJoin {
+                left: Box::new(left.map_expressions(f)),
+                right: Box::new(right.map_expressions(f)),
+                condition: condition.map(f),
+            },
+            PhysicalPlan::Explain { analyze, input } => PhysicalPlan::Explain {
+                analyze,
+                input: Box::new(input.map_expressions(f)),
+            },
+            PhysicalPlan::SelectInto { table_name, input } => PhysicalPlan::SelectInto {
+                table_name,
+                input: Box::new(input.map_expressions(f)),
+            },
+            PhysicalPlan::Insert {
+                table_name,
+                file_id,
+                schema,
+                rows,
+            } => PhysicalPlan::Insert {
+                table_name,
+                file_id,
+                schema,
+                rows: rows
+                    .into_iter()
+                    .map(|row| row.into_iter().map(|expr| expr.map(f)).collect())
+                    .collect(),
+            },
+            PhysicalPlan::Update {
+                table_name,
+                file_id,
+                schema,
+                assignments,
+                predicate,
+            } => PhysicalPlan::Update {
+                table_name,
+                file_id,
+                schema,
+                assignments: assignments
+                    .into_iter()
+                    .map(|(index, expr)| (index, expr.map(f)))
+                    .collect(),
+                predicate: predicate.map(|predicate| predicate.map(f)),
+            },
+            PhysicalPlan::Delete {
+                table_name,
+                file_id,
+                schema,
+                predicate,
+            } => PhysicalPlan::Delete {
+                table_name,
+                file_id,
+                schema,
+                predicate: predicate.map(|predicate| predicate.map(f)),
+            },
+        }
+    }
+
+    // Replaces every `$n` in the plan with `params[n - 1]`.
+    pub fn bind_parameters(self, params: &[Value]) -> anyhow::Result<PhysicalPlan> {
+        let mut missing = None;
+        let plan = self.map_expressions(&mut |expr| match expr {
+            Expression::Parameter { index } => match params.get(index - 1) {
+                Some(value) => Expression::Literal {
+                    value: value.clone(),
+                },
+                None => {
+                    missing.get_or_insert(index);
+                    expr
+                }
+            },
+            expr => expr,
+        });
+        match missing {
+            Some(index) => anyhow::bail!(
+                "Parameter ${} is not bound; {} parameters were given",
+                index,
+                params.len()
+            ),
+            None => Ok(plan),
+        }
+    }
 }
 
-pub struct QueryPlanner {
-    // TODO: Add catalog/schema registry
+pub struct QueryPlanner<'a> {
+    catalog: &'a Catalog,
+    config: SessionConfig,
 }
 
-impl QueryPlanner {
-    pub fn new() -> Self {
-        Self {}
+impl<'a> QueryPlanner<'a> {
+    pub fn new(catalog: &'a Catalog) -> Self {
+        Self::with_config(catalog, SessionConfig::default())
+    }
+
+    pub fn with_config(catalog: &'a Catalog, config: SessionConfig) -> Self {
+        Self { catalog, config }
+    }
+
+    pub fn catalog(&self) -> &'a Catalog {
+        self.catalog
+    }
+
+    pub fn config(&self) -> &SessionConfig {
+        &self.config
     }
 
     pub fn plan(&self, stmt: &Statement) -> anyhow::Result<PhysicalPlan> {
+        Ok(optimizer::optimize(self.plan_statement(stmt)?))
+    }
+
+    fn plan_statement(&self, stmt: &Statement) -> anyhow::Result<PhysicalPlan> {
         match stmt {
-            Statement::Select(select) => self.plan_select(select),
+            Statement::Select(select) => match &select.into {
+                Some(table) => self.plan_select_into(table, select),
+                None => self.plan_select(select),
+            },
+            Statement::Values(rows) => Self::plan_values(rows),
+            Statement::SetOperation {
+                op,
+                all,
+                left,
+                right,
+            } => {
+                for operand in [left, right] {
+                    if let Statement::Select(SelectStatement { into: Some(_), .. }) = &**operand {
+                        anyhow::bail!("SELECT INTO cannot be an operand of {:?}", op);
+                    }
+                }
+                Ok(PhysicalPlan::SetOperation {
+                    op: *op,
+                    all: *all,
+                    left: Box::new(self.plan_statement(left)?),
+                    right: Box::new(self.plan_statement(right)?),
+                })
+            }
+            Statement::Explain { analyze, statement } => Ok(PhysicalPlan::Explain {
+                analyze: *analyze,
+                input: Box::new(self.plan_statement(statement)?),
+            }),
+            Statement::Insert {
+                table,
+                columns,
+                rows,
+            } => self.plan_insert(table, columns.as_deref(), rows),
+            Statement::Update {
+                table,
+                assignments,
+                where_clause,
+            } => self.plan_update(table, assignments, where_clause.as_ref()),
+            Statement::Delete {
+                table,
+                where_clause,
+            } => {
+                let (table_name, info) = self.lookup_table(table)?;
+                let mut predicate = where_clause.clone();
+                if let Some(predicate) = &mut predicate {
+                    self.resolve_columns(predicate, &info.schema)?;
+                }
+                Ok(PhysicalPlan::Delete {
+                    table_name,
+                    file_id: info.file_id,
+                    schema: info.schema.clone(),
+                    predicate,
+                })
+            }
         }
     }
 
-    fn plan_select(&self, select: &SelectStatement) -> anyhow::Result<PhysicalPlan> {
-        let mut plan = if let Some(table_name) = &select.from {
-            // TODO: Look up schema from catalog
-            let schema = self.get_table_schema(table_name)?;
-            PhysicalPlan::SeqScan {
-                table_name: table_name.clone(),
-                schema,
+    fn plan_update(
+        &self,
+        table_name: &str,
+        assignments: &[(ColumnName, Expression)],
+        where_clause: Option<&Expression>,
+    ) -> anyhow::Result<PhysicalPlan> {
+        let (resolved_name, table) = self.lookup_table(table_name)?;
+        let schema = &table.schema;
+
+        let mut resolved: Vec<(usize, Expression)> = Vec::with_capacity(assignments.len());
+        for (name, expr) in assignments {
+            let column = self.resolve_column(&name.name, name.quoted, schema)?;
+            let Some(index) = schema.columns.iter().position(|c| c.name == column) else {
+                anyhow::bail!("Column '{}' not found in table '{}'", name.name, table_name);
+            };
+            if resolved.iter().any(|(seen, _)| *seen == index) {
+                anyhow::bail!("Column '{}' assigned more than once", column);
+            }
+            let mut expr = expr.clone();
+            self.resolve_columns(&mut expr, schema)?;
+            resolved.push((index, expr));
+        }
+
+        let mut predicate = where_clause.cloned();
+        if let Some(predicate) = &mut predicate {
+            self.resolve_columns(predicate, schema)?;
+        }
+        Ok(PhysicalPlan::Update {
+            table_name: resolved_name,
+            file_id: table.file_id,
+            schema: schema.clone(),
+            assignments: resolved,
+            predicate,
+        })
+    }
+
+    // Reorders each row into table column order. Every column needs a value
+    // since there are no defaults; value types are checked by the executor.
+    fn plan_insert(
+        &self,
+        table_name: &str,
+        columns: Option<&[ColumnName]>,
+        rows: &[Vec<Expression>],
+    ) -> anyhow::Result<PhysicalPlan> {
+        let (resolved_name, table) = self.lookup_table(table_name)?;
+        let schema = &table.schema;
+
+        // For each schema column, its position in the statement's rows
+        let positions: Vec<usize> = match columns {
+            None => (0..schema.columns.len()).collect(),
+            Some(columns) => {
+                let mut targets = Vec::with_capacity(columns.len());
+                for name in columns {
+                    let resolved = self.resolve_column(&name.name, name.quoted, schema)?;
+                    let Some(index) = schema.columns.iter().position(|c| c.name == resolved) else {
+                        anyhow::bail!("Column '{}' not found in table '{}'", name.name, table_name);
+                    };
+                    if targets.contains(&index) {
+                        anyhow::bail!("Column '{}' specified more than once", resolved);
+                    }
+                    targets.push(index);
+                }
+                let mut positions = Vec::with_capacity(schema.columns.len());
+                for (index, column) in schema.columns.iter().enumerate() {
+                    let Some(position) = targets.iter().position(|&t| t == index) else {
+                        anyhow::bail!("No value given for column '{}'", column.name);
+                    };
+                    positions.push(position);
+                }
+                positions
             }
-        } else {
-            anyhow::bail!("SELECT without FROM not yet supported");
         };
 
-        if let Some(where_expr) = &select.where_clause {
-            plan = PhysicalPlan::Filter {
-                predicate: where_expr.clone(),
-                input: Box::new(plan),
+        let expected = columns.map_or(schema.columns.len(), <[ColumnName]>::len);
+        let mut ordered = Vec::with_capacity(rows.len());
+        for row in rows {
+            if row.len() != expected {
+                anyhow::bail!("INSERT has {} values but expects {}", row.len(), expected);
+            }
+            ordered.push(positions.iter().map(|&p| row[p].clone()).collect());
+        }
+
+        Ok(PhysicalPlan::Insert {
+            table_name: resolved_name,
+            file_id: table.file_id,
+            schema: schema.clone(),
+            rows: ordered,
+        })
+    }
+
+    // Columns are named column1, column2, ... and typed from their non-NULL
+    // literals, which must agree; varchars take the longest width.
+    fn plan_values(rows: &[Vec<Expression>]) -> anyhow::Result<PhysicalPlan> {
+        let width = rows.first().map_or(0, Vec::len);
+        let mut columns: Vec<Column> = (1..=width)
+            .map(|i| Column {
+                name: format!("column{}", i),
+                data_type: DataType::Null,
+                nullable: false,
+            })
+            .collect();
+
+        let mut values = Vec::with_capacity(rows.len());
+        for row in rows {
+            if row.len() != width {
+                anyhow::bail!(
+                    "VALUES lists must all be the same length: expected {}, got {}",
+                    width,
+                    row.len()
+                );
+            }
+            let mut out = Vec::with_capacity(width);
+            for (expr, column) in row.iter().zip(columns.iter_mut()) {
+                let Expression::Literal { value } = expr else {
+                    anyhow::bail!("VALUES only supports literals, got {}", expr);
+                };
+                column.data_type = match (&column.data_type, value.data_type()) {
+                    (_, DataType::Null) => {
+                        column.nullable = true;
+                        column.data_type.clone()
+                    }
+                    (DataType::Null, ty) => ty,
+                    (DataType::Varchar(l), DataType::Varchar(r)) => DataType::Varchar(*l.max(&r)),
+                    (l, r) if *l == r => r,
+                    (l, r) => anyhow::bail!(
+                        "VALUES types {:?} and {:?} cannot be matched in {}",
+                        l,
+                        r,
+                        column.name
+                    ),
+                };
+                out.push(value.clone());
+            }
+            values.push(out);
+        }
+
+        Ok(PhysicalPlan::Values {
+            schema: Schema::new(columns),
+            rows: values,
+        })
+    }
+
+    // The new table's schema is only known once the query has been type
+    // checked, so the table is created when the plan runs.
+    fn plan_select_into(
+        &self,
+        table: &str,
+        select: &SelectStatement,
+    ) -> anyhow::Result<PhysicalPlan> {
+        if self.catalog.table(table).is_ok() {
+            return Err(CatalogError::TableExists(table.to_string()).into());
+        }
+        let query = SelectStatement {
+            into: None,
+            ..select.clone()
+        };
+        Ok(PhysicalPlan::SelectInto {
+            table_name: table.to_string(),
+            input: Box::new(self.plan_select(&query)?),
+        })
+    }
+
+    // Joins the tables left to right. Their columns are named table.column, or
+    // alias.column for an aliased table, so that columns of the same name in
+    // different tables stay apart.
+    fn plan_joins(
+        &self,
+        table_name: &str,
+        alias: Option<&str>,
+        joins: &[Join],
+    ) -> anyhow::Result<(PhysicalPlan, Schema)> {
+        let qualifier = alias.unwrap_or(table_name);
+        let (mut plan, mut schema) = self.qualified_scan(table_name, qualifier)?;
+        let mut qualifiers = vec![qualifier];
+        for join in joins {
+            let qualifier = join.alias.as_deref().unwrap_or(&join.table);
+            if qualifiers.iter().any(|q| q.eq_ignore_ascii_case(qualifier)) {
+                anyhow::bail!("Table '{}' appears more than once in FROM", qualifier);
+            }
+            qualifiers.push(qualifier);
+            let (right, right_schema) = self.qualified_scan(&join.table, qualifier)?;
+            schema.columns.extend(right_schema.columns);
+            let mut condition = join.condition.clone();
+            self.resolve_columns(&mut condition, &schema)?;
+            if condition.contains_aggregate() {
+                anyhow::bail!("Aggregate functions are not allowed in JOIN conditions");
+            }
+            plan = PhysicalPlan::NestedLoopJoin {
+                left: Box::new(plan),
+                right: Box::new(right),
+                condition,
             };
         }
+        Ok((plan, schema))
+    }
+
+    fn qualified_scan(
+        &self,
+        table_name: &str,
+        qualifier: &str,
+    ) -> anyhow::Result<(PhysicalPlan, Schema)> {
+        let (resolved_name, table) = self.lookup_table(table_name)?;
+        let columns = table
+            .schema
+            .columns
+            .iter()
+            .map(|col| Column {
+                name: format!("{}.{}", qualifier, col.name),
+                ..col.clone()
+            })
+            .collect();
+        let schema = Schema::new(columns);
+        let plan = PhysicalPlan::SeqScan {
+            table_name: resolved_name,
+            file_id: table.file_id,
+            schema: schema.clone(),
+        };
+        Ok((plan, schema))
+    }
+
+    fn plan_table_function(&self, function: &Expression) -> anyhow::Result<(PhysicalPlan, Schema)> {
+        let Expression::Function { name, args } = function else {
+            anyhow::bail!("Expected a table function, got {}", function);
+        };
+        if !name.eq_ignore_ascii_case("generate_series") {
+            anyhow::bail!("Unknown table function: {}", name);
+        }
+        let mut args = args.clone();
+        match args.len() {
+            2 => args.push(Expression::integer(1)),
+            3 => {}
+            n => anyhow::bail!("GENERATE_SERIES takes 2 or 3 arguments, got {}", n),
+        }
+        if args.iter().any(Expression::contains_aggregate) {
+            anyhow::bail!("Aggregate functions are not allowed in GENERATE_SERIES");
+        }
+        let step = args.pop().unwrap();
+        let stop = args.pop().unwrap();
+        let start = args.pop().unwrap();
+        if step == Expression::integer(0) {
+            anyhow::bail!("GENERATE_SERIES step cannot be zero");
+        }
+        let plan = PhysicalPlan::GenerateSeries { start, stop, step };
+        Ok((plan, generate_series_schema()))
+    }
 
-        if !select
+    fn plan_select(&self, select: &SelectStatement) -> anyhow::Result<PhysicalPlan> {
+        let (mut plan, schema) = if let Some(table_name) = &select.from
+            && !select.joins.is_empty()
+        {
+            self.plan_joins(table_name, select.from_alias.as_deref(), &select.joins)?
+        } else if let Some(table_name) = &select.from
+            && let Some(alias) = &select.from_alias
+        {
+            self.qualified_scan(table_name, alias)?
+        } else if let Some(table_name) = &select.from {
+            let (resolved_name, table) = self.lookup_table(table_name)?;
+            let schema = table.schema.clone();
+            let plan = PhysicalPlan::SeqScan {
+                table_name: resolved_name,
+                file_id: table.file_id,
+                schema: schema.clone(),
+            };
+            (plan, schema)
+        } else if !select.joins.is_empty() {
+            anyhow::bail!("JOIN requires a table in FROM");
+        } else if let Some(function) = &select.table_function {
+            self.plan_table_function(function)?
+        } else {
+            anyhow::bail!("SELECT without FROM not yet supported");
+        };
+
+        let mut predicate = select.where_clause.clone();
+        let mut aliases = Vec::new();
+        let mut projection = if !select
             .select_list
             .iter()
             .any(|item| matches!(item, crate::query::ast::SelectItem::Wildcard))
@@ -84,65 +955,423 @@ impl QueryPlanner {
                 .select_list
                 .iter()
                 .map(|item| match item {
-                    crate::query::ast::SelectItem::Expression { expr, .. } => Ok(expr.clone()),
+                    crate::query::ast::SelectItem::Expression { expr, alias } => {
+                        aliases.push(alias.clone());
+                        Ok(expr.clone())
+                    }
                     crate::query::ast::SelectItem::Wildcard => {
                         anyhow::bail!("Wildcard not supported in projection")
                     }
                 })
                 .collect();
+            Some(exprs?)
+        } else {
+            None
+        };
+        if aliases.iter().all(Option::is_none) {
+            aliases.clear();
+        }
 
+        let mut order_by = select.order_by.clone();
+        // A sort key naming a select-list alias sorts by the aliased
+        // expression; the alias wins over a column of the same name
+        if let Some(exprs) = &projection {
+            let case_insensitive = self.config.case_insensitive_identifiers;
+            for (key, _) in order_by.iter_mut() {
+                let Expression::Column { name, quoted } = key else {
+                    continue;
+                };
+                // A quoted key names the alias exactly
+                let fold = case_insensitive && !*quoted;
+                let aliased = aliases.iter().position(|alias| {
+                    alias.as_deref().is_some_and(|alias| {
+                        alias == name || (fold && alias.eq_ignore_ascii_case(name))
+                    })
+                });
+                if let Some(i) = aliased {
+                    *key = exprs[i].clone();
+                }
+            }
+        }
+        // ORDER BY n sorts by the n-th output column, counting from 1
+        for (key, _) in order_by.iter_mut() {
+            let Expression::Literal {
+                value: Value::Integer(position),
+            } = *key
+            else {
+                continue;
+            };
+            let width = projection.as_ref().map_or(schema.columns.len(), Vec::len);
+            let index = match usize::try_from(position) {
+                Ok(n) if (1..=width).contains(&n) => n - 1,
+                _ => anyhow::bail!(
+                    "ORDER BY position {} is not in the select list (1..{})",
+                    position,
+                    width
+                ),
+            };
+            *key = match &projection {
+                Some(exprs) => exprs[index].clone(),
+                None => Expression::column(&schema.columns[index].name),
+            };
+        }
+        let mut group_by = select.group_by.clone();
+        let mut having = select.having.clone();
+        let filtered = usize::from(predicate.is_some());
+        let mut targets: Vec<&mut Expression> = predicate.iter_mut().collect();
+        targets.extend(group_by.iter_mut());
+        targets.extend(having.iter_mut());
+        if let Some(exprs) = projection.as_mut() {
+            targets.extend(exprs.iter_mut());
+        }
+        targets.extend(order_by.iter_mut().map(|(expr, _)| expr));
+        for expr in targets.iter_mut() {
+            self.resolve_columns(expr, &schema)?;
+        }
+        // Computed columns would be added below the aggregation, where the
+        // expressions above it cannot read them. They are computed above the
+        // filter, so that only rows the WHERE clause keeps are evaluated, and
+        // the predicate is left as written.
+        let grouped = !select.group_by.is_empty()
+            || select.having.is_some()
+            || targets.iter().any(|expr| expr.contains_aggregate());
+        let common = if grouped {
+            Vec::new()
+        } else {
+            self.eliminate_common_subexpressions(&mut targets[filtered..])
+        };
+
+        if predicate.as_ref().is_some_and(Self::uses_row_number)
+            || having.as_ref().is_some_and(Self::uses_row_number)
+            || order_by.iter().any(|(expr, _)| Self::uses_row_number(expr))
+        {
+            anyhow::bail!("ROW_NUMBER() is only allowed in the select list");
+        }
+        if predicate
+            .as_ref()
+            .is_some_and(Expression::contains_aggregate)
+        {
+            anyhow::bail!("Aggregate functions are not allowed in WHERE");
+        }
+        if let Some(predicate) = predicate {
+            plan = PhysicalPlan::Filter {
+                predicate,
+                input: Box::new(plan),
+            };
+        }
+        if !common.is_empty() {
+            plan = PhysicalPlan::Compute {
+                exprs: common,
+                input: Box::new(plan),
+            };
+            // Keep the computed columns out of a SELECT * result
+            projection.get_or_insert_with(|| {
+                schema
+                    .columns
+                    .iter()
+                    .map(|col| Expression::column(&col.name))
+                    .collect()
+            });
+        }
+
+        if grouped {
+            plan =
+                Self::plan_aggregate(plan, group_by, having, projection.as_mut(), &mut order_by)?;
+        }
+
+        // Sorting below the projection lets the keys use columns that are
+        // not selected
+        if !order_by.is_empty() {
+            plan = PhysicalPlan::Sort {
+                keys: order_by,
+                input: Box::new(plan),
+            };
+        }
+
+        // Row numbers follow output order, so they are assigned to the rows
+        // that pass the filter; a LIMIT above only truncates them.
+        if let Some(exprs) = projection
+            .as_mut()
+            .filter(|e| e.iter().any(Self::uses_row_number))
+        {
+            for expr in exprs.iter_mut() {
+                *expr = std::mem::replace(expr, Expression::integer(0)).map(&mut |node| {
+                    if Self::is_row_number(&node) {
+                        Expression::column(ROW_NUMBER_COLUMN)
+                    } else {
+                        node
+                    }
+                });
+            }
+            plan = PhysicalPlan::RowNumber {
+                input: Box::new(plan),
+            };
+        }
+
+        if let Some(exprs) = projection {
             plan = PhysicalPlan::Projection {
-                exprs: exprs?,
+                exprs,
+                aliases,
                 input: Box::new(plan),
             };
         }
 
-        if let Some(limit) = select.limit {
-            plan = PhysicalPlan::Limit {
-                limit,
+        // Deduplicating the sorted rows keeps each row's first position
+        if select.distinct {
+            plan = PhysicalPlan::Distinct {
                 input: Box::new(plan),
             };
         }
 
+        match select.limit {
+            Some(percent) if select.limit_percent => {
+                plan = PhysicalPlan::LimitPercent {
+                    percent,
+                    input: Box::new(plan),
+                };
+            }
+            Some(limit) => {
+                plan = PhysicalPlan::Limit {
+                    limit,
+                    input: Box::new(plan),
+                };
+            }
+            None => {}
+        }
+
         Ok(plan)
     }
 
-    fn get_table_schema(&self, _table_name: &str) -> anyhow::Result<Schema> {
-        // TODO: Implement proper catalog lookup
-        // For now, return a simple test schema
-        use crate::query::types::{Column, DataType};
+    // Adds the Aggregate node, and the HAVING filter over its groups, above
+    // `plan`. Everything evaluated above the aggregation reads its output, so
+    // those expressions are rewritten to read group keys from their columns.
+    fn plan_aggregate(
+        plan: PhysicalPlan,
+        group_by: Vec<Expression>,
+        mut having: Option<Expression>,
+        projection: Option<&mut Vec<Expression>>,
+        order_by: &mut [(Expression, bool)],
+    ) -> anyhow::Result<PhysicalPlan> {
+        let Some(projection) = projection else {
+            anyhow::bail!("SELECT * cannot be combined with GROUP BY or aggregate functions");
+        };
+        if group_by.iter().any(Expression::contains_aggregate) {
+            anyhow::bail!("Aggregate functions are not allowed in GROUP BY");
+        }
 
-        let schema = Schema::new(vec![
-            Column {
-                name: "id".to_string(),
-                data_type: DataType::Integer,
-                nullable: false,
-            },
-            Column {
-                name: "name".to_string(),
-                data_type: DataType::Varchar(255),
-                nullable: true,
-            },
-        ]);
+        let mut outputs: Vec<&mut Expression> = projection.iter_mut().collect();
+        outputs.extend(having.iter_mut());
+        outputs.extend(order_by.iter_mut().map(|(expr, _)| expr));
+        let aggregates = Self::collect_aggregates(&outputs)?;
+        for expr in &outputs {
+            Self::check_grouped(expr, &group_by)?;
+        }
+        let names = group_column_names(&group_by);
+        for expr in outputs {
+            expr.walk_mut(&mut |node| {
+                if let Some(i) = group_by.iter().position(|key| key == node) {
+                    *node = Expression::column(&names[i]);
+                    return false;
+                }
+                // Aggregates are looked up by their original text
+                !matches!(node, Expression::Aggregate { .. })
+            });
+        }
+
+        let mut plan = PhysicalPlan::Aggregate {
+            aggregates,
+            group_by,
+            input: Box::new(plan),
+        };
+        if let Some(predicate) = having {
+            plan = PhysicalPlan::Filter {
+                predicate,
+                input: Box::new(plan),
+            };
+        }
+        Ok(plan)
+    }
 
-        Ok(schema)
+    // The distinct aggregate calls in `exprs`, in order of appearance.
+    fn collect_aggregates(exprs: &[&mut Expression]) -> anyhow::Result<Vec<Expression>> {
+        let mut aggregates = Vec::new();
+        let mut nested = false;
+        for expr in exprs {
+            expr.walk(&mut |node| {
+                if let Expression::Aggregate { arg, .. } = node {
+                    nested |= arg.as_ref().is_some_and(|arg| arg.contains_aggregate());
+                    if !aggregates.contains(node) {
+                        aggregates.push(node.clone());
+                    }
+                }
+            });
+        }
+        if nested {
+            anyhow::bail!("Aggregate function calls cannot be nested");
+        }
+        Ok(aggregates)
     }
-}
 
-impl Default for QueryPlanner {
-    fn default() -> Self {
-        Self::new()
+    // Fails if `expr` reads a column other than through a group key or
+    // inside an aggregate call.
+    fn check_grouped(expr: &Expression, group_by: &[Expression]) -> anyhow::Result<()> {
+        if group_by.contains(expr) {
+            return Ok(());
+        }
+        match expr {
+            Expression::Aggregate { .. } => Ok(()),
+            Expression::Column { name, .. } => anyhow::bail!(
+                "Column '{}' must appear in GROUP BY or be used in an aggregate function",
+                name
+            ),
+            _ => expr
+                .children()
+                .into_iter()
+                .try_for_each(|child| Self::check_grouped(child, group_by)),
+        }
+    }
+
+    fn is_row_number(expr: &Expression) -> bool {
+        matches!(expr, Expression::Function { name, .. } if name.eq_ignore_ascii_case("row_number"))
+    }
+
+    fn calls_function(expr: &Expression) -> bool {
+        let mut found = false;
+        expr.walk(&mut |node| found |= matches!(node, Expression::Function { .. }));
+        found
+    }
+
+    fn uses_row_number(expr: &Expression) -> bool {
+        let mut found = false;
+        expr.walk(&mut |node| found |= Self::is_row_number(node));
+        found
+    }
+
+    // Rewrites column references to the exact schema column names. Quoted
+    // names must match exactly; unquoted names match ignoring case unless
+    // that has been turned off.
+    fn resolve_columns(&self, expr: &mut Expression, schema: &Schema) -> anyhow::Result<()> {
+        let mut result = Ok(());
+        expr.walk_mut(&mut |node| {
+            if let Expression::Column { name, quoted } = node {
+                match self.resolve_column(name, *quoted, schema) {
+                    Ok(resolved) => {
+                        *name = resolved;
+                        *quoted = false;
+                    }
+                    Err(e) if result.is_ok() => result = Err(e),
+                    Err(_) => {}
+                }
+            }
+            true
+        });
+        result
+    }
+
+    fn resolve_column(&self, name: &str, quoted: bool, schema: &Schema) -> anyhow::Result<String> {
+        if quoted || schema.find_column(name).is_some() {
+            return Ok(name.to_string());
+        }
+        // An unqualified name also matches a joined table's table.column
+        let case_insensitive = self.config.case_insensitive_identifiers;
+        let same = |a: &str, b: &str| a == b || (case_insensitive && a.eq_ignore_ascii_case(b));
+        let mut matches = schema.columns.iter().filter(|col| {
+            same(&col.name, name)
+                || (!name.contains('.')
+                    && col
+                        .name
+                        .split_once('.')
+                        .is_some_and(|(_, column)| same(column, name)))
+        });
+        match (matches.next(), matches.next()) {
+            (Some(col), None) => Ok(col.name.clone()),
+            (Some(_), Some(_)) => anyhow::bail!("Column reference '{}' is ambiguous", name),
+            (None, _) => Ok(name.to_string()),
+        }
+    }
+
+    // Replaces every operator subexpression that occurs more than once across
+    // `exprs` with a reference to a computed column, and returns the distinct
+    // subexpressions in column order so each is evaluated once per row.
+    fn eliminate_common_subexpressions(&self, exprs: &mut [&mut Expression]) -> Vec<Expression> {
+        let mut counts: Vec<(Expression, usize)> = Vec::new();
+        for expr in exprs.iter() {
+            expr.walk(&mut |node| {
+                // Row numbers are assigned above the computed columns, and a
+                // registered function may not return the same value twice,
+                // so neither is cached. The planner cannot tell registered
+                // functions from built-in ones, so no call is.
+                if let Expression::BinaryOp { .. } = node
+                    && !Self::calls_function(node)
+                {
+                    match counts.iter_mut().find(|(seen, _)| seen == node) {
+                        Some((_, count)) => *count += 1,
+                        None => counts.push((node.clone(), 1)),
+                    }
+                }
+            });
+        }
+        let repeated: Vec<Expression> = counts
+            .into_iter()
+            .filter(|(_, count)| *count > 1)
+            .map(|(expr, _)| expr)
+            .collect();
+
+        let mut common = Vec::new();
+        if !repeated.is_empty() {
+            for expr in exprs.iter_mut() {
+                expr.walk_mut(&mut |node| {
+                    if !matches!(node, Expression::BinaryOp { .. }) || !repeated.contains(node) {
+                        return true;
+                    }
+                    let index = match common.iter().position(|c| c == node) {
+                        Some(index) => index,
+                        None => {
+                            common.push(node.clone());
+                            common.len() - 1
+                        }
+                    };
+                    *node = Expression::column(&format!("{}{}", COMPUTED_COLUMN_PREFIX, index));
+                    false
+                });
+            }
+        }
+        common
+    }
+
+    // Table names follow the same case rules as column names: an exact match
+    // wins, otherwise a single case-insensitive match when enabled.
+    // The table `name` refers to, and the name the catalog has it under,
+    // which plans record so they can be checked against the catalog later
+    fn lookup_table(&self, name: &str) -> anyhow::Result<(String, &'a TableInfo)> {
+        let catalog = self.catalog;
+        match catalog.table(name) {
+            Ok(table) => Ok((name.to_string(), table)),
+            Err(err) if !self.config.case_insensitive_identifiers => Err(err.into()),
+            Err(err) => {
+                let mut matches = catalog
+                    .tables()
+                    .filter(|(candidate, _)| candidate.eq_ignore_ascii_case(name));
+                match (matches.next(), matches.next()) {
+                    (Some((candidate, table)), None) => Ok((candidate.to_string(), table)),
+                    (None, _) => Err(err.into()),
+                    (Some(_), Some(_)) => anyhow::bail!("table name '{}' is ambiguous", name),
+                }
+            }
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::catalog::users_catalog;
     use crate::query::ast::SelectStatement;
 
     #[test]
     fn test_simple_select_planning() {
-        let planner = QueryPlanner::new();
+        let catalog = users_catalog();
+        let planner = QueryPlanner::new(&catalog);
         let select = SelectStatement::select_all_from("users");
         let stmt = Statement::Select(select);
 
@@ -155,4 +1384,143 @@ mod tests {
             _ => panic!("Expected SeqScan plan"),
         }
     }
+
+    #[test]
+    fn test_repeated_subexpression_computed_once() {
+        let catalog = users_catalog();
+        let planner = QueryPlanner::new(&catalog);
+        let stmt =
+            crate::query::parser::parse_sql("SELECT id + 1, name FROM users WHERE id + 1 > 3")
+                .unwrap();
+
+        let plan = planner.plan(&stmt).unwrap();
+
+        // The predicate keeps its own copy, since only rows that pass the
+        // filter are computed
+        let id_plus_one = Expression::add(Expression::column("id"), Expression::integer(1));
+        let PhysicalPlan::Projection { exprs, input, .. } = plan else {
+            panic!("Expected Projection plan");
+        };
+        assert_eq!(exprs, vec![id_plus_one.clone(), Expression::column("name")]);
+        assert!(matches!(*input, PhysicalPlan::Filter { .. }));
+
+        let stmt = crate::query::parser::parse_sql(
+            "SELECT id + 1, (id + 1) * 2 FROM users WHERE id + 1 > 3",
+        )
+        .unwrap();
+        let plan = planner.plan(&stmt).unwrap();
+
+        let computed = Expression::column("#cse0");
+        let PhysicalPlan::Projection { exprs, input, .. } = plan else {
+            panic!("Expected Projection plan");
+        };
+        assert_eq!(
+            exprs,
+            vec![
+                computed.clone(),
+                Expression::BinaryOp {
+                    left: Box::new(computed),
+                    op: crate::query::ast::BinaryOperator::Mul,
+                    right: Box::new(Expression::integer(2)),
+                }
+            ]
+        );
+        let PhysicalPlan::Compute { exprs, input } = *input else {
+            panic!("Expected Compute plan");
+        };
+        assert_eq!(exprs, vec![id_plus_one.clone()]);
+        let PhysicalPlan::Filter { predicate, input } = *input else {
+            panic!("Expected Filter plan");
+        };
+        assert_eq!(
+            predicate,
+            Expression::BinaryOp {
+                left: Box::new(id_plus_one),
+                op: crate::query::ast::BinaryOperator::Gt,
+                right: Box::new(Expression::integer(3)),
+            }
+        );
+        assert!(matches!(*input, PhysicalPlan::SeqScan { .. }));
+    }
+
+    #[test]
+    fn test_distinct_subexpressions_not_computed() {
+        let catalog = users_catalog();
+        let planner = QueryPlanner::new(&catalog);
+        let stmt =
+            crate::query::parser::parse_sql("SELECT * FROM users WHERE id + 1 > id * 2").unwrap();
+
+        let plan = planner.plan(&stmt).unwrap();
+
+        let PhysicalPlan::Filter { input, .. } = plan else {
+            panic!("Expected Filter plan");
+        };
+        assert!(matches!(*input, PhysicalPlan::SeqScan { .. }));
+    }
+
+    #[test]
+    fn test_plan_walk_and_map_expressions() {
+        let catalog = users_catalog();
+        let planner = QueryPlanner::new(&catalog);
+        let stmt =
+            crate::query::parser::parse_sql("SELECT name FROM users WHERE id > 1 LIMIT 2").unwrap();
+        let plan = planner.plan(&stmt).unwrap();
+
+        let mut nodes = 0;
+        plan.walk(&mut |_| nodes += 1);
+        assert_eq!(nodes, 4);
+
+        let plan = plan.map_expressions(&mut |expr| match expr {
+            Expression::Column { name, .. } => Expression::column(&name.to_uppercase()),
+            other => other,
+        });
+        let PhysicalPlan::Limit { input, .. } = plan else {
+            panic!("Expected Limit plan");
+        };
+        let PhysicalPlan::Projection { exprs, input, .. } = *input else {
+            panic!("Expected Projection plan");
+        };
+        assert_eq!(exprs, vec![Expression::column("NAME")]);
+        let PhysicalPlan::Filter { predicate, .. } = *input else {
+            panic!("Expected Filter plan");
+        };
+        let mut columns = Vec::new();
+        predicate.walk(&mut |node| {
+            if let Expression::Column { name, .. } = node {
+                columns.push(name.clone());
+            }
+        });
+        assert_eq!(columns, vec!["ID".to_string()]);
+    }
+
+    #[test]
+    fn test_values_type_inference() {
+        let catalog = users_catalog();
+        let planner = QueryPlanner::new(&catalog);
+        let stmt =
+            crate::query::parser::parse_sql("VALUES (1, 'a', NULL), (NULL, 'abc', true)").unwrap();
+
+        let PhysicalPlan::Values { schema, rows } = planner.plan(&stmt).unwrap() else {
+            panic!("Expected Values plan");
+        };
+        let types: Vec<_> = schema
+            .columns
+            .iter()
+            .map(|col| (col.name.as_str(), col.data_type.clone(), col.nullable))
+            .collect();
+        assert_eq!(
+            types,
+            vec![
+                ("column1", DataType::Integer, true),
+                ("column2", DataType::Varchar(3), false),
+                ("column3", DataType::Boolean, true),
+            ]
+        );
+        assert_eq!(rows.len(), 2);
+
+        for sql in ["VALUES (1), (1, 2)", "VALUES (1), ('a')", "VALUES (1 + 1)"] {
+            let stmt = crate::query::parser::parse_sql(sql).unwrap();
+            assert!(planner.plan(&stmt).is_err(), "{}", sql);
+        }
+    }
 }
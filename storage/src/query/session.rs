@@ -0,0 +1,17 @@
+// Per-connection settings shared by planning and execution.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SessionConfig {
+    // Unquoted identifiers match columns regardless of case
+    pub case_insensitive_identifiers: bool,
+    // Results are cut off after this many rows
+    pub max_rows: Option<u32>,
+}
+
+impl Default for SessionConfig {
+    fn default() -> Self {
+        Self {
+            case_insensitive_identifiers: true,
+            max_rows: None,
+        }
+    }
+}
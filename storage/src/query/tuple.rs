@@ -0,0 +1,387 @@
+use crate::heap::toast::ToastPointer;
+use crate::query::types::{DataType, Row, Schema, Value};
+
+// Tuple layout: boolean columns are packed into a bitmap at the front of the
+// tuple (bit i, least significant first, holds the i-th boolean column),
+// followed by the remaining columns in schema order. Integers are 4 bytes
+// little-endian and varchars a u32 little-endian length plus UTF-8 bytes.
+// Schemas without boolean columns have no bitmap. In a fixed-width schema
+// (no varchars) each column therefore has a constant offset, and a single
+// column can be read without decoding the ones before it.
+//
+// A varchar stored out of line has TOASTED in place of its length, followed
+// by its ToastPointer as a u32 page number and u32 byte length. A NULL in a
+// nullable varchar column is just NULL_VARCHAR in place of the length.
+
+const TOASTED: u32 = 1 << 31;
+const NULL_VARCHAR: u32 = u32::MAX;
+
+fn bitmap_len(schema: &Schema) -> usize {
+    let booleans = schema
+        .columns
+        .iter()
+        .filter(|c| c.data_type == DataType::Boolean)
+        .count();
+    booleans.div_ceil(8)
+}
+
+pub fn serialize_row(row: &[Value], schema: &Schema) -> anyhow::Result<Vec<u8>> {
+    serialize_row_with(row, schema, &mut |_| Ok(None))
+}
+
+// Like `serialize_row`, but offers each varchar to `toast`, which may store
+// it elsewhere and return a pointer to keep in the tuple instead.
+pub fn serialize_row_with(
+    row: &[Value],
+    schema: &Schema,
+    toast: &mut dyn FnMut(&str) -> anyhow::Result<Option<ToastPointer>>,
+) -> anyhow::Result<Vec<u8>> {
+    if row.len() != schema.columns.len() {
+        anyhow::bail!(
+            "Row has {} values but schema has {} columns",
+            row.len(),
+            schema.columns.len()
+        );
+    }
+
+    let mut data = vec![0u8; bitmap_len(schema)];
+    let mut bit = 0;
+    for (value, column) in row.iter().zip(&schema.columns) {
+        match (&column.data_type, value) {
+            (DataType::Boolean, Value::Boolean(b)) => {
+                if *b {
+                    data[bit / 8] |= 1 << (bit % 8);
+                }
+                bit += 1;
+            }
+            (DataType::Varchar(_), Value::Varchar(s)) => match toast(s)? {
+                Some(pointer) => {
+                    data.extend_from_slice(&TOASTED.to_le_bytes());
+                    data.extend_from_slice(&pointer.page_no.to_le_bytes());
+                    data.extend_from_slice(&pointer.len.to_le_bytes());
+                }
+                None => data.extend_from_slice(&value.to_bytes()),
+            },
+            (DataType::Varchar(_), Value::Null) if column.nullable => {
+                data.extend_from_slice(&NULL_VARCHAR.to_le_bytes());
+            }
+            (DataType::Integer, Value::Integer(_)) => {
+                data.extend_from_slice(&value.to_bytes());
+            }
+            (DataType::Null, Value::Null) => {}
+            _ => anyhow::bail!(
+                "Cannot store {:?} in column '{}' of type {:?}",
+                value,
+                column.name,
+                column.data_type
+            ),
+        }
+    }
+    Ok(data)
+}
+
+pub fn deserialize_row(data: &[u8], schema: &Schema) -> anyhow::Result<Row> {
+    deserialize_row_with(data, schema, &|pointer| {
+        anyhow::bail!(
+            "varchar stored out of line at overflow page {} cannot be read here",
+            pointer.page_no
+        )
+    })
+}
+
+// Like `deserialize_row`, but reads varchars stored out of line through
+// `detoast`.
+pub fn deserialize_row_with(
+    data: &[u8],
+    schema: &Schema,
+    detoast: &dyn Fn(ToastPointer) -> anyhow::Result<Vec<u8>>,
+) -> anyhow::Result<Row> {
+    let mut row = Vec::new();
+    let mut offset = bitmap_len(schema);
+    if offset > data.len() {
+        anyhow::bail!("Not enough data for boolean bitmap");
+    }
+    let bitmap = &data[..offset];
+    let mut bit = 0;
+
+    for column in &schema.columns {
+        let value = match &column.data_type {
+            DataType::Integer => {
+                if offset + 4 > data.len() {
+                    anyhow::bail!("Not enough data for integer column");
+                }
+                let bytes = &data[offset..offset + 4];
+                let val = i32::from_le_bytes(bytes.try_into()?);
+                offset += 4;
+                Value::Integer(val)
+            }
+            DataType::Varchar(_) => {
+                if offset + 4 > data.len() {
+                    anyhow::bail!("Not enough data for varchar length");
+                }
+                let len_bytes = &data[offset..offset + 4];
+                let len = u32::from_le_bytes(len_bytes.try_into()?) as usize;
+                offset += 4;
+
+                if len == NULL_VARCHAR as usize {
+                    row.push(Value::Null);
+                    continue;
+                }
+                let bytes = if len == TOASTED as usize {
+                    let pointer = data
+                        .get(offset..offset + 8)
+                        .ok_or_else(|| anyhow::anyhow!("Not enough data for varchar pointer"))?;
+                    offset += 8;
+                    detoast(ToastPointer {
+                        page_no: u32::from_le_bytes(pointer[..4].try_into()?),
+                        len: u32::from_le_bytes(pointer[4..].try_into()?),
+                    })?
+                } else {
+                    if offset + len > data.len() {
+                        anyhow::bail!("Not enough data for varchar content");
+                    }
+                    let string_bytes = data[offset..offset + len].to_vec();
+                    offset += len;
+                    string_bytes
+                };
+                Value::Varchar(String::from_utf8(bytes)?)
+            }
+            DataType::Boolean => {
+                let val = bitmap[bit / 8] & (1 << (bit % 8)) != 0;
+                bit += 1;
+                Value::Boolean(val)
+            }
+            DataType::Null => Value::Null,
+        };
+        row.push(value);
+    }
+
+    Ok(row)
+}
+
+// The out-of-line values `data` points to.
+pub fn toast_pointers(data: &[u8], schema: &Schema) -> anyhow::Result<Vec<ToastPointer>> {
+    let pointers = std::cell::RefCell::new(Vec::new());
+    deserialize_row_with(data, schema, &|pointer| {
+        pointers.borrow_mut().push(pointer);
+        Ok(Vec::new())
+    })?;
+    Ok(pointers.into_inner())
+}
+
+// Offset of the column at `index` in every tuple of a fixed-width schema.
+// Booleans live in the bitmap and have no byte offset of their own.
+fn fixed_column_offset(schema: &Schema, index: usize) -> Option<usize> {
+    if !schema.is_fixed_width() || schema.columns[index].data_type != DataType::Integer {
+        return None;
+    }
+    let integers_before = schema.columns[..index]
+        .iter()
+        .filter(|c| c.data_type == DataType::Integer)
+        .count();
+    Some(bitmap_len(schema) + 4 * integers_before)
+}
+
+// Reads one column of a stored tuple. Integers in fixed-width schemas are
+// read in place at their offset; anything else decodes the row.
+pub fn read_column(data: &[u8], schema: &Schema, index: usize) -> anyhow::Result<Value> {
+    if index >= schema.columns.len() {
+        anyhow::bail!(
+            "Column {} out of range for {} columns",
+            index,
+            schema.columns.len()
+        );
+    }
+    if let Some(offset) = fixed_column_offset(schema, index) {
+        let bytes = data
+            .get(offset..offset + 4)
+            .ok_or_else(|| anyhow::anyhow!("Not enough data for integer column"))?;
+        return Ok(Value::Integer(i32::from_le_bytes(bytes.try_into()?)));
+    }
+    if schema.columns[index].data_type == DataType::Boolean {
+        let bit = schema.columns[..index]
+            .iter()
+            .filter(|c| c.data_type == DataType::Boolean)
+            .count();
+        let byte = data
+            .get(bit / 8)
+            .ok_or_else(|| anyhow::anyhow!("Not enough data for boolean bitmap"))?;
+        return Ok(Value::Boolean(byte & (1 << (bit % 8)) != 0));
+    }
+    Ok(deserialize_row(data, schema)?.swap_remove(index))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::query::types::Column;
+
+    fn column(name: &str, data_type: DataType) -> Column {
+        Column {
+            name: name.to_string(),
+            data_type,
+            nullable: false,
+        }
+    }
+
+    #[test]
+    fn round_trip_mixed_row() -> anyhow::Result<()> {
+        let schema = Schema::new(vec![
+            column("id", DataType::Integer),
+            column("active", DataType::Boolean),
+            column("name", DataType::Varchar(255)),
+            column("admin", DataType::Boolean),
+        ]);
+        let row = vec![
+            Value::Integer(7),
+            Value::Boolean(true),
+            Value::Varchar("user_7".to_string()),
+            Value::Boolean(false),
+        ];
+
+        let data = serialize_row(&row, &schema)?;
+        assert_eq!(data.len(), 1 + 4 + 4 + 6);
+        assert_eq!(deserialize_row(&data, &schema)?, row);
+        Ok(())
+    }
+
+    #[test]
+    fn null_only_in_nullable_varchar() -> anyhow::Result<()> {
+        let mut nickname = column("nickname", DataType::Varchar(255));
+        nickname.nullable = true;
+        let schema = Schema::new(vec![column("id", DataType::Integer), nickname]);
+        let row = vec![Value::Integer(1), Value::Null];
+
+        let data = serialize_row(&row, &schema)?;
+        assert_eq!(data.len(), 4 + 4);
+        assert_eq!(deserialize_row(&data, &schema)?, row);
+
+        let strict = Schema::new(vec![column("name", DataType::Varchar(255))]);
+        assert!(serialize_row(&[Value::Null], &strict).is_err());
+        let flag = Schema::new(vec![column("on", DataType::Boolean)]);
+        assert!(serialize_row(&[Value::Null], &flag).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn toasted_varchar_keeps_a_pointer() -> anyhow::Result<()> {
+        let schema = Schema::new(vec![
+            column("id", DataType::Integer),
+            column("body", DataType::Varchar(10_000)),
+        ]);
+        let long = "z".repeat(5000);
+        let row = vec![Value::Integer(1), Value::Varchar(long.clone())];
+
+        let data = serialize_row_with(&row, &schema, &mut |s| {
+            Ok((s.len() > 100).then_some(ToastPointer {
+                page_no: 7,
+                len: s.len() as u32,
+            }))
+        })?;
+        assert_eq!(data.len(), 4 + 4 + 8);
+        assert!(deserialize_row(&data, &schema).is_err());
+
+        let read = deserialize_row_with(&data, &schema, &|pointer| {
+            assert_eq!(
+                pointer,
+                ToastPointer {
+                    page_no: 7,
+                    len: 5000
+                }
+            );
+            Ok(long.clone().into_bytes())
+        })?;
+        assert_eq!(read, row);
+        assert_eq!(
+            toast_pointers(&data, &schema)?,
+            vec![ToastPointer {
+                page_no: 7,
+                len: 5000
+            }]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn booleans_are_packed_into_bits() -> anyhow::Result<()> {
+        let columns = (0..20)
+            .map(|i| column(&format!("flag{}", i), DataType::Boolean))
+            .collect();
+        let schema = Schema::new(columns);
+        let row: Row = (0..20).map(|i| Value::Boolean(i % 3 == 0)).collect();
+
+        let data = serialize_row(&row, &schema)?;
+        assert_eq!(data.len(), 3);
+        assert!(data.len() < row.len());
+        assert_eq!(deserialize_row(&data, &schema)?, row);
+        Ok(())
+    }
+
+    #[test]
+    fn layout_without_booleans_is_unchanged() -> anyhow::Result<()> {
+        let schema = Schema::new(vec![
+            column("id", DataType::Integer),
+            column("name", DataType::Varchar(255)),
+        ]);
+        let row = vec![Value::Integer(1), Value::Varchar("a".to_string())];
+
+        let mut expected = 1i32.to_le_bytes().to_vec();
+        expected.extend_from_slice(&1u32.to_le_bytes());
+        expected.push(b'a');
+        assert_eq!(serialize_row(&row, &schema)?, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn fixed_width_columns_read_in_place() -> anyhow::Result<()> {
+        let schema = Schema::new(vec![
+            column("a", DataType::Integer),
+            column("flag", DataType::Boolean),
+            column("b", DataType::Integer),
+            column("c", DataType::Integer),
+        ]);
+        assert!(schema.is_fixed_width());
+        let row = vec![
+            Value::Integer(-1),
+            Value::Boolean(true),
+            Value::Integer(2),
+            Value::Integer(3),
+        ];
+
+        let data = serialize_row(&row, &schema)?;
+        assert_eq!(data.len(), 1 + 3 * 4);
+        assert_eq!(deserialize_row(&data, &schema)?, row);
+
+        assert_eq!(fixed_column_offset(&schema, 2), Some(5));
+        assert_eq!(&data[9..13], &3i32.to_le_bytes());
+        for (i, value) in row.iter().enumerate() {
+            assert_eq!(&read_column(&data, &schema, i)?, value);
+        }
+        assert!(read_column(&data, &schema, 4).is_err());
+        assert!(read_column(&data[..8], &schema, 2).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn variable_width_columns_fall_back_to_decoding() -> anyhow::Result<()> {
+        let schema = Schema::new(vec![
+            column("name", DataType::Varchar(255)),
+            column("id", DataType::Integer),
+        ]);
+        assert!(!schema.is_fixed_width());
+        assert_eq!(fixed_column_offset(&schema, 1), None);
+
+        let row = vec![Value::Varchar("abc".to_string()), Value::Integer(9)];
+        let data = serialize_row(&row, &schema)?;
+        assert_eq!(read_column(&data, &schema, 1)?, Value::Integer(9));
+        Ok(())
+    }
+
+    #[test]
+    fn mismatched_values_are_rejected() {
+        let schema = Schema::new(vec![column("active", DataType::Boolean)]);
+        assert!(serialize_row(&[Value::Integer(1)], &schema).is_err());
+        assert!(serialize_row(&[], &schema).is_err());
+        assert!(deserialize_row(&[], &schema).is_err());
+    }
+}
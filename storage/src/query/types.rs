@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
 use std::fmt;
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -6,9 +7,13 @@ pub enum DataType {
     Integer,
     Varchar(usize),
     Boolean,
+    // The type of a bare NULL whose type cannot be inferred from context
+    Null,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+// Eq and Hash treat NULL as equal to NULL, which is what grouping and set
+// operations want; comparisons in expressions handle NULL separately.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Value {
     Integer(i32),
     Varchar(String),
@@ -22,7 +27,20 @@ impl Value {
             Value::Integer(_) => DataType::Integer,
             Value::Varchar(s) => DataType::Varchar(s.len()),
             Value::Boolean(_) => DataType::Boolean,
-            Value::Null => DataType::Varchar(0),
+            Value::Null => DataType::Null,
+        }
+    }
+
+    // Orders two non-null values of the same type. Booleans order `false`
+    // before `true`; comparing mismatched types or `Null` is an error.
+    // ORDER BY, GREATEST/LEAST and the `<`-style operators all order values
+    // this way.
+    pub fn compare(&self, other: &Value) -> anyhow::Result<Ordering> {
+        match (self, other) {
+            (Value::Integer(l), Value::Integer(r)) => Ok(l.cmp(r)),
+            (Value::Varchar(l), Value::Varchar(r)) => Ok(l.cmp(r)),
+            (Value::Boolean(l), Value::Boolean(r)) => Ok(l.cmp(r)),
+            _ => anyhow::bail!("Cannot compare {:?} and {:?}", self, other),
         }
     }
 
@@ -67,6 +85,12 @@ impl Value {
                 }
                 Ok(Value::Boolean(bytes[0] != 0))
             }
+            DataType::Null => {
+                if !bytes.is_empty() {
+                    anyhow::bail!("Invalid null length: {}", bytes.len());
+                }
+                Ok(Value::Null)
+            }
         }
     }
 }
@@ -106,6 +130,14 @@ impl Schema {
     pub fn column_index(&self, name: &str) -> Option<usize> {
         self.columns.iter().position(|c| c.name == name)
     }
+
+    // True when no column has a variable-length encoding, so every column
+    // of a stored tuple sits at the same offset in every row.
+    pub fn is_fixed_width(&self) -> bool {
+        self.columns
+            .iter()
+            .all(|c| !matches!(c.data_type, DataType::Varchar(_)))
+    }
 }
 
 pub type Row = Vec<Value>;
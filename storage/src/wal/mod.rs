@@ -0,0 +1,585 @@
+use crate::disk::disk_manager::DiskManager;
+use crate::page::{
+    constants::PAGE_SIZE,
+    page_file::Page,
+    page_id::{PageFlags, PageId},
+};
+use std::collections::{BTreeMap, HashSet, btree_map::Entry};
+use std::fs::{self, OpenOptions};
+use std::io::{Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+pub const WAL_FILE_NAME: &str = "wal.log";
+// A checkpoint writes the new log here and renames it over the old one
+const WAL_TEMP_FILE_NAME: &str = "wal.log.tmp";
+
+// Every record is framed by its body length and the body's CRC32
+const FRAME_HEADER_LEN: usize = 8;
+// The page checksum (bytes 0..4) is kept up to date by `Page::patch`, so it
+// is never logged
+const CHECKSUM_LEN: usize = 4;
+
+const KIND_CHANGES: u8 = 0;
+const KIND_IMAGE: u8 = 1;
+const KIND_CHECKPOINT: u8 = 2;
+
+// A run of bytes changed on a page, with its contents before and after.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PageChange {
+    pub offset: u16,
+    pub before: Vec<u8>,
+    pub after: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WalPayload {
+    // The byte runs one operation changed on the page
+    Changes(Vec<PageChange>),
+    // The whole page after the first change to it since the last
+    // checkpoint. Replaying starts from it, so a page torn by a crash is
+    // rebuilt rather than read.
+    Image(Box<[u8; PAGE_SIZE]>),
+    // Starts a log truncated by a checkpoint; its LSN is the last one
+    // handed out, so LSNs keep increasing across the truncation
+    Checkpoint,
+}
+
+// A redo record: what one operation did to a page, and the LSN the page
+// was stamped with afterwards.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WalRecord {
+    pub lsn: u64,
+    pub page_id: PageId,
+    pub payload: WalPayload,
+}
+
+impl WalRecord {
+    fn encode(&self) -> Vec<u8> {
+        let mut body = Vec::new();
+        body.extend_from_slice(&self.lsn.to_le_bytes());
+        body.extend_from_slice(&self.page_id.0.to_le_bytes());
+        match &self.payload {
+            WalPayload::Changes(changes) => {
+                body.push(KIND_CHANGES);
+                body.extend_from_slice(&(changes.len() as u16).to_le_bytes());
+                for change in changes {
+                    body.extend_from_slice(&change.offset.to_le_bytes());
+                    body.extend_from_slice(&(change.after.len() as u16).to_le_bytes());
+                    body.extend_from_slice(&change.before);
+                    body.extend_from_slice(&change.after);
+                }
+            }
+            WalPayload::Image(image) => {
+                body.push(KIND_IMAGE);
+                body.extend_from_slice(&image[..]);
+            }
+            WalPayload::Checkpoint => body.push(KIND_CHECKPOINT),
+        }
+
+        let mut frame = Vec::with_capacity(FRAME_HEADER_LEN + body.len());
+        frame.extend_from_slice(&(body.len() as u32).to_le_bytes());
+        frame.extend_from_slice(&crc32fast::hash(&body).to_le_bytes());
+        frame.extend_from_slice(&body);
+        frame
+    }
+
+    fn decode(mut body: &[u8]) -> Option<Self> {
+        let lsn = u64::from_le_bytes(take(&mut body, 8)?.try_into().ok()?);
+        let page_id = PageId(u64::from_le_bytes(take(&mut body, 8)?.try_into().ok()?));
+        let payload = match take(&mut body, 1)?[0] {
+            KIND_CHANGES => {
+                let count = u16::from_le_bytes(take(&mut body, 2)?.try_into().ok()?);
+                let mut changes = Vec::with_capacity(count as usize);
+                for _ in 0..count {
+                    let offset = u16::from_le_bytes(take(&mut body, 2)?.try_into().ok()?);
+                    let len = u16::from_le_bytes(take(&mut body, 2)?.try_into().ok()?) as usize;
+                    let before = take(&mut body, len)?.to_vec();
+                    let after = take(&mut body, len)?.to_vec();
+                    changes.push(PageChange {
+                        offset,
+                        before,
+                        after,
+                    });
+                }
+                WalPayload::Changes(changes)
+            }
+            KIND_IMAGE => WalPayload::Image(Box::new(take(&mut body, PAGE_SIZE)?.try_into().ok()?)),
+            KIND_CHECKPOINT => WalPayload::Checkpoint,
+            _ => return None,
+        };
+        body.is_empty().then_some(Self {
+            lsn,
+            page_id,
+            payload,
+        })
+    }
+}
+
+fn take<'a>(buf: &mut &'a [u8], n: usize) -> Option<&'a [u8]> {
+    if buf.len() < n {
+        return None;
+    }
+    let (head, tail) = buf.split_at(n);
+    *buf = tail;
+    Some(head)
+}
+
+// The complete records in the log and the length they take up. A crash can
+// leave a partly written record at the end; it and anything after it are
+// ignored.
+fn read_log(path: &Path) -> anyhow::Result<(Vec<WalRecord>, u64)> {
+    let bytes = match fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok((Vec::new(), 0)),
+        Err(e) => return Err(e.into()),
+    };
+    let mut records = Vec::new();
+    let mut rest = bytes.as_slice();
+    let mut valid_len = 0;
+    while let Some(header) = take(&mut rest, FRAME_HEADER_LEN) {
+        let len = u32::from_le_bytes(header[0..4].try_into().unwrap()) as usize;
+        let crc = u32::from_le_bytes(header[4..8].try_into().unwrap());
+        let Some(body) = take(&mut rest, len) else {
+            break;
+        };
+        if crc32fast::hash(body) != crc {
+            break;
+        }
+        let Some(record) = WalRecord::decode(body) else {
+            break;
+        };
+        records.push(record);
+        valid_len += FRAME_HEADER_LEN + len;
+    }
+    Ok((records, valid_len as u64))
+}
+
+// The records in the log of the data directory `dir`, oldest first.
+pub fn read_records(dir: impl AsRef<Path>) -> anyhow::Result<Vec<WalRecord>> {
+    Ok(read_log(&dir.as_ref().join(WAL_FILE_NAME))?.0)
+}
+
+// The runs of bytes that differ between two versions of a page
+fn diff(before: &Page, after: &Page) -> Vec<PageChange> {
+    let mut changes = Vec::new();
+    let mut offset = CHECKSUM_LEN;
+    while offset < PAGE_SIZE {
+        if before.buf[offset] == after.buf[offset] {
+            offset += 1;
+            continue;
+        }
+        let start = offset;
+        while offset < PAGE_SIZE && before.buf[offset] != after.buf[offset] {
+            offset += 1;
+        }
+        changes.push(PageChange {
+            offset: start as u16,
+            before: before.buf[start..offset].to_vec(),
+            after: after.buf[start..offset].to_vec(),
+        });
+    }
+    changes
+}
+
+// Appends redo records to the `wal.log` of a data directory. LSNs start
+// at 1 and carry on from the last record already in the log.
+pub struct WalWriter {
+    dir: PathBuf,
+    file: fs::File,
+    next_lsn: u64,
+    // Pages logged in full since the last checkpoint; later changes to them
+    // are logged as diffs
+    imaged: HashSet<PageId>,
+}
+
+impl WalWriter {
+    pub fn open(dir: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = dir.as_ref().join(WAL_FILE_NAME);
+        let (records, valid_len) = read_log(&path)?;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .read(true)
+            .write(true)
+            .open(&path)?;
+        // New records go after the last complete one, over any torn tail
+        file.set_len(valid_len)?;
+        file.seek(SeekFrom::End(0))?;
+        // Pages imaged before a crash are imaged again; the data file may
+        // not hold what the old log was replayed onto
+        Ok(Self {
+            dir: dir.as_ref().to_path_buf(),
+            file,
+            next_lsn: records.last().map_or(1, |record| record.lsn + 1),
+            imaged: HashSet::new(),
+        })
+    }
+
+    pub fn next_lsn(&self) -> u64 {
+        self.next_lsn
+    }
+
+    // Logs how `page` differs from `before`, its contents before an edit, and
+    // stamps `page` with the record's LSN. The first change to a page since
+    // the last checkpoint logs the whole stamped page instead. Nothing is
+    // logged, and None is returned, if the edit changed nothing.
+    pub fn log_page_changes(
+        &mut self,
+        before: &Page,
+        page: &mut Page,
+    ) -> anyhow::Result<Option<u64>> {
+        let changes = diff(before, page);
+        if changes.is_empty() {
+            return Ok(None);
+        }
+        let lsn = self.next_lsn;
+        let page_id = page.page_id();
+        let payload = if self.imaged.contains(&page_id) {
+            WalPayload::Changes(changes)
+        } else {
+            page.patch_lsn(lsn);
+            WalPayload::Image(Box::new(page.buf))
+        };
+        let record = WalRecord {
+            lsn,
+            page_id,
+            payload,
+        };
+        self.file.write_all(&record.encode())?;
+        self.next_lsn += 1;
+        self.imaged.insert(page_id);
+        page.patch_lsn(lsn);
+        Ok(Some(lsn))
+    }
+
+    // Forces the log to disk. Call it before writing a page whose changes
+    // were logged, so the data file never gets ahead of the log.
+    pub fn sync(&mut self) -> anyhow::Result<()> {
+        self.file.sync_data()?;
+        Ok(())
+    }
+
+    // Empties the log once every page it covers is durable in the data file.
+    // The new log holds a single checkpoint record and replaces the old one
+    // by a rename, so a crash part way leaves one or the other.
+    pub fn checkpoint(&mut self) -> anyhow::Result<()> {
+        let temp_path = self.dir.join(WAL_TEMP_FILE_NAME);
+        let mut file = OpenOptions::new()
+            .create(true)
+            .truncate(true)
+            .read(true)
+            .write(true)
+            .open(&temp_path)?;
+        let record = WalRecord {
+            lsn: self.next_lsn - 1,
+            page_id: PageId(0),
+            payload: WalPayload::Checkpoint,
+        };
+        file.write_all(&record.encode())?;
+        file.sync_data()?;
+        fs::rename(&temp_path, self.dir.join(WAL_FILE_NAME))?;
+        fs::File::open(&self.dir)?.sync_all()?;
+        self.file = file;
+        self.imaged.clear();
+        Ok(())
+    }
+}
+
+// Replays the log of the data directory `dir` onto the pages of `dm` and
+// returns the number of records applied. A record is only applied to a page
+// whose stored `page_lsn` is below the record's LSN, so changes that reached
+// the data file before a crash are not applied twice and recovering again
+// is harmless. The log holds a full image of every page it changes, so a
+// page torn by the crash, which cannot be read, is rebuilt from it.
+pub fn recover<D: DiskManager>(dir: impl AsRef<Path>, dm: &mut D) -> anyhow::Result<usize> {
+    // Each page touched by the log, with the LSN it had on disk; 0 if it
+    // could not be read
+    let mut pages: BTreeMap<PageId, (Page, u64)> = BTreeMap::new();
+    let mut applied = 0;
+    for record in read_records(dir)? {
+        let changes = match record.payload {
+            WalPayload::Checkpoint => continue,
+            WalPayload::Image(image) => {
+                let (page, stored_lsn) = match pages.entry(record.page_id) {
+                    Entry::Occupied(entry) => entry.into_mut(),
+                    Entry::Vacant(entry) => {
+                        let stored = dm.read_page(record.page_id).ok();
+                        let lsn = stored.as_ref().map_or(0, |page| page.header().page_lsn);
+                        let page = stored.unwrap_or(Page { buf: *image });
+                        entry.insert((page, lsn))
+                    }
+                };
+                if record.lsn > *stored_lsn {
+                    page.buf = *image;
+                    applied += 1;
+                }
+                continue;
+            }
+            WalPayload::Changes(changes) => changes,
+        };
+        let (page, stored_lsn) = match pages.entry(record.page_id) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let page = dm.read_page(record.page_id)?;
+                let lsn = page.header().page_lsn;
+                entry.insert((page, lsn))
+            }
+        };
+        if record.lsn <= *stored_lsn {
+            continue;
+        }
+        for change in &changes {
+            let start = change.offset as usize;
+            let end = start + change.before.len();
+            if start < CHECKSUM_LEN || end > PAGE_SIZE || page.buf[start..end] != change.before {
+                anyhow::bail!(
+                    "WAL record {} does not match the contents of {:?}",
+                    record.lsn,
+                    record.page_id
+                );
+            }
+            page.patch(start, &change.after);
+        }
+        page.patch_lsn(record.lsn);
+        applied += 1;
+    }
+
+    for (page, stored_lsn) in pages.values() {
+        if page.header().page_lsn != *stored_lsn {
+            dm.write_page(page)?;
+        }
+    }
+    dm.sync()?;
+    Ok(applied)
+}
+
+// A disk manager that logs every page write to the write-ahead log of a
+// data directory before handing it to `disk`. A write is diffed against the
+// stored page, the difference is logged and the log synced, and only then
+// is the page, stamped with the record's LSN, written. New pages are synced
+// to `disk` as soon as they are allocated, so every page the log refers to
+// exists when it is replayed.
+pub struct WalDiskManager<D> {
+    disk: D,
+    wal: WalWriter,
+}
+
+impl<D: DiskManager> WalDiskManager<D> {
+    // Replays the log of `dir` onto `disk`, which leaves it synced, and
+    // starts a new log.
+    pub fn open(dir: impl AsRef<Path>, mut disk: D) -> anyhow::Result<Self> {
+        recover(&dir, &mut disk)?;
+        let mut wal = WalWriter::open(dir)?;
+        wal.checkpoint()?;
+        Ok(Self { disk, wal })
+    }
+
+    pub fn disk(&self) -> &D {
+        &self.disk
+    }
+
+    pub fn into_inner(self) -> D {
+        self.disk
+    }
+}
+
+impl<D: DiskManager> DiskManager for WalDiskManager<D> {
+    // A zeroed page fails validation and could not be read back to replay
+    // the log onto it, so new pages always get a header.
+    fn allocate_page(&mut self, file_id: u32) -> anyhow::Result<PageId> {
+        Ok(self
+            .allocate_initialized_page(file_id, PageFlags::Heap)?
+            .page_id())
+    }
+
+    fn allocate_initialized_page(
+        &mut self,
+        file_id: u32,
+        flags: PageFlags,
+    ) -> anyhow::Result<Page> {
+        let page = self.disk.allocate_initialized_page(file_id, flags)?;
+        self.disk.sync()?;
+        Ok(page)
+    }
+
+    fn read_page(&self, pid: PageId) -> anyhow::Result<Page> {
+        self.disk.read_page(pid)
+    }
+
+    fn write_page(&mut self, page: &Page) -> anyhow::Result<()> {
+        let before = self.disk.read_page(page.page_id())?;
+        let mut page = page.clone();
+        // Generations are assigned by `disk` on every write and stay out of
+        // the log
+        page.set_generation(before.generation());
+        if self.wal.log_page_changes(&before, &mut page)?.is_some() {
+            self.wal.sync()?;
+        }
+        self.disk.write_page(&page)
+    }
+
+    // Once `disk` has every logged change, the log is no longer needed and
+    // is truncated.
+    fn sync(&mut self) -> anyhow::Result<()> {
+        self.disk.sync()?;
+        self.wal.checkpoint()
+    }
+
+    fn page_count(&self, file_id: u32) -> anyhow::Result<u32> {
+        self.disk.page_count(file_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::disk::file_system::FsDiskManager;
+    use crate::heap::heap_page::HeapPage;
+    use tempfile::TempDir;
+
+    fn tuples(dm: &FsDiskManager, pid: PageId) -> anyhow::Result<Vec<Vec<u8>>> {
+        let hp = HeapPage {
+            page: dm.read_page(pid)?,
+        };
+        Ok(hp.tuples().map(|(_, tuple)| tuple.to_vec()).collect())
+    }
+
+    fn log_insert(hp: &mut HeapPage, tuple: &[u8], wal: &mut WalWriter) -> anyhow::Result<()> {
+        let before = hp.page.clone();
+        hp.insert_tuple(tuple)?;
+        wal.log_page_changes(&before, &mut hp.page)?;
+        Ok(())
+    }
+
+    #[test]
+    fn recovery_replays_changes_missing_from_the_data_file() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        // Buffered pages only reach the data file on sync, so dropping the
+        // manager without one is a crash after the log was written
+        let mut dm = WalDiskManager::open(
+            temp_dir.path(),
+            FsDiskManager::new_buffered(temp_dir.path())?,
+        )?;
+
+        let mut hp = HeapPage::allocate(&mut dm, 1)?;
+        let pid = hp.page.page_id();
+        hp.insert_tuple(b"alpha")?;
+        hp.insert_tuple(b"gone")?;
+        dm.write_page(&hp.page)?;
+        dm.sync()?;
+        assert_eq!(dm.read_page(pid)?.header().page_lsn, 1);
+
+        hp.insert_tuple(b"beta")?;
+        hp.delete_tuple(1)?;
+        dm.write_page(&hp.page)?;
+        drop(dm);
+
+        let mut dm = FsDiskManager::new(temp_dir.path())?;
+        assert_eq!(tuples(&dm, pid)?, vec![b"alpha".to_vec(), b"gone".to_vec()]);
+        assert_eq!(recover(temp_dir.path(), &mut dm)?, 1);
+        assert_eq!(tuples(&dm, pid)?, vec![b"alpha".to_vec(), b"beta".to_vec()]);
+        assert_eq!(dm.read_page(pid)?.header().page_lsn, 2);
+
+        // Everything is now on the page, so a second recovery does nothing
+        assert_eq!(recover(temp_dir.path(), &mut dm)?, 0);
+        assert_eq!(WalWriter::open(temp_dir.path())?.next_lsn(), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn torn_page_is_rebuilt_from_its_image() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let mut dm = WalDiskManager::open(temp_dir.path(), FsDiskManager::new(temp_dir.path())?)?;
+        let mut hp = HeapPage::allocate(&mut dm, 1)?;
+        let pid = hp.page.page_id();
+        hp.insert_tuple(b"alpha")?;
+        dm.write_page(&hp.page)?;
+        hp.insert_tuple(b"beta")?;
+        dm.write_page(&hp.page)?;
+        drop(dm);
+
+        let records = read_records(temp_dir.path())?;
+        assert!(matches!(records[1].payload, WalPayload::Image(_)));
+        assert!(matches!(records[2].payload, WalPayload::Changes(_)));
+
+        // Only the first half of the last write reached the disk
+        let path = temp_dir.path().join("base_1.db");
+        let mut data = fs::read(&path)?;
+        let start = pid.page_no() as usize * PAGE_SIZE;
+        data[start + PAGE_SIZE / 2..start + PAGE_SIZE].fill(0);
+        fs::write(&path, data)?;
+        assert!(FsDiskManager::new(temp_dir.path())?.read_page(pid).is_err());
+
+        WalDiskManager::open(temp_dir.path(), FsDiskManager::new(temp_dir.path())?)?;
+        let dm = FsDiskManager::new(temp_dir.path())?;
+        assert_eq!(tuples(&dm, pid)?, vec![b"alpha".to_vec(), b"beta".to_vec()]);
+        Ok(())
+    }
+
+    #[test]
+    fn sync_truncates_the_log() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let path = temp_dir.path().join(WAL_FILE_NAME);
+        let mut dm = WalDiskManager::open(temp_dir.path(), FsDiskManager::new(temp_dir.path())?)?;
+        let mut hp = HeapPage::allocate(&mut dm, 1)?;
+        for tuple in [b"alpha", b"bravo", b"gamma"] {
+            hp.insert_tuple(tuple)?;
+            dm.write_page(&hp.page)?;
+        }
+        let logged = fs::metadata(&path)?.len();
+        assert!(logged > PAGE_SIZE as u64);
+
+        dm.sync()?;
+        assert!(fs::metadata(&path)?.len() < logged);
+        let records = read_records(temp_dir.path())?;
+        assert_eq!(records.len(), 1);
+        assert_eq!(
+            (records[0].lsn, &records[0].payload),
+            (3, &WalPayload::Checkpoint)
+        );
+
+        // LSNs carry on, and the next change to the page is imaged again
+        hp.insert_tuple(b"delta")?;
+        dm.write_page(&hp.page)?;
+        drop(dm);
+        let records = read_records(temp_dir.path())?;
+        assert_eq!(records[1].lsn, 4);
+        assert!(matches!(records[1].payload, WalPayload::Image(_)));
+        Ok(())
+    }
+
+    #[test]
+    fn torn_tail_record_is_dropped() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let mut dm = FsDiskManager::new(temp_dir.path())?;
+        let mut wal = WalWriter::open(temp_dir.path())?;
+        let mut hp = HeapPage::allocate(&mut dm, 1)?;
+        log_insert(&mut hp, b"alpha", &mut wal)?;
+        log_insert(&mut hp, b"beta", &mut wal)?;
+        drop(wal);
+
+        // Cut the last record short, as a crash mid-append would
+        let path = temp_dir.path().join(WAL_FILE_NAME);
+        let len = fs::metadata(&path)?.len();
+        OpenOptions::new()
+            .write(true)
+            .open(&path)?
+            .set_len(len - 3)?;
+
+        let records = read_records(temp_dir.path())?;
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].lsn, 1);
+        assert_eq!(records[0].page_id, hp.page.page_id());
+
+        // The writer picks up after the last complete record
+        let mut wal = WalWriter::open(temp_dir.path())?;
+        assert_eq!(wal.next_lsn(), 2);
+        log_insert(&mut hp, b"gamma", &mut wal)?;
+        let lsns: Vec<u64> = read_records(temp_dir.path())?
+            .iter()
+            .map(|record| record.lsn)
+            .collect();
+        assert_eq!(lsns, vec![1, 2]);
+        Ok(())
+    }
+}